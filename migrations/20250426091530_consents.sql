@@ -0,0 +1,28 @@
+-- first-party clients are trusted and never show the consent page
+alter table clients
+    add column first_party boolean not null default false;
+
+create table consents (
+    id uuid primary key default gen_random_uuid(),
+    user_id uuid not null references users(id) on delete cascade,
+    client_id uuid not null references clients(id) on delete cascade,
+    scopes text[] not null default '{}',
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    unique (user_id, client_id)
+);
+
+-- indexes
+create index idx_consents_user_id on consents (user_id);
+create index idx_consents_client_id on consents (client_id);
+
+-- triggers
+create trigger set_consents_timestamps
+    before insert on consents
+    for each row
+execute function set_created_at_column();
+
+create trigger update_consents_updated_at
+    before update on consents
+    for each row
+execute function update_updated_at_column();
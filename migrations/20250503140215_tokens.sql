@@ -0,0 +1,61 @@
+-- per client access token format and lifetimes; null lifetimes fall back to the server defaults
+alter table clients
+    add column access_token_format text not null default 'jwt'
+        check (access_token_format in ('jwt', 'opaque')),
+    add column access_token_lifetime integer check (access_token_lifetime > 0),
+    add column id_token_lifetime integer check (id_token_lifetime > 0);
+
+-- keys used to sign id tokens and jwt access tokens
+create table signing_keys (
+    id uuid primary key default gen_random_uuid(),
+    kid text not null unique,
+    algorithm text not null,
+    private_key text not null,
+    public_key text not null,
+    active boolean not null default true,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+-- every issued access token, keyed by its jti. opaque tokens are looked up by the
+-- sha-256 hash of the token value, jwt access tokens by the jti claim
+create table access_tokens (
+    id uuid primary key,
+    token_hash bytea unique,
+    format text not null check (format in ('jwt', 'opaque')),
+    client_id uuid not null references clients(id) on delete cascade,
+    user_id uuid references users(id) on delete cascade,
+    scopes text[] not null default '{}',
+    audience text[] not null default '{}',
+    expires_at timestamptz not null,
+    revoked_at timestamptz,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+-- indexes
+create index idx_signing_keys_active on signing_keys (active);
+create index idx_access_tokens_client_id on access_tokens (client_id);
+create index idx_access_tokens_user_id on access_tokens (user_id);
+create index idx_access_tokens_expires_at on access_tokens (expires_at);
+
+-- triggers
+create trigger set_signing_keys_timestamps
+    before insert on signing_keys
+    for each row
+execute function set_created_at_column();
+
+create trigger update_signing_keys_updated_at
+    before update on signing_keys
+    for each row
+execute function update_updated_at_column();
+
+create trigger set_access_tokens_timestamps
+    before insert on access_tokens
+    for each row
+execute function set_created_at_column();
+
+create trigger update_access_tokens_updated_at
+    before update on access_tokens
+    for each row
+execute function update_updated_at_column();
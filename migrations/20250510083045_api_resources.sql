@@ -0,0 +1,49 @@
+-- apis that access tokens can be audience-restricted to (rfc 8707). a resource may
+-- override the token format, lifetime and signing algorithm of the requesting client
+create table api_resources (
+    id uuid primary key default gen_random_uuid(),
+    identifier text not null unique,
+    name text not null,
+    description text,
+    access_token_format text check (access_token_format in ('jwt', 'opaque')),
+    access_token_lifetime integer check (access_token_lifetime > 0),
+    signing_algorithm text not null default 'RS256',
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+-- scopes owned by an api resource. names are unique across resources so a
+-- requested scope always maps to exactly one resource
+create table api_resource_scopes (
+    id uuid primary key default gen_random_uuid(),
+    resource_id uuid not null references api_resources(id) on delete cascade,
+    name text not null unique,
+    description text,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+-- indexes
+create index idx_api_resources_identifier on api_resources (identifier);
+create index idx_api_resource_scopes_resource_id on api_resource_scopes (resource_id);
+
+-- triggers
+create trigger set_api_resources_timestamps
+    before insert on api_resources
+    for each row
+execute function set_created_at_column();
+
+create trigger update_api_resources_updated_at
+    before update on api_resources
+    for each row
+execute function update_updated_at_column();
+
+create trigger set_api_resource_scopes_timestamps
+    before insert on api_resource_scopes
+    for each row
+execute function set_created_at_column();
+
+create trigger update_api_resource_scopes_updated_at
+    before update on api_resource_scopes
+    for each row
+execute function update_updated_at_column();
@@ -0,0 +1,8 @@
+-- per client id token signing algorithm
+alter table clients
+    add column id_token_signed_response_alg text not null default 'RS256'
+        check (id_token_signed_response_alg in ('RS256', 'PS256', 'ES256', 'ES384', 'EdDSA'));
+
+alter table api_resources
+    add constraint api_resources_signing_algorithm_check
+        check (signing_algorithm in ('RS256', 'PS256', 'ES256', 'ES384', 'EdDSA'));
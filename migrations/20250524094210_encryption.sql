@@ -0,0 +1,16 @@
+-- public keys registered by clients, used to encrypt tokens addressed to them
+alter table clients
+    add column jwks jsonb,
+    add column id_token_encrypted_response_alg text
+        check (id_token_encrypted_response_alg in ('RSA-OAEP-256', 'ECDH-ES')),
+    add column id_token_encrypted_response_enc text
+        check (id_token_encrypted_response_enc in ('A256GCM')),
+    add column userinfo_encrypted_response_alg text
+        check (userinfo_encrypted_response_alg in ('RSA-OAEP-256', 'ECDH-ES')),
+    add column userinfo_encrypted_response_enc text
+        check (userinfo_encrypted_response_enc in ('A256GCM')),
+    -- an encryption method is only meaningful together with a key management algorithm
+    add constraint clients_id_token_encryption_check
+        check (id_token_encrypted_response_enc is null or id_token_encrypted_response_alg is not null),
+    add constraint clients_userinfo_encryption_check
+        check (userinfo_encrypted_response_enc is null or userinfo_encrypted_response_alg is not null);
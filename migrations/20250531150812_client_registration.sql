@@ -0,0 +1,31 @@
+-- client metadata for dynamic client registration (rfc 7591)
+alter table clients
+    add column client_name text,
+    add column logo_uri text,
+    add column grant_types text[] not null default '{authorization_code,client_credentials}',
+    -- null accepts any supported method, as for clients created before registration existed
+    add column token_endpoint_auth_method text
+        check (token_endpoint_auth_method in ('client_secret_basic', 'client_secret_post', 'none')),
+    -- sha-256 hash of the registration access token of self-registered clients (rfc 7592)
+    add column registration_token_hash bytea unique;
+
+-- tokens gating client registration when open registration is disabled
+create table initial_access_tokens (
+    id uuid primary key default gen_random_uuid(),
+    token_hash bytea not null unique,
+    expires_at timestamptz,
+    -- null for unlimited uses
+    remaining_uses integer check (remaining_uses >= 0),
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+create trigger set_initial_access_tokens_created_at
+    before insert on initial_access_tokens
+    for each row
+    execute function set_created_at_column();
+
+create trigger update_initial_access_tokens_updated_at
+    before update on initial_access_tokens
+    for each row
+    execute function update_updated_at_column();
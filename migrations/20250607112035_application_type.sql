@@ -0,0 +1,4 @@
+-- native apps may use private-use uri scheme redirects (rfc 8252)
+alter table clients
+    add column application_type text not null default 'web'
+        check (application_type in ('web', 'native'));
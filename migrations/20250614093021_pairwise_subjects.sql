@@ -0,0 +1,5 @@
+-- pairwise subject identifiers (openid connect core 1.0, section 8.1)
+alter table clients
+    add column subject_type text not null default 'public'
+        check (subject_type in ('public', 'pairwise')),
+    add column sector_identifier_uri text;
@@ -0,0 +1,3 @@
+-- claims requested individually for the userinfo response through the "claims" parameter
+alter table access_tokens
+    add column claims text[] not null default '{}';
@@ -0,0 +1,36 @@
+-- free-form user attributes, such as a tenant id, that protocol mappers can put into tokens
+alter table users
+    add column attributes jsonb not null default '{}';
+
+-- protocol mappers add claims to tokens. a mapper belongs either to a client or to a scope,
+-- in which case it applies to every client the scope is granted to
+create table protocol_mappers (
+    id uuid primary key default gen_random_uuid(),
+    name text not null,
+    client_id uuid references clients(id) on delete cascade,
+    scope text,
+    mapper_type text not null check (mapper_type in ('user_attribute', 'user_roles', 'static')),
+    config jsonb not null default '{}',
+    claim_name text not null,
+    id_token boolean not null default true,
+    access_token boolean not null default true,
+    userinfo boolean not null default true,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    check ((client_id is null) <> (scope is null))
+);
+
+-- indexes
+create index idx_protocol_mappers_client_id on protocol_mappers (client_id);
+create index idx_protocol_mappers_scope on protocol_mappers (scope);
+
+-- triggers
+create trigger set_protocol_mappers_timestamps
+    before insert on protocol_mappers
+    for each row
+execute function set_created_at_column();
+
+create trigger update_protocol_mappers_updated_at
+    before update on protocol_mappers
+    for each row
+execute function update_updated_at_column();
@@ -0,0 +1,12 @@
+-- roles owned by a client. realm roles have no client; names are unique among the
+-- realm roles and among the roles of each client
+alter table roles
+    add column client_id uuid references clients(id) on delete cascade,
+    drop constraint roles_name_key;
+
+create unique index idx_roles_realm_name on roles (name) where client_id is null;
+create unique index idx_roles_client_name on roles (client_id, name) where client_id is not null;
+
+-- whether the user's roles are included in id tokens, not only in access tokens
+alter table clients
+    add column id_token_role_claims boolean not null default false;
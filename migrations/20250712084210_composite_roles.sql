@@ -0,0 +1,31 @@
+-- composite roles. users with a composite role effectively have each of its child roles,
+-- transitively. the repository rejects composites that would form a cycle
+create table role_composites (
+    role_id uuid not null references roles(id) on delete cascade,
+    child_role_id uuid not null references roles(id) on delete cascade,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    primary key (role_id, child_role_id),
+    check (role_id <> child_role_id)
+);
+
+-- indexes
+create index idx_role_composites_child_role_id on role_composites (child_role_id);
+
+-- triggers
+create trigger set_role_composites_timestamps
+    before insert on role_composites
+    for each row
+execute function set_created_at_column();
+
+create trigger update_role_composites_updated_at
+    before update on role_composites
+    for each row
+execute function update_updated_at_column();
+
+-- admins are users too
+insert into role_composites (role_id, child_role_id)
+select admin.id, member.id
+from roles admin, roles member
+where admin.name = 'admin' and admin.client_id is null
+  and member.name = 'user' and member.client_id is null;
@@ -0,0 +1,69 @@
+-- groups form a tree. members inherit the role mappings and attributes of their groups and of
+-- every ancestor group; attributes of nested groups override those of their ancestors
+create table groups (
+    id uuid primary key default gen_random_uuid(),
+    parent_id uuid references groups(id) on delete cascade,
+    name text not null check (name <> '' and position('/' in name) = 0),
+    attributes jsonb not null default '{}',
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+create table group_roles (
+    group_id uuid references groups(id) on delete cascade,
+    role_id uuid references roles(id) on delete cascade,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    primary key (group_id, role_id)
+);
+
+create table user_groups (
+    user_id uuid references users(id) on delete cascade,
+    group_id uuid references groups(id) on delete cascade,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    primary key (user_id, group_id)
+);
+
+-- indexes
+create unique index idx_groups_root_name on groups (name) where parent_id is null;
+create unique index idx_groups_child_name on groups (parent_id, name) where parent_id is not null;
+create index idx_group_roles_role_id on group_roles (role_id);
+create index idx_user_groups_group_id on user_groups (group_id);
+
+-- triggers
+create trigger set_groups_timestamps
+    before insert on groups
+    for each row
+execute function set_created_at_column();
+
+create trigger update_groups_updated_at
+    before update on groups
+    for each row
+execute function update_updated_at_column();
+
+create trigger set_group_roles_timestamps
+    before insert on group_roles
+    for each row
+execute function set_created_at_column();
+
+create trigger update_group_roles_updated_at
+    before update on group_roles
+    for each row
+execute function update_updated_at_column();
+
+create trigger set_user_groups_timestamps
+    before insert on user_groups
+    for each row
+execute function set_created_at_column();
+
+create trigger update_user_groups_updated_at
+    before update on user_groups
+    for each row
+execute function update_updated_at_column();
+
+-- the group membership mapper emits the full paths of the user's groups
+alter table protocol_mappers
+    drop constraint protocol_mappers_mapper_type_check,
+    add constraint protocol_mappers_mapper_type_check
+        check (mapper_type in ('user_attribute', 'user_roles', 'group_membership', 'static'));
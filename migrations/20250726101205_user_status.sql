@@ -0,0 +1,7 @@
+-- disabled users cannot log in. soft-deleted users are kept for auditing but no longer
+-- show up anywhere; their username and email stay taken
+alter table users
+    add column enabled boolean not null default true,
+    add column deleted_at timestamptz;
+
+create index idx_users_created_at on users (created_at);
@@ -0,0 +1,21 @@
+-- objects declared in the provisioning section of the configuration. pruning only removes
+-- objects listed here, never those created through the admin API
+create table provisioned_objects (
+    kind text not null check (kind in ('client', 'role', 'group', 'user')),
+    -- client_id, role name ("client_id/name" for client roles), group path or username
+    name text not null,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    primary key (kind, name)
+);
+
+-- triggers
+create trigger set_provisioned_objects_timestamps
+    before insert on provisioned_objects
+    for each row
+execute function set_created_at_column();
+
+create trigger update_provisioned_objects_updated_at
+    before update on provisioned_objects
+    for each row
+execute function update_updated_at_column();
@@ -0,0 +1,86 @@
+-- realms are isolated tenants with their own issuer, clients, users, roles, groups and keys.
+-- a realm is served under /realms/{name} and, when a host is set, on that host
+create table realms (
+    id uuid primary key default gen_random_uuid(),
+    name text not null unique check (name ~ '^[a-z0-9][a-z0-9_-]*$'),
+    -- the issuer and base url of the realm's endpoints, derived from the server's
+    -- external_url when null
+    external_url text,
+    host text unique,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+-- triggers
+create trigger set_realms_timestamps
+    before insert on realms
+    for each row
+execute function set_created_at_column();
+
+create trigger update_realms_updated_at
+    before update on realms
+    for each row
+execute function update_updated_at_column();
+
+-- everything that exists so far belongs to the default realm
+insert into realms (name) values ('master');
+
+alter table clients add column realm_id uuid references realms(id) on delete cascade;
+alter table users add column realm_id uuid references realms(id) on delete cascade;
+alter table roles add column realm_id uuid references realms(id) on delete cascade;
+alter table groups add column realm_id uuid references realms(id) on delete cascade;
+alter table signing_keys add column realm_id uuid references realms(id) on delete cascade;
+alter table api_resources add column realm_id uuid references realms(id) on delete cascade;
+alter table api_resource_scopes add column realm_id uuid references realms(id) on delete cascade;
+alter table initial_access_tokens add column realm_id uuid references realms(id) on delete cascade;
+alter table protocol_mappers add column realm_id uuid references realms(id) on delete cascade;
+alter table provisioned_objects add column realm_id uuid references realms(id) on delete cascade;
+
+update clients set realm_id = (select id from realms where name = 'master');
+update users set realm_id = (select id from realms where name = 'master');
+update roles set realm_id = (select id from realms where name = 'master');
+update groups set realm_id = (select id from realms where name = 'master');
+update signing_keys set realm_id = (select id from realms where name = 'master');
+update api_resources set realm_id = (select id from realms where name = 'master');
+update api_resource_scopes set realm_id = (select id from realms where name = 'master');
+update initial_access_tokens set realm_id = (select id from realms where name = 'master');
+update protocol_mappers set realm_id = (select id from realms where name = 'master');
+update provisioned_objects set realm_id = (select id from realms where name = 'master');
+
+alter table clients alter column realm_id set not null;
+alter table users alter column realm_id set not null;
+alter table roles alter column realm_id set not null;
+alter table groups alter column realm_id set not null;
+alter table signing_keys alter column realm_id set not null;
+alter table api_resources alter column realm_id set not null;
+alter table api_resource_scopes alter column realm_id set not null;
+alter table initial_access_tokens alter column realm_id set not null;
+alter table protocol_mappers alter column realm_id set not null;
+alter table provisioned_objects alter column realm_id set not null;
+
+-- names are unique within a realm
+alter table clients drop constraint clients_client_id_key;
+alter table clients add constraint clients_realm_id_client_id_key unique (realm_id, client_id);
+alter table users drop constraint users_username_key;
+alter table users add constraint users_realm_id_username_key unique (realm_id, username);
+alter table users drop constraint users_email_key;
+alter table users add constraint users_realm_id_email_key unique (realm_id, email);
+alter table api_resources drop constraint api_resources_identifier_key;
+alter table api_resources add constraint api_resources_realm_id_identifier_key unique (realm_id, identifier);
+alter table api_resource_scopes drop constraint api_resource_scopes_name_key;
+alter table api_resource_scopes add constraint api_resource_scopes_realm_id_name_key unique (realm_id, name);
+alter table provisioned_objects drop constraint provisioned_objects_pkey;
+alter table provisioned_objects add primary key (realm_id, kind, name);
+
+drop index idx_roles_realm_name;
+create unique index idx_roles_realm_name on roles (realm_id, name) where client_id is null;
+drop index idx_groups_root_name;
+create unique index idx_groups_root_name on groups (realm_id, name) where parent_id is null;
+
+-- indexes
+create index idx_clients_realm_id on clients (realm_id);
+create index idx_users_realm_id on users (realm_id);
+create index idx_roles_realm_id on roles (realm_id);
+create index idx_groups_realm_id on groups (realm_id);
+create index idx_signing_keys_realm_id on signing_keys (realm_id);
+create index idx_protocol_mappers_realm_id on protocol_mappers (realm_id);
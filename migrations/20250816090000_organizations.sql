@@ -0,0 +1,95 @@
+-- organizations are the companies using a realm. members carry organization roles, which are
+-- plain names only meaningful within the organization
+create table organizations (
+    id uuid primary key default gen_random_uuid(),
+    realm_id uuid not null references realms(id) on delete cascade,
+    name text not null check (name <> ''),
+    display_name text,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    unique (realm_id, name)
+);
+
+-- users with an email address in a verified domain join the organization when they log in.
+-- a domain belongs to at most one organization of a realm
+create table organization_domains (
+    organization_id uuid not null references organizations(id) on delete cascade,
+    realm_id uuid not null references realms(id) on delete cascade,
+    domain text not null check (domain = lower(domain) and domain <> ''),
+    verified boolean not null default false,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    primary key (organization_id, domain),
+    unique (realm_id, domain)
+);
+
+create table organization_members (
+    organization_id uuid not null references organizations(id) on delete cascade,
+    user_id uuid not null references users(id) on delete cascade,
+    roles text[] not null default '{}',
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    primary key (organization_id, user_id)
+);
+
+-- invitations are accepted by signing in through the invitation link with an account that
+-- has the invited email address. only the sha-256 hash of the link's token is stored
+create table organization_invitations (
+    id uuid primary key default gen_random_uuid(),
+    organization_id uuid not null references organizations(id) on delete cascade,
+    email text not null,
+    roles text[] not null default '{}',
+    token_hash bytea not null unique,
+    expires_at timestamptz not null,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+-- the organization the user picked when logging in, released by the organization scope
+alter table access_tokens
+    add column organization_id uuid references organizations(id) on delete set null;
+
+-- indexes
+create index idx_organization_members_user_id on organization_members (user_id);
+create index idx_organization_invitations_organization_id on organization_invitations (organization_id);
+
+-- triggers
+create trigger set_organizations_timestamps
+    before insert on organizations
+    for each row
+execute function set_created_at_column();
+
+create trigger update_organizations_updated_at
+    before update on organizations
+    for each row
+execute function update_updated_at_column();
+
+create trigger set_organization_domains_timestamps
+    before insert on organization_domains
+    for each row
+execute function set_created_at_column();
+
+create trigger update_organization_domains_updated_at
+    before update on organization_domains
+    for each row
+execute function update_updated_at_column();
+
+create trigger set_organization_members_timestamps
+    before insert on organization_members
+    for each row
+execute function set_created_at_column();
+
+create trigger update_organization_members_updated_at
+    before update on organization_members
+    for each row
+execute function update_updated_at_column();
+
+create trigger set_organization_invitations_timestamps
+    before insert on organization_invitations
+    for each row
+execute function set_created_at_column();
+
+create trigger update_organization_invitations_updated_at
+    before update on organization_invitations
+    for each row
+execute function update_updated_at_column();
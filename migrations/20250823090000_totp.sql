@@ -0,0 +1,43 @@
+-- rfc 6238 totp authenticators. the secret is encrypted with the server's mfa encryption key;
+-- an authenticator only counts once the user confirmed it with a valid code
+create table user_totp (
+    user_id uuid primary key references users(id) on delete cascade,
+    encrypted_secret text not null,
+    confirmed_at timestamptz,
+    -- the time step of the last accepted code, so no code is accepted twice
+    last_used_step bigint,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+-- single-use codes for users who lost their authenticator. only sha-256 hashes are stored
+create table recovery_codes (
+    id uuid primary key default gen_random_uuid(),
+    user_id uuid not null references users(id) on delete cascade,
+    code_hash bytea not null,
+    used_at timestamptz,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    unique (user_id, code_hash)
+);
+
+-- triggers
+create trigger set_user_totp_timestamps
+    before insert on user_totp
+    for each row
+execute function set_created_at_column();
+
+create trigger update_user_totp_updated_at
+    before update on user_totp
+    for each row
+execute function update_updated_at_column();
+
+create trigger set_recovery_codes_timestamps
+    before insert on recovery_codes
+    for each row
+execute function set_created_at_column();
+
+create trigger update_recovery_codes_updated_at
+    before update on recovery_codes
+    for each row
+execute function update_updated_at_column();
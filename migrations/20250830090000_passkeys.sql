@@ -0,0 +1,33 @@
+-- webauthn credentials. passkeys serve as a second factor and, when discoverable, for passwordless login
+create table passkeys (
+    id uuid primary key default gen_random_uuid(),
+    user_id uuid not null references users(id) on delete cascade,
+    credential_id bytea not null unique,
+    -- the credential public key as a cose key
+    public_key bytea not null,
+    algorithm integer not null,
+    -- the authenticator's signature counter, which must increase with every use unless it stays 0
+    sign_count bigint not null default 0,
+    -- the authenticator model, all zeros when not attested
+    aaguid uuid not null,
+    attestation_format text not null,
+    transports text[] not null default '{}',
+    backup_eligible boolean not null default false,
+    name text not null,
+    last_used_at timestamptz,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+create index passkeys_user_id_idx on passkeys (user_id);
+
+-- triggers
+create trigger set_passkeys_timestamps
+    before insert on passkeys
+    for each row
+execute function set_created_at_column();
+
+create trigger update_passkeys_updated_at
+    before update on passkeys
+    for each row
+execute function update_updated_at_column();
@@ -0,0 +1,27 @@
+-- failed logins, counted per user and per client address to slow down password guessing.
+-- user counters are keyed by the user id, address counters by the client ip address
+create table login_failures (
+    realm_id uuid not null references realms(id) on delete cascade,
+    kind text not null check (kind in ('user', 'ip')),
+    key text not null,
+    -- failures since the last lockout or successful login
+    failures integer not null default 0,
+    last_failure_at timestamptz not null,
+    locked_until timestamptz,
+    -- set when the user stays locked out until an administrator unlocks them
+    locked_permanently boolean not null default false,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp,
+    primary key (realm_id, kind, key)
+);
+
+-- triggers
+create trigger set_login_failures_timestamps
+    before insert on login_failures
+    for each row
+execute function set_created_at_column();
+
+create trigger update_login_failures_updated_at
+    before update on login_failures
+    for each row
+execute function update_updated_at_column();
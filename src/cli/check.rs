@@ -0,0 +1,129 @@
+use crate::{db, Config};
+use clap::Subcommand;
+use sqlx::migrate::Migrate;
+use url::Url;
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Validate the configuration and the database connection without changing anything
+    Check,
+}
+
+pub async fn run(command: ConfigCommand, config: &Config) -> Result<(), String> {
+    match command {
+        ConfigCommand::Check => {
+            let problems = check_config(config);
+            for problem in &problems {
+                println!("error: {}", problem);
+            }
+
+            let pool = db::connect(&config.postgres)
+                .await
+                .map_err(|e| format!("Failed to connect to the database: {}", e))?;
+            let mut connection = pool.acquire().await.map_err(|e| e.to_string())?;
+
+            // A fresh database has no migrations table yet
+            let applied = connection.list_applied_migrations().await.unwrap_or_default();
+            let pending = sqlx::migrate!("./migrations")
+                .iter()
+                .filter(|migration| !applied.iter().any(|a| a.version == migration.version))
+                .count();
+            println!("Connected to the database, {} pending migration(s)", pending);
+
+            if !problems.is_empty() {
+                return Err(format!("The configuration has {} error(s)", problems.len()));
+            }
+            println!("The configuration is valid");
+        }
+    }
+
+    Ok(())
+}
+
+/// The problems with the configuration that can be found without connecting anywhere.
+fn check_config(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    match config.server.port {
+        Some(0) | None => problems.push("server.port must be between 1 and 65535".to_string()),
+        Some(_) => {}
+    }
+    if config.server.bind_addr.as_deref().unwrap_or_default().is_empty() {
+        problems.push("server.bind_addr is required".to_string());
+    }
+
+    let external_url = config.oidc.external_url.as_deref().unwrap_or_default();
+    if !Url::parse(external_url).is_ok_and(|url| url.scheme() == "https" || url.scheme() == "http") {
+        problems.push(format!("oidc.external_url '{}' is not an http or https URL", external_url));
+    }
+    for (name, lifetime) in [
+        ("oidc.access_token_lifetime", config.oidc.access_token_lifetime),
+        ("oidc.id_token_lifetime", config.oidc.id_token_lifetime),
+    ] {
+        if lifetime == Some(0) {
+            problems.push(format!("{} must be positive", name));
+        }
+    }
+    if config.oidc.pairwise_salt.as_deref() == Some("") {
+        problems.push("oidc.pairwise_salt must not be empty when set".to_string());
+    }
+    match config.oidc.mfa_encryption_key.as_deref() {
+        Some(key) if key.len() < 32 => {
+            problems.push("oidc.mfa_encryption_key must be at least 32 characters".to_string());
+        }
+        None if config.oidc.require_mfa == Some(true) => {
+            problems.push("oidc.require_mfa needs oidc.mfa_encryption_key".to_string());
+        }
+        _ => {}
+    }
+
+    let lockout = &config.lockout;
+    for (name, value) in [
+        ("lockout.max_failures", lockout.max_failures.map(u64::from)),
+        ("lockout.max_failures_per_ip", lockout.max_failures_per_ip.map(u64::from)),
+        ("lockout.lockout_duration", lockout.lockout_duration),
+        ("lockout.failure_reset", lockout.failure_reset),
+    ] {
+        if value == Some(0) {
+            problems.push(format!("{} must be positive", name));
+        }
+    }
+
+    let bootstrap = &config.bootstrap;
+    if bootstrap.admin_username.is_none() && (bootstrap.admin_email.is_some() || bootstrap.admin_password.is_some()) {
+        problems.push("bootstrap.admin_email and bootstrap.admin_password need bootstrap.admin_username".to_string());
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(check_config(&Config::default()).is_empty());
+    }
+
+    #[test]
+    fn test_check_config() {
+        let mut config = Config::default();
+        config.server.port = Some(0);
+        config.oidc.external_url = Some("auth.example.com".to_string());
+        config.oidc.id_token_lifetime = Some(0);
+        config.oidc.require_mfa = Some(true);
+        config.lockout.max_failures = Some(0);
+        config.bootstrap.admin_password = Some("supersecret".to_string());
+
+        let problems = check_config(&config);
+
+        assert_eq!(problems.len(), 6);
+        assert!(problems[0].starts_with("server.port"));
+        assert!(problems[1].starts_with("oidc.external_url"));
+        assert!(problems[2].starts_with("oidc.id_token_lifetime"));
+        assert!(problems[3].starts_with("oidc.require_mfa"));
+        assert!(problems[4].starts_with("lockout.max_failures"));
+        assert!(problems[5].starts_with("bootstrap."));
+    }
+}
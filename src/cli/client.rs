@@ -0,0 +1,111 @@
+use std::sync::Arc;
+use crate::di::MyModule;
+use crate::domain::access_token::AccessTokenFormat;
+use crate::domain::client::ClientId;
+use crate::domain::realm::Realm;
+use crate::oidc::client_auth::hash_client_secret;
+use crate::oidc::registration::{check_metadata, generate_token, ClientMetadata};
+use crate::repository::client_repository::{ClientRepository, CreateClientParams};
+use clap::Subcommand;
+use shaku::HasComponent;
+
+#[derive(Subcommand, Debug)]
+pub enum ClientCommand {
+    /// Create a client. Confidential clients get a generated secret, printed once.
+    Create {
+        client_id: String,
+        /// May be given several times
+        #[arg(long = "redirect-uri")]
+        redirect_uris: Vec<String>,
+        /// Space-separated scopes the client may request, "openid" when not given
+        #[arg(long)]
+        scope: Option<String>,
+        /// May be given several times, "authorization_code" when not given
+        #[arg(long = "grant-type")]
+        grant_types: Vec<String>,
+        /// A public client without a secret
+        #[arg(long)]
+        public: bool,
+        /// Operated by us, so users are not asked for consent
+        #[arg(long)]
+        first_party: bool,
+        /// Human readable name shown on the consent page
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Replace the secret of a confidential client and print the new one
+    RotateSecret { client_id: String },
+}
+
+pub async fn run(command: ClientCommand, module: &MyModule, realm: &Realm) -> Result<(), String> {
+    let client_repository: Arc<dyn ClientRepository> = module.resolve();
+
+    match command {
+        ClientCommand::Create { client_id, redirect_uris, scope, grant_types, public, first_party, name } => {
+            if client_repository.find_by_id(realm.uuid, &ClientId(client_id.clone())).await.is_some() {
+                return Err(format!("Client {} already exists", client_id));
+            }
+
+            // The same checks as for clients registering themselves
+            let metadata = ClientMetadata {
+                redirect_uris,
+                scope,
+                grant_types: (!grant_types.is_empty()).then_some(grant_types),
+                token_endpoint_auth_method: public.then(|| "none".to_string()),
+                client_name: name,
+                ..Default::default()
+            };
+            let validated = check_metadata(&metadata).map_err(|e| e.to_string())?;
+
+            let client_secret = (!public).then(generate_token);
+            let client = client_repository
+                .create(CreateClientParams {
+                    realm_uuid: realm.uuid,
+                    client_id,
+                    redirect_uris: validated.redirect_uris,
+                    scopes: validated.scopes,
+                    client_secret_hash: client_secret.as_deref().map(hash_client_secret),
+                    client_name: validated.client_name,
+                    logo_uri: validated.logo_uri,
+                    application_type: validated.application_type,
+                    subject_type: validated.subject_type,
+                    sector_identifier_uri: validated.sector_identifier_uri,
+                    grant_types: validated.grant_types,
+                    token_endpoint_auth_method: validated.token_endpoint_auth_method,
+                    registration_token_hash: None,
+                    first_party,
+                    id_token_role_claims: false,
+                    access_token_format: AccessTokenFormat::Jwt,
+                    access_token_lifetime: None,
+                    id_token_lifetime: None,
+                    id_token_signed_response_alg: validated.id_token_signed_response_alg,
+                    jwks: validated.jwks,
+                    id_token_encrypted_response_alg: validated.id_token_encrypted_response_alg,
+                    id_token_encrypted_response_enc: validated.id_token_encrypted_response_enc,
+                    userinfo_encrypted_response_alg: validated.userinfo_encrypted_response_alg,
+                    userinfo_encrypted_response_enc: validated.userinfo_encrypted_response_enc,
+                })
+                .await?;
+
+            println!("Created client {}", client.id.0);
+            if let Some(secret) = client_secret {
+                println!("Client secret: {}", secret);
+            }
+        }
+        ClientCommand::RotateSecret { client_id } => {
+            let id = ClientId(client_id);
+            let Some(client) = client_repository.find_by_id(realm.uuid, &id).await else {
+                return Err(format!("Client {} not found", id.0));
+            };
+            if client.is_public() {
+                return Err(format!("Client {} is a public client without a secret", id.0));
+            }
+
+            let secret = generate_token();
+            client_repository.set_secret_hash(realm.uuid, &id, &hash_client_secret(&secret)).await?;
+            println!("Client secret: {}", secret);
+        }
+    }
+
+    Ok(())
+}
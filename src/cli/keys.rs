@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use crate::di::MyModule;
+use crate::domain::realm::Realm;
+use crate::oidc::keys;
+use crate::repository::signing_key_repository::SigningKeyRepository;
+use clap::Subcommand;
+use shaku::HasComponent;
+
+#[derive(Subcommand, Debug)]
+pub enum KeysCommand {
+    /// Sign with a new key from now on. The old keys stay published, so issued tokens remain valid.
+    Rotate {
+        #[arg(long, default_value = keys::DEFAULT_ALGORITHM)]
+        algorithm: String,
+    },
+    /// List the signing keys
+    List,
+}
+
+pub async fn run(command: KeysCommand, module: &MyModule, realm: &Realm) -> Result<(), String> {
+    let signing_key_repository: Arc<dyn SigningKeyRepository> = module.resolve();
+
+    match command {
+        KeysCommand::Rotate { algorithm } => {
+            let key = keys::rotate_key(signing_key_repository.as_ref(), realm.uuid, &algorithm).await?;
+            println!("Signing with {} key {}", key.algorithm, key.kid);
+        }
+        KeysCommand::List => {
+            for key in signing_key_repository.list(realm.uuid).await? {
+                let status = if key.active { "active" } else { "inactive" };
+                println!("{}\t{}\t{}\t{}", key.kid, key.algorithm, status, key.created_at);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+//! Subcommands of the vaulton binary.
+//! Besides `serve`, they work on the database directly through the repositories of `MyModule`,
+//! so operations can be scripted without running the HTTP API.
+
+mod check;
+mod client;
+mod keys;
+mod realm;
+mod role;
+mod user;
+
+use std::io::BufRead;
+use std::sync::Arc;
+use crate::di::{create_module, MyModule};
+use crate::domain::realm::Realm;
+use crate::repository::realm_repository::RealmRepository;
+use crate::{db, server, Config};
+use clap::Subcommand;
+use shaku::HasComponent;
+
+pub use crate::domain::realm::DEFAULT_REALM;
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the server, the default without a subcommand
+    Serve,
+    /// Apply pending database migrations
+    Migrate,
+    /// Manage users
+    #[command(subcommand)]
+    User(user::UserCommand),
+    /// Manage clients
+    #[command(subcommand)]
+    Client(client::ClientCommand),
+    /// Manage role assignments
+    #[command(subcommand)]
+    Role(role::RoleCommand),
+    /// Manage signing keys
+    #[command(subcommand)]
+    Keys(keys::KeysCommand),
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(check::ConfigCommand),
+    /// Manage realms
+    #[command(subcommand)]
+    Realm(realm::RealmCommand),
+    /// Reconcile the clients, roles, groups and users declared in the configuration
+    Provision {
+        /// Only print the changes
+        #[arg(long)]
+        dry_run: bool,
+        /// Delete provisioned objects that are no longer declared
+        #[arg(long)]
+        prune: bool,
+    },
+    /// Export clients, roles, groups, users, consents and signing keys to JSON or YAML
+    Export(realm::ExportArgs),
+    /// Import an export, e.g. into another environment
+    Import(realm::ImportArgs),
+}
+
+/// Runs the command. All but `serve`, `migrate`, `config` and `realm` work in the named realm.
+pub async fn run(command: Command, config: Config, realm: &str) -> Result<(), String> {
+    match command {
+        Command::Serve => server::serve(config).await,
+        Command::Migrate => {
+            db::connect_to_db(&config.postgres).await.map_err(|e| e.to_string())?;
+            println!("The database is up to date");
+            Ok(())
+        }
+        Command::User(command) => {
+            let (module, realm) = realm_module(&config, realm).await?;
+            user::run(command, &module, &realm).await
+        }
+        Command::Client(command) => {
+            let (module, realm) = realm_module(&config, realm).await?;
+            client::run(command, &module, &realm).await
+        }
+        Command::Role(command) => {
+            let (module, realm) = realm_module(&config, realm).await?;
+            role::run(command, &module, &realm).await
+        }
+        Command::Keys(command) => {
+            let (module, realm) = realm_module(&config, realm).await?;
+            keys::run(command, &module, &realm).await
+        }
+        Command::Config(command) => check::run(command, &config).await,
+        Command::Realm(command) => realm::run(command, &module(&config).await?).await,
+        Command::Provision { dry_run, prune } => {
+            let (module, realm) = realm_module(&config, realm).await?;
+            let provisioning = &config.provisioning;
+            let dry_run = dry_run || provisioning.dry_run.unwrap_or(false);
+            let prune = prune || provisioning.prune.unwrap_or(false);
+            server::provisioning::run(&module, realm.uuid, provisioning, dry_run, prune).await
+        }
+        Command::Export(args) => {
+            let (module, realm) = realm_module(&config, realm).await?;
+            realm::export(args, &module, &realm).await
+        }
+        Command::Import(args) => {
+            let (module, realm) = realm_module(&config, realm).await?;
+            realm::import(args, &module, &realm).await
+        }
+    }
+}
+
+async fn module(config: &Config) -> Result<MyModule, String> {
+    let pool = db::connect_to_db(&config.postgres)
+        .await
+        .map_err(|e| format!("Failed to connect to the database: {}", e))?;
+
+    Ok(create_module(pool))
+}
+
+/// Connects to the database and looks up the realm to work in.
+async fn realm_module(config: &Config, realm: &str) -> Result<(MyModule, Realm), String> {
+    let module = module(config).await?;
+    let realm_repository: Arc<dyn RealmRepository> = module.resolve();
+    let realm = realm_repository
+        .find_by_name(realm)
+        .await
+        .ok_or_else(|| format!("Realm {} not found", realm))?;
+
+    Ok((module, realm))
+}
+
+/// The password from the command line, or else the first line of stdin, which keeps it
+/// out of the shell history.
+fn read_password(password: Option<String>) -> Result<String, String> {
+    let password = match password {
+        Some(password) => password,
+        None => {
+            let mut line = String::new();
+            std::io::stdin().lock().read_line(&mut line).map_err(|e| e.to_string())?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+
+    if password.is_empty() {
+        return Err("The password must not be empty".to_string());
+    }
+
+    Ok(password)
+}
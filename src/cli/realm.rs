@@ -0,0 +1,204 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::cli::read_password;
+use std::sync::Arc;
+use crate::di::MyModule;
+use crate::domain::realm::Realm;
+use crate::repository::realm_repository::{CreateRealmParams, RealmRepository};
+use crate::server::bootstrap;
+use crate::server::export::{self, ExportOptions, ImportOptions, OnConflict, RealmExport};
+use clap::{Args, Subcommand};
+use shaku::HasComponent;
+
+#[derive(Subcommand, Debug)]
+pub enum RealmCommand {
+    /// Create a realm with its own signing key and "admin" role
+    Create {
+        name: String,
+        /// The realm's issuer, "{server external_url}/realms/{name}" when not given
+        #[arg(long)]
+        external_url: Option<String>,
+        /// A host name serving the realm, besides the /realms/{name} path
+        #[arg(long)]
+        host: Option<String>,
+    },
+    /// List the realms
+    List,
+    /// Delete a realm with all its clients, users, roles, groups and keys
+    Delete { name: String },
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// The file to write, stdout when unset
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+    /// "json" or "yaml", taken from the file extension when unset
+    #[arg(long)]
+    format: Option<Format>,
+    /// Leave out the users and their consents
+    #[arg(long)]
+    exclude_users: bool,
+    /// Leave out password hashes, client secret hashes and the signing keys
+    #[arg(long)]
+    exclude_secrets: bool,
+    /// Passphrase encrypting the private signing keys, read from stdin when unset
+    #[arg(long)]
+    passphrase: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// The export to import
+    file: PathBuf,
+    /// "json" or "yaml", taken from the file extension when unset
+    #[arg(long)]
+    format: Option<Format>,
+    /// What to do with objects that already exist: "skip", "overwrite" or "fail"
+    #[arg(long, default_value = "fail")]
+    on_conflict: OnConflict,
+    /// Leave out the users and their consents
+    #[arg(long)]
+    exclude_users: bool,
+    /// Ignore password hashes, client secret hashes and the signing keys
+    #[arg(long)]
+    exclude_secrets: bool,
+    /// Passphrase decrypting the private signing keys, read from stdin when unset
+    #[arg(long)]
+    passphrase: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Yaml,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            other => Err(format!("unknown format: {}", other)),
+        }
+    }
+}
+
+impl Format {
+    /// The explicit format, else the one of the file extension, else JSON.
+    fn resolve(format: Option<Format>, path: Option<&Path>) -> Format {
+        format
+            .or_else(|| path?.extension()?.to_str()?.parse().ok())
+            .unwrap_or(Format::Json)
+    }
+}
+
+pub async fn run(command: RealmCommand, module: &MyModule) -> Result<(), String> {
+    let realm_repository: Arc<dyn RealmRepository> = module.resolve();
+
+    match command {
+        RealmCommand::Create { name, external_url, host } => {
+            let realm = bootstrap::create_realm(module, CreateRealmParams { name, external_url, host }).await?;
+            println!("Created realm {} ({})", realm.name, realm.uuid);
+        }
+        RealmCommand::List => {
+            for realm in realm_repository.list().await? {
+                let external_url = realm.external_url.as_deref().unwrap_or("-");
+                let host = realm.host.as_deref().unwrap_or("-");
+                println!("{}\t{}\t{}\t{}", realm.uuid, realm.name, external_url, host);
+            }
+        }
+        RealmCommand::Delete { name } => {
+            let realm = realm_repository
+                .find_by_name(&name)
+                .await
+                .ok_or_else(|| format!("Realm {} not found", name))?;
+            if realm.is_default() {
+                return Err("The default realm cannot be deleted".to_string());
+            }
+            realm_repository.delete(realm.uuid).await?;
+            println!("Deleted realm {}", realm.name);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn export(args: ExportArgs, module: &MyModule, realm: &Realm) -> Result<(), String> {
+    let passphrase = match args.exclude_secrets {
+        true => None,
+        false => Some(read_password(args.passphrase)?),
+    };
+    let options = ExportOptions {
+        exclude_users: args.exclude_users,
+        exclude_secrets: args.exclude_secrets,
+    };
+    let export = export::export_realm(module, realm.uuid, &options, passphrase.as_deref()).await?;
+
+    let contents = match Format::resolve(args.format, args.output.as_deref()) {
+        Format::Json => serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?,
+        Format::Yaml => serde_yaml::to_string(&export).map_err(|e| e.to_string())?,
+    };
+
+    // Stdout carries the export, so the summary goes to stderr
+    match &args.output {
+        Some(path) => std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?,
+        None => println!("{}", contents),
+    }
+    eprintln!(
+        "Exported {} client(s), {} role(s), {} group(s), {} user(s) and {} signing key(s)",
+        export.clients.len(),
+        export.roles.len(),
+        export.groups.len(),
+        export.users.len(),
+        export.signing_keys.len()
+    );
+
+    Ok(())
+}
+
+pub async fn import(args: ImportArgs, module: &MyModule, realm: &Realm) -> Result<(), String> {
+    let contents = std::fs::read_to_string(&args.file)
+        .map_err(|e| format!("Failed to read {}: {}", args.file.display(), e))?;
+    let export: RealmExport = match Format::resolve(args.format, Some(&args.file)) {
+        Format::Json => serde_json::from_str(&contents).map_err(|e| e.to_string())?,
+        Format::Yaml => serde_yaml::from_str(&contents).map_err(|e| e.to_string())?,
+    };
+
+    let passphrase = match args.exclude_secrets || export.signing_keys.is_empty() {
+        true => None,
+        false => Some(read_password(args.passphrase)?),
+    };
+    let options = ImportOptions {
+        exclude_users: args.exclude_users,
+        exclude_secrets: args.exclude_secrets,
+        on_conflict: args.on_conflict,
+    };
+    let report = export::import_realm(module, realm.uuid, &export, &options, passphrase.as_deref()).await?;
+
+    for change in &report.changes {
+        println!("{}", change);
+    }
+    if !report.skipped.is_empty() {
+        println!("Skipped {} existing object(s): {}", report.skipped.len(), report.skipped.join(", "));
+    }
+    println!("Imported {} object(s)", report.changes.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_format() {
+        assert_eq!(Format::resolve(None, None), Format::Json);
+        assert_eq!(Format::resolve(None, Some(Path::new("realm.yaml"))), Format::Yaml);
+        assert_eq!(Format::resolve(None, Some(Path::new("realm.yml"))), Format::Yaml);
+        assert_eq!(Format::resolve(None, Some(Path::new("realm.txt"))), Format::Json);
+        assert_eq!(Format::resolve(Some(Format::Json), Some(Path::new("realm.yaml"))), Format::Json);
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use super::user::find_user;
+use crate::di::MyModule;
+use crate::domain::client::ClientId;
+use crate::domain::realm::Realm;
+use crate::repository::client_repository::ClientRepository;
+use crate::repository::roles_repository::RoleRepository;
+use clap::Subcommand;
+use shaku::HasComponent;
+
+#[derive(Subcommand, Debug)]
+pub enum RoleCommand {
+    /// Assign a role to a user
+    Grant {
+        username: String,
+        role: String,
+        /// The client owning the role, a realm role when not given
+        #[arg(long)]
+        client: Option<String>,
+    },
+}
+
+pub async fn run(command: RoleCommand, module: &MyModule, realm: &Realm) -> Result<(), String> {
+    let role_repository: Arc<dyn RoleRepository> = module.resolve();
+
+    match command {
+        RoleCommand::Grant { username, role, client } => {
+            let user = find_user(module, realm, &username).await?;
+
+            let client_uuid = match client {
+                Some(client_id) => {
+                    let client_repository: Arc<dyn ClientRepository> = module.resolve();
+                    let client = client_repository
+                        .find_by_id(realm.uuid, &ClientId(client_id.clone()))
+                        .await
+                        .ok_or_else(|| format!("Client {} not found", client_id))?;
+                    Some(client.uuid)
+                }
+                None => None,
+            };
+
+            let role = role_repository
+                .find_by_name(realm.uuid, client_uuid, &role)
+                .await
+                .ok_or_else(|| format!("Role {} not found", role))?;
+            role_repository.assign(user.uuid, role.uuid).await?;
+            println!("Granted {} to {}", role.name, user.username);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use super::read_password;
+use crate::di::MyModule;
+use crate::domain::realm::Realm;
+use crate::domain::user::User;
+use crate::oidc::login::hash_password;
+use crate::repository::access_token_repository::AccessTokenRepository;
+use crate::repository::user_repository::{CreateUserParams, ListUsersParams, UserRepository};
+use clap::Subcommand;
+use shaku::HasComponent;
+
+const PAGE_SIZE: i64 = 100;
+
+#[derive(Subcommand, Debug)]
+pub enum UserCommand {
+    /// Create a user
+    Create {
+        username: String,
+        email: String,
+        /// Read from stdin when not given
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// List users
+    List {
+        /// Only users whose username contains the text, ignoring case
+        #[arg(long)]
+        username: Option<String>,
+    },
+    /// Set the password of a user
+    SetPassword {
+        username: String,
+        /// Read from stdin when not given
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Disable a user and revoke their tokens
+    Disable { username: String },
+    /// Enable a disabled user
+    Enable { username: String },
+}
+
+pub async fn run(command: UserCommand, module: &MyModule, realm: &Realm) -> Result<(), String> {
+    let user_repository: Arc<dyn UserRepository> = module.resolve();
+
+    match command {
+        UserCommand::Create { username, email, password } => {
+            let password_hash = hash_password(&read_password(password)?)?;
+            let user = user_repository
+                .create(CreateUserParams {
+                    realm_uuid: realm.uuid,
+                    username,
+                    email,
+                    password_hash,
+                    attributes: serde_json::json!({}),
+                })
+                .await?;
+            println!("Created user {} ({})", user.username, user.uuid);
+        }
+        UserCommand::List { username } => {
+            let mut offset = 0;
+            loop {
+                let page = user_repository
+                    .list(
+                        realm.uuid,
+                        ListUsersParams {
+                            username: username.clone(),
+                            offset,
+                            limit: PAGE_SIZE,
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+                for user in &page.users {
+                    let status = if user.enabled { "enabled" } else { "disabled" };
+                    println!("{}\t{}\t{}\t{}", user.uuid, user.username, user.email, status);
+                }
+
+                offset += PAGE_SIZE;
+                if offset >= page.total {
+                    break;
+                }
+            }
+        }
+        UserCommand::SetPassword { username, password } => {
+            let user = find_user(module, realm, &username).await?;
+            let password_hash = hash_password(&read_password(password)?)?;
+            user_repository.set_password_hash(realm.uuid, user.uuid, &password_hash).await?;
+            println!("Changed the password of {}", user.username);
+        }
+        UserCommand::Disable { username } => {
+            let user = find_user(module, realm, &username).await?;
+            user_repository.set_enabled(realm.uuid, user.uuid, false).await?;
+
+            let access_token_repository: Arc<dyn AccessTokenRepository> = module.resolve();
+            access_token_repository.revoke_by_user(user.uuid).await?;
+            println!("Disabled {}", user.username);
+        }
+        UserCommand::Enable { username } => {
+            let user = find_user(module, realm, &username).await?;
+            user_repository.set_enabled(realm.uuid, user.uuid, true).await?;
+            println!("Enabled {}", user.username);
+        }
+    }
+
+    Ok(())
+}
+
+/// Looks up a user of the realm by username or email address.
+pub(super) async fn find_user(module: &MyModule, realm: &Realm, username: &str) -> Result<User, String> {
+    let user_repository: Arc<dyn UserRepository> = module.resolve();
+
+    user_repository
+        .find_by_username_or_email(realm.uuid, username)
+        .await
+        .ok_or_else(|| format!("User {} not found", username))
+}
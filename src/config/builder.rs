@@ -62,10 +62,16 @@ impl<FS: FileSystem, E: Env> ConfigBuilder<FS, E> {
 
         // Apply YAML config if path is set
         if let Some(path) = self.yaml_path {
-            let yaml_source = YamlConfigSource::new(path, self.fs);
+            let yaml_source = YamlConfigSource::new(path, &self.fs);
             yaml_source.apply(&mut config)?;
         }
 
+        // Apply the provisioning seed file the YAML config points to
+        if let Some(path) = config.provisioning.file.clone() {
+            let seed_source = YamlConfigSource::new(path, &self.fs);
+            seed_source.apply(&mut config)?;
+        }
+
         // Apply environment variables
         let env_source = EnvConfigSource::new(self.env);
         env_source.apply(&mut config)?;
@@ -141,6 +147,33 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_provisioning_seed_file() {
+        let fs = MockFileSystem::new()
+            .with_file("config.yaml", "provisioning:\n  file: seed.yaml\n  roles: [{name: auditor}]")
+            .with_file("seed.yaml", "provisioning:\n  roles: [{name: viewer}]\n  prune: true");
+
+        let builder = ConfigBuilder::new(fs, TestEnv::new())
+            .with_yaml_file("config.yaml");
+
+        let config = builder.build().unwrap();
+        let roles = config.provisioning.roles.unwrap();
+        assert_eq!(roles.len(), 2);
+        assert_eq!(roles[1].name, "viewer");
+        assert_eq!(config.provisioning.prune, Some(true));
+    }
+
+    #[test]
+    fn test_missing_seed_file() {
+        let fs = MockFileSystem::new()
+            .with_file("config.yaml", "provisioning:\n  file: seed.yaml");
+
+        let result = ConfigBuilder::new(fs, TestEnv::new())
+            .with_yaml_file("config.yaml")
+            .build();
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_env_only_config() {
         let env = TestEnv::with_vars([
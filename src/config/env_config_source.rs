@@ -128,6 +128,37 @@ mod tests {
         assert_eq!(config.server.port, Some(9000));
     }
 
+    #[test]
+    fn test_bootstrap_env() {
+        let env = TestEnv::with_vars([
+            (
+                "VAULTON__BOOTSTRAP__ADMIN_USERNAME".to_string(),
+                "admin".to_string(),
+            ),
+            (
+                "VAULTON__BOOTSTRAP__ADMIN_PASSWORD".to_string(),
+                "supersecret".to_string(),
+            ),
+            (
+                "VAULTON__BOOTSTRAP__ADMIN_CLIENT_REDIRECT_URI".to_string(),
+                "https://admin.example.com/callback".to_string(),
+            ),
+        ]);
+
+        let source = EnvConfigSource::new(env);
+        let mut config = Config::default();
+
+        source.apply(&mut config).unwrap();
+
+        assert_eq!(config.bootstrap.admin_username, Some("admin".to_string()));
+        assert_eq!(config.bootstrap.admin_password, Some("supersecret".to_string()));
+        assert_eq!(config.bootstrap.admin_client_secret, None);
+        assert_eq!(
+            config.bootstrap.admin_client_redirect_uri,
+            Some("https://admin.example.com/callback".to_string())
+        );
+    }
+
     #[test]
     fn test_invalid_port() {
         let env = TestEnv::with_vars([(
@@ -1,6 +1,7 @@
 pub mod yaml_config_source;
 pub mod env_config_source;
 pub mod builder;
+pub mod provisioning;
 
 use std::any::TypeId;
 use crate::utils::merge::Merge;
@@ -8,6 +9,7 @@ use serde::Deserialize;
 use std::error::Error;
 
 use vaulton_derive::ConfigMetadata;
+use provisioning::ProvisioningConfig;
 
 pub trait ConfigMetadata {
     /// Returns a list of all possible config paths and their types
@@ -46,6 +48,12 @@ pub struct Config {
     pub oidc: OIDCConfig,
     #[serde(default)]
     pub postgres: PostgresConfig,
+    #[serde(default)]
+    pub bootstrap: BootstrapConfig,
+    #[serde(default)]
+    pub provisioning: ProvisioningConfig,
+    #[serde(default)]
+    pub lockout: LockoutConfig,
 }
 
 impl Default for Config {
@@ -54,6 +62,9 @@ impl Default for Config {
             server: ServerConfig::default(),
             oidc: OIDCConfig::default(),
             postgres: PostgresConfig::default(),
+            bootstrap: BootstrapConfig::default(),
+            provisioning: ProvisioningConfig::default(),
+            lockout: LockoutConfig::default(),
         }
     }
 }
@@ -63,6 +74,9 @@ impl Merge for Config {
         self.server.merge(other.server);
         self.oidc.merge(other.oidc);
         self.postgres.merge(other.postgres);
+        self.bootstrap.merge(other.bootstrap);
+        self.provisioning.merge(other.provisioning);
+        self.lockout.merge(other.lockout);
     }
 }
 
@@ -81,6 +95,10 @@ pub struct ServerConfig {
     /// Valid values are from 1 to 65535, though ports below 1024
     /// typically require root/administrator privileges
     pub port: Option<u16>,
+
+    /// Take the client address from the X-Forwarded-For header of a reverse proxy.
+    /// Only enable it behind a proxy that sets the header, clients can fake it otherwise.
+    pub trust_forwarded_for: Option<bool>,
 }
 
 impl Default for ServerConfig {
@@ -88,6 +106,7 @@ impl Default for ServerConfig {
         Self {
             bind_addr: Some("127.0.0.1".to_string()),
             port: Some(3000),
+            trust_forwarded_for: Some(false),
         }
     }
 }
@@ -97,6 +116,7 @@ impl Merge for ServerConfig {
     fn merge(&mut self, other: Self) {
         self.bind_addr.merge(other.bind_addr);
         self.port.merge(other.port);
+        self.trust_forwarded_for.merge(other.trust_forwarded_for);
     }
 }
 
@@ -107,12 +127,45 @@ pub struct OIDCConfig {
     /// This is used for generating URLs in OIDC discovery document
     /// Example: "https://auth.example.com"
     pub external_url: Option<String>,
+
+    /// Default access token lifetime in seconds, used for clients without their own lifetime
+    pub access_token_lifetime: Option<u64>,
+
+    /// Default ID token lifetime in seconds, used for clients without their own lifetime
+    pub id_token_lifetime: Option<u64>,
+
+    /// Allow anyone to register clients at the registration endpoint.
+    /// When disabled, registration requires an initial access token issued by an administrator.
+    pub open_registration: Option<bool>,
+
+    /// Let registered redirect URIs like "https://*.dev.example.com/cb" match any single
+    /// subdomain. Meant for development environments with per-branch deployments.
+    pub allow_wildcard_redirect_uris: Option<bool>,
+
+    /// Secret salt for pairwise subject identifiers. Pairwise clients are only supported when set.
+    /// Changing it changes every pairwise subject, so keep it stable once clients rely on it.
+    pub pairwise_salt: Option<String>,
+
+    /// Ask every user for a second factor, enrolling users without one when they log in.
+    /// Clients can also demand it by requesting the multi-factor authentication context class.
+    pub require_mfa: Option<bool>,
+
+    /// Secret of at least 32 characters the users' TOTP secrets are encrypted with. Multi-factor
+    /// authentication is only available when set. Changing it invalidates every enrolled authenticator.
+    pub mfa_encryption_key: Option<String>,
 }
 
 impl Default for OIDCConfig {
     fn default() -> Self {
         Self {
             external_url: Some("http://localhost:3000".to_string()),
+            access_token_lifetime: Some(3600),
+            id_token_lifetime: Some(3600),
+            open_registration: Some(false),
+            allow_wildcard_redirect_uris: Some(false),
+            pairwise_salt: None,
+            require_mfa: Some(false),
+            mfa_encryption_key: None,
         }
     }
 }
@@ -120,6 +173,13 @@ impl Default for OIDCConfig {
 impl Merge for OIDCConfig {
     fn merge(&mut self, other: Self) {
         self.external_url.merge(other.external_url);
+        self.access_token_lifetime.merge(other.access_token_lifetime);
+        self.id_token_lifetime.merge(other.id_token_lifetime);
+        self.open_registration.merge(other.open_registration);
+        self.allow_wildcard_redirect_uris.merge(other.allow_wildcard_redirect_uris);
+        self.pairwise_salt.merge(other.pairwise_salt);
+        self.require_mfa.merge(other.require_mfa);
+        self.mfa_encryption_key.merge(other.mfa_encryption_key);
     }
 }
 
@@ -173,6 +233,75 @@ impl Merge for PostgresConfig {
     }
 }
 
+/// Initial administrator created on startup, see `server::bootstrap`.
+/// Without an admin username, a one-time setup token is printed until an administrator exists.
+#[derive(Clone, Debug, Default, Deserialize, ConfigMetadata)]
+pub struct BootstrapConfig {
+    /// Username of the initial administrator, created with the "admin" role if missing
+    pub admin_username: Option<String>,
+    /// Email address of the initial administrator
+    pub admin_email: Option<String>,
+    /// Password of the initial administrator, only used when the user is created
+    pub admin_password: Option<String>,
+    /// Secret of the built-in admin client. A random secret is generated and printed once
+    /// when unset.
+    pub admin_client_secret: Option<String>,
+    /// Redirect URI of the admin console. Lets administrators sign in to the built-in admin
+    /// client with the authorization code flow and PKCE; client credentials only when unset.
+    pub admin_client_redirect_uri: Option<String>,
+}
+
+impl Merge for BootstrapConfig {
+    fn merge(&mut self, other: Self) {
+        self.admin_username.merge(other.admin_username);
+        self.admin_email.merge(other.admin_email);
+        self.admin_password.merge(other.admin_password);
+        self.admin_client_secret.merge(other.admin_client_secret);
+        self.admin_client_redirect_uri.merge(other.admin_client_redirect_uri);
+    }
+}
+
+/// Brute-force protection of password logins, see `oidc::lockout`.
+#[derive(Clone, Debug, Deserialize, ConfigMetadata)]
+pub struct LockoutConfig {
+    /// Failed logins of a user before the user is locked out
+    pub max_failures: Option<u32>,
+    /// Failed logins from one client address before the address is locked out
+    pub max_failures_per_ip: Option<u32>,
+    /// Seconds the user's next attempt is refused after a failed login, doubling with every further failure
+    pub failure_delay: Option<u64>,
+    /// Seconds a lockout lasts
+    pub lockout_duration: Option<u64>,
+    /// Seconds without a failed login after which the earlier failures are forgotten
+    pub failure_reset: Option<u64>,
+    /// Lock users out until an administrator unlocks them instead of for the lockout duration.
+    /// Client addresses are only ever locked out for the lockout duration.
+    pub permanent_lockout: Option<bool>,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: Some(5),
+            max_failures_per_ip: Some(50),
+            failure_delay: Some(1),
+            lockout_duration: Some(900),
+            failure_reset: Some(3600),
+            permanent_lockout: Some(false),
+        }
+    }
+}
+
+impl Merge for LockoutConfig {
+    fn merge(&mut self, other: Self) {
+        self.max_failures.merge(other.max_failures);
+        self.max_failures_per_ip.merge(other.max_failures_per_ip);
+        self.failure_delay.merge(other.failure_delay);
+        self.lockout_duration.merge(other.lockout_duration);
+        self.failure_reset.merge(other.failure_reset);
+        self.permanent_lockout.merge(other.permanent_lockout);
+    }
+}
 
 /// Trait for loading static configuration from different sources
 pub trait ConfigSource {
@@ -195,6 +324,11 @@ mod tests {
     fn test_oidc_config_default() {
         let config = OIDCConfig::default();
         assert_eq!(config.external_url, Some("http://localhost:3000".to_string()));
+        assert_eq!(config.access_token_lifetime, Some(3600));
+        assert_eq!(config.id_token_lifetime, Some(3600));
+        assert_eq!(config.open_registration, Some(false));
+        assert_eq!(config.allow_wildcard_redirect_uris, Some(false));
+        assert_eq!(config.pairwise_salt, None);
     }
 
     #[test]
@@ -212,11 +346,28 @@ mod tests {
             server: ServerConfig {
                 bind_addr: Some("0.0.0.0".to_string()),
                 port: Some(8080),
+                trust_forwarded_for: None,
             },
             oidc: OIDCConfig {
                 external_url: Some("https://example.com".to_string()),
+                access_token_lifetime: Some(300),
+                id_token_lifetime: None,
+                open_registration: Some(true),
+                allow_wildcard_redirect_uris: None,
+                pairwise_salt: Some("salt".to_string()),
+                require_mfa: None,
+                mfa_encryption_key: None,
             },
             postgres: PostgresConfig::default(),
+            bootstrap: BootstrapConfig {
+                admin_username: Some("admin".to_string()),
+                ..Default::default()
+            },
+            provisioning: ProvisioningConfig::default(),
+            lockout: LockoutConfig {
+                max_failures: Some(3),
+                ..Default::default()
+            },
         };
 
         base.merge(other);
@@ -224,6 +375,14 @@ mod tests {
         assert_eq!(base.server.bind_addr, Some("0.0.0.0".to_string()));
         assert_eq!(base.server.port, Some(8080));
         assert_eq!(base.oidc.external_url, Some("https://example.com".to_string()));
+        assert_eq!(base.oidc.access_token_lifetime, Some(300));
+        assert_eq!(base.oidc.id_token_lifetime, Some(3600));
+        assert_eq!(base.oidc.open_registration, Some(true));
+        assert_eq!(base.oidc.pairwise_salt, Some("salt".to_string()));
+        assert_eq!(base.bootstrap.admin_username, Some("admin".to_string()));
+        assert_eq!(base.bootstrap.admin_password, None);
+        assert_eq!(base.lockout.max_failures, Some(3));
+        assert_eq!(base.lockout.lockout_duration, Some(900));
     }
 
     #[test]
@@ -232,6 +391,7 @@ mod tests {
         let other = ServerConfig {
             bind_addr: Some("0.0.0.0".to_string()),
             port: None,
+            trust_forwarded_for: None,
         };
 
         base.merge(other);
@@ -0,0 +1,183 @@
+use std::any::TypeId;
+use crate::config::{ConfigMetadata, ConfigPath};
+use crate::utils::merge::Merge;
+use serde::Deserialize;
+
+/// Clients, roles, groups and users declared in the configuration, reconciled into the
+/// database on startup. The declarations can also live in a separate seed file, which has the
+/// same layout as the configuration file.
+#[derive(Clone, Debug, Default, Deserialize, ConfigMetadata)]
+pub struct ProvisioningConfig {
+    /// Path of a seed file with more declarations, e.g. "seed.yaml"
+    pub file: Option<String>,
+    /// Delete provisioned objects that are no longer declared. Objects created through the
+    /// admin API are never deleted.
+    pub prune: Option<bool>,
+    /// Only print the changes instead of applying them
+    pub dry_run: Option<bool>,
+    pub clients: Option<Vec<ClientSpec>>,
+    pub roles: Option<Vec<RoleSpec>>,
+    pub groups: Option<Vec<GroupSpec>>,
+    pub users: Option<Vec<UserSpec>>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ClientSpec {
+    pub client_id: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    /// Scopes the client may request, "openid" when unset
+    pub scopes: Option<Vec<String>>,
+    /// "authorization_code" when unset
+    pub grant_types: Option<Vec<String>>,
+    /// A public client without a secret
+    #[serde(default)]
+    pub public: bool,
+    #[serde(default)]
+    pub first_party: bool,
+    /// Secret of a confidential client. A random secret is generated and printed once when unset.
+    pub secret: Option<String>,
+}
+
+/// A realm role, or a client role when `client` is set.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RoleSpec {
+    pub name: String,
+    pub description: Option<String>,
+    /// The client_id of the client owning the role
+    pub client: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GroupSpec {
+    /// The full path, e.g. "/engineering/backend". Parent groups must be declared or exist.
+    pub path: String,
+    #[serde(default = "empty_object")]
+    pub attributes: serde_json::Value,
+    /// Names of the realm roles mapped to the group
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+/// A user, typically a service account.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct UserSpec {
+    pub username: String,
+    pub email: String,
+    /// Only used when the user is created. Users without one cannot log in until an
+    /// administrator sets a password.
+    pub password: Option<String>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    #[serde(default = "empty_object")]
+    pub attributes: serde_json::Value,
+    /// Names of the realm roles assigned to the user
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Paths of the groups the user is a member of
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+fn empty_object() -> serde_json::Value {
+    serde_json::json!({})
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl ProvisioningConfig {
+    /// Whether nothing is declared.
+    pub fn is_empty(&self) -> bool {
+        self.clients.as_ref().is_none_or(Vec::is_empty)
+            && self.roles.as_ref().is_none_or(Vec::is_empty)
+            && self.groups.as_ref().is_none_or(Vec::is_empty)
+            && self.users.as_ref().is_none_or(Vec::is_empty)
+    }
+}
+
+/// Declarations add up across sources, so a seed file extends the configuration file.
+impl Merge for ProvisioningConfig {
+    fn merge(&mut self, other: Self) {
+        self.file.merge(other.file);
+        self.prune.merge(other.prune);
+        self.dry_run.merge(other.dry_run);
+        append(&mut self.clients, other.clients);
+        append(&mut self.roles, other.roles);
+        append(&mut self.groups, other.groups);
+        append(&mut self.users, other.users);
+    }
+}
+
+fn append<T>(list: &mut Option<Vec<T>>, other: Option<Vec<T>>) {
+    if let Some(other) = other {
+        list.get_or_insert_with(Vec::new).extend(other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: &str = r#"
+clients:
+  - client_id: app
+    redirect_uris: ["https://app.example.com/cb"]
+    first_party: true
+roles:
+  - name: auditor
+  - name: reports:read
+    client: app
+groups:
+  - path: /engineering
+    attributes: {cost_center: "4711"}
+    roles: [auditor]
+users:
+  - username: ci-bot
+    email: ci-bot@example.com
+    groups: [/engineering]
+"#;
+
+    #[test]
+    fn test_parse_declarations() {
+        let config: ProvisioningConfig = serde_yaml::from_str(SEED).unwrap();
+
+        let clients = config.clients.unwrap();
+        assert_eq!(clients[0].client_id, "app");
+        assert!(clients[0].first_party);
+        assert!(!clients[0].public);
+        assert_eq!(clients[0].scopes, None);
+
+        let roles = config.roles.unwrap();
+        assert_eq!(roles[1].client.as_deref(), Some("app"));
+
+        let groups = config.groups.unwrap();
+        assert_eq!(groups[0].attributes, serde_json::json!({"cost_center": "4711"}));
+
+        let users = config.users.unwrap();
+        assert!(users[0].enabled);
+        assert_eq!(users[0].attributes, serde_json::json!({}));
+        assert_eq!(users[0].groups, vec!["/engineering".to_string()]);
+    }
+
+    #[test]
+    fn test_merge_appends_declarations() {
+        let mut config: ProvisioningConfig = serde_yaml::from_str(SEED).unwrap();
+        let seed: ProvisioningConfig = serde_yaml::from_str("roles: [{name: viewer}]\nprune: true").unwrap();
+
+        config.merge(seed);
+
+        let names = config.roles.unwrap().into_iter().map(|r| r.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["auditor", "reports:read", "viewer"]);
+        assert_eq!(config.clients.unwrap().len(), 1);
+        assert_eq!(config.prune, Some(true));
+    }
+
+    #[test]
+    fn test_is_empty() {
+        assert!(ProvisioningConfig::default().is_empty());
+        assert!(!serde_yaml::from_str::<ProvisioningConfig>(SEED).unwrap().is_empty());
+    }
+}
@@ -12,11 +12,17 @@ pub struct DatabaseImpl {
     pool: PgPool,
 }
 
-pub async fn connect_to_db(config: &crate::config::PostgresConfig) -> Result<PgPool, sqlx::Error> {
-    let pool = PgPoolOptions::new()
+/// Connects to the database without touching the schema.
+pub async fn connect(config: &crate::config::PostgresConfig) -> Result<PgPool, sqlx::Error> {
+    PgPoolOptions::new()
         .max_connections(5)
         .acquire_timeout(Duration::from_secs(3))
-        .connect(&config.connection_string()).await?;
+        .connect(&config.connection_string()).await
+}
+
+/// Connects to the database and applies pending migrations.
+pub async fn connect_to_db(config: &crate::config::PostgresConfig) -> Result<PgPool, sqlx::Error> {
+    let pool = connect(config).await?;
 
     sqlx::migrate!("./migrations")
         .run(&pool)
@@ -12,7 +12,31 @@ module! {
             crate::repository::client_repository::PostgresClientRepository,
             crate::repository::user_repository::PostgresUserRepository,
             crate::repository::roles_repository::PostgresRoleRepository,
-            crate::repository::auth_request_repository::InMemoryAuthRequestRepository,        ],
+            crate::repository::consent_repository::PostgresConsentRepository,
+            crate::repository::signing_key_repository::PostgresSigningKeyRepository,
+            crate::repository::access_token_repository::PostgresAccessTokenRepository,
+            crate::repository::api_resource_repository::PostgresApiResourceRepository,
+            crate::repository::initial_access_token_repository::PostgresInitialAccessTokenRepository,
+            crate::repository::protocol_mapper_repository::PostgresProtocolMapperRepository,
+            crate::repository::group_repository::PostgresGroupRepository,
+            crate::repository::provisioning_repository::PostgresProvisioningRepository,
+            crate::repository::realm_repository::PostgresRealmRepository,
+            crate::repository::organization_repository::PostgresOrganizationRepository,
+            crate::repository::mfa_repository::PostgresMfaRepository,
+            crate::repository::passkey_repository::PostgresPasskeyRepository,
+            crate::repository::login_failure_repository::PostgresLoginFailureRepository,
+            crate::repository::auth_request_repository::InMemoryAuthRequestRepository,
+            crate::repository::authorization_code_repository::InMemoryAuthorizationCodeRepository,
+            crate::repository::setup_token_repository::InMemorySetupTokenRepository,        ],
         providers = []
     }
-}
\ No newline at end of file
+}
+
+/// Creates the module with every component on top of the database pool.
+pub fn create_module(pool: sqlx::PgPool) -> MyModule {
+    MyModule::builder()
+        .with_component_parameters::<crate::db::DatabaseImpl>(crate::db::DatabaseImplParameters {
+            pool,
+        })
+        .build()
+}
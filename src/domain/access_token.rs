@@ -0,0 +1,78 @@
+use std::fmt;
+use std::str::FromStr;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::domain::client::ClientId;
+
+/// How access tokens are handed out to a client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessTokenFormat {
+    /// Signed JWT access tokens following RFC 9068, validated locally by resource servers
+    Jwt,
+    /// Random reference tokens, validated through introspection
+    Opaque,
+}
+
+impl fmt::Display for AccessTokenFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jwt => write!(f, "jwt"),
+            Self::Opaque => write!(f, "opaque"),
+        }
+    }
+}
+
+impl FromStr for AccessTokenFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jwt" => Ok(Self::Jwt),
+            "opaque" => Ok(Self::Opaque),
+            other => Err(format!("unknown access token format: {}", other)),
+        }
+    }
+}
+
+/// An issued access token. The `uuid` doubles as the `jti` of JWT access tokens.
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub uuid: Uuid,
+    pub format: AccessTokenFormat,
+    pub client_uuid: Uuid,
+    pub client_id: ClientId,
+    /// The resource owner, absent for tokens issued through the client credentials grant
+    pub user_id: Option<Uuid>,
+    pub scopes: Vec<String>,
+    pub audience: Vec<String>,
+    /// Claims requested individually for the UserInfo response
+    pub claims: Vec<String>,
+    /// The organization the user picked when logging in
+    pub organization_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AccessToken {
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at > Utc::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_round_trip() {
+        for format in [AccessTokenFormat::Jwt, AccessTokenFormat::Opaque] {
+            assert_eq!(format.to_string().parse::<AccessTokenFormat>(), Ok(format));
+        }
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        assert!("reference".parse::<AccessTokenFormat>().is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::domain::access_token::AccessTokenFormat;
+
+/// An API that access tokens can be audience-restricted to with the `resource` parameter (RFC 8707).
+#[derive(Debug, Clone)]
+pub struct ApiResource {
+    pub uuid: Uuid,
+    /// Absolute URI identifying the API, used as the `aud` of its access tokens
+    pub identifier: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Overrides the token format of the requesting client when set
+    pub access_token_format: Option<AccessTokenFormat>,
+    /// Overrides the token lifetime of the requesting client when set, in seconds
+    pub access_token_lifetime: Option<i32>,
+    /// JWS algorithm used to sign JWT access tokens for this API
+    pub signing_algorithm: String,
+    pub scopes: Vec<ApiResourceScope>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiResourceScope {
+    pub uuid: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+impl ApiResource {
+    pub fn owns_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s.name == scope)
+    }
+}
@@ -1,5 +1,8 @@
+use url::Url;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use crate::domain::access_token::AccessTokenFormat;
+use crate::domain::redirect_uri::{self, RedirectUriKind};
 
 #[derive(Clone, Debug)]
 pub struct ClientId(pub String);
@@ -11,16 +14,163 @@ pub struct Client {
     pub secret_hash: Option<Vec<u8>>,
     pub redirect_uris: Vec<String>,
     pub allowed_scopes: Vec<String>,
+    /// Human readable name and logo of the client, shown on the consent page
+    pub client_name: Option<String>,
+    pub logo_uri: Option<String>,
+    /// "web" or "native"; only public native apps may use private-use URI scheme redirects
+    pub application_type: String,
+    /// "public" or "pairwise"; pairwise clients get a subject identifier of their own for each user
+    pub subject_type: String,
+    /// Document listing the redirect URIs that share the client's pairwise subject identifiers
+    pub sector_identifier_uri: Option<String>,
+    /// Grant types the client may use at the token endpoint
+    pub grant_types: Vec<String>,
+    /// The only authentication method accepted from the client, any supported method when unset
+    pub token_endpoint_auth_method: Option<String>,
+    /// Hash of the registration access token of dynamically registered clients
+    pub registration_token_hash: Option<Vec<u8>>,
+    /// First-party clients are operated by us and skip the consent page
+    pub first_party: bool,
+    /// Whether ID tokens carry the user's role claims too, not only access tokens
+    pub id_token_role_claims: bool,
+    pub access_token_format: AccessTokenFormat,
+    /// Access token lifetime in seconds, falls back to the server default when unset
+    pub access_token_lifetime: Option<i32>,
+    /// ID token lifetime in seconds, falls back to the server default when unset
+    pub id_token_lifetime: Option<i32>,
+    /// JWS algorithm the client's ID tokens are signed with
+    pub id_token_signed_response_alg: String,
+    /// The client's public keys as a JWKS document, for encrypting tokens addressed to it
+    pub jwks: Option<serde_json::Value>,
+    /// JWE algorithms for encrypted ID tokens, unencrypted when unset
+    pub id_token_encrypted_response_alg: Option<String>,
+    pub id_token_encrypted_response_enc: Option<String>,
+    /// JWE algorithms for encrypted UserInfo responses, plain JSON when unset
+    pub userinfo_encrypted_response_alg: Option<String>,
+    pub userinfo_encrypted_response_enc: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
 impl Client {
-    pub fn validate_redirect_uri(&self, uri: &str) -> bool {
-        self.redirect_uris.contains(&uri.to_string())
+    /// Checks the requested redirect URI against the registered ones, see [`redirect_uri::matches`].
+    /// Wildcard patterns only match when `allow_wildcards` is set by the server configuration.
+    pub fn validate_redirect_uri(&self, uri: &str, allow_wildcards: bool) -> bool {
+        self.redirect_uris.iter().any(|registered| match redirect_uri::classify(registered) {
+            Some(RedirectUriKind::PrivateUse) if !(self.is_native() && self.is_public()) => false,
+            Some(_) => redirect_uri::matches(registered, uri, allow_wildcards),
+            None => false,
+        })
     }
     
     pub fn validate_scopes(&self, scopes: &Vec<&str>) -> bool {
         scopes.iter().all(|s| self.allowed_scopes.contains(&s.to_string()))
     }
-}
\ No newline at end of file
+
+    pub fn requires_consent(&self) -> bool {
+        !self.first_party
+    }
+
+    pub fn allows_grant_type(&self, grant_type: &str) -> bool {
+        self.grant_types.iter().any(|g| g == grant_type)
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.application_type == "native"
+    }
+
+    /// Public clients have no secret and must use PKCE.
+    pub fn is_public(&self) -> bool {
+        self.secret_hash.is_none()
+    }
+
+    pub fn is_pairwise(&self) -> bool {
+        self.subject_type == "pairwise"
+    }
+
+    /// The sector a pairwise subject identifier is computed for: the host of the sector
+    /// identifier URI, or else the host all redirect URIs share (OpenID Connect Core 1.0,
+    /// section 8.1). None if the redirect URIs are spread over several hosts.
+    pub fn sector_identifier(&self) -> Option<String> {
+        if let Some(uri) = &self.sector_identifier_uri {
+            return sector_host(uri);
+        }
+
+        let mut hosts = self.redirect_uris.iter().map(|uri| sector_host(uri));
+        let first = hosts.next()??;
+        hosts.all(|host| host.as_deref() == Some(first.as_str())).then_some(first)
+    }
+}
+
+/// The host of a URI, or its scheme for private-use URI schemes without a host.
+pub fn sector_host(uri: &str) -> Option<String> {
+    let url = Url::parse(uri).ok()?;
+    Some(url.host_str().unwrap_or(url.scheme()).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(application_type: &str, secret_hash: Option<Vec<u8>>, redirect_uris: &[&str]) -> Client {
+        Client {
+            uuid: Uuid::new_v4(),
+            id: ClientId("app".to_string()),
+            secret_hash,
+            redirect_uris: redirect_uris.iter().map(|s| s.to_string()).collect(),
+            allowed_scopes: vec![],
+            client_name: None,
+            logo_uri: None,
+            application_type: application_type.to_string(),
+            subject_type: "public".to_string(),
+            sector_identifier_uri: None,
+            grant_types: vec!["authorization_code".to_string()],
+            token_endpoint_auth_method: None,
+            registration_token_hash: None,
+            first_party: false,
+            id_token_role_claims: false,
+            access_token_format: AccessTokenFormat::Jwt,
+            access_token_lifetime: None,
+            id_token_lifetime: None,
+            id_token_signed_response_alg: "RS256".to_string(),
+            jwks: None,
+            id_token_encrypted_response_alg: None,
+            id_token_encrypted_response_enc: None,
+            userinfo_encrypted_response_alg: None,
+            userinfo_encrypted_response_enc: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_private_use_schemes_only_for_public_native_clients() {
+        let uris = ["com.example.app:/cb", "http://127.0.0.1/cb"];
+
+        let native = client("native", None, &uris);
+        assert!(native.validate_redirect_uri("com.example.app:/cb", false));
+        assert!(native.validate_redirect_uri("http://127.0.0.1:49152/cb", false));
+
+        let confidential = client("native", Some(vec![1]), &uris);
+        assert!(!confidential.validate_redirect_uri("com.example.app:/cb", false));
+        assert!(confidential.validate_redirect_uri("http://127.0.0.1:49152/cb", false));
+
+        let web = client("web", None, &uris);
+        assert!(!web.validate_redirect_uri("com.example.app:/cb", false));
+    }
+
+    #[test]
+    fn test_sector_identifier() {
+        let mut web = client("web", None, &["https://a.example.com/cb", "https://a.example.com/other"]);
+        assert_eq!(web.sector_identifier().as_deref(), Some("a.example.com"));
+
+        web.redirect_uris.push("https://b.example.com/cb".to_string());
+        assert_eq!(web.sector_identifier(), None);
+
+        web.sector_identifier_uri = Some("https://example.com/sector.json".to_string());
+        assert_eq!(web.sector_identifier().as_deref(), Some("example.com"));
+
+        let native = client("native", None, &["com.example.app:/cb"]);
+        assert_eq!(native.sector_identifier().as_deref(), Some("com.example.app"));
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::domain::client::ClientId;
+
+/// A user's persisted decision to grant a client access to a set of scopes.
+#[derive(Debug, Clone)]
+pub struct Consent {
+    pub uuid: Uuid,
+    pub user_id: Uuid,
+    pub client_uuid: Uuid,
+    pub client_id: ClientId,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Consent {
+    /// Returns true if every requested scope has already been granted.
+    pub fn covers(&self, scopes: &[&str]) -> bool {
+        scopes.iter().all(|s| self.scopes.iter().any(|granted| granted == s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn consent(scopes: &[&str]) -> Consent {
+        Consent {
+            uuid: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            client_uuid: Uuid::new_v4(),
+            client_id: ClientId("app".to_string()),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_covers_exact_scopes() {
+        assert!(consent(&["openid", "email"]).covers(&["openid", "email"]));
+        assert!(consent(&["openid", "email"]).covers(&["email", "openid"]));
+    }
+
+    #[test]
+    fn test_covers_fewer_scopes() {
+        assert!(consent(&["openid", "email", "profile"]).covers(&["openid", "profile"]));
+    }
+
+    #[test]
+    fn test_does_not_cover_more_scopes() {
+        assert!(!consent(&["openid"]).covers(&["openid", "email"]));
+        assert!(!consent(&[]).covers(&["openid"]));
+    }
+
+    #[test]
+    fn test_covers_no_scopes() {
+        assert!(consent(&[]).covers(&[]));
+        assert!(consent(&["openid"]).covers(&[]));
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// A node in the group tree. Members inherit the group's role mappings and attributes.
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub uuid: Uuid,
+    /// The parent group, None for top-level groups
+    pub parent_uuid: Option<Uuid>,
+    pub name: String,
+    /// The names from the top-level group down to this one, like "/engineering/backend"
+    pub path: String,
+    /// Custom attributes as a JSON object
+    pub attributes: Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Group {
+    /// The number of groups on the path, 1 for top-level groups.
+    pub fn depth(&self) -> usize {
+        self.path.matches('/').count()
+    }
+}
+
+/// Checks a group name: not empty and without slashes, which separate the parts of paths.
+pub fn check_group_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name.contains('/') {
+        return Err(format!("Invalid group name '{}'", name));
+    }
+    Ok(())
+}
+
+/// Merges the attributes of the user's groups and their ancestors. Nested groups override their
+/// ancestors; between groups at the same depth, the one with the later path wins.
+pub fn inherited_attributes(groups: &[Group]) -> Map<String, Value> {
+    let mut ordered = groups.iter().collect::<Vec<_>>();
+    ordered.sort_by(|a, b| a.depth().cmp(&b.depth()).then_with(|| a.path.cmp(&b.path)));
+
+    let mut attributes = Map::new();
+    for group in ordered {
+        if let Value::Object(own) = &group.attributes {
+            attributes.extend(own.clone());
+        }
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn group(path: &str, attributes: Value) -> Group {
+        Group {
+            uuid: Uuid::new_v4(),
+            parent_uuid: None,
+            name: path.rsplit('/').next().unwrap().to_string(),
+            path: path.to_string(),
+            attributes,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_depth() {
+        assert_eq!(group("/engineering", json!({})).depth(), 1);
+        assert_eq!(group("/engineering/backend", json!({})).depth(), 2);
+    }
+
+    #[test]
+    fn test_check_group_name() {
+        assert!(check_group_name("backend").is_ok());
+        assert!(check_group_name("").is_err());
+        assert!(check_group_name(" ").is_err());
+        assert!(check_group_name("a/b").is_err());
+    }
+
+    #[test]
+    fn test_nested_groups_override_ancestors() {
+        let groups = [
+            group("/engineering/backend", json!({"cost_center": "4711", "on_call": true})),
+            group("/engineering", json!({"cost_center": "4700", "department": "engineering"})),
+        ];
+
+        assert_eq!(
+            Value::Object(inherited_attributes(&groups)),
+            json!({"cost_center": "4711", "department": "engineering", "on_call": true})
+        );
+    }
+
+    #[test]
+    fn test_groups_at_same_depth_are_ordered_by_path() {
+        let groups = [
+            group("/sales", json!({"region": "emea"})),
+            group("/marketing", json!({"region": "apac"})),
+        ];
+
+        assert_eq!(Value::Object(inherited_attributes(&groups)), json!({"region": "emea"}));
+    }
+}
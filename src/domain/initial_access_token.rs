@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A token handed out by administrators that allows registering clients when open
+/// registration is disabled. Only the hash of the token is stored.
+#[derive(Debug, Clone)]
+pub struct InitialAccessToken {
+    pub uuid: Uuid,
+    /// No expiry when unset
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Number of registrations left, unlimited when unset
+    pub remaining_uses: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,38 @@
+use std::net::IpAddr;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// What failed logins are counted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureSubject {
+    User(Uuid),
+    /// The client address the login came from
+    Ip(IpAddr),
+}
+
+impl FailureSubject {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FailureSubject::User(_) => "user",
+            FailureSubject::Ip(_) => "ip",
+        }
+    }
+
+    pub fn key(&self) -> String {
+        match self {
+            FailureSubject::User(user_id) => user_id.to_string(),
+            FailureSubject::Ip(address) => address.to_string(),
+        }
+    }
+}
+
+/// The failed logins of a user or client address.
+#[derive(Debug, Clone)]
+pub struct LoginFailures {
+    /// Failures since the last lockout or successful login
+    pub failures: i32,
+    pub last_failure_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+    /// Locked out until an administrator unlocks the user
+    pub locked_permanently: bool,
+}
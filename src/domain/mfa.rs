@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A user's TOTP authenticator. The secret is only stored encrypted.
+#[derive(Debug, Clone)]
+pub struct TotpCredential {
+    pub user_id: Uuid,
+    pub encrypted_secret: String,
+    /// Set once the user entered a valid code during enrolment
+    pub confirmed_at: Option<DateTime<Utc>>,
+    /// The time step of the last accepted code
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TotpCredential {
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
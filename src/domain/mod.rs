@@ -1,3 +1,16 @@
 pub mod client;
 pub mod user;
 pub mod role;
+pub mod consent;
+pub mod access_token;
+pub mod signing_key;
+pub mod api_resource;
+pub mod initial_access_token;
+pub mod redirect_uri;
+pub mod protocol_mapper;
+pub mod group;
+pub mod realm;
+pub mod organization;
+pub mod mfa;
+pub mod passkey;
+pub mod login_failure;
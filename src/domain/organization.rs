@@ -0,0 +1,138 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A company using the realm. Its members log in as themselves and pick the organization
+/// they act for.
+#[derive(Debug, Clone)]
+pub struct Organization {
+    pub uuid: Uuid,
+    pub name: String,
+    /// Shown to users picking an organization, the name when unset
+    pub display_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Organization {
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// An email domain of an organization. Users of verified domains join automatically.
+#[derive(Debug, Clone)]
+pub struct OrganizationDomain {
+    pub domain: String,
+    pub verified: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A user's membership in an organization, with the roles they hold there.
+#[derive(Debug, Clone)]
+pub struct Membership {
+    pub organization: Organization,
+    pub user_id: Uuid,
+    pub roles: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An invitation to join an organization, accepted by signing in with the invited email
+/// address. Only the hash of the token in the invitation link is stored.
+#[derive(Debug, Clone)]
+pub struct Invitation {
+    pub uuid: Uuid,
+    pub organization_uuid: Uuid,
+    pub email: String,
+    /// The roles the user gets when accepting
+    pub roles: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Invitation {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now()
+    }
+
+    /// Whether the invitation was sent to the email address, ignoring case.
+    pub fn is_for(&self, email: &str) -> bool {
+        self.email.eq_ignore_ascii_case(email)
+    }
+}
+
+/// The lowercase domain of an email address, None if there is none.
+pub fn email_domain(email: &str) -> Option<String> {
+    let (local, domain) = email.rsplit_once('@')?;
+    (!local.is_empty() && !domain.is_empty()).then(|| domain.to_ascii_lowercase())
+}
+
+/// Normalizes and checks a domain name: dot-separated labels of letters, digits and dashes.
+pub fn normalize_domain(domain: &str) -> Result<String, String> {
+    let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+    let valid_label = |label: &str| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+
+    if !domain.contains('.') || !domain.split('.').all(valid_label) {
+        return Err(format!("Invalid domain '{}'", domain));
+    }
+    Ok(domain)
+}
+
+/// Checks organization role names: not empty and without whitespace, so they fit in a claim.
+pub fn check_organization_roles(roles: &[String]) -> Result<(), String> {
+    match roles.iter().find(|r| r.is_empty() || r.chars().any(char::is_whitespace)) {
+        Some(role) => Err(format!("Invalid organization role '{}'", role)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_email_domain() {
+        assert_eq!(email_domain("alice@Example.com"), Some("example.com".to_string()));
+        assert_eq!(email_domain("a@b@corp.example.com"), Some("corp.example.com".to_string()));
+        assert_eq!(email_domain("alice"), None);
+        assert_eq!(email_domain("@example.com"), None);
+        assert_eq!(email_domain("alice@"), None);
+    }
+
+    #[test]
+    fn test_normalize_domain() {
+        assert_eq!(normalize_domain("Example.COM."), Ok("example.com".to_string()));
+        assert_eq!(normalize_domain("eu-west.corp.example.com"), Ok("eu-west.corp.example.com".to_string()));
+        assert!(normalize_domain("localhost").is_err());
+        assert!(normalize_domain("-corp.example.com").is_err());
+        assert!(normalize_domain("example..com").is_err());
+        assert!(normalize_domain("exa mple.com").is_err());
+    }
+
+    #[test]
+    fn test_check_organization_roles() {
+        assert!(check_organization_roles(&["owner".to_string(), "billing:admin".to_string()]).is_ok());
+        assert!(check_organization_roles(&[]).is_ok());
+        assert!(check_organization_roles(&[String::new()]).is_err());
+        assert!(check_organization_roles(&["billing admin".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_invitation_is_for() {
+        let invitation = Invitation {
+            uuid: Uuid::new_v4(),
+            organization_uuid: Uuid::new_v4(),
+            email: "Bob@Example.com".to_string(),
+            roles: vec![],
+            expires_at: Utc::now(),
+            created_at: Utc::now(),
+        };
+
+        assert!(invitation.is_for("bob@example.com"));
+        assert!(!invitation.is_for("alice@example.com"));
+    }
+}
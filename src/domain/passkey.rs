@@ -0,0 +1,26 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A WebAuthn credential registered by a user.
+#[derive(Debug, Clone)]
+pub struct Passkey {
+    pub uuid: Uuid,
+    pub user_id: Uuid,
+    pub credential_id: Vec<u8>,
+    /// The credential public key as a COSE key
+    pub public_key: Vec<u8>,
+    pub algorithm: i32,
+    /// The authenticator's signature counter at its last use
+    pub sign_count: i64,
+    /// The authenticator model, zero when not attested
+    pub aaguid: Uuid,
+    pub attestation_format: String,
+    /// How the browser can reach the authenticator, like "usb" or "internal"
+    pub transports: Vec<String>,
+    /// Whether the credential may be synced to other devices
+    pub backup_eligible: bool,
+    /// A label telling the user's passkeys apart
+    pub name: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// The realm everything belongs to unless another realm is addressed.
+pub const DEFAULT_REALM: &str = "master";
+
+/// An isolated tenant with its own issuer, clients, users, roles, groups and signing keys.
+#[derive(Debug, Clone)]
+pub struct Realm {
+    pub uuid: Uuid,
+    pub name: String,
+    /// The issuer and base URL of the realm's endpoints, derived from the server's when unset
+    pub external_url: Option<String>,
+    /// A host name the realm is served on, besides the /realms/{name} path
+    pub host: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Realm {
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT_REALM
+    }
+
+    /// The realm's own external URL, else the server's for the default realm and the server's
+    /// followed by /realms/{name} for the others.
+    pub fn external_url(&self, server_url: &str) -> String {
+        match &self.external_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None if self.is_default() => server_url.to_string(),
+            None => format!("{}/realms/{}", server_url.trim_end_matches('/'), self.name),
+        }
+    }
+}
+
+/// Checks a realm name: lowercase letters, digits, dashes and underscores, so it fits in a path.
+pub fn check_realm_name(name: &str) -> Result<(), String> {
+    let mut chars = name.chars();
+    let valid_start = chars.next().is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+    if !valid_start || !chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_') {
+        return Err(format!("Invalid realm name '{}'", name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn realm(name: &str, external_url: Option<&str>) -> Realm {
+        Realm {
+            uuid: Uuid::new_v4(),
+            name: name.to_string(),
+            external_url: external_url.map(str::to_string),
+            host: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_external_url() {
+        let server_url = "https://auth.example.com";
+
+        assert_eq!(realm(DEFAULT_REALM, None).external_url(server_url), server_url);
+        assert_eq!(realm("sales", None).external_url(server_url), "https://auth.example.com/realms/sales");
+        assert_eq!(
+            realm("sales", Some("https://sales.example.com/")).external_url(server_url),
+            "https://sales.example.com"
+        );
+    }
+
+    #[test]
+    fn test_check_realm_name() {
+        assert!(check_realm_name("sales").is_ok());
+        assert!(check_realm_name("unit-2_eu").is_ok());
+        assert!(check_realm_name("").is_err());
+        assert!(check_realm_name("-sales").is_err());
+        assert!(check_realm_name("Sales").is_err());
+        assert!(check_realm_name("a/b").is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! Redirect URI matching.
+//! Requested redirect URIs are compared with the registered ones after URL normalisation.
+//! On top of exact matches, native apps get the relaxations of RFC 8252: loopback redirects
+//! on any port (section 7.3) and private-use URI schemes (section 7.1). Wildcard subdomain
+//! patterns such as `https://*.dev.example.com/cb` only match when an administrator enabled them.
+
+use std::net::IpAddr;
+use url::{Host, Url};
+
+/// Schemes that must never be used for redirects, whatever was registered
+const FORBIDDEN_SCHEMES: &[&str] = &["javascript", "data", "vbscript", "file", "about", "blob"];
+
+const WILDCARD_PREFIX: &str = "*.";
+
+/// How a registered redirect URI is matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedirectUriKind {
+    /// An https or http URI matched exactly
+    Web,
+    /// An http URI on 127.0.0.1 or [::1], matched on any port
+    Loopback,
+    /// A private-use URI scheme like `com.example.app:/callback`, for native apps
+    PrivateUse,
+    /// A `*.` subdomain pattern, only honoured when wildcards are enabled
+    Wildcard,
+}
+
+/// Classifies a registered redirect URI, or returns None if it can never be a valid one.
+pub fn classify(registered: &str) -> Option<RedirectUriKind> {
+    if let Some(base) = wildcard_base(registered) {
+        return base.map(|_| RedirectUriKind::Wildcard);
+    }
+
+    let url = parse_strict(registered)?;
+    match url.scheme() {
+        "https" => Some(RedirectUriKind::Web),
+        "http" if is_loopback_ip(&url) => Some(RedirectUriKind::Loopback),
+        "http" => Some(RedirectUriKind::Web),
+        scheme if is_private_use_scheme(scheme) => Some(RedirectUriKind::PrivateUse),
+        _ => None,
+    }
+}
+
+/// Checks whether the requested redirect URI matches the registered one.
+pub fn matches(registered: &str, requested: &str, allow_wildcards: bool) -> bool {
+    let Some(requested) = parse_strict(requested) else {
+        return false;
+    };
+
+    if let Some(base) = wildcard_base(registered) {
+        return allow_wildcards && base.is_some_and(|base| matches_wildcard(&base, &requested));
+    }
+
+    let Some(registered) = parse_strict(registered) else {
+        return false;
+    };
+
+    // RFC 8252, section 7.3: the port of loopback redirects is chosen at runtime
+    if registered.scheme() == "http" && is_loopback_ip(&registered) {
+        return requested.scheme() == "http"
+            && requested.host() == registered.host()
+            && requested.path() == registered.path()
+            && requested.query() == registered.query();
+    }
+
+    registered == requested
+}
+
+/// Private-use schemes must look like a reversed domain name (RFC 8252, section 7.1).
+pub fn is_private_use_scheme(scheme: &str) -> bool {
+    scheme.contains('.') && !matches!(scheme, "http" | "https") && !FORBIDDEN_SCHEMES.contains(&scheme)
+}
+
+/// Parses a redirect URI, rejecting fragments, credentials and forbidden schemes.
+fn parse_strict(uri: &str) -> Option<Url> {
+    let url = Url::parse(uri).ok()?;
+
+    if url.fragment().is_some()
+        || !url.username().is_empty()
+        || url.password().is_some()
+        || FORBIDDEN_SCHEMES.contains(&url.scheme())
+    {
+        return None;
+    }
+
+    // Web redirects need a host; private-use schemes have none
+    if matches!(url.scheme(), "http" | "https") && url.host().is_none() {
+        return None;
+    }
+
+    Some(url)
+}
+
+fn is_loopback_ip(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Ipv4(ip)) => IpAddr::V4(ip).is_loopback(),
+        Some(Host::Ipv6(ip)) => IpAddr::V6(ip).is_loopback(),
+        _ => false,
+    }
+}
+
+/// For a wildcard pattern, returns the pattern with the wildcard label removed: Some(None)
+/// if the pattern is malformed, None if the URI is no wildcard pattern at all.
+fn wildcard_base(registered: &str) -> Option<Option<Url>> {
+    let (scheme, rest) = registered.split_once("://")?;
+    let rest = rest.strip_prefix(WILDCARD_PREFIX)?;
+
+    let base = parse_strict(&format!("{}://{}", scheme, rest)).filter(|base| {
+        // The wildcard must sit on top of a domain with at least two labels, so "*.com" is no pattern
+        matches!(base.scheme(), "http" | "https")
+            && matches!(base.host(), Some(Host::Domain(domain)) if domain.contains('.'))
+    });
+
+    Some(base)
+}
+
+/// A wildcard stands for exactly one DNS label in front of the base domain.
+fn matches_wildcard(base: &Url, requested: &Url) -> bool {
+    let (Some(Host::Domain(base_domain)), Some(Host::Domain(domain))) = (base.host(), requested.host()) else {
+        return false;
+    };
+
+    let Some(label) = domain.strip_suffix(base_domain).and_then(|d| d.strip_suffix('.')) else {
+        return false;
+    };
+
+    is_dns_label(label)
+        && requested.scheme() == base.scheme()
+        && requested.port_or_known_default() == base.port_or_known_default()
+        && requested.path() == base.path()
+        && requested.query() == base.query()
+}
+
+fn is_dns_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= 63
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_matching() {
+        let registered = "https://app.example.com/cb";
+        let cases = [
+            ("https://app.example.com/cb", true),
+            // Normalisation of host case and default port
+            ("https://APP.example.com/cb", true),
+            ("https://app.example.com:443/cb", true),
+            ("https://app.example.com/cb/", false),
+            ("https://app.example.com/cb?next=/admin", false),
+            ("https://app.example.com/cb/../evil", false),
+            ("https://app.example.com/cb#fragment", false),
+            ("https://app.example.com:8443/cb", false),
+            ("http://app.example.com/cb", false),
+            // Credentials, and hosts hidden behind them or behind backslashes
+            ("https://user@app.example.com/cb", false),
+            ("https://app.example.com@evil.com/cb", false),
+            ("https://evil.com\\@app.example.com/cb", false),
+            ("https://app.example.com.evil.com/cb", false),
+            ("//app.example.com/cb", false),
+            ("/cb", false),
+        ];
+
+        for (requested, expected) in cases {
+            assert_eq!(matches(registered, requested, false), expected, "{}", requested);
+        }
+    }
+
+    #[test]
+    fn test_loopback_matching() {
+        let cases = [
+            ("http://127.0.0.1/cb", "http://127.0.0.1:51004/cb", true),
+            ("http://127.0.0.1:8080/cb", "http://127.0.0.1:51004/cb", true),
+            ("http://127.0.0.1/cb", "http://127.0.0.1/cb", true),
+            ("http://[::1]/cb", "http://[::1]:51004/cb", true),
+            ("http://127.0.0.1/cb", "http://[::1]:51004/cb", false),
+            ("http://127.0.0.1/cb", "http://127.0.0.1:51004/cb/evil", false),
+            ("http://127.0.0.1/cb", "http://127.0.0.1:51004/cb?x=1", false),
+            ("http://127.0.0.1/cb", "https://127.0.0.1:51004/cb", false),
+            ("http://127.0.0.1/cb", "http://127.0.0.1.evil.com:51004/cb", false),
+            ("http://127.0.0.1/cb", "http://localhost:51004/cb", false),
+            // "localhost" is not a loopback IP literal and keeps exact matching
+            ("http://localhost:8080/cb", "http://localhost:8080/cb", true),
+            ("http://localhost:8080/cb", "http://localhost:9090/cb", false),
+        ];
+
+        for (registered, requested, expected) in cases {
+            assert_eq!(matches(registered, requested, false), expected, "{} / {}", registered, requested);
+        }
+    }
+
+    #[test]
+    fn test_private_use_scheme_matching() {
+        let registered = "com.example.app:/oauth/callback";
+
+        assert!(matches(registered, "com.example.app:/oauth/callback", false));
+        assert!(!matches(registered, "com.example.app:/oauth/callback2", false));
+        assert!(!matches(registered, "com.example.evil:/oauth/callback", false));
+        assert!(!matches("javascript:alert(1)", "javascript:alert(1)", false));
+        assert!(!matches("data:text/html,x", "data:text/html,x", false));
+    }
+
+    #[test]
+    fn test_wildcard_matching() {
+        let registered = "https://*.dev.example.com/cb";
+        let cases = [
+            ("https://pr-42.dev.example.com/cb", true),
+            ("https://PR-42.dev.example.com/cb", true),
+            ("https://dev.example.com/cb", false),
+            ("https://a.b.dev.example.com/cb", false),
+            ("https://-x.dev.example.com/cb", false),
+            ("https://pr-42.dev.example.com.evil.com/cb", false),
+            ("https://pr-42evil.com/.dev.example.com/cb", false),
+            ("https://evildev.example.com/cb", false),
+            ("https://pr-42.dev.example.com/cb/evil", false),
+            ("https://pr-42.dev.example.com:8443/cb", false),
+            ("http://pr-42.dev.example.com/cb", false),
+            ("https://user@pr-42.dev.example.com/cb", false),
+        ];
+
+        for (requested, expected) in cases {
+            assert_eq!(matches(registered, requested, true), expected, "{}", requested);
+        }
+
+        // Disabled unless an administrator opts in
+        assert!(!matches(registered, "https://pr-42.dev.example.com/cb", false));
+        // Wildcards need a real domain underneath and may not span the whole host
+        assert!(!matches("https://*.com/cb", "https://evil.com/cb", true));
+        assert!(!matches("https://*.127.0.0.1/cb", "https://a.127.0.0.1/cb", true));
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("https://app.example.com/cb"), Some(RedirectUriKind::Web));
+        assert_eq!(classify("http://127.0.0.1/cb"), Some(RedirectUriKind::Loopback));
+        assert_eq!(classify("http://[::1]:3000/cb"), Some(RedirectUriKind::Loopback));
+        assert_eq!(classify("com.example.app:/cb"), Some(RedirectUriKind::PrivateUse));
+        assert_eq!(classify("https://*.dev.example.com/cb"), Some(RedirectUriKind::Wildcard));
+        assert_eq!(classify("https://*.com/cb"), None);
+        assert_eq!(classify("myapp:/cb"), None);
+        assert_eq!(classify("javascript:alert(1)"), None);
+        assert_eq!(classify("https://app.example.com/cb#x"), None);
+    }
+}
@@ -1,19 +1,30 @@
 use chrono::{DateTime, Utc};
 use sqlx::types::Uuid;
+use crate::domain::client::ClientId;
 
+/// A role users can be assigned. Realm roles apply everywhere, client roles belong to one client.
 #[derive(Debug, Clone)]
 pub struct Role {
     pub uuid: Uuid,
+    /// The owning client of a client role, None for realm roles
+    pub client_uuid: Option<Uuid>,
+    pub client_id: Option<ClientId>,
     pub name: String,
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl Role {
+    pub fn is_realm_role(&self) -> bool {
+        self.client_uuid.is_none()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UserRole {
     pub user_id: Uuid,
     pub role_id: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-}
\ No newline at end of file
+}
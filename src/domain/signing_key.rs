@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A key pair used to sign ID tokens and JWT access tokens.
+/// Keys are stored PEM encoded, the private key as PKCS#8 and the public key as SPKI.
+#[derive(Debug, Clone)]
+pub struct SigningKey {
+    pub uuid: Uuid,
+    pub kid: String,
+    /// The JWS algorithm the key is used with, e.g. "RS256"
+    pub algorithm: String,
+    pub private_key: String,
+    pub public_key: String,
+    /// Only active keys are used for signing; inactive keys are still published for verification
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -7,6 +7,19 @@ pub struct User {
     pub username: String,
     pub password_hash: String,
     pub email: String,
+    /// Custom attributes as a JSON object, available to protocol mappers
+    pub attributes: serde_json::Value,
+    /// Disabled users cannot log in
+    pub enabled: bool,
+    /// Set for soft-deleted users
+    pub deleted_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-}
\ No newline at end of file
+}
+
+impl User {
+    /// Whether the user may log in and be issued tokens.
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.deleted_at.is_none()
+    }
+}
@@ -1,3 +1,4 @@
+use vaulton::cli::{Command, DEFAULT_REALM};
 use vaulton::config::builder::ConfigBuilder;
 
 use clap::Parser;
@@ -8,8 +9,15 @@ use tokio;
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Path to the config file
-    #[arg(short, long, default_value = "config.yaml")]
+    #[arg(short, long, default_value = "config.yaml", global = true)]
     config: String,
+
+    /// The realm the subcommands work in
+    #[arg(long, default_value = DEFAULT_REALM, global = true)]
+    realm: String,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
 #[tokio::main]
@@ -24,17 +32,9 @@ async fn main() {
             std::process::exit(1);
         });
     
-    let bind_addr = config.server.bind_addr.as_deref().unwrap();
-    let port = config.server.port.unwrap();
-    let addr = format!("{}:{}", bind_addr, port);
-    
-    let app = vaulton::server::create_server(config).await;
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap_or_else(|e| {
-        eprintln!("Failed to bind to {}: {}", addr, e);
+    let command = args.command.unwrap_or(Command::Serve);
+    if let Err(e) = vaulton::cli::run(command, config, &args.realm).await {
+        eprintln!("{}", e);
         std::process::exit(1);
-    });
-    
-    println!("Server running on http://{}", addr);
-    
-    axum::serve(listener, app).await.unwrap();
-}
\ No newline at end of file
+    }
+}
@@ -0,0 +1,299 @@
+//! Access token issuance and validation.
+//! Clients receive either RFC 9068 JWT access tokens or opaque reference tokens. Both are
+//! recorded in the access token repository, so introspection, revocation and the UserInfo
+//! endpoint treat them the same way.
+
+use std::sync::Arc;
+use super::{keys, mappers, organizations, roles, subject};
+use super::resources::TokenPolicy;
+use axum::http::{header, HeaderMap};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::Validation;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use shaku::HasComponent;
+use uuid::Uuid;
+use crate::config::OIDCConfig;
+use crate::domain::access_token::{AccessToken, AccessTokenFormat};
+use crate::domain::client::Client;
+use crate::domain::realm::Realm;
+use crate::domain::protocol_mapper::TokenTarget;
+use crate::repository::access_token_repository::{AccessTokenRepository, CreateAccessTokenParams};
+use crate::repository::signing_key_repository::SigningKeyRepository;
+use crate::server::AppState;
+
+/// The "typ" header value of JWT access tokens, as required by RFC 9068
+pub const JWT_ACCESS_TOKEN_TYPE: &str = "at+jwt";
+
+const OPAQUE_TOKEN_BYTES: usize = 32;
+
+/// The claims of a JWT access token as defined by RFC 9068
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: Vec<String>,
+    pub exp: i64,
+    pub iat: i64,
+    pub jti: String,
+    pub client_id: String,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<i64>,
+    /// The user's role claims, the claims added by protocol mappers and the organization claims
+    #[serde(flatten)]
+    pub mapped_claims: Map<String, Value>,
+}
+
+/// An access token as handed to the client, together with its stored record.
+pub struct IssuedAccessToken {
+    pub token: String,
+    pub record: AccessToken,
+}
+
+/// Returns the subject of a token issued to the client: the user for user tokens, by the same
+/// identifier the client's ID tokens carry, and the client otherwise.
+pub fn subject(config: &OIDCConfig, client: &Client, token: &AccessToken) -> Result<String, String> {
+    match token.user_id {
+        Some(user_id) => subject::subject_identifier(config, client, user_id),
+        None => Ok(token.client_id.0.clone()),
+    }
+}
+
+/// What an access token is issued for.
+pub struct AccessTokenParams {
+    /// The resource owner, None for the client credentials grant
+    pub user_id: Option<Uuid>,
+    pub scopes: Vec<String>,
+    pub audience: Vec<String>,
+    pub policy: TokenPolicy,
+    pub auth_time: Option<DateTime<Utc>>,
+    /// Claims requested individually for the UserInfo response
+    pub claims: Vec<String>,
+    /// The organization the user picked when logging in
+    pub organization_id: Option<Uuid>,
+}
+
+/// Issues an access token following the token policy derived for the client and resources.
+pub async fn issue(
+    state: &AppState,
+    realm: &Realm,
+    client: &Client,
+    params: AccessTokenParams,
+) -> Result<IssuedAccessToken, String> {
+    let now = Utc::now();
+    let jti = Uuid::new_v4();
+
+    let (token, token_hash) = match params.policy.format {
+        AccessTokenFormat::Opaque => {
+            let token = generate_opaque_token();
+            let token_hash = hash_token(&token);
+            (Some(token), Some(token_hash))
+        }
+        AccessTokenFormat::Jwt => (None, None),
+    };
+
+    let access_token_repository: Arc<dyn AccessTokenRepository> = state.module.resolve();
+    let record = access_token_repository
+        .create(CreateAccessTokenParams {
+            uuid: jti,
+            format: params.policy.format,
+            token_hash,
+            client_uuid: client.uuid,
+            user_id: params.user_id,
+            scopes: params.scopes,
+            audience: params.audience,
+            claims: params.claims,
+            organization_id: params.organization_id,
+            expires_at: now + Duration::seconds(params.policy.lifetime),
+        })
+        .await?;
+
+    let token = match token {
+        Some(token) => token,
+        None => {
+            let mut mapped_claims = match params.user_id {
+                Some(user_id) => roles::user_role_claims(state, user_id).await?,
+                None => Map::new(),
+            };
+            let mapped =
+                mappers::mapped_claims(state, realm, client, &record.scopes, params.user_id, TokenTarget::AccessToken).await?;
+            mappers::merge_claims(&mut mapped_claims, mapped);
+            if let Some(user_id) = params.user_id {
+                let organization =
+                    organizations::organization_claims(state, &record.scopes, record.organization_id, user_id).await;
+                mappers::merge_claims(&mut mapped_claims, organization);
+            }
+
+            let claims = AccessTokenClaims {
+                iss: state.external_url(realm),
+                sub: subject(&state.config.oidc, client, &record)?,
+                aud: record.audience.clone(),
+                exp: record.expires_at.timestamp(),
+                iat: now.timestamp(),
+                jti: record.uuid.to_string(),
+                client_id: record.client_id.0.clone(),
+                scope: record.scopes.join(" "),
+                auth_time: params.auth_time.map(|t| t.timestamp()),
+                mapped_claims,
+            };
+
+            let signing_key_repository: Arc<dyn SigningKeyRepository> = state.module.resolve();
+            let key = keys::active_key(signing_key_repository.as_ref(), realm.uuid, &params.policy.algorithm).await?;
+
+            keys::sign(&key, JWT_ACCESS_TOKEN_TYPE, &claims)?
+        }
+    };
+
+    Ok(IssuedAccessToken { token, record })
+}
+
+/// Looks up the stored record of a token issued in the realm, whether or not it is still active.
+/// JWT access tokens must carry a valid signature from one of the realm's keys.
+pub async fn resolve(state: &AppState, realm: &Realm, token: &str) -> Option<AccessToken> {
+    let access_token_repository: Arc<dyn AccessTokenRepository> = state.module.resolve();
+
+    if !looks_like_jwt(token) {
+        return access_token_repository.find_by_hash(realm.uuid, &hash_token(token)).await;
+    }
+
+    let header = jsonwebtoken::decode_header(token).ok()?;
+    if header.typ.as_deref() != Some(JWT_ACCESS_TOKEN_TYPE) {
+        return None;
+    }
+
+    let signing_key_repository: Arc<dyn SigningKeyRepository> = state.module.resolve();
+    let key = signing_key_repository.find_by_kid(realm.uuid, header.kid.as_deref()?).await?;
+
+    let mut validation = Validation::new(keys::algorithm(&key).ok()?);
+    validation.set_issuer(&[state.external_url(realm)]);
+    validation.validate_aud = false;
+
+    let data = jsonwebtoken::decode::<AccessTokenClaims>(token, &keys::decoding_key(&key).ok()?, &validation).ok()?;
+    let jti = Uuid::parse_str(&data.claims.jti).ok()?;
+
+    access_token_repository.find_by_id(realm.uuid, jti).await
+}
+
+/// Returns the stored record of the token if it is neither expired nor revoked.
+pub async fn validate(state: &AppState, realm: &Realm, token: &str) -> Option<AccessToken> {
+    resolve(state, realm, token).await.filter(|t| t.is_active())
+}
+
+/// Extracts the token from an "Authorization: Bearer" header.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+pub fn hash_token(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; OPAQUE_TOKEN_BYTES];
+    rand::rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn looks_like_jwt(token: &str) -> bool {
+    token.split('.').count() == 3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::client::ClientId;
+    use axum::http::HeaderValue;
+
+    fn pairwise_client() -> Client {
+        Client {
+            uuid: Uuid::new_v4(),
+            id: ClientId("app".to_string()),
+            secret_hash: None,
+            redirect_uris: vec!["https://app.example.com/cb".to_string()],
+            allowed_scopes: vec!["openid".to_string()],
+            client_name: None,
+            logo_uri: None,
+            application_type: "web".to_string(),
+            subject_type: "pairwise".to_string(),
+            sector_identifier_uri: None,
+            grant_types: vec!["authorization_code".to_string()],
+            token_endpoint_auth_method: None,
+            registration_token_hash: None,
+            first_party: false,
+            id_token_role_claims: false,
+            access_token_format: AccessTokenFormat::Jwt,
+            access_token_lifetime: None,
+            id_token_lifetime: None,
+            id_token_signed_response_alg: "RS256".to_string(),
+            jwks: None,
+            id_token_encrypted_response_alg: None,
+            id_token_encrypted_response_enc: None,
+            userinfo_encrypted_response_alg: None,
+            userinfo_encrypted_response_enc: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn token(client: &Client, user_id: Option<Uuid>) -> AccessToken {
+        AccessToken {
+            uuid: Uuid::new_v4(),
+            format: AccessTokenFormat::Jwt,
+            client_uuid: client.uuid,
+            client_id: client.id.clone(),
+            user_id,
+            scopes: vec!["openid".to_string()],
+            audience: vec![client.id.0.clone()],
+            claims: vec![],
+            organization_id: None,
+            expires_at: Utc::now() + Duration::hours(1),
+            revoked_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_subject_matches_id_token_subject() {
+        let config = OIDCConfig {
+            pairwise_salt: Some("salt".to_string()),
+            ..Default::default()
+        };
+        let client = pairwise_client();
+        let user_id = Uuid::new_v4();
+
+        // ID tokens carry the subject identifier of the client
+        let id_token_subject = subject::subject_identifier(&config, &client, user_id).unwrap();
+        let access_token_subject = subject(&config, &client, &token(&client, Some(user_id))).unwrap();
+        assert_eq!(access_token_subject, id_token_subject);
+        assert_ne!(access_token_subject, user_id.to_string());
+
+        assert_eq!(subject(&config, &client, &token(&client, None)).unwrap(), "app");
+    }
+
+    #[test]
+    fn test_opaque_tokens_are_not_jwts() {
+        let token = generate_opaque_token();
+        assert!(!looks_like_jwt(&token));
+        assert_eq!(token.len(), 43);
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer abc.def.ghi"));
+        assert_eq!(bearer_token(&headers), Some("abc.def.ghi"));
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic abc"));
+        assert_eq!(bearer_token(&headers), None);
+    }
+}
@@ -3,14 +3,22 @@
 
 use std::sync::Arc;
 use super::error::OAuthError;
-use axum::{extract::Query, response::Redirect};
-use axum::extract::State;
+use axum::extract::Query;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::extract::{Extension, RawQuery, State};
 use serde::Deserialize;
 use shaku::HasComponent;
+use url::Url;
+use uuid::Uuid;
 use crate::repository::client_repository::ClientRepository;
 use crate::repository::auth_request_repository::AuthRequestRepository;
+use crate::repository::authorization_code_repository::AuthorizationCodeRepository;
+use super::claims::ClaimsRequest;
+use super::subject;
+use super::resources::{self, resource_parameters};
 use crate::server::AppState;use chrono::{DateTime, Utc};
-use crate::domain::client::ClientId;
+use crate::domain::client::{Client, ClientId};
+use crate::domain::realm::Realm;
 
 /// Represents an OpenID Connect authorization request.
 /// Contains the parameters required for initiating the authentication flow.
@@ -30,25 +38,95 @@ pub struct AuthRequest {
     code_challenge: Option<String>,
     /// PKCE code challenge method (e.g., "S256")
     code_challenge_method: Option<String>,
+    /// Value passed through unmodified to the ID token to mitigate replay attacks
+    nonce: Option<String>,
+    /// JSON object requesting individual claims for the ID token and UserInfo response
+    claims: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct AuthorizationRequest {
     pub request_id: String,
+    /// The realm the request was made in; it is only continued there
+    pub realm_uuid: Uuid,
     pub client_id: String,
     pub redirect_uri: String,
     pub scope: String,
     pub state: Option<String>,
     pub code_challenge: Option<String>,
     pub code_challenge_method: Option<String>,
+    pub nonce: Option<String>,
+    /// Identifiers of the API resources the client wants tokens for (RFC 8707)
+    pub resources: Vec<String>,
+    /// Claims requested individually through the "claims" parameter
+    pub claims: ClaimsRequest,
+    /// The user whose password was verified while a second factor is still outstanding
+    pub pending_user_id: Option<Uuid>,
+    /// The user who authenticated for this request, set once login succeeded
+    pub user_id: Option<Uuid>,
+    /// When the user authenticated
+    pub auth_time: Option<DateTime<Utc>>,
+    /// How the user authenticated (RFC 8176)
+    pub amr: Vec<String>,
+    /// The challenge of the WebAuthn ceremony on the page shown last, used up by the response
+    pub webauthn_challenge: Option<String>,
+    /// The organization the user picked, for the "organization" scope
+    pub organization_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An authorization code handed to the client after the user authenticated and consented.
+/// It is redeemed exactly once at the token endpoint.
+#[derive(Debug, Clone)]
+pub struct AuthorizationCode {
+    pub code: String,
+    pub realm_uuid: Uuid,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub user_id: Uuid,
+    pub scope: String,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub nonce: Option<String>,
+    pub resources: Vec<String>,
+    pub claims: ClaimsRequest,
+    pub auth_time: Option<DateTime<Utc>>,
+    pub amr: Vec<String>,
+    pub organization_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
 }
 
 /// Handles the authorization request and initiates the authentication flow.
-/// Returns a redirect to either the login page or the error page depending on the validation result.
+/// The client and redirect URI are checked first: as long as the redirect URI is not known to
+/// belong to the client, errors are shown to the user instead of being redirected.
+/// Afterwards, returns a redirect to either the login page or the client's redirect URI.
 pub async fn authorize(
     State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    RawQuery(query): RawQuery,
     Query(params): Query<AuthRequest>,
+) -> Response {
+    let client_repository: Arc<dyn ClientRepository> = state.module.as_ref().resolve();
+
+    let Some(client) = client_repository.find_by_id(realm.uuid, &ClientId(params.client_id.clone())).await else {
+        return OAuthError::InvalidRequest("Client not found".to_string()).to_json_response();
+    };
+
+    let allow_wildcards = state.config.oidc.allow_wildcard_redirect_uris.unwrap_or(false);
+    if !client.validate_redirect_uri(&params.redirect_uri, allow_wildcards) {
+        return OAuthError::InvalidRequest("Invalid redirect URI".to_string()).to_json_response();
+    }
+
+    authorize_client(&state, &realm, query, params, client).await.into_response()
+}
+
+/// Validates the rest of the request for a client whose redirect URI was verified.
+async fn authorize_client(
+    state: &AppState,
+    realm: &Realm,
+    query: Option<String>,
+    params: AuthRequest,
+    client: Client,
 ) -> Redirect {
     if params.response_type != "code" {
         return OAuthError::UnsupportedResponseType(
@@ -77,18 +155,8 @@ pub async fn authorize(
             .to_redirect_response(&params.redirect_uri, params.state.as_deref());
     }
 
-    let client_repository: Arc<dyn ClientRepository> = state.module.as_ref().resolve();
-
-    let client = match client_repository.find_by_id(&ClientId(params.client_id.clone())).await {
-        Some(client) => client,
-        None => {
-            return OAuthError::InvalidClient("Client not found".to_string())
-                .to_redirect_response(&params.redirect_uri, params.state.as_deref());
-        }
-    };
-
-    if !client.validate_redirect_uri(&params.redirect_uri) {
-        return OAuthError::InvalidRequest("Invalid redirect URI".to_string())
+    if !client.allows_grant_type("authorization_code") {
+        return OAuthError::UnauthorizedClient("The client may not use the authorization code flow".to_string())
             .to_redirect_response(&params.redirect_uri, params.state.as_deref());
     }
 
@@ -97,13 +165,45 @@ pub async fn authorize(
             .to_redirect_response(&params.redirect_uri, params.state.as_deref());
     }
 
+    let requested_resources = resource_parameters(query.as_deref().unwrap_or_default().as_bytes());
+    let checked = resources::resolve_resources(state, realm, &requested_resources)
+        .await
+        .and_then(|resources| resources::check_scopes(&requested_scopes, &resources));
+    if let Err(e) = checked {
+        return e.to_redirect_response(&params.redirect_uri, params.state.as_deref());
+    }
+
+    let claims = match params.claims.as_deref().map(ClaimsRequest::parse).transpose() {
+        Ok(claims) => claims.unwrap_or_default(),
+        Err(e) => {
+            return OAuthError::InvalidRequest(e).to_redirect_response(&params.redirect_uri, params.state.as_deref());
+        }
+    };
+
+    if !claims.acr_satisfiable() {
+        return OAuthError::UnmetAuthenticationRequirements(
+            "None of the requested authentication context classes is supported".to_string(),
+        )
+        .to_redirect_response(&params.redirect_uri, params.state.as_deref());
+    }
+
     let auth_req = AuthorizationRequest {
+        realm_uuid: realm.uuid,
         client_id: params.client_id,
         redirect_uri: params.redirect_uri.clone(),
         scope: params.scope.unwrap_or_else(|| "openid".to_string()),
         state: params.state.clone(),
         code_challenge: params.code_challenge,
         code_challenge_method: params.code_challenge_method,
+        nonce: params.nonce,
+        resources: requested_resources,
+        claims,
+        pending_user_id: None,
+        user_id: None,
+        auth_time: None,
+        amr: vec![],
+        webauthn_challenge: None,
+        organization_id: None,
         // Generate a unique request ID
         request_id: generate_request_id(),
         // Set creation time
@@ -115,7 +215,8 @@ pub async fn authorize(
     // Store the authorization request
     match auth_request_repository.store_request(&auth_req).await {
         Ok(_) => {
-            Redirect::temporary(&format!("/login?request_id={}", auth_req.request_id))
+            // Relative to the realm's endpoints, whether addressed by path or by host
+            Redirect::temporary(&format!("login?request_id={}", auth_req.request_id))
         }
         Err(_) => {
             OAuthError::ServerError("Failed to store authorization request".to_string())
@@ -125,18 +226,98 @@ pub async fn authorize(
 
 }
 
+/// Finishes an authorization request for an authenticated user who has consented.
+/// Issues an authorization code and redirects back to the client.
+pub async fn complete_authorization(state: &AppState, request: AuthorizationRequest) -> Response {
+    let Some(user_id) = request.user_id else {
+        return Redirect::to(&format!("login?request_id={}", request.request_id)).into_response();
+    };
+    // Checked when the request was made, errors cannot be redirected without it
+    let Ok(mut url) = Url::parse(&request.redirect_uri) else {
+        return OAuthError::ServerError("The redirect URI of the request is invalid".to_string()).to_json_response();
+    };
+
+    // A client asking for a specific subject must not get tokens for anyone else
+    if let Some(requested_subject) = request.claims.requested_subject() {
+        if !is_subject(state, request.realm_uuid, &request.client_id, user_id, requested_subject).await {
+            return OAuthError::AccessDenied("The authenticated user is not the requested subject".to_string())
+                .to_redirect_response(&request.redirect_uri, request.state.as_deref())
+                .into_response();
+        }
+    }
+
+    let code = AuthorizationCode {
+        code: generate_authorization_code(),
+        realm_uuid: request.realm_uuid,
+        client_id: request.client_id,
+        redirect_uri: request.redirect_uri.clone(),
+        user_id,
+        scope: request.scope,
+        code_challenge: request.code_challenge,
+        code_challenge_method: request.code_challenge_method,
+        nonce: request.nonce,
+        resources: request.resources,
+        claims: request.claims,
+        auth_time: request.auth_time,
+        amr: request.amr,
+        organization_id: request.organization_id,
+        created_at: chrono::Utc::now(),
+    };
+
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+    let code_repository: Arc<dyn AuthorizationCodeRepository> = state.module.resolve();
+
+    auth_request_repository.remove(&request.request_id).await;
+
+    if code_repository.store_code(&code).await.is_err() {
+        return OAuthError::ServerError("Failed to store authorization code".to_string())
+            .to_redirect_response(&request.redirect_uri, request.state.as_deref())
+            .into_response();
+    }
+
+    url.query_pairs_mut().append_pair("code", &code.code);
+    if let Some(state) = &request.state {
+        url.query_pairs_mut().append_pair("state", state);
+    }
+
+    Redirect::to(url.as_str()).into_response()
+}
+
+async fn is_subject(
+    state: &AppState,
+    realm_uuid: Uuid,
+    client_id: &str,
+    user_id: Uuid,
+    requested_subject: &str,
+) -> bool {
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    let Some(client) = client_repository.find_by_id(realm_uuid, &ClientId(client_id.to_string())).await else {
+        return false;
+    };
+
+    subject::subject_identifier(&state.config.oidc, &client, user_id).is_ok_and(|subject| subject == requested_subject)
+}
+
 fn generate_request_id() -> String {
+    const REQUEST_ID_LEN: usize = 32;
+    generate_random_string(REQUEST_ID_LEN)
+}
+
+fn generate_authorization_code() -> String {
+    const CODE_LEN: usize = 43;
+    generate_random_string(CODE_LEN)
+}
+
+fn generate_random_string(len: usize) -> String {
     use rand::{rng, Rng};
     const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
-    const REQUEST_ID_LEN: usize = 32;
 
     let mut rng = rng();
-    let request_id: String = (0..REQUEST_ID_LEN)
+    (0..len)
         .map(|_| {
             let idx = rng.random_range(0..CHARSET.len());
             CHARSET[idx] as char
         })
-        .collect();
-    request_id
+        .collect()
 }
 
@@ -0,0 +1,291 @@
+//! Claim assembly for ID tokens and the UserInfo endpoint.
+//! Decides which user claims are released for the granted scopes and for the claims requested
+//! individually through the "claims" request parameter (OpenID Connect Core 1.0, section 5.5).
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use super::organizations;
+use crate::domain::user::User;
+
+/// The authentication context class of a password login
+pub const ACR_PASSWORD: &str = "urn:vaulton:acr:password";
+/// The authentication context class of a login with two factors
+pub const ACR_MFA: &str = "urn:vaulton:acr:mfa";
+
+/// Authentication context classes we can satisfy
+pub const ACR_VALUES_SUPPORTED: &[&str] = &[ACR_PASSWORD, ACR_MFA];
+
+/// Authentication method references (RFC 8176)
+pub const AMR_PASSWORD: &str = "pwd";
+pub const AMR_OTP: &str = "otp";
+pub const AMR_HARDWARE_KEY: &str = "hwk";
+pub const AMR_MFA: &str = "mfa";
+
+/// Claims about the user we can release, with the scope that releases them
+const USER_CLAIMS: &[(&str, &str)] = &[
+    ("preferred_username", "profile"),
+    ("updated_at", "profile"),
+    ("email", "email"),
+];
+
+/// Requirements on an individual claim. A claim requested as null has no requirements.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClaimRequirements {
+    /// Essential claims are needed for the client's use case, voluntary ones are nice to have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub essential: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<Value>>,
+}
+
+impl ClaimRequirements {
+    pub fn is_essential(&self) -> bool {
+        self.essential.unwrap_or(false)
+    }
+
+    /// The requested values, from either "value" or "values".
+    pub fn requested_values(&self) -> Vec<&Value> {
+        self.value.iter().chain(self.values.iter().flatten()).collect()
+    }
+}
+
+pub type RequestedClaims = BTreeMap<String, Option<ClaimRequirements>>;
+
+/// The "claims" request parameter: claims requested for the UserInfo response and the ID token.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClaimsRequest {
+    #[serde(default)]
+    pub userinfo: RequestedClaims,
+    #[serde(default)]
+    pub id_token: RequestedClaims,
+}
+
+impl ClaimsRequest {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid 'claims' parameter: {}", e))
+    }
+
+    /// Checks an essential "acr" request against the authentication context classes we support.
+    pub fn acr_satisfiable(&self) -> bool {
+        match self.id_token.get("acr") {
+            Some(Some(acr)) if acr.is_essential() => {
+                let requested = acr.requested_values();
+                requested.is_empty()
+                    || requested
+                        .iter()
+                        .any(|v| v.as_str().is_some_and(|v| ACR_VALUES_SUPPORTED.contains(&v)))
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether an essential "acr" request only accepts multi-factor logins.
+    pub fn requires_mfa(&self) -> bool {
+        match self.id_token.get("acr") {
+            Some(Some(acr)) if acr.is_essential() => {
+                let requested = acr.requested_values();
+                requested.iter().any(|v| v.as_str() == Some(ACR_MFA))
+                    && !requested.iter().any(|v| v.as_str() == Some(ACR_PASSWORD))
+            }
+            _ => false,
+        }
+    }
+
+    /// The names of the claims requested for the UserInfo response.
+    pub fn userinfo_claim_names(&self) -> Vec<String> {
+        self.userinfo.keys().cloned().collect()
+    }
+
+    /// The names of the claims requested for the ID token.
+    pub fn id_token_claim_names(&self) -> Vec<String> {
+        self.id_token.keys().cloned().collect()
+    }
+
+    /// Claims about the user requested by name that the granted scopes would not release.
+    /// Users have to approve them on the consent page.
+    pub fn claims_beyond_scopes(&self, scopes: &[&str]) -> Vec<&'static str> {
+        USER_CLAIMS
+            .iter()
+            .filter(|(name, scope)| {
+                !scopes.contains(scope) && (self.userinfo.contains_key(*name) || self.id_token.contains_key(*name))
+            })
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// The subject the client asked for, if any. Authorization must fail for any other user.
+    pub fn requested_subject(&self) -> Option<&str> {
+        [&self.id_token, &self.userinfo]
+            .into_iter()
+            .find_map(|claims| claims.get("sub")?.as_ref()?.value.as_ref()?.as_str())
+    }
+}
+
+/// The authentication context class of a login with the given authentication methods.
+pub fn acr_for(amr: &[String]) -> &'static str {
+    if amr.iter().any(|m| m == AMR_MFA) {
+        ACR_MFA
+    } else {
+        ACR_PASSWORD
+    }
+}
+
+/// The names of the claims the discovery document lists as supported.
+pub fn supported_claims() -> Vec<&'static str> {
+    let mut claims = vec!["sub", "iss", "aud", "exp", "iat", "auth_time", "nonce", "acr", "amr"];
+    claims.extend(USER_CLAIMS.iter().map(|(name, _)| *name));
+    claims.extend(organizations::ORGANIZATION_CLAIMS);
+    claims
+}
+
+/// Builds the claims released about the user for the granted scopes and the individually
+/// requested claims. The "sub" claim is always included, with the subject identifier the
+/// client knows the user by.
+pub fn user_claims(user: &User, subject: &str, scopes: &[String], requested: &[String]) -> Map<String, Value> {
+    let mut claims = released_claims(user, scopes, requested);
+    claims.insert("sub".to_string(), Value::String(subject.to_string()));
+    claims
+}
+
+/// The claims about the user released for the scopes or requested by name, without "sub".
+pub fn released_claims(user: &User, scopes: &[String], requested: &[String]) -> Map<String, Value> {
+    let mut claims = Map::new();
+
+    for (name, scope) in USER_CLAIMS {
+        if scopes.iter().any(|s| s == scope) || requested.iter().any(|r| r == name) {
+            claims.insert(name.to_string(), user_claim(user, name));
+        }
+    }
+
+    claims
+}
+
+fn user_claim(user: &User, name: &str) -> Value {
+    match name {
+        "preferred_username" => Value::String(user.username.clone()),
+        "updated_at" => Value::from(user.updated_at.timestamp()),
+        "email" => Value::String(user.email.clone()),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn user() -> User {
+        User {
+            uuid: Uuid::new_v4(),
+            username: "alice".to_string(),
+            password_hash: "hash".to_string(),
+            email: "alice@example.com".to_string(),
+            attributes: serde_json::json!({}),
+            enabled: true,
+            deleted_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_openid_only_releases_sub() {
+        let user = user();
+        let claims = user_claims(&user, &user.uuid.to_string(), &["openid".to_string()], &[]);
+
+        assert_eq!(claims.len(), 1);
+        assert_eq!(claims["sub"], Value::String(user.uuid.to_string()));
+    }
+
+    #[test]
+    fn test_profile_and_email_scopes() {
+        let user = user();
+        let claims = user_claims(
+            &user,
+            &user.uuid.to_string(),
+            &["openid".to_string(), "profile".to_string(), "email".to_string()],
+            &[],
+        );
+
+        assert_eq!(claims["preferred_username"], Value::String("alice".to_string()));
+        assert_eq!(claims["email"], Value::String("alice@example.com".to_string()));
+    }
+
+    #[test]
+    fn test_individually_requested_claims() {
+        let user = user();
+        let request = ClaimsRequest::parse(r#"{"userinfo": {"email": {"essential": true}, "nickname": null}}"#).unwrap();
+        let claims = user_claims(&user, "subject", &["openid".to_string()], &request.userinfo_claim_names());
+
+        assert_eq!(claims["sub"], Value::String("subject".to_string()));
+        assert_eq!(claims["email"], Value::String("alice@example.com".to_string()));
+        assert!(!claims.contains_key("preferred_username"));
+        // Claims we know nothing about are left out
+        assert!(!claims.contains_key("nickname"));
+        assert!(request.id_token.is_empty());
+    }
+
+    #[test]
+    fn test_parse_claims_request() {
+        assert!(ClaimsRequest::parse("not json").is_err());
+        assert!(ClaimsRequest::parse(r#"{"access_token": {}}"#).is_err());
+
+        let request = ClaimsRequest::parse(r#"{"id_token": {"auth_time": {"essential": false}, "email": null}}"#).unwrap();
+        assert!(!request.id_token["auth_time"].as_ref().unwrap().is_essential());
+        assert_eq!(request.id_token["email"], None);
+    }
+
+    #[test]
+    fn test_essential_acr() {
+        let satisfiable = |json: &str| ClaimsRequest::parse(json).unwrap().acr_satisfiable();
+
+        assert!(satisfiable(r#"{"id_token": {"acr": null}}"#));
+        assert!(satisfiable(r#"{"id_token": {"acr": {"essential": true}}}"#));
+        assert!(satisfiable(r#"{"id_token": {"acr": {"essential": false, "value": "urn:example:mfa"}}}"#));
+        assert!(satisfiable(&format!(
+            r#"{{"id_token": {{"acr": {{"essential": true, "values": ["urn:example:mfa", "{}"]}}}}}}"#,
+            ACR_PASSWORD
+        )));
+        assert!(!satisfiable(r#"{"id_token": {"acr": {"essential": true, "value": "urn:example:mfa"}}}"#));
+    }
+
+    #[test]
+    fn test_requires_mfa() {
+        let requires_mfa = |json: String| ClaimsRequest::parse(&json).unwrap().requires_mfa();
+
+        assert!(requires_mfa(format!(r#"{{"id_token": {{"acr": {{"essential": true, "value": "{}"}}}}}}"#, ACR_MFA)));
+        assert!(!requires_mfa(format!(r#"{{"id_token": {{"acr": {{"value": "{}"}}}}}}"#, ACR_MFA)));
+        assert!(!requires_mfa(format!(
+            r#"{{"id_token": {{"acr": {{"essential": true, "values": ["{}", "{}"]}}}}}}"#,
+            ACR_MFA, ACR_PASSWORD
+        )));
+        assert_eq!(acr_for(&["pwd".to_string()]), ACR_PASSWORD);
+        assert_eq!(acr_for(&["pwd".to_string(), "otp".to_string(), "mfa".to_string()]), ACR_MFA);
+    }
+
+    #[test]
+    fn test_requested_subject() {
+        let request = ClaimsRequest::parse(r#"{"id_token": {"sub": {"value": "248289761001"}}}"#).unwrap();
+        assert_eq!(request.requested_subject(), Some("248289761001"));
+
+        let request = ClaimsRequest::parse(r#"{"userinfo": {"sub": null}}"#).unwrap();
+        assert_eq!(request.requested_subject(), None);
+    }
+
+    #[test]
+    fn test_claims_beyond_scopes() {
+        let request = ClaimsRequest::parse(
+            r#"{"userinfo": {"email": null}, "id_token": {"preferred_username": null, "acr": null}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.claims_beyond_scopes(&["openid"]), vec!["preferred_username", "email"]);
+        assert_eq!(request.claims_beyond_scopes(&["openid", "email"]), vec!["preferred_username"]);
+        assert!(request.claims_beyond_scopes(&["openid", "profile", "email"]).is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+//! Client authentication for the token, introspection and revocation endpoints.
+//! Supports client_secret_basic, client_secret_post and public clients ("none").
+
+use std::sync::Arc;
+use super::error::OAuthError;
+use axum::http::{header, HeaderMap};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use percent_encoding::percent_decode_str;
+use sha2::{Digest, Sha256};
+use shaku::HasComponent;
+use crate::domain::client::{Client, ClientId};
+use crate::domain::realm::Realm;
+use crate::repository::client_repository::ClientRepository;
+use crate::server::AppState;
+
+/// Hashes a client secret for storage in `clients.client_secret_hash`.
+/// Client secrets are long random strings, so a fast hash is sufficient.
+pub fn hash_client_secret(secret: &str) -> Vec<u8> {
+    Sha256::digest(secret.as_bytes()).to_vec()
+}
+
+/// Authenticates a client of the realm from the Authorization header or the request body.
+/// Clients without a secret are public and authenticate with their client_id alone.
+/// Clients that registered a `token_endpoint_auth_method` must use exactly that method.
+pub async fn authenticate_client(
+    state: &AppState,
+    realm: &Realm,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<Client, OAuthError> {
+    let basic = basic_credentials(headers)?;
+    let method = match (&basic, client_secret) {
+        (Some(_), _) => "client_secret_basic",
+        (None, Some(_)) => "client_secret_post",
+        (None, None) => "none",
+    };
+
+    let (client_id, client_secret) = match basic {
+        Some((id, secret)) => (id, Some(secret)),
+        None => (
+            client_id
+                .ok_or_else(|| OAuthError::InvalidClient("Missing client credentials".to_string()))?
+                .to_string(),
+            client_secret.map(String::from),
+        ),
+    };
+
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    let client = client_repository
+        .find_by_id(realm.uuid, &ClientId(client_id))
+        .await
+        .ok_or_else(|| OAuthError::InvalidClient("Client authentication failed".to_string()))?;
+
+    // Registered clients are held to the authentication method they registered
+    if client.token_endpoint_auth_method.as_deref().is_some_and(|m| m != method) {
+        return Err(OAuthError::InvalidClient("Client authentication failed".to_string()));
+    }
+
+    match (&client.secret_hash, client_secret) {
+        (None, None) => Ok(client),
+        (Some(expected), Some(secret)) if constant_time_eq(expected, &hash_client_secret(&secret)) => Ok(client),
+        _ => Err(OAuthError::InvalidClient("Client authentication failed".to_string())),
+    }
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Result<Option<(String, String)>, OAuthError> {
+    let Some(value) = headers.get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+
+    let invalid = || OAuthError::InvalidClient("Malformed Authorization header".to_string());
+
+    let value = value.to_str().map_err(|_| invalid())?;
+    let Some(encoded) = value.strip_prefix("Basic ") else {
+        return Ok(None);
+    };
+
+    let decoded = STANDARD.decode(encoded.trim()).map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    let (id, secret) = decoded.split_once(':').ok_or_else(invalid)?;
+
+    // RFC 6749 section 2.3.1: both parts are form-urlencoded before being joined
+    let id = form_decode(id).ok_or_else(invalid)?;
+    let secret = form_decode(secret).ok_or_else(invalid)?;
+
+    Ok(Some((id, secret)))
+}
+
+fn form_decode(value: &str) -> Option<String> {
+    let value = value.replace('+', " ");
+    percent_decode_str(&value).decode_utf8().ok().map(|v| v.into_owned())
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_basic_credentials_are_form_decoded() {
+        let mut headers = HeaderMap::new();
+        let encoded = STANDARD.encode("my%20client:s3cr%3At");
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&format!("Basic {}", encoded)).unwrap());
+
+        let credentials = basic_credentials(&headers).unwrap();
+        assert_eq!(credentials, Some(("my client".to_string(), "s3cr:t".to_string())));
+    }
+
+    #[test]
+    fn test_bearer_header_is_not_client_credentials() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+
+        assert_eq!(basic_credentials(&headers).unwrap(), None);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secrets"));
+    }
+}
@@ -0,0 +1,203 @@
+//! Consent endpoint implementation.
+//! Asks the user to approve the scopes a third-party client requested and remembers the decision.
+
+use std::sync::Arc;
+use axum::extract::{Extension, Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use serde::Deserialize;
+use shaku::HasComponent;
+use super::auth::complete_authorization;
+use super::error::OAuthError;
+use super::scopes::describe_scope;
+use crate::domain::client::{Client, ClientId};
+use crate::domain::realm::Realm;
+use crate::repository::api_resource_repository::ApiResourceRepository;
+use crate::repository::auth_request_repository::AuthRequestRepository;
+use crate::repository::client_repository::ClientRepository;
+use crate::repository::consent_repository::{ConsentRepository, GrantConsentParams};
+use crate::server::AppState;
+use crate::utils::html;
+
+#[derive(Debug, Deserialize)]
+pub struct ConsentQuery {
+    request_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConsentForm {
+    request_id: String,
+    /// "approve" to grant the requested scopes, anything else denies the request
+    decision: String,
+}
+
+/// Shows the consent page, or skips it when the client is first-party or the user
+/// already granted every requested scope.
+pub async fn consent_page(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Query(query): Query<ConsentQuery>,
+) -> Response {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    let consent_repository: Arc<dyn ConsentRepository> = state.module.resolve();
+
+    let request = auth_request_repository.find_by_id(&query.request_id).await;
+    let Some(request) = request.filter(|r| r.realm_uuid == realm.uuid) else {
+        return (StatusCode::BAD_REQUEST, "Unknown or expired authorization request").into_response();
+    };
+
+    let Some(user_id) = request.user_id else {
+        return Redirect::to(&format!("login?request_id={}", request.request_id)).into_response();
+    };
+
+    let Some(client) = client_repository.find_by_id(realm.uuid, &ClientId(request.client_id.clone())).await else {
+        return OAuthError::InvalidClient("Client not found".to_string())
+            .to_redirect_response(&request.redirect_uri, request.state.as_deref())
+            .into_response();
+    };
+
+    let requested_scopes = request.scope.split_whitespace().collect::<Vec<_>>();
+
+    if !client.requires_consent() {
+        return complete_authorization(&state, request).await;
+    }
+
+    // Consent is remembered per scope, so claims requested beyond the scopes are asked for every time
+    let additional_claims = request.claims.claims_beyond_scopes(&requested_scopes);
+
+    if let Some(consent) = consent_repository.find(user_id, client.uuid).await {
+        if consent.covers(&requested_scopes) && additional_claims.is_empty() {
+            return complete_authorization(&state, request).await;
+        }
+    }
+
+    // Scopes owned by API resources carry their own descriptions
+    let api_resource_repository: Arc<dyn ApiResourceRepository> = state.module.resolve();
+    let resource_scopes = api_resource_repository
+        .find_scopes(realm.uuid, &requested_scopes.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+        .await
+        .unwrap_or_default();
+
+    let described_scopes = requested_scopes
+        .iter()
+        .map(|scope| {
+            let description = describe_scope(scope).map(String::from).or_else(|| {
+                resource_scopes
+                    .iter()
+                    .find(|s| s.name == *scope)
+                    .and_then(|s| s.description.clone())
+            });
+            (*scope, description)
+        })
+        .collect::<Vec<_>>();
+
+    Html(render_consent_page(&request.request_id, &client, &described_scopes, &additional_claims)).into_response()
+}
+
+/// Handles the user's decision on the consent page.
+/// An approval is persisted so the page is not shown again for the same scopes.
+pub async fn consent(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Form(form): Form<ConsentForm>,
+) -> Response {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    let consent_repository: Arc<dyn ConsentRepository> = state.module.resolve();
+
+    let request = auth_request_repository.find_by_id(&form.request_id).await;
+    let Some(request) = request.filter(|r| r.realm_uuid == realm.uuid) else {
+        return (StatusCode::BAD_REQUEST, "Unknown or expired authorization request").into_response();
+    };
+
+    let Some(user_id) = request.user_id else {
+        return Redirect::to(&format!("login?request_id={}", request.request_id)).into_response();
+    };
+
+    if form.decision != "approve" {
+        auth_request_repository.remove(&request.request_id).await;
+        return OAuthError::AccessDenied("The user denied the request".to_string())
+            .to_redirect_response(&request.redirect_uri, request.state.as_deref())
+            .into_response();
+    }
+
+    let Some(client) = client_repository.find_by_id(realm.uuid, &ClientId(request.client_id.clone())).await else {
+        return OAuthError::InvalidClient("Client not found".to_string())
+            .to_redirect_response(&request.redirect_uri, request.state.as_deref())
+            .into_response();
+    };
+
+    let grant_params = GrantConsentParams {
+        user_id,
+        client_uuid: client.uuid,
+        scopes: request.scope.split_whitespace().map(String::from).collect(),
+    };
+
+    if consent_repository.grant(grant_params).await.is_err() {
+        return OAuthError::ServerError("Failed to store consent".to_string())
+            .to_redirect_response(&request.redirect_uri, request.state.as_deref())
+            .into_response();
+    }
+
+    complete_authorization(&state, request).await
+}
+
+fn render_consent_page(
+    request_id: &str,
+    client: &Client,
+    scopes: &[(&str, Option<String>)],
+    additional_claims: &[&str],
+) -> String {
+    let scope_items = scopes
+        .iter()
+        .map(|(scope, description)| match description {
+            Some(description) => format!(
+                "<li><strong>{}</strong>: {}</li>",
+                html::escape(scope),
+                html::escape(description),
+            ),
+            None => format!("<li><strong>{}</strong></li>", html::escape(scope)),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let claims = if additional_claims.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<p>It also asks for the following information about you: {}</p>",
+            html::escape(&additional_claims.join(", "))
+        )
+    };
+
+    let logo = client
+        .logo_uri
+        .as_deref()
+        .map(|uri| format!(r#"<img src="{}" alt="" height="48">"#, html::escape(uri)))
+        .unwrap_or_default();
+
+    html::document(
+        "Authorize access",
+        &format!(
+            r#"{logo}
+<h1>Authorize {client}</h1>
+<p><strong>{client}</strong> is requesting permission to:</p>
+<ul>
+{scopes}
+</ul>
+{claims}
+<form method="post" action="consent">
+<input type="hidden" name="request_id" value="{request_id}">
+<button type="submit" name="decision" value="approve">Allow</button>
+<button type="submit" name="decision" value="deny">Deny</button>
+</form>"#,
+            logo = logo,
+            client = html::escape(client.client_name.as_deref().unwrap_or(&client.id.0)),
+            scopes = scope_items,
+            claims = claims,
+            request_id = html::escape(request_id),
+        ),
+    )
+}
@@ -1,17 +1,30 @@
 //! OpenID Connect Discovery endpoint implementation.
 //! Provides the OpenID Provider configuration information as specified in the OpenID Connect Discovery specification.
 
+use std::sync::Arc;
+use super::{claims, jwe, keys, scopes, subject};
 use super::types::OpenIDConfiguration;
 use crate::Config;
 use crate::config::OIDCConfig;
-use axum::extract::State;
+use axum::extract::{Extension, State};
 use axum::{response::Json, routing::get, Router};
+use shaku::HasComponent;
+use crate::domain::realm::Realm;
+use crate::repository::signing_key_repository::SigningKeyRepository;
 use crate::server::AppState;
 
 /// Handles the OpenID Configuration endpoint request.
-/// Returns a JSON response containing the OpenID Provider configuration information.
-pub async fn openid_configuration(State(app_state): State<AppState>) -> Json<OpenIDConfiguration> {
-    let base_url = app_state.config.oidc.external_url.unwrap();
+/// Returns a JSON response containing the OpenID Provider configuration information of the realm.
+pub async fn openid_configuration(
+    State(app_state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+) -> Json<OpenIDConfiguration> {
+    let base_url = app_state.external_url(&realm);
+
+    let signing_key_repository: Arc<dyn SigningKeyRepository> = app_state.module.resolve();
+    let signing_algorithms = keys::active_algorithms(signing_key_repository.as_ref(), realm.uuid)
+        .await
+        .unwrap_or_else(|_| vec![keys::DEFAULT_ALGORITHM.to_string()]);
 
     Json(OpenIDConfiguration {
         // The Issuer Identifier for the OpenID Provider
@@ -24,28 +37,53 @@ pub async fn openid_configuration(State(app_state): State<AppState>) -> Json<Ope
         userinfo_endpoint: format!("{}/userinfo", base_url),
         // URL of the JSON Web Key Set document
         jwks_uri: format!("{}/jwks", base_url),
+        // URL of the Token Introspection Endpoint
+        introspection_endpoint: format!("{}/introspect", base_url),
+        // URL of the Token Revocation Endpoint
+        revocation_endpoint: format!("{}/revoke", base_url),
+        // URL of the Dynamic Client Registration Endpoint
+        registration_endpoint: format!("{}/register", base_url),
         // List of OAuth 2.0 response_type values supported
         response_types_supported: vec!["code".to_string()],
+        // List of OAuth 2.0 grant types supported
+        grant_types_supported: vec![
+            "authorization_code".to_string(),
+            "client_credentials".to_string(),
+        ],
         // List of subject identifier types supported
-        subject_types_supported: vec!["public".to_string()],
+        subject_types_supported: to_strings(subject::supported_subject_types(&app_state.config.oidc)),
         // List of JWS signing algorithms supported for ID Token
-        id_token_signing_alg_values_supported: vec!["RS256".to_string()],
+        id_token_signing_alg_values_supported: signing_algorithms,
+        // List of JWE algorithms supported for encrypting ID Tokens
+        id_token_encryption_alg_values_supported: to_strings(jwe::SUPPORTED_ALGORITHMS),
+        id_token_encryption_enc_values_supported: to_strings(jwe::SUPPORTED_ENCRYPTIONS),
+        // List of JWE algorithms supported for encrypting UserInfo responses
+        userinfo_encryption_alg_values_supported: to_strings(jwe::SUPPORTED_ALGORITHMS),
+        userinfo_encryption_enc_values_supported: to_strings(jwe::SUPPORTED_ENCRYPTIONS),
         // List of OAuth 2.0 scope values supported
         scopes_supported: vec![
             "openid".to_string(),
             "profile".to_string(),
             "email".to_string(),
+            scopes::ORGANIZATION_SCOPE.to_string(),
         ],
         // List of client authentication methods supported
-        token_endpoint_auth_methods_supported: vec!["client_secret_basic".to_string()],
-        // List of claim names supported
-        claims_supported: vec![
-            "sub".to_string(),
-            "iss".to_string(),
-            "name".to_string(),
-            "email".to_string(),
+        token_endpoint_auth_methods_supported: vec![
+            "client_secret_basic".to_string(),
+            "client_secret_post".to_string(),
+            "none".to_string(),
         ],
+        // List of claim names supported
+        claims_supported: to_strings(&claims::supported_claims()),
+        // Claims can be requested individually for the ID token and the UserInfo response
+        claims_parameter_supported: true,
+        // List of authentication context classes supported
+        acr_values_supported: to_strings(claims::ACR_VALUES_SUPPORTED),
         // List of PKCE code challenge methods supported
         code_challenge_methods_supported: vec!["S256".to_string()],
     })
 }
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
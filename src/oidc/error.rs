@@ -1,5 +1,6 @@
 // src/oidc/error.rs
-use axum::response::Redirect;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Json, Redirect, Response};
 use serde::Serialize;
 use std::fmt;
 use url::Url;
@@ -14,28 +15,35 @@ pub enum OAuthError {
     ServerError(String),
     TemporarilyUnavailable(String),
     InvalidClient(String),
+    InvalidGrant(String),
+    UnsupportedGrantType(String),
+    InvalidToken(String),
+    /// The access token is valid but lacks the permission the request needs (RFC 6750, section 3.1)
+    InsufficientScope(String),
+    InvalidTarget(String),
+    InvalidRedirectUri(String),
+    InvalidClientMetadata(String),
+    /// The requested authentication context cannot be satisfied (OpenID Connect Unmet
+    /// Authentication Requirements 1.0)
+    UnmetAuthenticationRequirements(String),
+}
+
+#[derive(Serialize)]
+struct ErrorResponse<'a> {
+    error: &'a str,
+    error_description: &'a str,
 }
 
 impl fmt::Display for OAuthError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::InvalidRequest(desc) => write!(f, "invalid_request: {}", desc),
-            Self::UnauthorizedClient(desc) => write!(f, "unauthorized_client: {}", desc),
-            Self::AccessDenied(desc) => write!(f, "access_denied: {}", desc),
-            Self::UnsupportedResponseType(desc) => write!(f, "unsupported_response_type: {}", desc),
-            Self::InvalidScope(desc) => write!(f, "invalid_scope: {}", desc),
-            Self::ServerError(desc) => write!(f, "server_error: {}", desc),
-            Self::TemporarilyUnavailable(desc) => write!(f, "temporarily_unavailable: {}", desc),
-            Self::InvalidClient(desc) => write!(f, "invalid_client: {}", desc),       
-        }
+        let (error, description) = self.parts();
+        write!(f, "{}: {}", error, description)
     }
 }
 
 impl OAuthError {
-    pub fn to_redirect_response(&self, redirect_uri: &str, state: Option<&str>) -> Redirect {
-        let mut url = Url::parse(redirect_uri).expect("valid redirect URI");
-        
-        let (error, description) = match self {
+    fn parts(&self) -> (&'static str, &str) {
+        match self {
             Self::InvalidRequest(desc) => ("invalid_request", desc),
             Self::UnauthorizedClient(desc) => ("unauthorized_client", desc),
             Self::AccessDenied(desc) => ("access_denied", desc),
@@ -43,8 +51,22 @@ impl OAuthError {
             Self::InvalidScope(desc) => ("invalid_scope", desc),
             Self::ServerError(desc) => ("server_error", desc),
             Self::TemporarilyUnavailable(desc) => ("temporarily_unavailable", desc),
-            Self::InvalidClient(desc) => ("invalid_client", desc),       
-        };
+            Self::InvalidClient(desc) => ("invalid_client", desc),
+            Self::InvalidGrant(desc) => ("invalid_grant", desc),
+            Self::UnsupportedGrantType(desc) => ("unsupported_grant_type", desc),
+            Self::InvalidToken(desc) => ("invalid_token", desc),
+            Self::InsufficientScope(desc) => ("insufficient_scope", desc),
+            Self::InvalidTarget(desc) => ("invalid_target", desc),
+            Self::InvalidRedirectUri(desc) => ("invalid_redirect_uri", desc),
+            Self::InvalidClientMetadata(desc) => ("invalid_client_metadata", desc),
+            Self::UnmetAuthenticationRequirements(desc) => ("unmet_authentication_requirements", desc),
+        }
+    }
+
+    pub fn to_redirect_response(&self, redirect_uri: &str, state: Option<&str>) -> Redirect {
+        let mut url = Url::parse(redirect_uri).expect("valid redirect URI");
+
+        let (error, description) = self.parts();
 
         url.query_pairs_mut()
             .append_pair("error", error)
@@ -56,4 +78,44 @@ impl OAuthError {
 
         Redirect::to(url.as_str())
     }
-}
\ No newline at end of file
+
+    /// Renders the error as a JSON body, as used by the token, introspection, revocation and
+    /// registration endpoints.
+    pub fn to_json_response(&self) -> Response {
+        let (error, description) = self.parts();
+        let body = Json(ErrorResponse {
+            error,
+            error_description: description,
+        });
+
+        match self {
+            Self::InvalidClient(_) => (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Basic")],
+                body,
+            )
+                .into_response(),
+            Self::InvalidToken(_) => (
+                StatusCode::UNAUTHORIZED,
+                [(
+                    header::WWW_AUTHENTICATE,
+                    format!("Bearer error=\"{}\", error_description=\"{}\"", error, description),
+                )],
+                body,
+            )
+                .into_response(),
+            Self::InsufficientScope(_) => (
+                StatusCode::FORBIDDEN,
+                [(
+                    header::WWW_AUTHENTICATE,
+                    format!("Bearer error=\"{}\", error_description=\"{}\"", error, description),
+                )],
+                body,
+            )
+                .into_response(),
+            Self::ServerError(_) => (StatusCode::INTERNAL_SERVER_ERROR, body).into_response(),
+            Self::TemporarilyUnavailable(_) => (StatusCode::SERVICE_UNAVAILABLE, body).into_response(),
+            _ => (StatusCode::BAD_REQUEST, body).into_response(),
+        }
+    }
+}
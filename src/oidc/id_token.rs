@@ -0,0 +1,119 @@
+//! ID token issuance.
+//! ID tokens are signed with the client's chosen algorithm and, if the client asked for it,
+//! encrypted to its registered keys.
+
+use std::sync::Arc;
+use super::claims::{self, RequestedClaims};
+use super::{jwe, keys, mappers, organizations, roles, subject};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use shaku::HasComponent;
+use uuid::Uuid;
+use crate::domain::client::Client;
+use crate::domain::realm::Realm;
+use crate::domain::protocol_mapper::TokenTarget;
+use crate::repository::signing_key_repository::SigningKeyRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::server::AppState;
+
+/// The claims of an ID token as defined by OpenID Connect Core 1.0, section 2
+#[derive(Debug, Serialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acr: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    /// Claims requested through the "claims" parameter, role claims and claims added by protocol mappers
+    #[serde(flatten)]
+    pub user_claims: Map<String, Value>,
+}
+
+/// What an ID token is issued for
+pub struct IdTokenParams<'a> {
+    pub user_id: Uuid,
+    pub scopes: &'a [String],
+    pub auth_time: Option<DateTime<Utc>>,
+    /// How the user authenticated
+    pub amr: Vec<String>,
+    pub nonce: Option<String>,
+    /// Claims requested individually for the ID token
+    pub requested: &'a RequestedClaims,
+    /// The organization the user picked when logging in
+    pub organization_id: Option<Uuid>,
+}
+
+/// Issues a signed ID token for the user, addressed to the client. Claims about the user
+/// are only included when requested for the ID token, the scopes release them at UserInfo.
+/// Clients with role claims enabled get the user's roles, and protocol mappers of the client
+/// and the granted scopes add their claims. The organization scope adds the picked organization.
+/// The authentication context class is included when requested or when a second factor was used.
+pub async fn issue(
+    state: &AppState,
+    realm: &Realm,
+    client: &Client,
+    params: IdTokenParams<'_>,
+) -> Result<String, String> {
+    let IdTokenParams {
+        user_id,
+        scopes,
+        auth_time,
+        amr,
+        nonce,
+        requested,
+        organization_id,
+    } = params;
+    let lifetime = client
+        .id_token_lifetime
+        .map(i64::from)
+        .unwrap_or(state.config.oidc.id_token_lifetime.unwrap() as i64);
+    let now = Utc::now();
+
+    let requested_names = requested.keys().cloned().collect::<Vec<_>>();
+    let mut user_claims = if requested_names.is_empty() {
+        Map::new()
+    } else {
+        let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+        let user = user_repository.find_by_id(realm.uuid, user_id).await.ok_or("user not found")?;
+        claims::released_claims(&user, &[], &requested_names)
+    };
+    if client.id_token_role_claims {
+        mappers::merge_claims(&mut user_claims, roles::user_role_claims(state, user_id).await?);
+    }
+    let mapped = mappers::mapped_claims(state, realm, client, scopes, Some(user_id), TokenTarget::IdToken).await?;
+    mappers::merge_claims(&mut user_claims, mapped);
+    mappers::merge_claims(
+        &mut user_claims,
+        organizations::organization_claims(state, scopes, organization_id, user_id).await,
+    );
+
+    let claims = IdTokenClaims {
+        iss: state.external_url(realm),
+        sub: subject::subject_identifier(&state.config.oidc, client, user_id)?,
+        aud: client.id.0.clone(),
+        exp: (now + Duration::seconds(lifetime)).timestamp(),
+        iat: now.timestamp(),
+        auth_time: auth_time.map(|t| t.timestamp()),
+        nonce,
+        acr: (requested.contains_key("acr") || amr.iter().any(|m| m == claims::AMR_MFA))
+            .then(|| claims::acr_for(&amr).to_string()),
+        amr,
+        user_claims,
+    };
+
+    let signing_key_repository: Arc<dyn SigningKeyRepository> = state.module.resolve();
+    let key = keys::active_key(signing_key_repository.as_ref(), realm.uuid, &client.id_token_signed_response_alg).await?;
+
+    let id_token = keys::sign(&key, "JWT", &claims)?;
+
+    jwe::encrypt_id_token(client, id_token)
+}
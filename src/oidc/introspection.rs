@@ -0,0 +1,115 @@
+//! OAuth 2.0 Token Introspection endpoint implementation (RFC 7662).
+//! Lets resource servers check whether an access token is active, for either token format.
+
+use std::sync::Arc;
+use super::access_token;
+use super::client_auth::authenticate_client;
+use super::error::OAuthError;
+use axum::extract::{Extension, State};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Json, Response};
+use axum::Form;
+use serde::{Deserialize, Serialize};
+use shaku::HasComponent;
+use crate::repository::client_repository::ClientRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::domain::realm::Realm;
+use crate::server::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct IntrospectionRequest {
+    token: String,
+    /// Accepted for compatibility; only access tokens are issued, so it is ignored
+    #[allow(dead_code)]
+    token_type_hint: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectionResponse {
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aud: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,
+}
+
+/// Handles introspection requests. Only confidential clients may introspect tokens.
+pub async fn introspect(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    headers: HeaderMap,
+    Form(request): Form<IntrospectionRequest>,
+) -> Response {
+    let client = match authenticate_client(
+        &state,
+        &realm,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await
+    {
+        Ok(client) => client,
+        Err(e) => return e.to_json_response(),
+    };
+
+    if client.is_public() {
+        return OAuthError::UnauthorizedClient("Public clients cannot introspect tokens".to_string())
+            .to_json_response();
+    }
+
+    let Some(token) = access_token::validate(&state, &realm, &request.token).await else {
+        return Json(IntrospectionResponse::default()).into_response();
+    };
+
+    // The subject as the client the token was issued to knows it
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    let Some(token_client) = client_repository.find_by_id(realm.uuid, &token.client_id).await else {
+        return Json(IntrospectionResponse::default()).into_response();
+    };
+    let sub = match access_token::subject(&state.config.oidc, &token_client, &token) {
+        Ok(sub) => sub,
+        Err(e) => return OAuthError::ServerError(e).to_json_response(),
+    };
+
+    let username = match token.user_id {
+        Some(user_id) => {
+            let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+            user_repository.find_by_id(realm.uuid, user_id).await.map(|user| user.username)
+        }
+        None => None,
+    };
+
+    Json(IntrospectionResponse {
+        active: true,
+        scope: Some(token.scopes.join(" ")),
+        client_id: Some(token.client_id.0.clone()),
+        username,
+        token_type: Some("Bearer".to_string()),
+        exp: Some(token.expires_at.timestamp()),
+        iat: Some(token.created_at.timestamp()),
+        sub: Some(sub),
+        aud: Some(token.audience.clone()),
+        iss: Some(state.external_url(&realm)),
+        jti: Some(token.uuid.to_string()),
+    })
+    .into_response()
+}
@@ -0,0 +1,136 @@
+//! Organization invitations.
+//! Administrators hand out invitation links; the invited user opens the link and signs in with
+//! the account of the invited email address to join the organization with the invited roles.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use axum::extract::{ConnectInfo, Extension, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Form;
+use serde::Deserialize;
+use shaku::HasComponent;
+use super::access_token::hash_token;
+use super::lockout;
+use crate::domain::organization::{Invitation, Organization};
+use crate::domain::realm::Realm;
+use crate::repository::organization_repository::OrganizationRepository;
+use crate::server::AppState;
+use crate::utils::html;
+
+#[derive(Debug, Deserialize)]
+pub struct InvitationQuery {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvitationForm {
+    token: String,
+    /// Either the username or the email address of the user
+    username: String,
+    password: String,
+}
+
+/// Renders the sign-in form of an invitation link.
+pub async fn invitation_page(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Query(query): Query<InvitationQuery>,
+) -> Response {
+    match find_invitation(&state, &realm, &query.token).await {
+        Ok((_, organization)) => Html(render_invitation_page(&query.token, &organization, None)).into_response(),
+        Err(response) => response,
+    }
+}
+
+/// Signs the invited user in and adds them to the organization. The invitation is used up.
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Form(form): Form<InvitationForm>,
+) -> Response {
+    let (invitation, organization) = match find_invitation(&state, &realm, &form.token).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    let client = lockout::client_address(&state, peer, &headers);
+    let user = match lockout::authenticate(&state, &realm, client, &form.username, &form.password).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            let page = render_invitation_page(&form.token, &organization, Some("Invalid username or password"));
+            return (StatusCode::UNAUTHORIZED, Html(page)).into_response();
+        }
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+
+    if !user.is_active() {
+        let page = render_invitation_page(&form.token, &organization, Some("This account is disabled"));
+        return (StatusCode::FORBIDDEN, Html(page)).into_response();
+    }
+
+    if !invitation.is_for(&user.email) {
+        let page = render_invitation_page(
+            &form.token,
+            &organization,
+            Some("This invitation was sent to another email address"),
+        );
+        return (StatusCode::FORBIDDEN, Html(page)).into_response();
+    }
+
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+    if let Err(e) = organization_repository.add_member(organization.uuid, user.uuid, &invitation.roles).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+    }
+    if let Err(e) = organization_repository.delete_invitation(organization.uuid, invitation.uuid).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+    }
+
+    let body = format!(
+        "<h1>Welcome</h1>\n<p>You joined <strong>{}</strong>.</p>",
+        html::escape(organization.display_name())
+    );
+    Html(html::document("Invitation accepted", &body)).into_response()
+}
+
+async fn find_invitation(state: &AppState, realm: &Realm, token: &str) -> Result<(Invitation, Organization), Response> {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+    let invalid = || (StatusCode::BAD_REQUEST, "Unknown or expired invitation").into_response();
+
+    let invitation = organization_repository
+        .find_invitation_by_hash(realm.uuid, &hash_token(token))
+        .await
+        .filter(|i| !i.is_expired())
+        .ok_or_else(invalid)?;
+    let organization = organization_repository
+        .find_by_id(realm.uuid, invitation.organization_uuid)
+        .await
+        .ok_or_else(invalid)?;
+
+    Ok((invitation, organization))
+}
+
+fn render_invitation_page(token: &str, organization: &Organization, error: Option<&str>) -> String {
+    let error = error
+        .map(|e| format!("<p class=\"error\">{}</p>\n", html::escape(e)))
+        .unwrap_or_default();
+
+    html::document(
+        "Join an organization",
+        &format!(
+            r#"<h1>Join {organization}</h1>
+<p>Sign in to accept the invitation to <strong>{organization}</strong>.</p>
+{error}<form method="post" action="invitation">
+<input type="hidden" name="token" value="{token}">
+<label>Username or email <input type="text" name="username" autocomplete="username" required></label>
+<label>Password <input type="password" name="password" autocomplete="current-password" required></label>
+<button type="submit">Join</button>
+</form>"#,
+            organization = html::escape(organization.display_name()),
+            error = error,
+            token = html::escape(token),
+        ),
+    )
+}
@@ -0,0 +1,326 @@
+//! JSON Web Encryption (RFC 7516) of ID tokens and UserInfo responses.
+//! Tokens are encrypted to a key from the client's registered JWKS, using RSA-OAEP-256 key
+//! encryption or ECDH-ES direct key agreement, with A256GCM content encryption.
+
+use super::types::{JsonWebKey, JsonWebKeySet};
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rand::RngCore;
+use rsa::rand_core::OsRng;
+use rsa::{BigUint, Oaep, RsaPublicKey};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use crate::domain::client::Client;
+
+/// Key management algorithms we can encrypt with
+pub const SUPPORTED_ALGORITHMS: &[&str] = &["RSA-OAEP-256", "ECDH-ES"];
+
+/// Content encryption algorithms we can encrypt with
+pub const SUPPORTED_ENCRYPTIONS: &[&str] = &["A256GCM"];
+
+/// The content encryption used when a client only registers a key management algorithm
+pub const DEFAULT_ENCRYPTION: &str = "A256GCM";
+
+const CEK_BYTES: usize = 32;
+const IV_BYTES: usize = 12;
+
+#[derive(Serialize)]
+struct JweHeader<'a> {
+    alg: &'a str,
+    enc: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kid: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cty: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    epk: Option<JsonWebKey>,
+}
+
+/// Encrypts the ID token for the client if it registered `id_token_encrypted_response_alg`,
+/// otherwise returns the signed token unchanged.
+pub fn encrypt_id_token(client: &Client, id_token: String) -> Result<String, String> {
+    match &client.id_token_encrypted_response_alg {
+        Some(alg) => {
+            let enc = client.id_token_encrypted_response_enc.as_deref().unwrap_or(DEFAULT_ENCRYPTION);
+            encrypt(id_token.as_bytes(), &client_jwks(client)?, alg, enc, Some("JWT"))
+        }
+        None => Ok(id_token),
+    }
+}
+
+/// Encrypts the UserInfo claims for the client if it registered `userinfo_encrypted_response_alg`.
+pub fn encrypt_userinfo(client: &Client, claims: &[u8]) -> Option<Result<String, String>> {
+    let alg = client.userinfo_encrypted_response_alg.as_deref()?;
+    let enc = client.userinfo_encrypted_response_enc.as_deref().unwrap_or(DEFAULT_ENCRYPTION);
+
+    Some(client_jwks(client).and_then(|jwks| encrypt(claims, &jwks, alg, enc, None)))
+}
+
+fn client_jwks(client: &Client) -> Result<JsonWebKeySet, String> {
+    let jwks = client
+        .jwks
+        .clone()
+        .ok_or_else(|| format!("Client {} has no registered JWKS", client.id.0))?;
+
+    serde_json::from_value(jwks).map_err(|e| format!("Invalid JWKS of client {}: {}", client.id.0, e))
+}
+
+/// Encrypts the plaintext to a suitable key of the JWKS, in JWE compact serialization.
+pub fn encrypt(plaintext: &[u8], jwks: &JsonWebKeySet, alg: &str, enc: &str, cty: Option<&str>) -> Result<String, String> {
+    if !SUPPORTED_ENCRYPTIONS.contains(&enc) {
+        return Err(format!("unsupported content encryption: {}", enc));
+    }
+
+    let key = select_key(jwks, alg).ok_or_else(|| format!("No {} encryption key in the JWKS", alg))?;
+
+    let (cek, encrypted_key, epk) = match alg {
+        "RSA-OAEP-256" => {
+            let mut cek = vec![0u8; CEK_BYTES];
+            rand::rng().fill_bytes(&mut cek);
+            let encrypted_key = rsa_public_key(key)?
+                .encrypt(&mut OsRng, Oaep::new::<Sha256>(), &cek)
+                .map_err(|e| e.to_string())?;
+            (cek, encrypted_key, None)
+        }
+        "ECDH-ES" => {
+            let (z, epk) = ecdh_agreement(key)?;
+            (concat_kdf(&z, enc, b"", b"", CEK_BYTES * 8), Vec::new(), Some(epk))
+        }
+        other => return Err(format!("unsupported key management algorithm: {}", other)),
+    };
+
+    let header = JweHeader {
+        alg,
+        enc,
+        kid: key.kid.as_deref(),
+        cty,
+        epk,
+    };
+    let protected = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header).map_err(|e| e.to_string())?);
+
+    let mut iv = [0u8; IV_BYTES];
+    rand::rng().fill_bytes(&mut iv);
+
+    let cipher = Aes256Gcm::new_from_slice(&cek).map_err(|e| e.to_string())?;
+    let mut ciphertext = plaintext.to_vec();
+    let tag = cipher
+        .encrypt_in_place_detached(Nonce::from_slice(&iv), protected.as_bytes(), &mut ciphertext)
+        .map_err(|e| e.to_string())?;
+
+    Ok(format!(
+        "{}.{}.{}.{}.{}",
+        protected,
+        URL_SAFE_NO_PAD.encode(encrypted_key),
+        URL_SAFE_NO_PAD.encode(iv),
+        URL_SAFE_NO_PAD.encode(ciphertext),
+        URL_SAFE_NO_PAD.encode(tag),
+    ))
+}
+
+/// Picks the first key usable for encryption with the algorithm.
+fn select_key<'a>(jwks: &'a JsonWebKeySet, alg: &str) -> Option<&'a JsonWebKey> {
+    let kty = match alg {
+        "RSA-OAEP-256" => "RSA",
+        "ECDH-ES" => "EC",
+        _ => return None,
+    };
+
+    jwks.keys.iter().find(|key| {
+        key.kty == kty
+            && key.key_use.as_deref().is_none_or(|u| u == "enc")
+            && key.alg.as_deref().is_none_or(|a| a == alg)
+    })
+}
+
+fn decode_parameter(value: &Option<String>, name: &str) -> Result<Vec<u8>, String> {
+    let value = value.as_deref().ok_or_else(|| format!("JWK is missing '{}'", name))?;
+    URL_SAFE_NO_PAD.decode(value).map_err(|e| e.to_string())
+}
+
+fn rsa_public_key(key: &JsonWebKey) -> Result<RsaPublicKey, String> {
+    let n = BigUint::from_bytes_be(&decode_parameter(&key.n, "n")?);
+    let e = BigUint::from_bytes_be(&decode_parameter(&key.e, "e")?);
+    RsaPublicKey::new(n, e).map_err(|e| e.to_string())
+}
+
+/// Agrees on a shared secret with the recipient key using a fresh ephemeral key, returning
+/// the secret and the ephemeral public key for the "epk" header.
+fn ecdh_agreement(key: &JsonWebKey) -> Result<(Vec<u8>, JsonWebKey), String> {
+    // Uncompressed SEC1 encoding of the recipient's public point
+    let mut point = vec![0x04];
+    point.extend(decode_parameter(&key.x, "x")?);
+    point.extend(decode_parameter(&key.y, "y")?);
+
+    match key.crv.as_deref() {
+        Some("P-256") => {
+            let public_key = p256::PublicKey::from_sec1_bytes(&point).map_err(|e| e.to_string())?;
+            let ephemeral = p256::ecdh::EphemeralSecret::random(&mut OsRng);
+            let z = ephemeral.diffie_hellman(&public_key).raw_secret_bytes().to_vec();
+            let epk = ephemeral.public_key().to_encoded_point(false);
+            Ok((z, ephemeral_jwk("P-256", epk.x(), epk.y())))
+        }
+        Some("P-384") => {
+            let public_key = p384::PublicKey::from_sec1_bytes(&point).map_err(|e| e.to_string())?;
+            let ephemeral = p384::ecdh::EphemeralSecret::random(&mut OsRng);
+            let z = ephemeral.diffie_hellman(&public_key).raw_secret_bytes().to_vec();
+            let epk = ephemeral.public_key().to_encoded_point(false);
+            Ok((z, ephemeral_jwk("P-384", epk.x(), epk.y())))
+        }
+        other => Err(format!("unsupported curve: {}", other.unwrap_or("none"))),
+    }
+}
+
+fn ephemeral_jwk<B: AsRef<[u8]>>(crv: &str, x: Option<B>, y: Option<B>) -> JsonWebKey {
+    JsonWebKey {
+        kty: "EC".to_string(),
+        key_use: None,
+        alg: None,
+        kid: None,
+        n: None,
+        e: None,
+        crv: Some(crv.to_string()),
+        x: x.map(|x| URL_SAFE_NO_PAD.encode(x)),
+        y: y.map(|y| URL_SAFE_NO_PAD.encode(y)),
+    }
+}
+
+/// The Concat KDF of NIST SP 800-56A with SHA-256, as used by ECDH-ES (RFC 7518, section 4.6.2).
+fn concat_kdf(z: &[u8], algorithm_id: &str, apu: &[u8], apv: &[u8], key_bits: usize) -> Vec<u8> {
+    let mut other_info = Vec::new();
+    for field in [algorithm_id.as_bytes(), apu, apv] {
+        other_info.extend((field.len() as u32).to_be_bytes());
+        other_info.extend(field);
+    }
+    other_info.extend((key_bits as u32).to_be_bytes());
+
+    let key_bytes = key_bits / 8;
+    let mut key = Vec::with_capacity(key_bytes);
+    let mut counter: u32 = 1;
+    while key.len() < key_bytes {
+        let mut hasher = Sha256::new();
+        hasher.update(counter.to_be_bytes());
+        hasher.update(z);
+        hasher.update(&other_info);
+        key.extend(hasher.finalize());
+        counter += 1;
+    }
+    key.truncate(key_bytes);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::traits::PublicKeyParts;
+    use rsa::RsaPrivateKey;
+    use serde_json::Value;
+
+    /// Splits a compact JWE and decrypts its content with the given CEK.
+    fn decrypt_content(jwe: &str, cek: &[u8]) -> Vec<u8> {
+        let parts = jwe.split('.').collect::<Vec<_>>();
+        let iv = URL_SAFE_NO_PAD.decode(parts[2]).unwrap();
+        let mut plaintext = URL_SAFE_NO_PAD.decode(parts[3]).unwrap();
+        let tag = URL_SAFE_NO_PAD.decode(parts[4]).unwrap();
+
+        Aes256Gcm::new_from_slice(cek)
+            .unwrap()
+            .decrypt_in_place_detached(Nonce::from_slice(&iv), parts[0].as_bytes(), &mut plaintext, tag.as_slice().into())
+            .unwrap();
+        plaintext
+    }
+
+    fn header(jwe: &str) -> Value {
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(jwe.split('.').next().unwrap()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_concat_kdf_rfc7518_example() {
+        // Example from RFC 7518, appendix C
+        let z = [
+            158, 86, 217, 29, 129, 113, 53, 211, 114, 131, 66, 131, 191, 132, 38, 156, 251, 49, 110, 163, 218, 128,
+            106, 72, 246, 218, 167, 121, 140, 254, 144, 196,
+        ];
+
+        let key = concat_kdf(&z, "A128GCM", b"Alice", b"Bob", 128);
+
+        assert_eq!(URL_SAFE_NO_PAD.encode(key), "VqqN6vgjbSBcIijNcacQGg");
+    }
+
+    #[test]
+    fn test_rsa_oaep_256_round_trip() {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 2048).unwrap();
+        let jwks = JsonWebKeySet {
+            keys: vec![JsonWebKey {
+                kty: "RSA".to_string(),
+                key_use: Some("enc".to_string()),
+                alg: None,
+                kid: Some("rsa-1".to_string()),
+                n: Some(URL_SAFE_NO_PAD.encode(private_key.n().to_bytes_be())),
+                e: Some(URL_SAFE_NO_PAD.encode(private_key.e().to_bytes_be())),
+                crv: None,
+                x: None,
+                y: None,
+            }],
+        };
+
+        let jwe = encrypt(b"a.signed.jwt", &jwks, "RSA-OAEP-256", "A256GCM", Some("JWT")).unwrap();
+
+        let header = header(&jwe);
+        assert_eq!(header["alg"], "RSA-OAEP-256");
+        assert_eq!(header["enc"], "A256GCM");
+        assert_eq!(header["kid"], "rsa-1");
+        assert_eq!(header["cty"], "JWT");
+
+        let encrypted_key = URL_SAFE_NO_PAD.decode(jwe.split('.').nth(1).unwrap()).unwrap();
+        let cek = private_key.decrypt(Oaep::new::<Sha256>(), &encrypted_key).unwrap();
+        assert_eq!(decrypt_content(&jwe, &cek), b"a.signed.jwt");
+    }
+
+    #[test]
+    fn test_ecdh_es_round_trip() {
+        let private_key = p256::SecretKey::random(&mut OsRng);
+        let point = private_key.public_key().to_encoded_point(false);
+        let mut jwk = ephemeral_jwk("P-256", point.x(), point.y());
+        jwk.kid = Some("ec-1".to_string());
+        let jwks = JsonWebKeySet { keys: vec![jwk] };
+
+        let jwe = encrypt(b"{\"sub\":\"alice\"}", &jwks, "ECDH-ES", "A256GCM", None).unwrap();
+
+        let header = header(&jwe);
+        assert_eq!(header["alg"], "ECDH-ES");
+        assert_eq!(header["epk"]["crv"], "P-256");
+        assert!(header.get("cty").is_none());
+        assert_eq!(jwe.split('.').nth(1), Some(""));
+
+        let mut epk = vec![0x04];
+        epk.extend(URL_SAFE_NO_PAD.decode(header["epk"]["x"].as_str().unwrap()).unwrap());
+        epk.extend(URL_SAFE_NO_PAD.decode(header["epk"]["y"].as_str().unwrap()).unwrap());
+        let epk = p256::PublicKey::from_sec1_bytes(&epk).unwrap();
+        let z = p256::ecdh::diffie_hellman(private_key.to_nonzero_scalar(), epk.as_affine());
+
+        let cek = concat_kdf(z.raw_secret_bytes(), "A256GCM", b"", b"", 256);
+        assert_eq!(decrypt_content(&jwe, &cek), b"{\"sub\":\"alice\"}");
+    }
+
+    #[test]
+    fn test_key_selection() {
+        let signing_key = JsonWebKey {
+            kty: "RSA".to_string(),
+            key_use: Some("sig".to_string()),
+            alg: None,
+            kid: None,
+            n: Some("AQAB".to_string()),
+            e: Some("AQAB".to_string()),
+            crv: None,
+            x: None,
+            y: None,
+        };
+        let jwks = JsonWebKeySet { keys: vec![signing_key] };
+
+        assert!(select_key(&jwks, "RSA-OAEP-256").is_none());
+        assert!(encrypt(b"", &jwks, "RSA-OAEP-256", "A128GCM", None).is_err());
+    }
+}
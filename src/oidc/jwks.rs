@@ -0,0 +1,28 @@
+//! JSON Web Key Set endpoint implementation.
+//! Publishes the public keys resource servers and relying parties use to verify our tokens.
+
+use std::sync::Arc;
+use super::keys;
+use super::types::JsonWebKeySet;
+use axum::extract::{Extension, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json};
+use shaku::HasComponent;
+use crate::domain::realm::Realm;
+use crate::repository::signing_key_repository::SigningKeyRepository;
+use crate::server::AppState;
+
+/// Returns every signing key of the realm, so tokens signed by a deactivated key remain verifiable.
+pub async fn jwks(State(state): State<AppState>, Extension(realm): Extension<Realm>) -> impl IntoResponse {
+    let signing_key_repository: Arc<dyn SigningKeyRepository> = state.module.resolve();
+
+    let keys = match signing_key_repository.list(realm.uuid).await {
+        Ok(keys) => keys,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+
+    match keys.iter().map(keys::to_jwk).collect::<Result<Vec<_>, _>>() {
+        Ok(keys) => Json(JsonWebKeySet { keys }).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
@@ -0,0 +1,288 @@
+//! Signing key management.
+//! Generates and loads the keys used to sign ID tokens and JWT access tokens.
+
+use super::types::JsonWebKey;
+use crate::domain::signing_key::SigningKey;
+use crate::repository::signing_key_repository::{CreateSigningKeyParams, SigningKeyRepository};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rsa::pkcs8::{DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::rand_core::OsRng;
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// The algorithm used when a client does not ask for a specific one
+pub const DEFAULT_ALGORITHM: &str = "RS256";
+
+/// Algorithms keys can be generated for
+pub const SUPPORTED_ALGORITHMS: &[&str] = &["RS256", "PS256", "ES256", "ES384", "EdDSA"];
+
+const RSA_KEY_BITS: usize = 2048;
+
+/// The kind of key pair an algorithm needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyType {
+    Rsa,
+    P256,
+    P384,
+    Ed25519,
+}
+
+fn key_type(algorithm: &str) -> Result<KeyType, String> {
+    match algorithm {
+        "RS256" | "PS256" => Ok(KeyType::Rsa),
+        "ES256" => Ok(KeyType::P256),
+        "ES384" => Ok(KeyType::P384),
+        "EdDSA" => Ok(KeyType::Ed25519),
+        other => Err(format!("unsupported signing algorithm: {}", other)),
+    }
+}
+
+/// Makes sure the realm has an active key for every supported algorithm, generating the
+/// missing ones. Clients may ask for any of them, and relying parties must find the keys in the
+/// JWKS before the first token signed with them.
+pub async fn ensure_signing_keys(repository: &dyn SigningKeyRepository, realm_uuid: Uuid) -> Result<(), String> {
+    for algorithm in SUPPORTED_ALGORITHMS {
+        active_key(repository, realm_uuid, algorithm).await?;
+    }
+
+    Ok(())
+}
+
+/// Returns the realm's active key for the algorithm, generating one if there is none yet.
+pub async fn active_key(
+    repository: &dyn SigningKeyRepository,
+    realm_uuid: Uuid,
+    algorithm: &str,
+) -> Result<SigningKey, String> {
+    if let Some(key) = repository.find_active(realm_uuid, algorithm).await {
+        return Ok(key);
+    }
+
+    repository.create(generate_key(realm_uuid, algorithm)?).await
+}
+
+/// Generates a new key for the algorithm and signs with it from now on. Tokens signed with
+/// the previous keys stay valid, as those keys are still published.
+pub async fn rotate_key(
+    repository: &dyn SigningKeyRepository,
+    realm_uuid: Uuid,
+    algorithm: &str,
+) -> Result<SigningKey, String> {
+    let key = repository.create(generate_key(realm_uuid, algorithm)?).await?;
+    repository.deactivate_others(realm_uuid, algorithm, &key.kid).await?;
+
+    Ok(key)
+}
+
+/// The distinct algorithms of the active keys, as advertised in discovery.
+pub async fn active_algorithms(repository: &dyn SigningKeyRepository, realm_uuid: Uuid) -> Result<Vec<String>, String> {
+    let algorithms = repository
+        .list(realm_uuid)
+        .await?
+        .into_iter()
+        .filter(|key| key.active)
+        .map(|key| key.algorithm)
+        .collect::<BTreeSet<_>>();
+
+    Ok(algorithms.into_iter().collect())
+}
+
+/// Generates a new key pair for the given JWS algorithm.
+pub fn generate_key(realm_uuid: Uuid, algorithm: &str) -> Result<CreateSigningKeyParams, String> {
+    let (private_pem, public_pem) = match key_type(algorithm)? {
+        KeyType::Rsa => {
+            let private_key = RsaPrivateKey::new(&mut OsRng, RSA_KEY_BITS).map_err(|e| e.to_string())?;
+            let public_key = private_key.to_public_key();
+            (
+                private_key.to_pkcs8_pem(LineEnding::LF).map_err(|e| e.to_string())?,
+                public_key.to_public_key_pem(LineEnding::LF).map_err(|e| e.to_string())?,
+            )
+        }
+        KeyType::P256 => {
+            let private_key = p256::SecretKey::random(&mut OsRng);
+            (
+                private_key.to_pkcs8_pem(LineEnding::LF).map_err(|e| e.to_string())?,
+                private_key.public_key().to_public_key_pem(LineEnding::LF).map_err(|e| e.to_string())?,
+            )
+        }
+        KeyType::P384 => {
+            let private_key = p384::SecretKey::random(&mut OsRng);
+            (
+                private_key.to_pkcs8_pem(LineEnding::LF).map_err(|e| e.to_string())?,
+                private_key.public_key().to_public_key_pem(LineEnding::LF).map_err(|e| e.to_string())?,
+            )
+        }
+        KeyType::Ed25519 => {
+            let private_key = ed25519_dalek::SigningKey::generate(&mut OsRng);
+            (
+                private_key.to_pkcs8_pem(LineEnding::LF).map_err(|e| e.to_string())?,
+                private_key.verifying_key().to_public_key_pem(LineEnding::LF).map_err(|e| e.to_string())?,
+            )
+        }
+    };
+
+    Ok(CreateSigningKeyParams {
+        realm_uuid,
+        kid: Uuid::new_v4().to_string(),
+        algorithm: algorithm.to_string(),
+        private_key: private_pem.to_string(),
+        public_key: public_pem,
+    })
+}
+
+pub fn algorithm(key: &SigningKey) -> Result<Algorithm, String> {
+    Algorithm::from_str(&key.algorithm).map_err(|e| e.to_string())
+}
+
+/// Signs the claims with the key, setting the "kid" and "typ" headers.
+pub fn sign<T: Serialize>(key: &SigningKey, typ: &str, claims: &T) -> Result<String, String> {
+    let mut header = Header::new(algorithm(key)?);
+    header.kid = Some(key.kid.clone());
+    header.typ = Some(typ.to_string());
+
+    let pem = key.private_key.as_bytes();
+    let encoding_key = match key_type(&key.algorithm)? {
+        KeyType::Rsa => EncodingKey::from_rsa_pem(pem),
+        KeyType::P256 | KeyType::P384 => EncodingKey::from_ec_pem(pem),
+        KeyType::Ed25519 => EncodingKey::from_ed_pem(pem),
+    }
+    .map_err(|e| e.to_string())?;
+
+    jsonwebtoken::encode(&header, claims, &encoding_key).map_err(|e| e.to_string())
+}
+
+pub fn decoding_key(key: &SigningKey) -> Result<DecodingKey, String> {
+    let pem = key.public_key.as_bytes();
+    match key_type(&key.algorithm)? {
+        KeyType::Rsa => DecodingKey::from_rsa_pem(pem),
+        KeyType::P256 | KeyType::P384 => DecodingKey::from_ec_pem(pem),
+        KeyType::Ed25519 => DecodingKey::from_ed_pem(pem),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Converts the public half of the key into its JWK representation.
+pub fn to_jwk(key: &SigningKey) -> Result<JsonWebKey, String> {
+    let mut jwk = JsonWebKey {
+        kty: String::new(),
+        key_use: Some("sig".to_string()),
+        alg: Some(key.algorithm.clone()),
+        kid: Some(key.kid.clone()),
+        n: None,
+        e: None,
+        crv: None,
+        x: None,
+        y: None,
+    };
+
+    match key_type(&key.algorithm)? {
+        KeyType::Rsa => {
+            let public_key = RsaPublicKey::from_public_key_pem(&key.public_key).map_err(|e| e.to_string())?;
+            jwk.kty = "RSA".to_string();
+            jwk.n = Some(URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()));
+            jwk.e = Some(URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()));
+        }
+        KeyType::P256 => {
+            let point = p256::PublicKey::from_public_key_pem(&key.public_key)
+                .map_err(|e| e.to_string())?
+                .to_encoded_point(false);
+            jwk.kty = "EC".to_string();
+            jwk.crv = Some("P-256".to_string());
+            jwk.x = point.x().map(|x| URL_SAFE_NO_PAD.encode(x));
+            jwk.y = point.y().map(|y| URL_SAFE_NO_PAD.encode(y));
+        }
+        KeyType::P384 => {
+            let point = p384::PublicKey::from_public_key_pem(&key.public_key)
+                .map_err(|e| e.to_string())?
+                .to_encoded_point(false);
+            jwk.kty = "EC".to_string();
+            jwk.crv = Some("P-384".to_string());
+            jwk.x = point.x().map(|x| URL_SAFE_NO_PAD.encode(x));
+            jwk.y = point.y().map(|y| URL_SAFE_NO_PAD.encode(y));
+        }
+        KeyType::Ed25519 => {
+            let public_key = ed25519_dalek::VerifyingKey::from_public_key_pem(&key.public_key)
+                .map_err(|e| e.to_string())?;
+            jwk.kty = "OKP".to_string();
+            jwk.crv = Some("Ed25519".to_string());
+            jwk.x = Some(URL_SAFE_NO_PAD.encode(public_key.as_bytes()));
+        }
+    }
+
+    Ok(jwk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use jsonwebtoken::Validation;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Claims {
+        sub: String,
+        exp: i64,
+    }
+
+    fn signing_key(algorithm: &str) -> SigningKey {
+        let params = generate_key(Uuid::nil(), algorithm).unwrap();
+        SigningKey {
+            uuid: Uuid::new_v4(),
+            kid: params.kid,
+            algorithm: params.algorithm,
+            private_key: params.private_key,
+            public_key: params.public_key,
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_with_every_algorithm() {
+        let claims = Claims {
+            sub: "alice".to_string(),
+            exp: Utc::now().timestamp() + 60,
+        };
+
+        for alg in SUPPORTED_ALGORITHMS {
+            let key = signing_key(alg);
+            let token = sign(&key, "JWT", &claims).unwrap();
+
+            let header = jsonwebtoken::decode_header(&token).unwrap();
+            assert_eq!(header.alg, algorithm(&key).unwrap());
+            assert_eq!(header.kid.as_deref(), Some(key.kid.as_str()));
+
+            let validation = Validation::new(algorithm(&key).unwrap());
+            let decoded = jsonwebtoken::decode::<Claims>(&token, &decoding_key(&key).unwrap(), &validation).unwrap();
+            assert_eq!(decoded.claims, claims);
+        }
+    }
+
+    #[test]
+    fn test_jwk_parameters() {
+        let jwk = to_jwk(&signing_key("ES256")).unwrap();
+        assert_eq!(jwk.kty, "EC");
+        assert_eq!(jwk.crv.as_deref(), Some("P-256"));
+        assert_eq!(jwk.x.unwrap().len(), 43);
+        assert_eq!(jwk.y.unwrap().len(), 43);
+
+        let jwk = to_jwk(&signing_key("EdDSA")).unwrap();
+        assert_eq!(jwk.kty, "OKP");
+        assert_eq!(jwk.crv.as_deref(), Some("Ed25519"));
+        assert!(jwk.y.is_none());
+    }
+
+    #[test]
+    fn test_unsupported_algorithm() {
+        assert!(generate_key(Uuid::nil(), "HS256").is_err());
+    }
+}
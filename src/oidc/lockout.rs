@@ -0,0 +1,278 @@
+//! Brute-force protection of password logins.
+//! Failed logins are counted per user and per client address. Every failure makes the user's
+//! next attempt wait longer, and too many failures lock the user or address out for a while, or
+//! lock the user out until an administrator unlocks them. Refused attempts get the same answer as a
+//! wrong password, so lockouts do not tell which usernames exist. Passkeys cannot be guessed and
+//! keep working for locked-out users.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use axum::http::HeaderMap;
+use chrono::{DateTime, Duration, Utc};
+use shaku::HasComponent;
+use uuid::Uuid;
+use super::login::{hash_password, verify_password};
+use crate::config::LockoutConfig;
+use crate::domain::login_failure::{FailureSubject, LoginFailures};
+use crate::domain::realm::Realm;
+use crate::domain::user::User;
+use crate::repository::login_failure_repository::LoginFailureRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::server::AppState;
+
+/// Delays stop doubling here, long before they would overflow
+const MAX_DELAY_DOUBLINGS: u32 = 20;
+
+/// How failed logins are punished, from the lockout configuration.
+#[derive(Debug, Clone)]
+pub struct Policy {
+    max_failures: i32,
+    max_failures_per_ip: i32,
+    failure_delay: Duration,
+    lockout_duration: Duration,
+    failure_reset: Duration,
+    permanent_lockout: bool,
+}
+
+/// A lockout due after a failed login.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lockout {
+    Until(DateTime<Utc>),
+    /// Until an administrator unlocks the user
+    Permanent,
+}
+
+impl Policy {
+    pub fn from_config(config: &LockoutConfig) -> Self {
+        let seconds = |value: Option<u64>| Duration::seconds(value.unwrap_or_default().min(i64::MAX as u64) as i64);
+
+        Self {
+            max_failures: config.max_failures.unwrap_or(5).min(i32::MAX as u32) as i32,
+            max_failures_per_ip: config.max_failures_per_ip.unwrap_or(50).min(i32::MAX as u32) as i32,
+            failure_delay: seconds(config.failure_delay),
+            lockout_duration: seconds(config.lockout_duration),
+            failure_reset: seconds(config.failure_reset),
+            permanent_lockout: config.permanent_lockout.unwrap_or(false),
+        }
+    }
+
+    /// Whether attempts are refused for now: during a lockout, and for a while after each failure
+    /// of a user. Addresses may be shared by many users, who should not wait for each other.
+    pub fn is_blocked(&self, subject: FailureSubject, failures: &LoginFailures, now: DateTime<Utc>) -> bool {
+        let delayed = match subject {
+            FailureSubject::User(_) => failures.failures > 0 && failures.last_failure_at + self.delay(failures.failures) > now,
+            FailureSubject::Ip(_) => false,
+        };
+        delayed || failures.locked_permanently || failures.locked_until.is_some_and(|until| until > now)
+    }
+
+    /// How long attempts are refused after the given number of failures. The delay doubles
+    /// with every failure, but never outlasts a lockout.
+    pub fn delay(&self, failures: i32) -> Duration {
+        let doublings = (failures.max(1) - 1).unsigned_abs().min(MAX_DELAY_DOUBLINGS);
+        (self.failure_delay * 2i32.pow(doublings)).min(self.lockout_duration)
+    }
+
+    /// The lockout due once the subject failed this often, if any.
+    pub fn lockout(&self, subject: FailureSubject, failures: &LoginFailures) -> Option<Lockout> {
+        let max_failures = match subject {
+            FailureSubject::User(_) => self.max_failures,
+            FailureSubject::Ip(_) => self.max_failures_per_ip,
+        };
+        if failures.failures < max_failures {
+            return None;
+        }
+
+        match subject {
+            FailureSubject::User(_) if self.permanent_lockout => Some(Lockout::Permanent),
+            _ => Some(Lockout::Until(failures.last_failure_at + self.lockout_duration)),
+        }
+    }
+}
+
+/// Checks the username and password of a login from the client address. Returns the user, or
+/// None if the password is wrong, the user is unknown or the attempt was refused.
+pub async fn authenticate(
+    state: &AppState,
+    realm: &Realm,
+    client: IpAddr,
+    username: &str,
+    password: &str,
+) -> Result<Option<User>, String> {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    let user = user_repository.find_by_username_or_email(realm.uuid, username).await;
+    let mut subjects = vec![FailureSubject::Ip(client)];
+    subjects.extend(user.as_ref().map(|user| FailureSubject::User(user.uuid)));
+
+    let mut blocked = false;
+    for subject in &subjects {
+        blocked |= is_blocked(state, realm.uuid, *subject).await;
+    }
+
+    // The password is checked whatever happens, so every answer takes as long
+    let password_hash = match &user {
+        Some(user) => user.password_hash.as_str(),
+        None => unknown_user_hash(),
+    };
+    let valid = verify_password(password, password_hash);
+
+    match user {
+        Some(user) if valid && !blocked => {
+            clear(state, realm.uuid, FailureSubject::User(user.uuid)).await?;
+            Ok(Some(user))
+        }
+        _ if blocked => Ok(None),
+        _ => {
+            for subject in subjects {
+                record_failure(state, realm.uuid, subject).await?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+pub async fn is_blocked(state: &AppState, realm_id: Uuid, subject: FailureSubject) -> bool {
+    let login_failure_repository: Arc<dyn LoginFailureRepository> = state.module.resolve();
+
+    let policy = Policy::from_config(&state.config.lockout);
+    login_failure_repository
+        .find(realm_id, subject)
+        .await
+        .is_some_and(|failures| policy.is_blocked(subject, &failures, Utc::now()))
+}
+
+/// Counts a failed login, and locks the subject out once it failed too often.
+pub async fn record_failure(state: &AppState, realm_id: Uuid, subject: FailureSubject) -> Result<(), String> {
+    let login_failure_repository: Arc<dyn LoginFailureRepository> = state.module.resolve();
+
+    let policy = Policy::from_config(&state.config.lockout);
+    let failures = login_failure_repository
+        .record_failure(realm_id, subject, Utc::now() - policy.failure_reset)
+        .await?;
+
+    match policy.lockout(subject, &failures) {
+        Some(Lockout::Until(until)) => login_failure_repository.lock(realm_id, subject, Some(until)).await,
+        Some(Lockout::Permanent) => login_failure_repository.lock(realm_id, subject, None).await,
+        None => Ok(()),
+    }
+}
+
+/// Forgets the failures of the subject and lifts its lockout.
+pub async fn clear(state: &AppState, realm_id: Uuid, subject: FailureSubject) -> Result<bool, String> {
+    let login_failure_repository: Arc<dyn LoginFailureRepository> = state.module.resolve();
+    login_failure_repository.clear(realm_id, subject).await
+}
+
+/// The address of the client, from the X-Forwarded-For header when the proxy in front is trusted.
+pub fn client_address(state: &AppState, peer: SocketAddr, headers: &HeaderMap) -> IpAddr {
+    if !state.config.server.trust_forwarded_for.unwrap_or(false) {
+        return peer.ip();
+    }
+
+    forwarded_for(headers).unwrap_or(peer.ip())
+}
+
+/// The address the proxy added last to the X-Forwarded-For header. Addresses before it came
+/// from the client and prove nothing.
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    let header = headers.get_all("x-forwarded-for").iter().next_back()?.to_str().ok()?;
+    header.rsplit(',').next()?.trim().parse().ok()
+}
+
+/// A hash no password matches, checked for unknown users.
+fn unknown_user_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| hash_password(&Uuid::new_v4().to_string()).expect("a random password can be hashed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn policy(permanent_lockout: bool) -> Policy {
+        Policy::from_config(&LockoutConfig {
+            permanent_lockout: Some(permanent_lockout),
+            ..Default::default()
+        })
+    }
+
+    fn failures(failures: i32, last_failure_at: DateTime<Utc>) -> LoginFailures {
+        LoginFailures {
+            failures,
+            last_failure_at,
+            locked_until: None,
+            locked_permanently: false,
+        }
+    }
+
+    #[test]
+    fn test_progressive_delay() {
+        let policy = policy(false);
+
+        assert_eq!(policy.delay(1), Duration::seconds(1));
+        assert_eq!(policy.delay(2), Duration::seconds(2));
+        assert_eq!(policy.delay(4), Duration::seconds(8));
+        // Never longer than a lockout
+        assert_eq!(policy.delay(30), Duration::seconds(900));
+        assert_eq!(policy.delay(i32::MAX), Duration::seconds(900));
+    }
+
+    #[test]
+    fn test_is_blocked() {
+        let policy = policy(false);
+        let now = Utc::now();
+        let user = FailureSubject::User(Uuid::new_v4());
+        let ip = FailureSubject::Ip("192.0.2.1".parse().unwrap());
+
+        assert!(policy.is_blocked(user, &failures(3, now - Duration::seconds(3)), now));
+        assert!(!policy.is_blocked(user, &failures(3, now - Duration::seconds(5)), now));
+        assert!(!policy.is_blocked(user, &failures(0, now), now));
+        assert!(!policy.is_blocked(ip, &failures(3, now - Duration::seconds(3)), now));
+
+        let mut locked = failures(0, now - Duration::seconds(60));
+        locked.locked_until = Some(now + Duration::seconds(1));
+        assert!(policy.is_blocked(user, &locked, now));
+        assert!(policy.is_blocked(ip, &locked, now));
+        locked.locked_until = Some(now - Duration::seconds(1));
+        assert!(!policy.is_blocked(user, &locked, now));
+        locked.locked_permanently = true;
+        assert!(policy.is_blocked(user, &locked, now));
+    }
+
+    #[test]
+    fn test_lockout() {
+        let now = Utc::now();
+        let user = FailureSubject::User(Uuid::new_v4());
+        let ip = FailureSubject::Ip("192.0.2.1".parse().unwrap());
+
+        assert_eq!(policy(false).lockout(user, &failures(4, now)), None);
+        assert_eq!(
+            policy(false).lockout(user, &failures(5, now)),
+            Some(Lockout::Until(now + Duration::seconds(900)))
+        );
+        assert_eq!(policy(true).lockout(user, &failures(5, now)), Some(Lockout::Permanent));
+        // Addresses have their own threshold and are never locked out for good
+        assert_eq!(policy(true).lockout(ip, &failures(5, now)), None);
+        assert_eq!(
+            policy(true).lockout(ip, &failures(50, now)),
+            Some(Lockout::Until(now + Duration::seconds(900)))
+        );
+    }
+
+    #[test]
+    fn test_forwarded_for() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(forwarded_for(&headers), None);
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.7, 198.51.100.2"));
+        assert_eq!(forwarded_for(&headers), Some("198.51.100.2".parse().unwrap()));
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("2001:db8::1"));
+        assert_eq!(forwarded_for(&headers), Some("2001:db8::1".parse().unwrap()));
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("unknown"));
+        assert_eq!(forwarded_for(&headers), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Login endpoint implementation.
+//! Authenticates the user for a pending authorization request before consent is asked.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use axum::extract::{ConnectInfo, Extension, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use serde::Deserialize;
+use serde_json::Value;
+use shaku::HasComponent;
+use super::auth::AuthorizationRequest;
+use super::claims::AMR_PASSWORD;
+use super::{lockout, mfa, organizations, passkeys, webauthn};
+use crate::domain::realm::Realm;
+use crate::domain::user::User;
+use crate::repository::auth_request_repository::AuthRequestRepository;
+use crate::server::AppState;
+use crate::utils::html;
+
+#[derive(Debug, Deserialize)]
+pub struct LoginQuery {
+    /// The pending authorization request the user is logging in for
+    request_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginForm {
+    request_id: String,
+    /// Either the username or the email address of the user
+    username: String,
+    password: String,
+}
+
+/// Renders the login form for a pending authorization request.
+pub async fn login_page(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Query(query): Query<LoginQuery>,
+) -> Response {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+
+    let request = auth_request_repository.find_by_id(&query.request_id).await;
+    let Some(mut request) = request.filter(|r| r.realm_uuid == realm.uuid) else {
+        return (StatusCode::BAD_REQUEST, "Unknown or expired authorization request").into_response();
+    };
+
+    login_page_response(&state, &realm, &mut request, StatusCode::OK, None).await
+}
+
+/// Verifies the submitted credentials and attaches the user to the authorization request.
+/// Users with an authenticator, or who need one, continue with the second factor. Everyone else
+/// is redirected to the organization picker or the consent step. Failed logins are throttled,
+/// see `lockout`.
+pub async fn login(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+
+    let request = auth_request_repository.find_by_id(&form.request_id).await;
+    let Some(mut request) = request.filter(|r| r.realm_uuid == realm.uuid) else {
+        return (StatusCode::BAD_REQUEST, "Unknown or expired authorization request").into_response();
+    };
+
+    let client = lockout::client_address(&state, peer, &headers);
+    let user = match lockout::authenticate(&state, &realm, client, &form.username, &form.password).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            let error = Some("Invalid username or password");
+            return login_page_response(&state, &realm, &mut request, StatusCode::UNAUTHORIZED, error).await;
+        }
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+
+    if !user.is_active() {
+        let error = Some("This account is disabled");
+        return login_page_response(&state, &realm, &mut request, StatusCode::FORBIDDEN, error).await;
+    }
+
+    let has_second_factor = match mfa::second_factors(&state, user.uuid).await {
+        Ok(factors) => factors.any(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+    if has_second_factor || state.config.oidc.require_mfa.unwrap_or(false) || request.claims.requires_mfa() {
+        // The password is right, the user is only logged in once the second factor is checked
+        request.pending_user_id = Some(user.uuid);
+        let step = if has_second_factor { "mfa" } else { "mfa-setup" };
+
+        return match auth_request_repository.store_request(&request).await {
+            Ok(_) => Redirect::to(&format!("{}?request_id={}", step, request.request_id)).into_response(),
+            Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to store authorization request").into_response(),
+        };
+    }
+
+    match complete_login(&state, &realm, &mut request, &user, &[AMR_PASSWORD]).await {
+        Ok(next_step) => Redirect::to(&next_step).into_response(),
+        Err(response) => response,
+    }
+}
+
+/// Attaches the authenticated user to the authorization request. Returns where the login
+/// continues: the organization picker or the consent step.
+pub async fn complete_login(
+    state: &AppState,
+    realm: &Realm,
+    request: &mut AuthorizationRequest,
+    user: &User,
+    amr: &[&str],
+) -> Result<String, Response> {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+
+    if let Err(e) = organizations::join_by_email_domain(state, realm, user).await {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, e).into_response());
+    }
+
+    request.pending_user_id = None;
+    request.user_id = Some(user.uuid);
+    request.auth_time = Some(chrono::Utc::now());
+    request.amr = amr.iter().map(|m| m.to_string()).collect();
+
+    match auth_request_repository.store_request(request).await {
+        Ok(_) => Ok(organizations::next_step(request)),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to store authorization request").into_response()),
+    }
+}
+
+/// Renders the login form, offering to sign in with a passkey as well.
+pub async fn login_page_response(
+    state: &AppState,
+    realm: &Realm,
+    request: &mut AuthorizationRequest,
+    status: StatusCode,
+    error: Option<&str>,
+) -> Response {
+    let options = async {
+        let challenge = passkeys::start_ceremony(state, request).await?;
+        Ok::<_, String>(webauthn::request_options(&passkeys::relying_party(state, realm)?, &challenge, &[], "required"))
+    };
+
+    match options.await {
+        Ok(options) => (status, Html(render_login_page(&request.request_id, &options, error))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Hashes a user password with Argon2 for storage in `users.password_hash`.
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Checks a password against an Argon2 hash from `users.password_hash`.
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+        .unwrap_or(false)
+}
+
+fn render_login_page(request_id: &str, passkey_options: &Value, error: Option<&str>) -> String {
+    let error = error
+        .map(|e| format!("<p class=\"error\">{}</p>\n", html::escape(e)))
+        .unwrap_or_default();
+
+    html::document(
+        "Sign in",
+        &format!(
+            r#"<h1>Sign in</h1>
+{error}<form method="post" action="login">
+<input type="hidden" name="request_id" value="{request_id}">
+<label>Username or email <input type="text" name="username" autocomplete="username" required></label>
+<label>Password <input type="password" name="password" autocomplete="current-password" required></label>
+<button type="submit">Sign in</button>
+</form>
+<form id="passkey" method="post" action="passkey-login">
+<input type="hidden" name="request_id" value="{request_id}">
+<input type="hidden" name="credential">
+<p class="passkey-error error" hidden></p>
+<button type="submit">Sign in with a passkey</button>
+</form>
+{script}"#,
+            error = error,
+            request_id = html::escape(request_id),
+            script = passkeys::ceremony_script("passkey", "get", passkey_options),
+        ),
+    )
+}
@@ -0,0 +1,320 @@
+//! Protocol mappers.
+//! Adds the claims configured per client or per scope to ID tokens, JWT access tokens and
+//! UserInfo responses: user attributes, role and group memberships and static values.
+
+use std::sync::Arc;
+use serde_json::{Map, Value};
+use shaku::HasComponent;
+use uuid::Uuid;
+use crate::domain::client::Client;
+use crate::domain::group;
+use crate::domain::realm::Realm;
+use crate::domain::protocol_mapper::{MapperSource, ProtocolMapper, TokenTarget};
+use crate::domain::user::User;
+use crate::repository::group_repository::GroupRepository;
+use crate::repository::protocol_mapper_repository::ProtocolMapperRepository;
+use crate::repository::roles_repository::RoleRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::server::AppState;
+
+/// Claims set by the server itself, which mappers must not replace
+const RESERVED_CLAIMS: &[&str] = &[
+    "iss", "sub", "aud", "exp", "nbf", "iat", "jti", "auth_time", "nonce", "acr", "amr", "azp", "client_id",
+    "scope",
+];
+
+/// What mappers can take claim values from.
+pub struct MapperContext<'a> {
+    /// The resource owner, None for tokens issued through the client credentials grant
+    pub user: Option<&'a User>,
+    /// The names of the user's effective realm roles
+    pub roles: &'a [String],
+    /// The full paths of the groups the user is a direct member of
+    pub groups: &'a [String],
+    /// The attributes the user inherits from their groups, overridden by the user's own
+    pub group_attributes: &'a Map<String, Value>,
+}
+
+/// Checks a claim name: dot-separated, non-empty parts, not starting with a reserved claim.
+pub fn check_claim_name(name: &str) -> Result<(), String> {
+    if name.split('.').any(str::is_empty) {
+        return Err(format!("Invalid claim name '{}'", name));
+    }
+    if RESERVED_CLAIMS.contains(&top_level(name)) {
+        return Err(format!("The '{}' claim is reserved", top_level(name)));
+    }
+    Ok(())
+}
+
+/// Evaluates the mappers of the client and of the granted scopes for the token.
+pub async fn mapped_claims(
+    state: &AppState,
+    realm: &Realm,
+    client: &Client,
+    scopes: &[String],
+    user_id: Option<Uuid>,
+    target: TokenTarget,
+) -> Result<Map<String, Value>, String> {
+    let protocol_mapper_repository: Arc<dyn ProtocolMapperRepository> = state.module.resolve();
+    let mappers = protocol_mapper_repository
+        .find_applicable(realm.uuid, client.uuid, scopes)
+        .await?
+        .into_iter()
+        .filter(|mapper| mapper.applies_to(target))
+        .collect::<Vec<_>>();
+
+    if mappers.is_empty() {
+        return Ok(Map::new());
+    }
+
+    let user = match user_id {
+        Some(user_id) => {
+            let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+            Some(user_repository.find_by_id(realm.uuid, user_id).await.ok_or("user not found")?)
+        }
+        None => None,
+    };
+
+    let roles = match user_id {
+        Some(user_id) if mappers.iter().any(|m| m.source == MapperSource::UserRoles) => {
+            let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+            role_repository
+                .find_effective_by_user(user_id)
+                .await?
+                .into_iter()
+                .filter(|role| role.is_realm_role())
+                .map(|role| role.name)
+                .collect()
+        }
+        _ => vec![],
+    };
+
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+
+    let groups = match user_id {
+        Some(user_id) if mappers.iter().any(|m| m.source == MapperSource::GroupMembership) => group_repository
+            .find_by_user(user_id)
+            .await?
+            .into_iter()
+            .map(|group| group.path)
+            .collect(),
+        _ => vec![],
+    };
+
+    let group_attributes = match user_id {
+        Some(user_id) if mappers.iter().any(|m| matches!(m.source, MapperSource::UserAttribute { .. })) => {
+            group::inherited_attributes(&group_repository.find_lineage_by_user(user_id).await?)
+        }
+        _ => Map::new(),
+    };
+
+    let context = MapperContext {
+        user: user.as_ref(),
+        roles: &roles,
+        groups: &groups,
+        group_attributes: &group_attributes,
+    };
+
+    Ok(apply(&mappers, &context))
+}
+
+/// Applies the mappers in order, so later mappers win when they set the same claim.
+pub fn apply(mappers: &[ProtocolMapper], context: &MapperContext) -> Map<String, Value> {
+    let mut claims = Map::new();
+
+    for mapper in mappers {
+        if check_claim_name(&mapper.claim_name).is_err() {
+            continue;
+        }
+        if let Some(value) = source_value(&mapper.source, context) {
+            set_claim(&mut claims, &mapper.claim_name, value);
+        }
+    }
+
+    claims
+}
+
+/// Adds the mapped claims to the claims of a token, merging nested objects.
+pub fn merge_claims(claims: &mut Map<String, Value>, mapped: Map<String, Value>) {
+    for (name, value) in mapped {
+        match (claims.get_mut(&name), value) {
+            (Some(Value::Object(existing)), Value::Object(value)) => merge_claims(existing, value),
+            (_, value) => {
+                claims.insert(name, value);
+            }
+        }
+    }
+}
+
+fn source_value(source: &MapperSource, context: &MapperContext) -> Option<Value> {
+    match source {
+        MapperSource::Static { value } => Some(value.clone()),
+        MapperSource::UserRoles => context.user.map(|_| Value::from(context.roles.to_vec())),
+        MapperSource::GroupMembership => context.user.map(|_| Value::from(context.groups.to_vec())),
+        MapperSource::UserAttribute { attribute } => {
+            let user = context.user?;
+            match attribute.as_str() {
+                "id" => Some(Value::String(user.uuid.to_string())),
+                "username" => Some(Value::String(user.username.clone())),
+                "email" => Some(Value::String(user.email.clone())),
+                custom => user
+                    .attributes
+                    .get(custom)
+                    .or_else(|| context.group_attributes.get(custom))
+                    .filter(|v| !v.is_null())
+                    .cloned(),
+            }
+        }
+    }
+}
+
+/// Sets a possibly nested claim, creating the objects along the dotted path.
+fn set_claim(claims: &mut Map<String, Value>, name: &str, value: Value) {
+    match name.split_once('.') {
+        None => {
+            claims.insert(name.to_string(), value);
+        }
+        Some((parent, rest)) => {
+            let entry = claims.entry(parent.to_string()).or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            if let Value::Object(nested) = entry {
+                set_claim(nested, rest, value);
+            }
+        }
+    }
+}
+
+fn top_level(name: &str) -> &str {
+    name.split('.').next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn user() -> User {
+        User {
+            uuid: Uuid::new_v4(),
+            username: "alice".to_string(),
+            password_hash: "hash".to_string(),
+            email: "alice@example.com".to_string(),
+            attributes: json!({"tenant_id": "acme", "department": null}),
+            enabled: true,
+            deleted_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn mapper(claim_name: &str, source: MapperSource) -> ProtocolMapper {
+        ProtocolMapper {
+            uuid: Uuid::new_v4(),
+            name: claim_name.to_string(),
+            client_uuid: Some(Uuid::new_v4()),
+            scope: None,
+            source,
+            claim_name: claim_name.to_string(),
+            id_token: true,
+            access_token: true,
+            userinfo: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn attribute(name: &str) -> MapperSource {
+        MapperSource::UserAttribute { attribute: name.to_string() }
+    }
+
+    #[test]
+    fn test_apply_mappers() {
+        let user = user();
+        let roles = vec!["admin".to_string(), "user".to_string()];
+        let groups = vec!["/engineering".to_string(), "/engineering/backend".to_string()];
+        let group_attributes = json!({"tenant_id": "globex", "cost_center": "4711"}).as_object().unwrap().clone();
+        let context = MapperContext {
+            user: Some(&user),
+            roles: &roles,
+            groups: &groups,
+            group_attributes: &group_attributes,
+        };
+
+        let mappers = [
+            mapper("tenant", attribute("tenant_id")),
+            mapper("login", attribute("username")),
+            mapper("department", attribute("department")),
+            mapper("cost_center", attribute("cost_center")),
+            mapper("missing", attribute("building")),
+            mapper("realm_access.roles", MapperSource::UserRoles),
+            mapper("groups", MapperSource::GroupMembership),
+            mapper("realm_access.version", MapperSource::Static { value: json!(2) }),
+            mapper("env", MapperSource::Static { value: json!("production") }),
+        ];
+
+        assert_eq!(
+            Value::Object(apply(&mappers, &context)),
+            json!({
+                "tenant": "acme",
+                "login": "alice",
+                "cost_center": "4711",
+                "groups": ["/engineering", "/engineering/backend"],
+                "realm_access": {"roles": ["admin", "user"], "version": 2},
+                "env": "production",
+            })
+        );
+    }
+
+    #[test]
+    fn test_user_mappers_without_user() {
+        let group_attributes = Map::new();
+        let context = MapperContext { user: None, roles: &[], groups: &[], group_attributes: &group_attributes };
+        let mappers = [
+            mapper("tenant", attribute("tenant_id")),
+            mapper("roles", MapperSource::UserRoles),
+            mapper("groups", MapperSource::GroupMembership),
+            mapper("env", MapperSource::Static { value: json!("production") }),
+        ];
+
+        assert_eq!(Value::Object(apply(&mappers, &context)), json!({"env": "production"}));
+    }
+
+    #[test]
+    fn test_reserved_claims_are_not_mapped() {
+        let user = user();
+        let group_attributes = Map::new();
+        let context = MapperContext { user: Some(&user), roles: &[], groups: &[], group_attributes: &group_attributes };
+        let mappers = [
+            mapper("sub", attribute("username")),
+            mapper("aud.extra", MapperSource::Static { value: json!("x") }),
+        ];
+
+        assert!(apply(&mappers, &context).is_empty());
+    }
+
+    #[test]
+    fn test_check_claim_name() {
+        assert!(check_claim_name("tenant").is_ok());
+        assert!(check_claim_name("realm_access.roles").is_ok());
+        assert!(check_claim_name("").is_err());
+        assert!(check_claim_name("realm_access.").is_err());
+        assert!(check_claim_name("iss").is_err());
+        assert!(check_claim_name("scope.extra").is_err());
+    }
+
+    #[test]
+    fn test_merge_claims() {
+        let mut claims = json!({"sub": "alice", "address": {"country": "NL"}}).as_object().unwrap().clone();
+        let mapped = json!({"address": {"region": "ZH"}, "tenant": "acme"}).as_object().unwrap().clone();
+
+        merge_claims(&mut claims, mapped);
+
+        assert_eq!(
+            Value::Object(claims),
+            json!({"sub": "alice", "address": {"country": "NL", "region": "ZH"}, "tenant": "acme"})
+        );
+    }
+}
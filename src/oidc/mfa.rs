@@ -0,0 +1,455 @@
+//! The second factor of the login.
+//! Users with an authenticator app enter a code from it, or one of their recovery codes, after
+//! their password; users with a passkey may use it instead. Users who need a second factor but
+//! have none set it up here: they scan the QR code, confirm it with a first code and get their
+//! recovery codes, or they add a passkey.
+
+use std::sync::Arc;
+use axum::extract::{Extension, Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::Value;
+use shaku::HasComponent;
+use url::Url;
+use uuid::Uuid;
+use super::auth::AuthorizationRequest;
+use super::claims::{AMR_MFA, AMR_OTP, AMR_PASSWORD};
+use super::login::complete_login;
+use super::passkeys::{self, PasskeyForm};
+use super::{lockout, totp, webauthn};
+use crate::domain::login_failure::FailureSubject;
+use crate::domain::passkey::Passkey;
+use crate::domain::realm::Realm;
+use crate::domain::user::User;
+use crate::repository::auth_request_repository::AuthRequestRepository;
+use crate::repository::mfa_repository::MfaRepository;
+use crate::repository::passkey_repository::PasskeyRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::server::AppState;
+use crate::utils::html;
+
+/// The authentication methods of a login with a password and a one-time code
+const MFA_AMR: &[&str] = &[AMR_PASSWORD, AMR_OTP, AMR_MFA];
+
+#[derive(Debug, Deserialize)]
+pub struct MfaQuery {
+    request_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaForm {
+    request_id: String,
+    /// A code from the authenticator app or a recovery code
+    code: String,
+}
+
+/// The second factors a user has set up
+#[derive(Debug, Clone)]
+pub struct SecondFactors {
+    /// Whether the user has a confirmed authenticator app
+    pub totp: bool,
+    pub passkeys: Vec<Passkey>,
+}
+
+impl SecondFactors {
+    pub fn any(&self) -> bool {
+        self.totp || !self.passkeys.is_empty()
+    }
+}
+
+pub async fn second_factors(state: &AppState, user_id: Uuid) -> Result<SecondFactors, String> {
+    let mfa_repository: Arc<dyn MfaRepository> = state.module.resolve();
+    let passkey_repository: Arc<dyn PasskeyRepository> = state.module.resolve();
+
+    Ok(SecondFactors {
+        totp: mfa_repository.find_totp(user_id).await.is_some_and(|t| t.is_confirmed()),
+        passkeys: passkey_repository.find_by_user(user_id).await?,
+    })
+}
+
+/// Asks the user for a code from their authenticator app, or for one of their passkeys.
+pub async fn mfa_page(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Query(query): Query<MfaQuery>,
+) -> Response {
+    match find_pending_login(&state, &realm, &query.request_id).await {
+        Ok((mut request, user)) => mfa_page_response(&state, &realm, &mut request, &user, StatusCode::OK, None).await,
+        Err(response) => response,
+    }
+}
+
+/// Checks the code and logs the user in. A recovery code is used up.
+pub async fn verify_mfa(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Form(form): Form<MfaForm>,
+) -> Response {
+    let (mut request, user) = match find_pending_login(&state, &realm, &form.request_id).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    // Codes are as easy to guess as passwords, and count towards the same lockout
+    let subject = FailureSubject::User(user.uuid);
+    let valid = if lockout::is_blocked(&state, realm.uuid, subject).await {
+        Ok(false)
+    } else {
+        check_code(&state, &user, &form.code).await
+    };
+    match valid {
+        Ok(true) => {
+            if let Err(e) = lockout::clear(&state, realm.uuid, subject).await {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+            }
+        }
+        Ok(false) => {
+            if let Err(e) = lockout::record_failure(&state, realm.uuid, subject).await {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+            }
+            let error = Some("Invalid code");
+            return mfa_page_response(&state, &realm, &mut request, &user, StatusCode::UNAUTHORIZED, error).await;
+        }
+        Err(response) => return response,
+    }
+
+    match complete_login(&state, &realm, &mut request, &user, MFA_AMR).await {
+        Ok(next_step) => Redirect::to(&next_step).into_response(),
+        Err(response) => response,
+    }
+}
+
+/// Checks the user's passkey and logs them in.
+pub async fn verify_mfa_passkey(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Form(form): Form<PasskeyForm>,
+) -> Response {
+    let (mut request, user) = match find_pending_login(&state, &realm, &form.request_id).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    let passkey = match passkeys::take_challenge(&state, &mut request).await {
+        Ok(challenge) => passkeys::verify_passkey(&state, &realm, &challenge, &form.credential, false).await,
+        Err(e) => Err(e),
+    };
+    if !passkey.is_ok_and(|p| p.user_id == user.uuid) {
+        let error = Some("The passkey could not be verified");
+        return mfa_page_response(&state, &realm, &mut request, &user, StatusCode::UNAUTHORIZED, error).await;
+    }
+
+    match complete_login(&state, &realm, &mut request, &user, passkeys::SECOND_FACTOR_AMR).await {
+        Ok(next_step) => Redirect::to(&next_step).into_response(),
+        Err(response) => response,
+    }
+}
+
+/// Shows the QR code and key of a new authenticator. Reloading the page shows the same key
+/// until the authenticator is confirmed.
+pub async fn mfa_setup_page(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Query(query): Query<MfaQuery>,
+) -> Response {
+    let (request, user) = match find_pending_login(&state, &realm, &query.request_id).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+    let encryption_key = match encryption_key(&state) {
+        Ok(key) => key,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+
+    match second_factors(&state, user.uuid).await {
+        Ok(factors) if factors.any() => {
+            return Redirect::to(&format!("mfa?request_id={}", request.request_id)).into_response();
+        }
+        Ok(_) => {}
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+
+    let mfa_repository: Arc<dyn MfaRepository> = state.module.resolve();
+    let existing = mfa_repository.find_totp(user.uuid).await;
+
+    // An unconfirmed authenticator may already be in the user's app, keep its secret
+    let secret = match existing.and_then(|t| totp::decrypt_secret(encryption_key, user.uuid, &t.encrypted_secret).ok()) {
+        Some(secret) => secret,
+        None => {
+            let secret = totp::generate_secret();
+            let stored = match totp::encrypt_secret(encryption_key, user.uuid, &secret) {
+                Ok(encrypted) => mfa_repository.store_totp(user.uuid, &encrypted).await,
+                Err(e) => Err(e),
+            };
+            match stored {
+                Ok(true) => secret,
+                Ok(false) => return Redirect::to(&format!("mfa?request_id={}", request.request_id)).into_response(),
+                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+            }
+        }
+    };
+
+    match render_setup_page(&state, &realm, &request, &user, &secret, None) {
+        Ok(page) => Html(page).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Confirms the new authenticator with a first code, hands out recovery codes and logs the user in.
+pub async fn confirm_mfa_setup(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Form(form): Form<MfaForm>,
+) -> Response {
+    let (mut request, user) = match find_pending_login(&state, &realm, &form.request_id).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+    let encryption_key = match encryption_key(&state) {
+        Ok(key) => key,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+
+    let mfa_repository: Arc<dyn MfaRepository> = state.module.resolve();
+    let Some(credential) = mfa_repository.find_totp(user.uuid).await.filter(|t| !t.is_confirmed()) else {
+        return Redirect::to(&format!("mfa?request_id={}", request.request_id)).into_response();
+    };
+    let secret = match totp::decrypt_secret(encryption_key, user.uuid, &credential.encrypted_secret) {
+        Ok(secret) => secret,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+
+    let Some(step) = totp::verify(&secret, &form.code, Utc::now()) else {
+        return match render_setup_page(&state, &realm, &request, &user, &secret, Some("Invalid code")) {
+            Ok(page) => (StatusCode::UNAUTHORIZED, Html(page)).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        };
+    };
+
+    match mfa_repository.confirm_totp(user.uuid, step).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::CONFLICT, "The authenticator was already set up").into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+
+    let recovery_codes = totp::generate_recovery_codes();
+    let hashes = recovery_codes.iter().map(|c| totp::hash_recovery_code(c)).collect::<Vec<_>>();
+    if let Err(e) = mfa_repository.replace_recovery_codes(user.uuid, &hashes).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+    }
+
+    match complete_login(&state, &realm, &mut request, &user, MFA_AMR).await {
+        Ok(next_step) => Html(render_recovery_codes_page(&request.request_id, &recovery_codes, &next_step)).into_response(),
+        Err(response) => response,
+    }
+}
+
+/// The authorization request and the user whose password was verified for it.
+async fn find_pending_login(
+    state: &AppState,
+    realm: &Realm,
+    request_id: &str,
+) -> Result<(AuthorizationRequest, User), Response> {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    let request = auth_request_repository.find_by_id(request_id).await;
+    let Some(request) = request.filter(|r| r.realm_uuid == realm.uuid) else {
+        return Err((StatusCode::BAD_REQUEST, "Unknown or expired authorization request").into_response());
+    };
+
+    let login = Redirect::to(&format!("login?request_id={}", request.request_id)).into_response();
+    let Some(user_id) = request.pending_user_id else {
+        return Err(login);
+    };
+    match user_repository.find_by_id(realm.uuid, user_id).await {
+        Some(user) if user.is_active() => Ok((request, user)),
+        _ => Err(login),
+    }
+}
+
+/// Checks a code from the authenticator app, or else a recovery code, against the user's
+/// confirmed authenticator. Codes from the app are accepted only once.
+async fn check_code(state: &AppState, user: &User, code: &str) -> Result<bool, Response> {
+    let mfa_repository: Arc<dyn MfaRepository> = state.module.resolve();
+    let Some(credential) = mfa_repository.find_totp(user.uuid).await.filter(|t| t.is_confirmed()) else {
+        return Ok(false);
+    };
+    let internal_error = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+
+    let digits = code.replace(' ', "");
+    if digits.len() == 6 && digits.chars().all(|c| c.is_ascii_digit()) {
+        let secret = totp::decrypt_secret(encryption_key(state).map_err(internal_error)?, user.uuid, &credential.encrypted_secret)
+            .map_err(internal_error)?;
+        match totp::verify(&secret, &digits, Utc::now()) {
+            Some(step) => mfa_repository.use_totp_step(user.uuid, step).await.map_err(internal_error),
+            None => Ok(false),
+        }
+    } else {
+        mfa_repository
+            .use_recovery_code(user.uuid, &totp::hash_recovery_code(code))
+            .await
+            .map_err(internal_error)
+    }
+}
+
+fn encryption_key(state: &AppState) -> Result<&str, String> {
+    state
+        .config
+        .oidc
+        .mfa_encryption_key
+        .as_deref()
+        .ok_or_else(|| "Multi-factor authentication is not configured".to_string())
+}
+
+async fn mfa_page_response(
+    state: &AppState,
+    realm: &Realm,
+    request: &mut AuthorizationRequest,
+    user: &User,
+    status: StatusCode,
+    error: Option<&str>,
+) -> Response {
+    let page = async {
+        let factors = second_factors(state, user.uuid).await?;
+        let passkey_options = if factors.passkeys.is_empty() {
+            None
+        } else {
+            // The password was checked already, the passkey only has to be present
+            let allow = factors.passkeys.iter().map(|p| p.credential_id.clone()).collect::<Vec<_>>();
+            let challenge = passkeys::start_ceremony(state, request).await?;
+            let rp = passkeys::relying_party(state, realm)?;
+            Some(webauthn::request_options(&rp, &challenge, &allow, "discouraged"))
+        };
+        Ok::<_, String>(render_mfa_page(&request.request_id, factors.totp, passkey_options.as_ref(), error))
+    };
+
+    match page.await {
+        Ok(page) => (status, Html(page)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+fn render_mfa_page(request_id: &str, totp: bool, passkey_options: Option<&Value>, error: Option<&str>) -> String {
+    let error = error
+        .map(|e| format!("<p class=\"error\">{}</p>\n", html::escape(e)))
+        .unwrap_or_default();
+    let request_id = html::escape(request_id);
+
+    let code_form = if totp {
+        format!(
+            r#"<p>Enter the code from your authenticator app, or one of your recovery codes.</p>
+<form method="post" action="mfa">
+<input type="hidden" name="request_id" value="{}">
+<label>Code <input type="text" name="code" autocomplete="one-time-code" autofocus required></label>
+<button type="submit">Verify</button>
+</form>
+"#,
+            request_id,
+        )
+    } else {
+        String::new()
+    };
+    let passkey_form = match passkey_options {
+        Some(options) => format!(
+            r#"<form id="passkey" method="post" action="mfa-passkey">
+<input type="hidden" name="request_id" value="{}">
+<input type="hidden" name="credential">
+<p class="passkey-error error" hidden></p>
+<button type="submit">Use a passkey</button>
+</form>
+{}"#,
+            request_id,
+            passkeys::ceremony_script("passkey", "get", options),
+        ),
+        None => String::new(),
+    };
+
+    html::document(
+        "Two-factor authentication",
+        &format!("<h1>Two-factor authentication</h1>\n{}{}{}", error, code_form, passkey_form),
+    )
+}
+
+fn render_setup_page(
+    state: &AppState,
+    realm: &Realm,
+    request: &AuthorizationRequest,
+    user: &User,
+    secret: &[u8],
+    error: Option<&str>,
+) -> Result<String, String> {
+    // Authenticator apps list accounts by issuer, the host the user logs in at
+    let external_url = state.external_url(realm);
+    let issuer = Url::parse(&external_url)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+        .unwrap_or(external_url);
+    let uri = totp::otpauth_uri(&issuer, &user.email, secret);
+    let qr_code = totp::qr_code_svg(&uri)?;
+
+    let error = error
+        .map(|e| format!("<p class=\"error\">{}</p>\n", html::escape(e)))
+        .unwrap_or_default();
+
+    Ok(html::document(
+        "Set up two-factor authentication",
+        &format!(
+            r#"<h1>Set up two-factor authentication</h1>
+<p>Scan the QR code with your authenticator app, or enter the key by hand.</p>
+<a href="{uri}">{qr_code}</a>
+<p>Key: <code>{key}</code></p>
+{error}<form method="post" action="mfa-setup">
+<input type="hidden" name="request_id" value="{request_id}">
+<label>Code from the app <input type="text" name="code" autocomplete="one-time-code" required></label>
+<button type="submit">Confirm</button>
+</form>
+<p><a href="passkey-setup?request_id={request_id}">Use a passkey instead</a></p>"#,
+            uri = html::escape(&uri),
+            qr_code = qr_code,
+            key = totp::base32(secret),
+            error = error,
+            request_id = html::escape(&request.request_id),
+        ),
+    ))
+}
+
+fn render_recovery_codes_page(request_id: &str, codes: &[String], next_step: &str) -> String {
+    let codes = codes
+        .iter()
+        .map(|code| format!("<li><code>{}</code></li>", html::escape(code)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    html::document(
+        "Recovery codes",
+        &format!(
+            r#"<h1>Recovery codes</h1>
+<p>Keep these codes somewhere safe. Each of them logs you in once when you cannot use your
+authenticator app. They are not shown again.</p>
+<ul>
+{}
+</ul>
+<p><a href="{}">Continue</a></p>
+<p><a href="passkey-setup?request_id={}">Add a passkey as well</a></p>"#,
+            codes,
+            html::escape(next_step),
+            html::escape(request_id),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_recovery_codes_page() {
+        let page = render_recovery_codes_page("x", &["abcd-efgh".to_string()], "consent?request_id=x&y");
+        assert!(page.contains("<code>abcd-efgh</code>"));
+        assert!(page.contains(r#"href="consent?request_id=x&amp;y""#));
+    }
+}
@@ -1,12 +1,38 @@
 use crate::server::AppState;
 use crate::Config;
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::Router;
 
+pub mod access_token;
 pub mod auth;
+pub mod claims;
+pub mod client_auth;
+pub mod consent;
 pub mod discovery;
 pub mod error;
+pub mod id_token;
+pub mod introspection;
+pub mod invitation;
+pub mod jwe;
+pub mod jwks;
+pub mod keys;
+pub mod lockout;
+pub mod login;
+pub mod mappers;
+pub mod mfa;
+pub mod organizations;
+pub mod passkeys;
+pub mod registration;
+pub mod resources;
+pub mod revocation;
+pub mod roles;
+pub mod scopes;
+pub mod subject;
+pub mod token;
+pub mod totp;
 pub mod types;
+pub mod userinfo;
+pub mod webauthn;
 
 pub fn oidc_routes(app_state: AppState) -> Router {
     Router::new()
@@ -17,4 +43,32 @@ pub fn oidc_routes(app_state: AppState) -> Router {
         .with_state(app_state.clone())
         .route("/authorize", get(auth::authorize))
         .with_state(app_state.clone())
+        .route("/login", get(login::login_page).post(login::login))
+        .route("/mfa", get(mfa::mfa_page).post(mfa::verify_mfa))
+        .route("/mfa-setup", get(mfa::mfa_setup_page).post(mfa::confirm_mfa_setup))
+        .route("/mfa-passkey", post(mfa::verify_mfa_passkey))
+        .route("/passkey-login", post(passkeys::passkey_login))
+        .route(
+            "/passkey-setup",
+            get(passkeys::passkey_setup_page).post(passkeys::register_passkey),
+        )
+        .route(
+            "/organization",
+            get(organizations::organization_page).post(organizations::select_organization),
+        )
+        .route("/invitation", get(invitation::invitation_page).post(invitation::accept_invitation))
+        .route("/consent", get(consent::consent_page).post(consent::consent))
+        .route("/token", post(token::token))
+        .route("/userinfo", get(userinfo::userinfo).post(userinfo::userinfo))
+        .route("/introspect", post(introspection::introspect))
+        .route("/revoke", post(revocation::revoke))
+        .route("/jwks", get(jwks::jwks))
+        .route("/register", post(registration::register))
+        .route(
+            "/register/:client_id",
+            get(registration::read_registration)
+                .put(registration::update_registration)
+                .delete(registration::delete_registration),
+        )
+        .with_state(app_state.clone())
 }
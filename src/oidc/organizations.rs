@@ -0,0 +1,245 @@
+//! Organization claims and the organization picker.
+//! Users log in as themselves and, when the client asks for the "organization" scope, pick the
+//! organization they act for. Users belonging to a single organization skip the picker. Tokens
+//! carry the picked organization as `org_id`, `org_name` and `org_roles`.
+
+use std::sync::Arc;
+use axum::extract::{Extension, Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use shaku::HasComponent;
+use uuid::Uuid;
+use super::auth::AuthorizationRequest;
+use super::scopes::ORGANIZATION_SCOPE;
+use crate::domain::organization::{email_domain, Membership};
+use crate::domain::realm::Realm;
+use crate::domain::user::User;
+use crate::repository::auth_request_repository::AuthRequestRepository;
+use crate::repository::organization_repository::OrganizationRepository;
+use crate::server::AppState;
+use crate::utils::html;
+
+/// The claims released by the organization scope
+pub const ORGANIZATION_CLAIMS: &[&str] = &["org_id", "org_name", "org_roles"];
+
+#[derive(Debug, Deserialize)]
+pub struct OrganizationQuery {
+    request_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrganizationForm {
+    request_id: String,
+    organization_id: Uuid,
+}
+
+/// Where the login continues: the organization picker when the organization scope was
+/// requested, the consent step otherwise.
+pub fn next_step(request: &AuthorizationRequest) -> String {
+    let step = if requests_organization(&request.scope) {
+        "organization"
+    } else {
+        "consent"
+    };
+    format!("{}?request_id={}", step, request.request_id)
+}
+
+/// Adds the user to the organization that verified the domain of their email address.
+pub async fn join_by_email_domain(state: &AppState, realm: &Realm, user: &User) -> Result<(), String> {
+    let Some(domain) = email_domain(&user.email) else {
+        return Ok(());
+    };
+
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+    match organization_repository.find_by_verified_domain(realm.uuid, &domain).await {
+        Some(organization) => organization_repository.add_member(organization.uuid, user.uuid, &[]).await,
+        None => Ok(()),
+    }
+}
+
+/// The claims about the organization picked at login, if the organization scope was granted
+/// and the user still belongs to it.
+pub async fn organization_claims(
+    state: &AppState,
+    scopes: &[String],
+    organization_id: Option<Uuid>,
+    user_id: Uuid,
+) -> Map<String, Value> {
+    let Some(organization_id) = organization_id.filter(|_| scopes.iter().any(|s| s == ORGANIZATION_SCOPE)) else {
+        return Map::new();
+    };
+
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+    match organization_repository.find_membership(organization_id, user_id).await {
+        Some(membership) => membership_claims(&membership),
+        None => Map::new(),
+    }
+}
+
+pub fn membership_claims(membership: &Membership) -> Map<String, Value> {
+    let mut claims = Map::new();
+    claims.insert("org_id".to_string(), Value::String(membership.organization.uuid.to_string()));
+    claims.insert("org_name".to_string(), Value::String(membership.organization.name.clone()));
+    if !membership.roles.is_empty() {
+        claims.insert("org_roles".to_string(), Value::from(membership.roles.clone()));
+    }
+    claims
+}
+
+/// Shows the organizations of the logged-in user to pick from. Users with a single organization
+/// have it picked for them, users without one continue without.
+pub async fn organization_page(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Query(query): Query<OrganizationQuery>,
+) -> Response {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    let request = auth_request_repository.find_by_id(&query.request_id).await;
+    let Some(mut request) = request.filter(|r| r.realm_uuid == realm.uuid) else {
+        return (StatusCode::BAD_REQUEST, "Unknown or expired authorization request").into_response();
+    };
+
+    let Some(user_id) = request.user_id else {
+        return Redirect::to(&format!("login?request_id={}", request.request_id)).into_response();
+    };
+
+    let memberships = match organization_repository.find_by_user(user_id).await {
+        Ok(memberships) => memberships,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+
+    match memberships.as_slice() {
+        [] => Redirect::to(&format!("consent?request_id={}", request.request_id)).into_response(),
+        [membership] => {
+            request.organization_id = Some(membership.organization.uuid);
+            continue_to_consent(&state, &request).await
+        }
+        _ => Html(render_organization_page(&request.request_id, &memberships)).into_response(),
+    }
+}
+
+/// Records the organization the user picked and continues with the consent step.
+pub async fn select_organization(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Form(form): Form<OrganizationForm>,
+) -> Response {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    let request = auth_request_repository.find_by_id(&form.request_id).await;
+    let Some(mut request) = request.filter(|r| r.realm_uuid == realm.uuid) else {
+        return (StatusCode::BAD_REQUEST, "Unknown or expired authorization request").into_response();
+    };
+
+    let Some(user_id) = request.user_id else {
+        return Redirect::to(&format!("login?request_id={}", request.request_id)).into_response();
+    };
+
+    if organization_repository.find_membership(form.organization_id, user_id).await.is_none() {
+        return (StatusCode::BAD_REQUEST, "You are not a member of this organization").into_response();
+    }
+
+    request.organization_id = Some(form.organization_id);
+    continue_to_consent(&state, &request).await
+}
+
+async fn continue_to_consent(state: &AppState, request: &AuthorizationRequest) -> Response {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+
+    match auth_request_repository.store_request(request).await {
+        Ok(_) => Redirect::to(&format!("consent?request_id={}", request.request_id)).into_response(),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to store authorization request").into_response(),
+    }
+}
+
+fn requests_organization(scope: &str) -> bool {
+    scope.split_whitespace().any(|s| s == ORGANIZATION_SCOPE)
+}
+
+fn render_organization_page(request_id: &str, memberships: &[Membership]) -> String {
+    let options = memberships
+        .iter()
+        .enumerate()
+        .map(|(i, membership)| {
+            format!(
+                r#"<label><input type="radio" name="organization_id" value="{}"{}> {}</label>"#,
+                membership.organization.uuid,
+                if i == 0 { " checked" } else { "" },
+                html::escape(membership.organization.display_name()),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    html::document(
+        "Choose an organization",
+        &format!(
+            r#"<h1>Choose an organization</h1>
+<form method="post" action="organization">
+<input type="hidden" name="request_id" value="{}">
+{}
+<button type="submit">Continue</button>
+</form>"#,
+            html::escape(request_id),
+            options,
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+    use crate::domain::organization::Organization;
+
+    fn membership(roles: &[&str]) -> Membership {
+        Membership {
+            organization: Organization {
+                uuid: Uuid::nil(),
+                name: "acme".to_string(),
+                display_name: Some("ACME Corp".to_string()),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            },
+            user_id: Uuid::new_v4(),
+            roles: roles.iter().map(|r| r.to_string()).collect(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_membership_claims() {
+        assert_eq!(
+            Value::Object(membership_claims(&membership(&["billing", "owner"]))),
+            json!({
+                "org_id": "00000000-0000-0000-0000-000000000000",
+                "org_name": "acme",
+                "org_roles": ["billing", "owner"],
+            })
+        );
+        assert!(!membership_claims(&membership(&[])).contains_key("org_roles"));
+    }
+
+    #[test]
+    fn test_requests_organization() {
+        assert!(requests_organization("openid organization email"));
+        assert!(!requests_organization("openid organizations"));
+    }
+
+    #[test]
+    fn test_render_organization_page_escapes_names() {
+        let mut member = membership(&[]);
+        member.organization.display_name = Some("<b>Evil</b>".to_string());
+
+        let page = render_organization_page("abc", &[member]);
+        assert!(page.contains("&lt;b&gt;Evil&lt;/b&gt;"));
+        assert!(page.contains(r#"value="abc""#));
+    }
+}
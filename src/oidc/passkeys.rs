@@ -0,0 +1,391 @@
+//! Passkeys in the login flow.
+//! Users sign in with a passkey instead of their password, or use one as their second factor.
+//! Passkeys are registered while setting up a second factor, or right after logging in.
+//! Every page running a WebAuthn ceremony stores a fresh challenge with the authorization
+//! request; the response to the ceremony uses it up.
+
+use std::sync::Arc;
+use axum::extract::{Extension, Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Form;
+use serde::Deserialize;
+use serde_json::Value;
+use shaku::HasComponent;
+use super::auth::AuthorizationRequest;
+use super::claims::{AMR_HARDWARE_KEY, AMR_MFA, AMR_PASSWORD};
+use super::login::{self, complete_login};
+use super::webauthn::{self, AuthenticationResponse, RegistrationResponse, RelyingParty};
+use super::{mfa, organizations};
+use crate::domain::passkey::Passkey;
+use crate::domain::realm::Realm;
+use crate::domain::user::User;
+use crate::repository::auth_request_repository::AuthRequestRepository;
+use crate::repository::passkey_repository::{CreatePasskeyParams, PasskeyRepository};
+use crate::repository::user_repository::UserRepository;
+use crate::server::AppState;
+use crate::utils::html;
+
+/// The authentication methods of a passwordless login. The authenticator verified the user
+/// with a PIN or biometrics, which makes the passkey a second factor of its own.
+const PASSWORDLESS_AMR: &[&str] = &[AMR_HARDWARE_KEY, AMR_MFA];
+/// The authentication methods of a login with a password and a passkey
+pub const SECOND_FACTOR_AMR: &[&str] = &[AMR_PASSWORD, AMR_HARDWARE_KEY, AMR_MFA];
+
+const DEFAULT_PASSKEY_NAME: &str = "Passkey";
+
+#[derive(Debug, Deserialize)]
+pub struct PasskeyQuery {
+    request_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasskeyForm {
+    pub request_id: String,
+    /// The browser's response to the ceremony, as JSON
+    pub credential: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasskeySetupForm {
+    request_id: String,
+    credential: String,
+    #[serde(default)]
+    name: String,
+}
+
+/// Logs the user in with a discoverable passkey, without a password.
+pub async fn passkey_login(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Form(form): Form<PasskeyForm>,
+) -> Response {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    let request = auth_request_repository.find_by_id(&form.request_id).await;
+    let Some(mut request) = request.filter(|r| r.realm_uuid == realm.uuid) else {
+        return (StatusCode::BAD_REQUEST, "Unknown or expired authorization request").into_response();
+    };
+
+    let passkey = match take_challenge(&state, &mut request).await {
+        Ok(challenge) => verify_passkey(&state, &realm, &challenge, &form.credential, true).await,
+        Err(e) => Err(e),
+    };
+    let user = match passkey {
+        Ok(passkey) => user_repository.find_by_id(realm.uuid, passkey.user_id).await,
+        Err(_) => None,
+    };
+
+    match user {
+        Some(user) if user.is_active() => {
+            match complete_login(&state, &realm, &mut request, &user, PASSWORDLESS_AMR).await {
+                Ok(next_step) => Redirect::to(&next_step).into_response(),
+                Err(response) => response,
+            }
+        }
+        Some(_) => login::login_page_response(&state, &realm, &mut request, StatusCode::FORBIDDEN, Some("This account is disabled")).await,
+        None => {
+            login::login_page_response(&state, &realm, &mut request, StatusCode::UNAUTHORIZED, Some("Passkey sign-in failed")).await
+        }
+    }
+}
+
+/// Offers to register a passkey, while setting up a second factor or right after logging in.
+pub async fn passkey_setup_page(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Query(query): Query<PasskeyQuery>,
+) -> Response {
+    match find_registering_user(&state, &realm, &query.request_id).await {
+        Ok((mut request, user)) => setup_page_response(&state, &realm, &mut request, &user, StatusCode::OK, None).await,
+        Err(response) => response,
+    }
+}
+
+/// Stores the passkey created by the browser. Users setting up their second factor are logged
+/// in with it; everyone else continues with their login.
+pub async fn register_passkey(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Form(form): Form<PasskeySetupForm>,
+) -> Response {
+    let (mut request, user) = match find_registering_user(&state, &realm, &form.request_id).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    let registered = match take_challenge(&state, &mut request).await {
+        Ok(challenge) => verify_registration(&state, &realm, &challenge, &form.credential),
+        Err(e) => Err(e),
+    };
+    let registered = match registered {
+        Ok(registered) => registered,
+        Err(_) => {
+            let error = Some("The passkey could not be registered");
+            return setup_page_response(&state, &realm, &mut request, &user, StatusCode::BAD_REQUEST, error).await;
+        }
+    };
+
+    let name = match form.name.trim() {
+        "" => DEFAULT_PASSKEY_NAME.to_string(),
+        name => name.to_string(),
+    };
+    let passkey_repository: Arc<dyn PasskeyRepository> = state.module.resolve();
+    let params = CreatePasskeyParams {
+        user_id: user.uuid,
+        credential_id: registered.credential_id,
+        public_key: registered.public_key,
+        algorithm: registered.algorithm as i32,
+        sign_count: registered.sign_count as i64,
+        aaguid: registered.aaguid,
+        attestation_format: registered.attestation_format,
+        transports: registered.transports,
+        backup_eligible: registered.backup_eligible,
+        name,
+    };
+    if let Err(e) = passkey_repository.create(params).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+    }
+
+    if request.user_id.is_some() {
+        return Redirect::to(&organizations::next_step(&request)).into_response();
+    }
+    match complete_login(&state, &realm, &mut request, &user, SECOND_FACTOR_AMR).await {
+        Ok(next_step) => Redirect::to(&next_step).into_response(),
+        Err(response) => response,
+    }
+}
+
+/// The relying party of the realm, from its external URL.
+pub fn relying_party(state: &AppState, realm: &Realm) -> Result<RelyingParty, String> {
+    RelyingParty::from_url(&state.external_url(realm))
+}
+
+/// Stores a fresh challenge with the authorization request, for the page about to be shown.
+pub async fn start_ceremony(state: &AppState, request: &mut AuthorizationRequest) -> Result<String, String> {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+
+    let challenge = webauthn::generate_challenge();
+    request.webauthn_challenge = Some(challenge.clone());
+    auth_request_repository.store_request(request).await?;
+    Ok(challenge)
+}
+
+/// Uses up the challenge of the page shown last.
+pub async fn take_challenge(state: &AppState, request: &mut AuthorizationRequest) -> Result<String, String> {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+
+    let challenge = request.webauthn_challenge.take().ok_or("no WebAuthn ceremony was started")?;
+    auth_request_repository.store_request(request).await?;
+    Ok(challenge)
+}
+
+/// Checks the response to an assertion ceremony against the stored passkey and records its
+/// use. Returns the passkey, whose user is the one who authenticated.
+pub async fn verify_passkey(
+    state: &AppState,
+    realm: &Realm,
+    challenge: &str,
+    credential: &str,
+    require_user_verification: bool,
+) -> Result<Passkey, String> {
+    let passkey_repository: Arc<dyn PasskeyRepository> = state.module.resolve();
+
+    let response: AuthenticationResponse = serde_json::from_str(credential).map_err(|e| e.to_string())?;
+    let passkey = passkey_repository
+        .find_by_credential_id(&webauthn::decode(&response.id)?)
+        .await
+        .ok_or("unknown passkey")?;
+
+    let assertion = webauthn::verify_assertion(
+        &relying_party(state, realm)?,
+        challenge,
+        &response,
+        &passkey.public_key,
+        passkey.sign_count as u32,
+        require_user_verification,
+    )?;
+    if response.user_handle.is_some_and(|handle| handle != webauthn::user_handle(passkey.user_id)) {
+        return Err("the passkey belongs to another user".to_string());
+    }
+
+    if !passkey_repository.record_use(passkey.uuid, assertion.sign_count as i64).await? {
+        return Err("the signature counter did not increase".to_string());
+    }
+    Ok(passkey)
+}
+
+/// The script running a WebAuthn ceremony when the form's button is clicked. It posts the
+/// browser's response as JSON in the form's "credential" field.
+pub fn ceremony_script(form_id: &str, method: &str, options: &Value) -> String {
+    // The options are embedded in the script, which must not end early
+    let options = options.to_string().replace('<', "\\u003c");
+
+    format!(
+        r#"<script>
+(() => {{
+  const options = {options};
+  const form = document.getElementById("{form_id}");
+  const decode = value => Uint8Array.from(atob(value.replace(/-/g, "+").replace(/_/g, "/")), c => c.charCodeAt(0));
+  const encode = buffer => btoa(String.fromCharCode(...new Uint8Array(buffer)))
+    .replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+  form.addEventListener("submit", async event => {{
+    if (form.credential.value) return;
+    event.preventDefault();
+    const publicKey = JSON.parse(JSON.stringify(options));
+    publicKey.challenge = decode(publicKey.challenge);
+    if (publicKey.user) publicKey.user.id = decode(publicKey.user.id);
+    for (const credential of [...(publicKey.excludeCredentials || []), ...(publicKey.allowCredentials || [])]) {{
+      credential.id = decode(credential.id);
+    }}
+    try {{
+      const credential = await navigator.credentials.{method}({{ publicKey }});
+      const response = credential.response;
+      form.credential.value = JSON.stringify({{
+        id: credential.id,
+        clientDataJSON: encode(response.clientDataJSON),
+        attestationObject: response.attestationObject && encode(response.attestationObject),
+        transports: response.getTransports ? response.getTransports() : [],
+        authenticatorData: response.authenticatorData && encode(response.authenticatorData),
+        signature: response.signature && encode(response.signature),
+        userHandle: response.userHandle ? encode(response.userHandle) : null,
+      }});
+      form.submit();
+    }} catch (error) {{
+      const message = form.querySelector(".passkey-error");
+      message.textContent = "The passkey was not used: " + error.message;
+      message.hidden = false;
+    }}
+  }});
+}})();
+</script>"#,
+        options = options,
+        form_id = form_id,
+        method = method,
+    )
+}
+
+/// The authorization request and the user who may register a passkey: the logged-in user, or
+/// the user setting up their first second factor after their password.
+async fn find_registering_user(
+    state: &AppState,
+    realm: &Realm,
+    request_id: &str,
+) -> Result<(AuthorizationRequest, User), Response> {
+    let auth_request_repository: Arc<dyn AuthRequestRepository> = state.module.resolve();
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    let request = auth_request_repository.find_by_id(request_id).await;
+    let Some(request) = request.filter(|r| r.realm_uuid == realm.uuid) else {
+        return Err((StatusCode::BAD_REQUEST, "Unknown or expired authorization request").into_response());
+    };
+
+    let login = Redirect::to(&format!("login?request_id={}", request.request_id)).into_response();
+    let user_id = match (request.user_id, request.pending_user_id) {
+        (Some(user_id), _) => user_id,
+        (None, Some(user_id)) => {
+            // A password alone must not add a second factor next to an existing one
+            match mfa::second_factors(state, user_id).await {
+                Ok(factors) if factors.any() => {
+                    return Err(Redirect::to(&format!("mfa?request_id={}", request.request_id)).into_response());
+                }
+                Ok(_) => user_id,
+                Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e).into_response()),
+            }
+        }
+        (None, None) => return Err(login),
+    };
+
+    match user_repository.find_by_id(realm.uuid, user_id).await {
+        Some(user) if user.is_active() => Ok((request, user)),
+        _ => Err(login),
+    }
+}
+
+fn verify_registration(
+    state: &AppState,
+    realm: &Realm,
+    challenge: &str,
+    credential: &str,
+) -> Result<webauthn::RegisteredCredential, String> {
+    let response: RegistrationResponse = serde_json::from_str(credential).map_err(|e| e.to_string())?;
+    webauthn::verify_registration(&relying_party(state, realm)?, challenge, &response, false)
+}
+
+async fn setup_page_response(
+    state: &AppState,
+    realm: &Realm,
+    request: &mut AuthorizationRequest,
+    user: &User,
+    status: StatusCode,
+    error: Option<&str>,
+) -> Response {
+    let passkey_repository: Arc<dyn PasskeyRepository> = state.module.resolve();
+
+    let options = async {
+        let existing = passkey_repository.find_by_user(user.uuid).await?;
+        let exclude = existing.into_iter().map(|p| p.credential_id).collect::<Vec<_>>();
+        let challenge = start_ceremony(state, request).await?;
+        Ok::<_, String>(webauthn::creation_options(
+            &relying_party(state, realm)?,
+            user.uuid,
+            &user.email,
+            &challenge,
+            &exclude,
+        ))
+    };
+
+    match options.await {
+        Ok(options) => (status, Html(render_setup_page(request, &options, error))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+fn render_setup_page(request: &AuthorizationRequest, options: &Value, error: Option<&str>) -> String {
+    let error = error
+        .map(|e| format!("<p class=\"error\">{}</p>\n", html::escape(e)))
+        .unwrap_or_default();
+    // Users who are logged in already may skip adding a passkey
+    let skip = if request.user_id.is_some() {
+        format!("<p><a href=\"{}\">Not now</a></p>", html::escape(&organizations::next_step(request)))
+    } else {
+        String::new()
+    };
+
+    html::document(
+        "Add a passkey",
+        &format!(
+            r#"<h1>Add a passkey</h1>
+<p>Sign in with your fingerprint, face, screen lock or security key instead of a password.</p>
+{error}<form id="passkey" method="post" action="passkey-setup">
+<input type="hidden" name="request_id" value="{request_id}">
+<input type="hidden" name="credential">
+<label>Name <input type="text" name="name" placeholder="{default_name}"></label>
+<p class="passkey-error error" hidden></p>
+<button type="submit">Create a passkey</button>
+</form>
+{skip}
+{script}"#,
+            error = error,
+            request_id = html::escape(&request.request_id),
+            default_name = DEFAULT_PASSKEY_NAME,
+            skip = skip,
+            script = ceremony_script("passkey", "create", options),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_ceremony_script_escapes_options() {
+        let script = ceremony_script("passkey", "get", &json!({ "rpId": "</script><script>alert(1)" }));
+        assert!(!script.contains("</script><script>"));
+        assert!(script.contains(r#"document.getElementById("passkey")"#));
+        assert!(script.contains("navigator.credentials.get("));
+    }
+}
@@ -0,0 +1,877 @@
+//! Dynamic Client Registration (RFC 7591) and its management protocol (RFC 7592).
+//! Clients register themselves at `/register` and manage their registration at the returned
+//! `registration_client_uri`, authenticated with the `registration_access_token`.
+
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use super::access_token::{bearer_token, hash_token};
+use super::client_auth::{constant_time_eq, hash_client_secret};
+use super::error::OAuthError;
+use super::scopes::is_builtin_scope;
+use super::types::JsonWebKeySet;
+use super::{jwe, keys, subject};
+use axum::extract::{Extension, Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use shaku::HasComponent;
+use url::{Host, Url};
+use uuid::Uuid;
+use crate::domain::access_token::AccessTokenFormat;
+use crate::domain::api_resource::ApiResource;
+use crate::domain::client::{sector_host, Client, ClientId};
+use crate::domain::realm::Realm;
+use crate::domain::redirect_uri::{self, RedirectUriKind};
+use crate::repository::api_resource_repository::ApiResourceRepository;
+use crate::repository::client_repository::{ClientRepository, CreateClientParams, UpdateClientMetadataParams};
+use crate::server::api::permissions::{admin_audience, ADMIN};
+use crate::server::AppState;
+
+/// Grant types clients may register for
+const SUPPORTED_GRANT_TYPES: &[&str] = &["authorization_code", "client_credentials"];
+
+/// Token endpoint authentication methods clients may register
+const SUPPORTED_AUTH_METHODS: &[&str] = &["client_secret_basic", "client_secret_post", "none"];
+
+const TOKEN_BYTES: usize = 32;
+
+const SECTOR_FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sector identifier documents list redirect URIs, they have no business being larger
+const SECTOR_DOCUMENT_MAX_BYTES: usize = 64 * 1024;
+
+/// Client metadata as sent in registration and update requests (RFC 7591, section 2).
+#[derive(Debug, Default, Deserialize)]
+pub struct ClientMetadata {
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    pub token_endpoint_auth_method: Option<String>,
+    pub grant_types: Option<Vec<String>>,
+    pub response_types: Option<Vec<String>>,
+    pub client_name: Option<String>,
+    pub logo_uri: Option<String>,
+    /// "web" (the default) or "native"
+    pub application_type: Option<String>,
+    /// "public" (the default) or "pairwise"
+    pub subject_type: Option<String>,
+    /// A JSON array of redirect URIs the client shares its pairwise subjects with
+    pub sector_identifier_uri: Option<String>,
+    pub scope: Option<String>,
+    pub jwks: Option<serde_json::Value>,
+    pub id_token_signed_response_alg: Option<String>,
+    pub id_token_encrypted_response_alg: Option<String>,
+    pub id_token_encrypted_response_enc: Option<String>,
+    pub userinfo_encrypted_response_alg: Option<String>,
+    pub userinfo_encrypted_response_enc: Option<String>,
+    /// Only present in update requests, must match the client being updated
+    pub client_id: Option<String>,
+    /// Only present in update requests, must match the client's secret if given
+    pub client_secret: Option<String>,
+}
+
+/// The client information response (RFC 7591, section 3.2.1 and RFC 7592, section 3).
+#[derive(Debug, Serialize)]
+struct ClientInformationResponse {
+    client_id: String,
+    /// Only returned when the secret is issued; we do not keep it in plain text afterwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+    client_id_issued_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret_expires_at: Option<i64>,
+    registration_access_token: String,
+    registration_client_uri: String,
+    redirect_uris: Vec<String>,
+    token_endpoint_auth_method: String,
+    grant_types: Vec<String>,
+    response_types: Vec<String>,
+    scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logo_uri: Option<String>,
+    application_type: String,
+    subject_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sector_identifier_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jwks: Option<serde_json::Value>,
+    id_token_signed_response_alg: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_token_encrypted_response_alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_token_encrypted_response_enc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    userinfo_encrypted_response_alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    userinfo_encrypted_response_enc: Option<String>,
+}
+
+/// Registers a new client. Requires an initial access token unless open registration is enabled.
+pub async fn register(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    headers: HeaderMap,
+    Json(metadata): Json<ClientMetadata>,
+) -> Response {
+    let initial_access_token_hash = match state.config.oidc.open_registration.unwrap_or(false) {
+        true => None,
+        false => match bearer_token(&headers) {
+            Some(token) => Some(hash_token(token)),
+            None => {
+                return OAuthError::InvalidToken("An initial access token is required".to_string()).to_json_response()
+            }
+        },
+    };
+
+    // The initial access token is only used up by a registration that succeeds
+    let validated = match validate_metadata(&state, &realm, &metadata).await {
+        Ok(validated) => validated,
+        Err(e) => return e.to_json_response(),
+    };
+
+    let client_secret = (validated.token_endpoint_auth_method.as_deref() != Some("none")).then(generate_token);
+    let registration_access_token = generate_token();
+
+    let create_params = CreateClientParams {
+        realm_uuid: realm.uuid,
+        client_id: Uuid::new_v4().to_string(),
+        redirect_uris: validated.redirect_uris,
+        scopes: validated.scopes,
+        client_secret_hash: client_secret.as_deref().map(hash_client_secret),
+        client_name: validated.client_name,
+        logo_uri: validated.logo_uri,
+        application_type: validated.application_type,
+        subject_type: validated.subject_type,
+        sector_identifier_uri: validated.sector_identifier_uri,
+        grant_types: validated.grant_types,
+        token_endpoint_auth_method: validated.token_endpoint_auth_method,
+        registration_token_hash: Some(hash_token(&registration_access_token)),
+        first_party: false,
+        id_token_role_claims: false,
+        access_token_format: AccessTokenFormat::Jwt,
+        access_token_lifetime: None,
+        id_token_lifetime: None,
+        id_token_signed_response_alg: validated.id_token_signed_response_alg,
+        jwks: validated.jwks,
+        id_token_encrypted_response_alg: validated.id_token_encrypted_response_alg,
+        id_token_encrypted_response_enc: validated.id_token_encrypted_response_enc,
+        userinfo_encrypted_response_alg: validated.userinfo_encrypted_response_alg,
+        userinfo_encrypted_response_enc: validated.userinfo_encrypted_response_enc,
+    };
+
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+
+    match client_repository.register(create_params, initial_access_token_hash.as_deref()).await {
+        Ok(Some(client)) => {
+            let response = client_information(&state, &realm, &client, client_secret, registration_access_token);
+            (StatusCode::CREATED, [(header::CACHE_CONTROL, "no-store")], Json(response)).into_response()
+        }
+        Ok(None) => OAuthError::InvalidToken("The initial access token is invalid".to_string()).to_json_response(),
+        Err(e) => OAuthError::ServerError(e).to_json_response(),
+    }
+}
+
+/// Returns the client's current registration. The registration access token is rotated.
+pub async fn read_registration(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    headers: HeaderMap,
+    Path(client_id): Path<String>,
+) -> Response {
+    let client = match authorize_registration(&state, &realm, &headers, &client_id).await {
+        Ok(client) => client,
+        Err(e) => return e.to_json_response(),
+    };
+
+    registration_response(&state, &realm, client).await
+}
+
+/// Replaces the client's metadata (RFC 7592, section 2.2). The registration access token is rotated.
+pub async fn update_registration(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    headers: HeaderMap,
+    Path(client_id): Path<String>,
+    Json(metadata): Json<ClientMetadata>,
+) -> Response {
+    let client = match authorize_registration(&state, &realm, &headers, &client_id).await {
+        Ok(client) => client,
+        Err(e) => return e.to_json_response(),
+    };
+
+    if metadata.client_id.as_deref() != Some(client.id.0.as_str()) {
+        return OAuthError::InvalidRequest("The client_id does not match the registration".to_string())
+            .to_json_response();
+    }
+
+    if let Some(secret) = &metadata.client_secret {
+        let matches = client
+            .secret_hash
+            .as_deref()
+            .is_some_and(|expected| constant_time_eq(expected, &hash_client_secret(secret)));
+        if !matches {
+            return OAuthError::InvalidRequest("The client_secret does not match the registration".to_string())
+                .to_json_response();
+        }
+    }
+
+    let validated = match validate_metadata(&state, &realm, &metadata).await {
+        Ok(validated) => validated,
+        Err(e) => return e.to_json_response(),
+    };
+
+    // Switching between public and confidential would need a secret to be issued or dropped
+    if (validated.token_endpoint_auth_method.as_deref() == Some("none")) != client.is_public() {
+        return OAuthError::InvalidClientMetadata(
+            "The token_endpoint_auth_method cannot switch between public and confidential".to_string(),
+        )
+        .to_json_response();
+    }
+
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+
+    match client_repository.update_metadata(realm.uuid, &client.id, validated).await {
+        Ok(client) => registration_response(&state, &realm, client).await,
+        Err(e) => OAuthError::ServerError(e).to_json_response(),
+    }
+}
+
+/// Deletes the client together with its tokens and consents (RFC 7592, section 2.3).
+pub async fn delete_registration(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    headers: HeaderMap,
+    Path(client_id): Path<String>,
+) -> Response {
+    let client = match authorize_registration(&state, &realm, &headers, &client_id).await {
+        Ok(client) => client,
+        Err(e) => return e.to_json_response(),
+    };
+
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+
+    match client_repository.delete(realm.uuid, &client.id).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => OAuthError::ServerError(e).to_json_response(),
+    }
+}
+
+/// Checks the registration access token. Unknown clients get the same answer as a wrong
+/// token, so the endpoint does not reveal which clients exist.
+async fn authorize_registration(
+    state: &AppState,
+    realm: &Realm,
+    headers: &HeaderMap,
+    client_id: &str,
+) -> Result<Client, OAuthError> {
+    let invalid = || OAuthError::InvalidToken("The registration access token is invalid".to_string());
+
+    let token = bearer_token(headers).ok_or_else(invalid)?;
+
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    let client = client_repository
+        .find_by_id(realm.uuid, &ClientId(client_id.to_string()))
+        .await
+        .ok_or_else(invalid)?;
+
+    match &client.registration_token_hash {
+        Some(expected) if constant_time_eq(expected, &hash_token(token)) => Ok(client),
+        _ => Err(invalid()),
+    }
+}
+
+/// Issues a fresh registration access token and returns the client information.
+async fn registration_response(state: &AppState, realm: &Realm, client: Client) -> Response {
+    let registration_access_token = generate_token();
+
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    if let Err(e) = client_repository
+        .set_registration_token_hash(realm.uuid, &client.id, &hash_token(&registration_access_token))
+        .await
+    {
+        return OAuthError::ServerError(e).to_json_response();
+    }
+
+    let response = client_information(state, realm, &client, None, registration_access_token);
+    ([(header::CACHE_CONTROL, "no-store")], Json(response)).into_response()
+}
+
+fn client_information(
+    state: &AppState,
+    realm: &Realm,
+    client: &Client,
+    client_secret: Option<String>,
+    registration_access_token: String,
+) -> ClientInformationResponse {
+    let base_url = state.external_url(realm);
+
+    let token_endpoint_auth_method = client.token_endpoint_auth_method.clone().unwrap_or_else(|| {
+        if client.is_public() { "none" } else { "client_secret_basic" }.to_string()
+    });
+    let response_types = if client.allows_grant_type("authorization_code") {
+        vec!["code".to_string()]
+    } else {
+        vec![]
+    };
+
+    ClientInformationResponse {
+        client_id: client.id.0.clone(),
+        client_secret_expires_at: client_secret.as_ref().map(|_| 0),
+        client_secret,
+        client_id_issued_at: client.created_at.timestamp(),
+        registration_access_token,
+        registration_client_uri: format!("{}/register/{}", base_url, client.id.0),
+        redirect_uris: client.redirect_uris.clone(),
+        token_endpoint_auth_method,
+        grant_types: client.grant_types.clone(),
+        response_types,
+        scope: client.allowed_scopes.join(" "),
+        client_name: client.client_name.clone(),
+        logo_uri: client.logo_uri.clone(),
+        application_type: client.application_type.clone(),
+        subject_type: client.subject_type.clone(),
+        sector_identifier_uri: client.sector_identifier_uri.clone(),
+        jwks: client.jwks.clone(),
+        id_token_signed_response_alg: client.id_token_signed_response_alg.clone(),
+        id_token_encrypted_response_alg: client.id_token_encrypted_response_alg.clone(),
+        id_token_encrypted_response_enc: client.id_token_encrypted_response_enc.clone(),
+        userinfo_encrypted_response_alg: client.userinfo_encrypted_response_alg.clone(),
+        userinfo_encrypted_response_enc: client.userinfo_encrypted_response_enc.clone(),
+    }
+}
+
+/// Validates the metadata and fills in defaults. Scopes must be defined by the server or
+/// owned by a registered API resource other than the admin API, and the sector identifier
+/// document must list all redirect URIs.
+async fn validate_metadata(
+    state: &AppState,
+    realm: &Realm,
+    metadata: &ClientMetadata,
+) -> Result<UpdateClientMetadataParams, OAuthError> {
+    let params = check_metadata(metadata)?;
+
+    if !subject::supported_subject_types(&state.config.oidc).contains(&params.subject_type.as_str()) {
+        return Err(OAuthError::InvalidClientMetadata(format!(
+            "Unsupported subject_type '{}'",
+            params.subject_type
+        )));
+    }
+
+    if let Some(uri) = &params.sector_identifier_uri {
+        let listed = fetch_sector_redirect_uris(uri).await?;
+        if let Some(missing) = params.redirect_uris.iter().find(|r| !listed.contains(r)) {
+            return Err(OAuthError::InvalidClientMetadata(format!(
+                "Redirect URI '{}' is not listed in the sector identifier document",
+                missing
+            )));
+        }
+    }
+
+    let custom_scopes = params
+        .scopes
+        .iter()
+        .filter(|s| !is_builtin_scope(s))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if !custom_scopes.is_empty() {
+        let api_resource_repository: Arc<dyn ApiResourceRepository> = state.module.resolve();
+        let admin_api = api_resource_repository
+            .find_by_identifier(realm.uuid, &admin_audience(&state.external_url(realm)))
+            .await;
+        check_registrable_scopes(&custom_scopes, admin_api.as_ref())?;
+
+        let known = api_resource_repository
+            .find_scopes(realm.uuid, &custom_scopes)
+            .await
+            .map_err(OAuthError::ServerError)?;
+
+        if let Some(unknown) = custom_scopes.iter().find(|s| !known.iter().any(|k| &k.name == *s)) {
+            return Err(OAuthError::InvalidClientMetadata(format!("Unknown scope '{}'", unknown)));
+        }
+    }
+
+    Ok(params)
+}
+
+/// Refuses the scopes of the admin API, which only clients set up by administrators may use.
+fn check_registrable_scopes(scopes: &[String], admin_api: Option<&ApiResource>) -> Result<(), OAuthError> {
+    match scopes.iter().find(|s| *s == ADMIN || admin_api.is_some_and(|api| api.owns_scope(s))) {
+        Some(scope) => Err(OAuthError::InvalidClientMetadata(format!(
+            "Scope '{}' cannot be registered dynamically",
+            scope
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// The checks on client metadata that need nothing but the metadata itself.
+pub(crate) fn check_metadata(metadata: &ClientMetadata) -> Result<UpdateClientMetadataParams, OAuthError> {
+    let invalid = |message: String| Err(OAuthError::InvalidClientMetadata(message));
+
+    let grant_types = metadata
+        .grant_types
+        .clone()
+        .unwrap_or_else(|| vec!["authorization_code".to_string()]);
+    if grant_types.is_empty() {
+        return invalid("At least one grant type is required".to_string());
+    }
+    if let Some(grant_type) = grant_types.iter().find(|g| !SUPPORTED_GRANT_TYPES.contains(&g.as_str())) {
+        return invalid(format!("Unsupported grant type '{}'", grant_type));
+    }
+    let uses_code = grant_types.iter().any(|g| g == "authorization_code");
+
+    // The "code" response type and the authorization_code grant go together (RFC 7591, section 2.1)
+    let response_types = metadata
+        .response_types
+        .clone()
+        .unwrap_or_else(|| if uses_code { vec!["code".to_string()] } else { vec![] });
+    if response_types.iter().any(|r| r != "code") {
+        return invalid("Only the 'code' response type is supported".to_string());
+    }
+    if response_types.is_empty() == uses_code {
+        return invalid("The 'code' response type requires the authorization_code grant and vice versa".to_string());
+    }
+
+    let auth_method = metadata
+        .token_endpoint_auth_method
+        .clone()
+        .unwrap_or_else(|| "client_secret_basic".to_string());
+    if !SUPPORTED_AUTH_METHODS.contains(&auth_method.as_str()) {
+        return invalid(format!("Unsupported token_endpoint_auth_method '{}'", auth_method));
+    }
+    if auth_method == "none" && grant_types.iter().any(|g| g == "client_credentials") {
+        return invalid("Public clients cannot use the client_credentials grant".to_string());
+    }
+
+    let application_type = metadata.application_type.clone().unwrap_or_else(|| "web".to_string());
+    if !matches!(application_type.as_str(), "web" | "native") {
+        return invalid(format!("Unsupported application_type '{}'", application_type));
+    }
+
+    if uses_code && metadata.redirect_uris.is_empty() {
+        return Err(OAuthError::InvalidRedirectUri("At least one redirect URI is required".to_string()));
+    }
+    for uri in &metadata.redirect_uris {
+        check_redirect_uri(uri, &application_type, &auth_method)?;
+    }
+
+    let subject_type = metadata.subject_type.clone().unwrap_or_else(|| "public".to_string());
+    if !matches!(subject_type.as_str(), "public" | "pairwise") {
+        return invalid(format!("Unsupported subject_type '{}'", subject_type));
+    }
+
+    match &metadata.sector_identifier_uri {
+        Some(uri) => {
+            let valid = Url::parse(uri).is_ok_and(|url| url.scheme() == "https" && url.host().is_some_and(is_public_host));
+            if !valid {
+                return invalid("The sector_identifier_uri must be an https URL on a public host".to_string());
+            }
+        }
+        // Without a sector identifier document the redirect URIs define the sector
+        None if subject_type == "pairwise" => {
+            let hosts = metadata.redirect_uris.iter().filter_map(|uri| sector_host(uri)).collect::<HashSet<_>>();
+            if hosts.len() > 1 {
+                return invalid("Redirect URIs on several hosts require a sector_identifier_uri".to_string());
+            }
+        }
+        None => {}
+    }
+
+    if let Some(logo_uri) = &metadata.logo_uri {
+        if !Url::parse(logo_uri).is_ok_and(|url| url.scheme() == "https" || url.scheme() == "http") {
+            return invalid("The logo_uri must be an http or https URL".to_string());
+        }
+    }
+
+    if let Some(jwks) = &metadata.jwks {
+        if serde_json::from_value::<JsonWebKeySet>(jwks.clone()).is_err() {
+            return invalid("The jwks is not a valid JSON Web Key Set".to_string());
+        }
+    }
+
+    let id_token_signed_response_alg = metadata
+        .id_token_signed_response_alg
+        .clone()
+        .unwrap_or_else(|| keys::DEFAULT_ALGORITHM.to_string());
+    if !keys::SUPPORTED_ALGORITHMS.contains(&id_token_signed_response_alg.as_str()) {
+        return invalid(format!("Unsupported id_token_signed_response_alg '{}'", id_token_signed_response_alg));
+    }
+
+    for (name, alg, enc) in [
+        ("id_token", &metadata.id_token_encrypted_response_alg, &metadata.id_token_encrypted_response_enc),
+        ("userinfo", &metadata.userinfo_encrypted_response_alg, &metadata.userinfo_encrypted_response_enc),
+    ] {
+        match (alg, enc) {
+            (None, Some(_)) => return invalid(format!("{}_encrypted_response_enc requires an alg", name)),
+            (Some(alg), _) if !jwe::SUPPORTED_ALGORITHMS.contains(&alg.as_str()) => {
+                return invalid(format!("Unsupported {}_encrypted_response_alg '{}'", name, alg))
+            }
+            (_, Some(enc)) if !jwe::SUPPORTED_ENCRYPTIONS.contains(&enc.as_str()) => {
+                return invalid(format!("Unsupported {}_encrypted_response_enc '{}'", name, enc))
+            }
+            (Some(_), _) if metadata.jwks.is_none() => {
+                return invalid(format!("{} encryption requires a jwks", name))
+            }
+            _ => {}
+        }
+    }
+
+    let scopes = metadata
+        .scope
+        .as_deref()
+        .unwrap_or("openid")
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+
+    Ok(UpdateClientMetadataParams {
+        redirect_uris: metadata.redirect_uris.clone(),
+        scopes,
+        client_name: metadata.client_name.clone(),
+        logo_uri: metadata.logo_uri.clone(),
+        application_type,
+        subject_type,
+        sector_identifier_uri: metadata.sector_identifier_uri.clone(),
+        grant_types,
+        token_endpoint_auth_method: Some(auth_method),
+        id_token_signed_response_alg,
+        jwks: metadata.jwks.clone(),
+        id_token_encrypted_response_alg: metadata.id_token_encrypted_response_alg.clone(),
+        id_token_encrypted_response_enc: metadata.id_token_encrypted_response_enc.clone(),
+        userinfo_encrypted_response_alg: metadata.userinfo_encrypted_response_alg.clone(),
+        userinfo_encrypted_response_enc: metadata.userinfo_encrypted_response_enc.clone(),
+    })
+}
+
+/// Checks a redirect URI against the rules for the kind of client registering it.
+/// Wildcard patterns are left to administrators and cannot be self-registered.
+fn check_redirect_uri(uri: &str, application_type: &str, auth_method: &str) -> Result<(), OAuthError> {
+    let invalid = |reason: &str| Err(OAuthError::InvalidRedirectUri(format!("Redirect URI '{}' {}", uri, reason)));
+
+    match redirect_uri::classify(uri) {
+        None => invalid("is not a valid redirect URI"),
+        Some(RedirectUriKind::Wildcard) => invalid("uses a wildcard, which only administrators can configure"),
+        Some(RedirectUriKind::PrivateUse) if application_type != "native" || auth_method != "none" => {
+            invalid("uses a private-use scheme, which is only allowed for public native clients")
+        }
+        Some(RedirectUriKind::Web) if uri.starts_with("http:") && !is_localhost(uri) => {
+            invalid("must use https unless it points to the local machine")
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+fn is_localhost(uri: &str) -> bool {
+    Url::parse(uri).is_ok_and(|url| url.host_str() == Some("localhost"))
+}
+
+/// Whether the host may be contacted on behalf of clients: neither a local name nor an address
+/// of the loopback, private, link-local or other special-purpose ranges.
+fn is_public_host(host: Host<&str>) -> bool {
+    match host {
+        Host::Domain(domain) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost")
+        }
+        Host::Ipv4(ip) => is_public_address(IpAddr::V4(ip)),
+        Host::Ipv6(ip) => is_public_address(IpAddr::V6(ip)),
+    }
+}
+
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // Shared address space of carrier-grade NAT
+                || (a == 100 && (64..128).contains(&b))
+                // Benchmarking and reserved ranges
+                || (a == 198 && (18..20).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_address(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local, link-local and documentation addresses
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+/// Fetches the sector identifier document, a JSON array of redirect URIs
+/// (OpenID Connect Dynamic Client Registration 1.0, section 5).
+/// The host must resolve to public addresses only, and the document is fetched from exactly
+/// the addresses checked, so clients cannot make the server reach into internal networks.
+async fn fetch_sector_redirect_uris(uri: &str) -> Result<Vec<String>, OAuthError> {
+    let invalid = |reason: String| {
+        OAuthError::InvalidClientMetadata(format!("The sector identifier document could not be used: {}", reason))
+    };
+
+    let url = Url::parse(uri).map_err(|e| invalid(e.to_string()))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let mut builder = reqwest::Client::builder()
+        .timeout(SECTOR_FETCH_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none());
+
+    let addresses: Vec<SocketAddr> = match url.host() {
+        Some(Host::Domain(domain)) => {
+            let addresses = tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|e| invalid(e.to_string()))?
+                .collect::<Vec<_>>();
+            builder = builder.resolve_to_addrs(domain, &addresses);
+            addresses
+        }
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(IpAddr::V4(ip), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(IpAddr::V6(ip), port)],
+        None => return Err(invalid("the URI has no host".to_string())),
+    };
+    if addresses.is_empty() || !addresses.iter().all(|address| is_public_address(address.ip())) {
+        return Err(invalid("the host does not resolve to public addresses only".to_string()));
+    }
+
+    let client = builder.build().map_err(|e| OAuthError::ServerError(e.to_string()))?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| invalid(e.to_string()))?;
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| invalid(e.to_string()))? {
+        if body.len() + chunk.len() > SECTOR_DOCUMENT_MAX_BYTES {
+            return Err(invalid("the document is too large".to_string()));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    serde_json::from_slice::<Vec<String>>(&body).map_err(|e| invalid(e.to_string()))
+}
+
+/// Generates a client secret, registration access token or initial access token.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::api_resource::ApiResourceScope;
+
+    fn web_client() -> ClientMetadata {
+        ClientMetadata {
+            redirect_uris: vec!["https://app.example.com/callback".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn error_code(result: Result<UpdateClientMetadataParams, OAuthError>) -> String {
+        match result {
+            Err(e) => e.to_string().split(':').next().unwrap().to_string(),
+            Ok(_) => "ok".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let params = check_metadata(&web_client()).unwrap();
+
+        assert_eq!(params.grant_types, vec!["authorization_code"]);
+        assert_eq!(params.token_endpoint_auth_method.as_deref(), Some("client_secret_basic"));
+        assert_eq!(params.scopes, vec!["openid"]);
+        assert_eq!(params.id_token_signed_response_alg, "RS256");
+    }
+
+    #[test]
+    fn test_redirect_uris() {
+        assert_eq!(error_code(check_metadata(&ClientMetadata::default())), "invalid_redirect_uri");
+
+        let mut metadata = web_client();
+        metadata.redirect_uris = vec!["https://app.example.com/callback#fragment".to_string()];
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_redirect_uri");
+
+        metadata.redirect_uris = vec!["http://app.example.com/callback".to_string()];
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_redirect_uri");
+
+        metadata.redirect_uris = vec!["https://*.example.com/callback".to_string()];
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_redirect_uri");
+
+        metadata.redirect_uris = vec!["http://localhost:8080/callback".to_string(), "http://127.0.0.1/cb".to_string()];
+        assert_eq!(error_code(check_metadata(&metadata)), "ok");
+
+        // Machine-to-machine clients do not redirect
+        let metadata = ClientMetadata {
+            grant_types: Some(vec!["client_credentials".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(error_code(check_metadata(&metadata)), "ok");
+    }
+
+    #[test]
+    fn test_grant_and_response_types_must_agree() {
+        let mut metadata = web_client();
+        metadata.grant_types = Some(vec!["client_credentials".to_string()]);
+        metadata.response_types = Some(vec!["code".to_string()]);
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_client_metadata");
+
+        let mut metadata = web_client();
+        metadata.grant_types = Some(vec!["implicit".to_string()]);
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_client_metadata");
+    }
+
+    #[test]
+    fn test_private_use_schemes_need_public_native_clients() {
+        let mut metadata = web_client();
+        metadata.redirect_uris = vec!["com.example.app:/callback".to_string()];
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_redirect_uri");
+
+        metadata.application_type = Some("native".to_string());
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_redirect_uri");
+
+        metadata.token_endpoint_auth_method = Some("none".to_string());
+        assert_eq!(error_code(check_metadata(&metadata)), "ok");
+
+        metadata.redirect_uris = vec!["myapp:/callback".to_string()];
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_redirect_uri");
+    }
+
+    #[test]
+    fn test_pairwise_subjects() {
+        let mut metadata = web_client();
+        metadata.subject_type = Some("anonymous".to_string());
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_client_metadata");
+
+        metadata.subject_type = Some("pairwise".to_string());
+        assert_eq!(error_code(check_metadata(&metadata)), "ok");
+
+        // Redirect URIs on several hosts only form one sector with a sector identifier document
+        metadata.redirect_uris.push("https://other.example.com/callback".to_string());
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_client_metadata");
+
+        metadata.sector_identifier_uri = Some("https://example.com/sector.json".to_string());
+        assert_eq!(error_code(check_metadata(&metadata)), "ok");
+
+        metadata.sector_identifier_uri = Some("http://example.com/sector.json".to_string());
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_client_metadata");
+
+        // Clients must not make the server fetch documents from internal hosts
+        for uri in [
+            "http://localhost:8000/sector.json",
+            "https://localhost/sector.json",
+            "https://127.0.0.1/sector.json",
+            "https://10.0.0.8/sector.json",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]/sector.json",
+            "https://[fd00::1]/sector.json",
+        ] {
+            metadata.sector_identifier_uri = Some(uri.to_string());
+            assert_eq!(error_code(check_metadata(&metadata)), "invalid_client_metadata", "{}", uri);
+        }
+    }
+
+    #[test]
+    fn test_admin_api_scopes_cannot_be_registered() {
+        let scope = |name: &str| ApiResourceScope {
+            uuid: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+        };
+        let admin_api = ApiResource {
+            uuid: Uuid::new_v4(),
+            identifier: "https://auth.example.com/api".to_string(),
+            name: "Admin API".to_string(),
+            description: None,
+            access_token_format: None,
+            access_token_lifetime: None,
+            signing_algorithm: "RS256".to_string(),
+            scopes: vec![scope("admin"), scope("users:write")],
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        let scopes = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        assert!(check_registrable_scopes(&scopes(&["orders:read"]), Some(&admin_api)).is_ok());
+        assert!(check_registrable_scopes(&scopes(&["orders:read", "users:write"]), Some(&admin_api)).is_err());
+        assert!(check_registrable_scopes(&scopes(&["admin"]), None).is_err());
+    }
+
+    #[test]
+    fn test_public_addresses() {
+        let public = |ip: &str| is_public_address(ip.parse().unwrap());
+
+        assert!(public("93.184.215.14"));
+        assert!(public("2606:2800:21f:cb07:6820:80da:af6b:8b2c"));
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fc00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(!public(ip), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_public_clients_cannot_use_client_credentials() {
+        let mut metadata = web_client();
+        metadata.token_endpoint_auth_method = Some("none".to_string());
+        metadata.grant_types = Some(vec!["authorization_code".to_string(), "client_credentials".to_string()]);
+
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_client_metadata");
+    }
+
+    #[test]
+    fn test_encryption_requires_jwks() {
+        let mut metadata = web_client();
+        metadata.id_token_encrypted_response_alg = Some("RSA-OAEP-256".to_string());
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_client_metadata");
+
+        metadata.jwks = Some(serde_json::json!({ "keys": [] }));
+        assert_eq!(error_code(check_metadata(&metadata)), "ok");
+
+        metadata.id_token_encrypted_response_enc = Some("A128CBC-HS256".to_string());
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_client_metadata");
+    }
+
+    #[test]
+    fn test_logo_uri_must_be_http() {
+        let mut metadata = web_client();
+        metadata.logo_uri = Some("javascript:alert(1)".to_string());
+
+        assert_eq!(error_code(check_metadata(&metadata)), "invalid_client_metadata");
+    }
+}
@@ -0,0 +1,281 @@
+//! Resource indicators (RFC 8707).
+//! Resolves the `resource` parameters of a request into registered API resources, and derives
+//! the audience, scopes and token policy of the access tokens issued for them.
+
+use std::sync::Arc;
+use super::error::OAuthError;
+use super::keys;
+use super::scopes::is_builtin_scope;
+use shaku::HasComponent;
+use url::Url;
+use crate::domain::access_token::AccessTokenFormat;
+use crate::domain::api_resource::ApiResource;
+use crate::domain::client::Client;
+use crate::domain::realm::Realm;
+use crate::repository::api_resource_repository::ApiResourceRepository;
+use crate::server::AppState;
+
+/// How an access token is issued: its format, lifetime in seconds and signing algorithm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenPolicy {
+    pub format: AccessTokenFormat,
+    pub lifetime: i64,
+    pub algorithm: String,
+}
+
+/// Collects every `resource` parameter from a form-urlencoded query string or body.
+/// The parameter may be repeated to request a token for several resources.
+pub fn resource_parameters(encoded: &[u8]) -> Vec<String> {
+    url::form_urlencoded::parse(encoded)
+        .filter(|(key, _)| key == "resource")
+        .map(|(_, value)| value.into_owned())
+        .collect()
+}
+
+/// Checks that the identifier is an absolute URI without a fragment (RFC 8707, section 2).
+pub fn is_valid_identifier(identifier: &str) -> bool {
+    Url::parse(identifier)
+        .map(|url| url.fragment().is_none())
+        .unwrap_or(false)
+}
+
+/// Looks up the realm's API resources for the requested identifiers.
+pub async fn resolve_resources(
+    state: &AppState,
+    realm: &Realm,
+    identifiers: &[String],
+) -> Result<Vec<ApiResource>, OAuthError> {
+    let api_resource_repository: Arc<dyn ApiResourceRepository> = state.module.resolve();
+
+    let mut resources = Vec::with_capacity(identifiers.len());
+    for identifier in identifiers {
+        if !is_valid_identifier(identifier) {
+            return Err(OAuthError::InvalidTarget(format!("Invalid resource '{}'", identifier)));
+        }
+
+        let resource = api_resource_repository
+            .find_by_identifier(realm.uuid, identifier)
+            .await
+            .ok_or_else(|| OAuthError::InvalidTarget(format!("Unknown resource '{}'", identifier)))?;
+        resources.push(resource);
+    }
+
+    Ok(resources)
+}
+
+/// Checks explicitly requested scopes against the target resources: every scope the server
+/// does not define itself must be owned by one of them. Without resources any scope may be
+/// requested, as far as the client is allowed to.
+pub fn check_scopes(scopes: &[&str], resources: &[ApiResource]) -> Result<(), OAuthError> {
+    if resources.is_empty() {
+        return Ok(());
+    }
+
+    match scopes.iter().find(|s| !is_builtin_scope(s) && !resources.iter().any(|r| r.owns_scope(s))) {
+        Some(scope) => Err(OAuthError::InvalidScope(format!(
+            "Scope '{}' is not defined by the requested resources",
+            scope
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Restricts the scopes to those meaningful for the target resources: the scopes the server
+/// defines itself plus the scopes the resources own. Without resources all scopes are kept.
+pub fn scopes_for_resources(scopes: &[String], resources: &[ApiResource]) -> Vec<String> {
+    if resources.is_empty() {
+        return scopes.to_vec();
+    }
+
+    scopes
+        .iter()
+        .filter(|s| is_builtin_scope(s) || resources.iter().any(|r| r.owns_scope(s)))
+        .cloned()
+        .collect()
+}
+
+/// The audience of an access token: the target resources, or the client itself without any.
+pub fn audience(client: &Client, resources: &[ApiResource]) -> Vec<String> {
+    if resources.is_empty() {
+        return vec![client.id.0.clone()];
+    }
+
+    resources.iter().map(|r| r.identifier.clone()).collect()
+}
+
+/// Combines the client's token settings with the policies of the target resources.
+/// Resource settings take precedence; when several resources set a lifetime the shortest wins.
+pub fn token_policy(client: &Client, resources: &[ApiResource], default_lifetime: i64) -> Result<TokenPolicy, OAuthError> {
+    let conflict = || OAuthError::InvalidTarget("The requested resources have conflicting token policies".to_string());
+
+    let mut format = None;
+    for resource_format in resources.iter().filter_map(|r| r.access_token_format) {
+        if format.is_some_and(|f| f != resource_format) {
+            return Err(conflict());
+        }
+        format = Some(resource_format);
+    }
+
+    let mut algorithm: Option<&str> = None;
+    for resource in resources {
+        if algorithm.is_some_and(|a| a != resource.signing_algorithm) {
+            return Err(conflict());
+        }
+        algorithm = Some(&resource.signing_algorithm);
+    }
+
+    let lifetime = resources
+        .iter()
+        .filter_map(|r| r.access_token_lifetime)
+        .min()
+        .or(client.access_token_lifetime)
+        .map(i64::from)
+        .unwrap_or(default_lifetime);
+
+    Ok(TokenPolicy {
+        format: format.unwrap_or(client.access_token_format),
+        lifetime,
+        algorithm: algorithm.unwrap_or(keys::DEFAULT_ALGORITHM).to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::api_resource::ApiResourceScope;
+    use crate::domain::client::ClientId;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn client() -> Client {
+        Client {
+            uuid: Uuid::new_v4(),
+            id: ClientId("app".to_string()),
+            secret_hash: None,
+            redirect_uris: vec![],
+            allowed_scopes: vec![],
+            client_name: None,
+            logo_uri: None,
+            application_type: "web".to_string(),
+            subject_type: "public".to_string(),
+            sector_identifier_uri: None,
+            grant_types: vec!["authorization_code".to_string()],
+            token_endpoint_auth_method: None,
+            registration_token_hash: None,
+            first_party: false,
+            id_token_role_claims: false,
+            access_token_format: AccessTokenFormat::Jwt,
+            access_token_lifetime: Some(900),
+            id_token_lifetime: None,
+            id_token_signed_response_alg: "RS256".to_string(),
+            jwks: None,
+            id_token_encrypted_response_alg: None,
+            id_token_encrypted_response_enc: None,
+            userinfo_encrypted_response_alg: None,
+            userinfo_encrypted_response_enc: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn resource(identifier: &str, scopes: &[&str]) -> ApiResource {
+        ApiResource {
+            uuid: Uuid::new_v4(),
+            identifier: identifier.to_string(),
+            name: identifier.to_string(),
+            description: None,
+            access_token_format: None,
+            access_token_lifetime: None,
+            signing_algorithm: "RS256".to_string(),
+            scopes: scopes
+                .iter()
+                .map(|s| ApiResourceScope {
+                    uuid: Uuid::new_v4(),
+                    name: s.to_string(),
+                    description: None,
+                })
+                .collect(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_resource_parameters_may_repeat() {
+        let query = b"client_id=app&resource=https%3A%2F%2Fapi.example.com%2Forders&resource=https://api.example.com/billing";
+        assert_eq!(
+            resource_parameters(query),
+            vec!["https://api.example.com/orders", "https://api.example.com/billing"],
+        );
+    }
+
+    #[test]
+    fn test_identifier_must_be_absolute_without_fragment() {
+        assert!(is_valid_identifier("https://api.example.com/orders"));
+        assert!(is_valid_identifier("urn:example:orders"));
+        assert!(!is_valid_identifier("/orders"));
+        assert!(!is_valid_identifier("https://api.example.com/orders#v1"));
+    }
+
+    #[test]
+    fn test_scopes_are_restricted_to_target_resources() {
+        let orders = resource("https://orders", &["orders:read"]);
+        let scopes = vec!["openid".to_string(), "orders:read".to_string(), "billing:read".to_string()];
+
+        assert_eq!(scopes_for_resources(&scopes, &[orders]), vec!["openid", "orders:read"]);
+        assert_eq!(scopes_for_resources(&scopes, &[]), scopes);
+    }
+
+    #[test]
+    fn test_scopes_must_belong_to_target_resources() {
+        let orders = resource("https://api.example.com/orders", &["orders:read"]);
+        let billing = resource("https://api.example.com/billing", &["billing:read"]);
+
+        let both = [orders, billing];
+
+        assert!(check_scopes(&["openid", "orders:read"], &both[..1]).is_ok());
+        assert!(check_scopes(&["billing:read"], &both[..1]).is_err());
+        assert!(check_scopes(&["orders:read", "billing:read"], &both).is_ok());
+        assert!(check_scopes(&["billing:read"], &[]).is_ok());
+    }
+
+    #[test]
+    fn test_audience() {
+        let client = client();
+        assert_eq!(audience(&client, &[]), vec!["app"]);
+        assert_eq!(audience(&client, &[resource("https://orders", &[])]), vec!["https://orders"]);
+    }
+
+    #[test]
+    fn test_policy_defaults_to_client_settings() {
+        let policy = token_policy(&client(), &[], 3600).unwrap();
+
+        assert_eq!(policy.format, AccessTokenFormat::Jwt);
+        assert_eq!(policy.lifetime, 900);
+        assert_eq!(policy.algorithm, "RS256");
+    }
+
+    #[test]
+    fn test_resource_policy_overrides_client() {
+        let mut orders = resource("https://orders", &[]);
+        orders.access_token_format = Some(AccessTokenFormat::Opaque);
+        orders.access_token_lifetime = Some(300);
+        let mut billing = resource("https://billing", &[]);
+        billing.access_token_lifetime = Some(600);
+
+        let policy = token_policy(&client(), &[orders, billing], 3600).unwrap();
+
+        assert_eq!(policy.format, AccessTokenFormat::Opaque);
+        assert_eq!(policy.lifetime, 300);
+    }
+
+    #[test]
+    fn test_conflicting_resource_formats() {
+        let mut orders = resource("https://orders", &[]);
+        orders.access_token_format = Some(AccessTokenFormat::Opaque);
+        let mut billing = resource("https://billing", &[]);
+        billing.access_token_format = Some(AccessTokenFormat::Jwt);
+
+        assert!(token_policy(&client(), &[orders, billing], 3600).is_err());
+    }
+}
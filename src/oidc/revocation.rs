@@ -0,0 +1,63 @@
+//! OAuth 2.0 Token Revocation endpoint implementation (RFC 7009).
+
+use std::sync::Arc;
+use super::access_token;
+use super::client_auth::authenticate_client;
+use super::error::OAuthError;
+use axum::extract::{Extension, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Form;
+use serde::Deserialize;
+use shaku::HasComponent;
+use crate::repository::access_token_repository::AccessTokenRepository;
+use crate::domain::realm::Realm;
+use crate::server::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct RevocationRequest {
+    token: String,
+    /// Accepted for compatibility; only access tokens are issued, so it is ignored
+    #[allow(dead_code)]
+    token_type_hint: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+/// Revokes an access token issued to the calling client.
+/// Unknown and already invalid tokens are answered with 200, as required by RFC 7009.
+pub async fn revoke(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    headers: HeaderMap,
+    Form(request): Form<RevocationRequest>,
+) -> Response {
+    let client = match authenticate_client(
+        &state,
+        &realm,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await
+    {
+        Ok(client) => client,
+        Err(e) => return e.to_json_response(),
+    };
+
+    let Some(token) = access_token::resolve(&state, &realm, &request.token).await else {
+        return StatusCode::OK.into_response();
+    };
+
+    if token.client_uuid != client.uuid {
+        return OAuthError::UnauthorizedClient("Token was issued to another client".to_string())
+            .to_json_response();
+    }
+
+    let access_token_repository: Arc<dyn AccessTokenRepository> = state.module.resolve();
+
+    match access_token_repository.revoke(token.uuid).await {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(e) => OAuthError::ServerError(e).to_json_response(),
+    }
+}
@@ -0,0 +1,97 @@
+//! Role claims.
+//! Access tokens carry the user's realm roles as `realm_access.roles` and the roles owned by
+//! each client as `resource_access.{client_id}.roles`. ID tokens carry them only for clients
+//! that opted in.
+
+use std::sync::Arc;
+use serde_json::{json, Map, Value};
+use shaku::HasComponent;
+use uuid::Uuid;
+use crate::domain::role::Role;
+use crate::repository::roles_repository::RoleRepository;
+use crate::server::AppState;
+
+/// Looks up the user's effective roles, composites expanded, and returns them as claims.
+pub async fn user_role_claims(state: &AppState, user_id: Uuid) -> Result<Map<String, Value>, String> {
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+    let roles = role_repository.find_effective_by_user(user_id).await?;
+
+    Ok(role_claims(&roles))
+}
+
+/// Groups the roles into the `realm_access` and `resource_access` claims, leaving out empty ones.
+pub fn role_claims(roles: &[Role]) -> Map<String, Value> {
+    let mut realm_roles = vec![];
+    let mut client_roles: Map<String, Value> = Map::new();
+
+    for role in roles {
+        match &role.client_id {
+            None => realm_roles.push(role.name.clone()),
+            Some(client_id) => {
+                let entry = client_roles.entry(client_id.0.clone()).or_insert_with(|| json!({"roles": []}));
+                if let Some(Value::Array(names)) = entry.get_mut("roles") {
+                    names.push(Value::String(role.name.clone()));
+                }
+            }
+        }
+    }
+
+    let mut claims = Map::new();
+    if !realm_roles.is_empty() {
+        claims.insert("realm_access".to_string(), json!({"roles": realm_roles}));
+    }
+    if !client_roles.is_empty() {
+        claims.insert("resource_access".to_string(), Value::Object(client_roles));
+    }
+    claims
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use crate::domain::client::ClientId;
+
+    fn role(client_id: Option<&str>, name: &str) -> Role {
+        Role {
+            uuid: Uuid::new_v4(),
+            client_uuid: client_id.map(|_| Uuid::new_v4()),
+            client_id: client_id.map(|id| ClientId(id.to_string())),
+            name: name.to_string(),
+            description: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_role_claims() {
+        let roles = [
+            role(None, "admin"),
+            role(Some("billing"), "invoices:read"),
+            role(Some("billing"), "invoices:write"),
+            role(Some("crm"), "sales"),
+            role(None, "user"),
+        ];
+
+        assert_eq!(
+            Value::Object(role_claims(&roles)),
+            json!({
+                "realm_access": {"roles": ["admin", "user"]},
+                "resource_access": {
+                    "billing": {"roles": ["invoices:read", "invoices:write"]},
+                    "crm": {"roles": ["sales"]},
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_role_claims_leave_out_empty_claims() {
+        assert!(role_claims(&[]).is_empty());
+        assert_eq!(
+            Value::Object(role_claims(&[role(Some("crm"), "sales")])),
+            json!({"resource_access": {"crm": {"roles": ["sales"]}}})
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Standard OpenID Connect scopes and the human-readable descriptions shown on the consent page.
+
+/// Scopes defined by OpenID Connect, as opposed to scopes owned by an API resource
+pub const STANDARD_SCOPES: &[&str] = &["openid", "profile", "email", "address", "phone", "offline_access"];
+
+/// Releases the organization the user picked when logging in
+pub const ORGANIZATION_SCOPE: &str = "organization";
+
+pub fn is_standard_scope(scope: &str) -> bool {
+    STANDARD_SCOPES.contains(&scope)
+}
+
+/// Whether the server defines the scope itself, rather than an API resource.
+pub fn is_builtin_scope(scope: &str) -> bool {
+    is_standard_scope(scope) || scope == ORGANIZATION_SCOPE
+}
+
+/// Returns a description of what granting the scope allows, if the scope is known.
+pub fn describe_scope(scope: &str) -> Option<&'static str> {
+    match scope {
+        "openid" => Some("Sign you in with your account"),
+        "profile" => Some("View your basic profile information, such as your name"),
+        "email" => Some("View your email address"),
+        "address" => Some("View your postal address"),
+        "phone" => Some("View your phone number"),
+        "offline_access" => Some("Stay signed in and access your data while you are away"),
+        ORGANIZATION_SCOPE => Some("Know which organization you sign in for and your roles there"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_known_scope() {
+        assert_eq!(describe_scope("email"), Some("View your email address"));
+    }
+
+    #[test]
+    fn test_describe_unknown_scope() {
+        assert_eq!(describe_scope("custom:scope"), None);
+    }
+
+    #[test]
+    fn test_builtin_scopes() {
+        assert!(is_builtin_scope("email"));
+        assert!(is_builtin_scope(ORGANIZATION_SCOPE));
+        assert!(!is_standard_scope(ORGANIZATION_SCOPE));
+        assert!(!is_builtin_scope("invoices:read"));
+    }
+}
@@ -0,0 +1,72 @@
+//! Subject identifiers (OpenID Connect Core 1.0, section 8).
+//! Public clients see the user's UUID. Pairwise clients see an identifier derived from their
+//! sector, the user and a server-side salt, so clients in different sectors cannot correlate users.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::config::OIDCConfig;
+use crate::domain::client::Client;
+
+/// The subject types pairwise clients can be registered with, depending on the configuration.
+pub fn supported_subject_types(config: &OIDCConfig) -> &'static [&'static str] {
+    if config.pairwise_salt.is_some() {
+        &["public", "pairwise"]
+    } else {
+        &["public"]
+    }
+}
+
+/// The "sub" value the client knows the user by.
+pub fn subject_identifier(config: &OIDCConfig, client: &Client, user_id: Uuid) -> Result<String, String> {
+    if !client.is_pairwise() {
+        return Ok(user_id.to_string());
+    }
+
+    let salt = config
+        .pairwise_salt
+        .as_deref()
+        .ok_or("pairwise subjects require the pairwise_salt setting")?;
+    let sector = client
+        .sector_identifier()
+        .ok_or_else(|| format!("client {} has no sector identifier", client.id.0))?;
+
+    Ok(pairwise_identifier(&sector, user_id, salt))
+}
+
+/// Hashes sector, user and salt as suggested in OpenID Connect Core 1.0, section 8.1.
+fn pairwise_identifier(sector: &str, user_id: Uuid, salt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(sector.as_bytes());
+    hasher.update(user_id.as_bytes());
+    hasher.update(salt.as_bytes());
+    URL_SAFE_NO_PAD.encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairwise_identifier() {
+        let alice = Uuid::new_v4();
+        let bob = Uuid::new_v4();
+
+        let subject = pairwise_identifier("a.example.com", alice, "salt");
+        assert_eq!(subject, pairwise_identifier("a.example.com", alice, "salt"));
+        assert_ne!(subject, pairwise_identifier("b.example.com", alice, "salt"));
+        assert_ne!(subject, pairwise_identifier("a.example.com", bob, "salt"));
+        assert_ne!(subject, pairwise_identifier("a.example.com", alice, "pepper"));
+        assert_ne!(subject, alice.to_string());
+    }
+
+    #[test]
+    fn test_supported_subject_types() {
+        let mut config = OIDCConfig::default();
+        assert_eq!(supported_subject_types(&config), &["public"]);
+
+        config.pairwise_salt = Some("salt".to_string());
+        assert_eq!(supported_subject_types(&config), &["public", "pairwise"]);
+    }
+}
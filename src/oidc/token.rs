@@ -0,0 +1,290 @@
+//! OAuth 2.0 Token endpoint implementation.
+//! Exchanges authorization codes and client credentials for access tokens and ID tokens.
+
+use std::sync::Arc;
+use super::access_token::AccessTokenParams;
+use super::client_auth::authenticate_client;
+use super::error::OAuthError;
+use super::id_token::IdTokenParams;
+use super::resources::{self, resource_parameters};
+use super::{access_token, id_token};
+use axum::extract::{Extension, RawForm, State};
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Json, Response};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shaku::HasComponent;
+use crate::domain::client::Client;
+use crate::domain::realm::Realm;
+use crate::repository::authorization_code_repository::AuthorizationCodeRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::server::bootstrap::ADMIN_CLIENT_ID;
+use crate::server::AppState;
+
+/// Authorization codes must be redeemed within this many seconds
+const AUTHORIZATION_CODE_LIFETIME: i64 = 600;
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    grant_type: String,
+    /// The authorization code, for the authorization_code grant
+    code: Option<String>,
+    /// Must match the redirect URI of the authorization request
+    redirect_uri: Option<String>,
+    /// PKCE code verifier
+    code_verifier: Option<String>,
+    /// Client credentials for client_secret_post and public clients
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    /// Space-separated list of scopes, for the client_credentials grant
+    scope: Option<String>,
+    /// The `resource` parameters (RFC 8707), which may repeat and are collected separately
+    #[serde(skip)]
+    resources: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    access_token: String,
+    token_type: String,
+    expires_in: i64,
+    scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_token: Option<String>,
+}
+
+/// Handles token requests for the authorization_code and client_credentials grants.
+pub async fn token(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    headers: HeaderMap,
+    RawForm(body): RawForm,
+) -> Response {
+    let mut request: TokenRequest = match serde_urlencoded::from_bytes(&body) {
+        Ok(request) => request,
+        Err(e) => return OAuthError::InvalidRequest(e.to_string()).to_json_response(),
+    };
+    request.resources = resource_parameters(&body);
+
+    let client = match authenticate_client(
+        &state,
+        &realm,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await
+    {
+        Ok(client) => client,
+        Err(e) => return e.to_json_response(),
+    };
+
+    let result = match request.grant_type.as_str() {
+        grant_type @ ("authorization_code" | "client_credentials") if !client.allows_grant_type(grant_type) => Err(
+            OAuthError::UnauthorizedClient(format!("The client may not use the '{}' grant", grant_type)),
+        ),
+        "authorization_code" => authorization_code_grant(&state, &realm, &client, &request).await,
+        "client_credentials" => client_credentials_grant(&state, &realm, &client, &request).await,
+        other => Err(OAuthError::UnsupportedGrantType(format!(
+            "Grant type '{}' is not supported",
+            other
+        ))),
+    };
+
+    match result {
+        Ok(response) => ([(header::CACHE_CONTROL, "no-store")], Json(response)).into_response(),
+        Err(e) => e.to_json_response(),
+    }
+}
+
+async fn authorization_code_grant(
+    state: &AppState,
+    realm: &Realm,
+    client: &Client,
+    request: &TokenRequest,
+) -> Result<TokenResponse, OAuthError> {
+    let code = request
+        .code
+        .as_deref()
+        .ok_or_else(|| OAuthError::InvalidRequest("Missing 'code' parameter".to_string()))?;
+
+    let code_repository: Arc<dyn AuthorizationCodeRepository> = state.module.resolve();
+    let code = code_repository
+        .take_code(code)
+        .await
+        .ok_or_else(|| OAuthError::InvalidGrant("Invalid authorization code".to_string()))?;
+
+    if code.realm_uuid != realm.uuid || code.client_id != client.id.0 {
+        return Err(OAuthError::InvalidGrant("Authorization code was issued to another client".to_string()));
+    }
+
+    if code.created_at + Duration::seconds(AUTHORIZATION_CODE_LIFETIME) < Utc::now() {
+        return Err(OAuthError::InvalidGrant("Authorization code expired".to_string()));
+    }
+
+    if request.redirect_uri.as_deref() != Some(code.redirect_uri.as_str()) {
+        return Err(OAuthError::InvalidGrant("Redirect URI mismatch".to_string()));
+    }
+
+    match (&code.code_challenge, &request.code_verifier) {
+        (Some(challenge), Some(verifier)) => {
+            if !verify_pkce(challenge, verifier) {
+                return Err(OAuthError::InvalidGrant("PKCE verification failed".to_string()));
+            }
+        }
+        (Some(_), None) => {
+            return Err(OAuthError::InvalidRequest("Missing 'code_verifier' parameter".to_string()));
+        }
+        (None, _) if client.is_public() => {
+            return Err(OAuthError::InvalidGrant("Public clients must use PKCE".to_string()));
+        }
+        (None, _) if client.id.0 == ADMIN_CLIENT_ID => {
+            return Err(OAuthError::InvalidGrant("The admin client must use PKCE".to_string()));
+        }
+        (None, _) => {}
+    }
+
+    // The user may have been disabled or deleted since they logged in
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    match user_repository.find_by_id(realm.uuid, code.user_id).await {
+        Some(user) if user.is_active() => {}
+        _ => return Err(OAuthError::InvalidGrant("The user is disabled or no longer exists".to_string())),
+    }
+
+    // A token request may narrow down the resources of the authorization request, but not add to them
+    if let Some(resource) = request.resources.iter().find(|r| !code.resources.contains(r)) {
+        return Err(OAuthError::InvalidTarget(format!("Resource '{}' was not authorized", resource)));
+    }
+    let resource_identifiers = if request.resources.is_empty() {
+        &code.resources
+    } else {
+        &request.resources
+    };
+    let resources = resources::resolve_resources(state, realm, resource_identifiers).await?;
+
+    let scopes = code.scope.split_whitespace().map(String::from).collect::<Vec<_>>();
+
+    let params = AccessTokenParams {
+        user_id: Some(code.user_id),
+        scopes: resources::scopes_for_resources(&scopes, &resources),
+        audience: resources::audience(client, &resources),
+        policy: resources::token_policy(client, &resources, default_lifetime(state))?,
+        auth_time: code.auth_time,
+        claims: code.claims.userinfo_claim_names(),
+        organization_id: code.organization_id,
+    };
+
+    let issued = access_token::issue(state, realm, client, params)
+        .await
+        .map_err(OAuthError::ServerError)?;
+
+    let id_token = if scopes.iter().any(|s| s == "openid") {
+        Some(
+            id_token::issue(
+                state,
+                realm,
+                client,
+                IdTokenParams {
+                    user_id: code.user_id,
+                    scopes: &scopes,
+                    auth_time: code.auth_time,
+                    amr: code.amr,
+                    nonce: code.nonce,
+                    requested: &code.claims.id_token,
+                    organization_id: code.organization_id,
+                },
+            )
+            .await
+            .map_err(OAuthError::ServerError)?,
+        )
+    } else {
+        None
+    };
+
+    Ok(token_response(issued, id_token))
+}
+
+async fn client_credentials_grant(
+    state: &AppState,
+    realm: &Realm,
+    client: &Client,
+    request: &TokenRequest,
+) -> Result<TokenResponse, OAuthError> {
+    if client.is_public() {
+        return Err(OAuthError::UnauthorizedClient(
+            "Public clients cannot use the client_credentials grant".to_string(),
+        ));
+    }
+
+    let resources = resources::resolve_resources(state, realm, &request.resources).await?;
+
+    let scopes: Vec<String> = match &request.scope {
+        Some(scope) => {
+            let requested = scope.split_whitespace().collect::<Vec<_>>();
+            if requested.contains(&"openid") || !client.validate_scopes(&requested) {
+                return Err(OAuthError::InvalidScope("Requested scopes not allowed for this client".to_string()));
+            }
+            resources::check_scopes(&requested, &resources)?;
+            requested.into_iter().map(String::from).collect()
+        }
+        None => client
+            .allowed_scopes
+            .iter()
+            .filter(|s| *s != "openid")
+            .cloned()
+            .collect(),
+    };
+
+    let params = AccessTokenParams {
+        user_id: None,
+        scopes: resources::scopes_for_resources(&scopes, &resources),
+        audience: resources::audience(client, &resources),
+        policy: resources::token_policy(client, &resources, default_lifetime(state))?,
+        auth_time: None,
+        claims: vec![],
+        organization_id: None,
+    };
+
+    let issued = access_token::issue(state, realm, client, params)
+        .await
+        .map_err(OAuthError::ServerError)?;
+
+    Ok(token_response(issued, None))
+}
+
+fn default_lifetime(state: &AppState) -> i64 {
+    state.config.oidc.access_token_lifetime.unwrap() as i64
+}
+
+fn token_response(issued: access_token::IssuedAccessToken, id_token: Option<String>) -> TokenResponse {
+    TokenResponse {
+        access_token: issued.token,
+        token_type: "Bearer".to_string(),
+        expires_in: (issued.record.expires_at - Utc::now()).num_seconds(),
+        scope: issued.record.scopes.join(" "),
+        id_token,
+    }
+}
+
+/// Checks the verifier against an S256 code challenge (RFC 7636, section 4.6).
+fn verify_pkce(challenge: &str, verifier: &str) -> bool {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) == challenge
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_pkce_rfc7636_example() {
+        // Example from RFC 7636, appendix B
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+        assert!(verify_pkce(challenge, verifier));
+        assert!(!verify_pkce(challenge, "wrong-verifier"));
+    }
+}
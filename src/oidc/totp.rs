@@ -0,0 +1,241 @@
+//! Time-based one-time passwords (RFC 6238).
+//! Authenticator apps derive six-digit codes from a shared secret and the current 30-second
+//! time step. The secret reaches the app through an otpauth URI, usually scanned as a QR code,
+//! and is stored encrypted with the server's MFA encryption key.
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use qrcode::render::svg;
+use qrcode::QrCode;
+use rand::{Rng, RngCore};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Seconds per time step
+const STEP: i64 = 30;
+const DIGITS: u32 = 6;
+/// Codes of the neighbouring time steps are accepted too, to allow for clock drift
+const ALLOWED_DRIFT: i64 = 1;
+/// 160 bits, the size of an HMAC-SHA1 key recommended by RFC 4226
+const SECRET_BYTES: usize = 20;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Letters and digits that are hard to mix up when typed from a printout
+const RECOVERY_CODE_CHARSET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; SECRET_BYTES];
+    rand::rng().fill_bytes(&mut secret);
+    secret
+}
+
+/// The time step a moment falls into.
+pub fn time_step(time: DateTime<Utc>) -> i64 {
+    time.timestamp().div_euclid(STEP)
+}
+
+/// The code for a time step.
+pub fn code(secret: &[u8], step: i64) -> String {
+    format!("{:0width$}", hotp(secret, step as u64), width = DIGITS as usize)
+}
+
+/// Checks a code entered at the given time. Returns the time step it belongs to, which must be
+/// recorded so the code cannot be used again.
+pub fn verify(secret: &[u8], entered: &str, time: DateTime<Utc>) -> Option<i64> {
+    let entered = entered.trim().replace(' ', "");
+    if entered.len() != DIGITS as usize || !entered.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = time_step(time);
+    (current - ALLOWED_DRIFT..=current + ALLOWED_DRIFT).find(|step| code(secret, *step) == entered)
+}
+
+/// The URI authenticator apps import the secret from (Key Uri Format of Google Authenticator).
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &[u8]) -> String {
+    let issuer = utf8_percent_encode(issuer, NON_ALPHANUMERIC).to_string();
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        utf8_percent_encode(account, NON_ALPHANUMERIC),
+        base32(secret),
+        issuer,
+        DIGITS,
+        STEP,
+    )
+}
+
+/// Renders the URI as an SVG QR code.
+pub fn qr_code_svg(uri: &str) -> Result<String, String> {
+    let code = QrCode::new(uri.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+}
+
+/// RFC 4648 base32 without padding, the encoding of secrets in otpauth URIs.
+pub fn base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    for chunk in bytes.chunks(5) {
+        let mut buffer = [0u8; 5];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        let bits = buffer.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+
+        let chars = (chunk.len() * 8).div_ceil(5);
+        for i in 0..chars {
+            let index = (bits >> (35 - i * 5)) & 0x1f;
+            encoded.push(ALPHABET[index as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Encrypts the secret for storage, bound to the user, as the base64url encoded nonce and ciphertext.
+pub fn encrypt_secret(encryption_key: &str, user_id: Uuid, secret: &[u8]) -> Result<String, String> {
+    let cipher = cipher(encryption_key)?;
+    let mut nonce = [0u8; 12];
+    rand::rng().fill_bytes(&mut nonce);
+    let payload = Payload {
+        msg: secret,
+        aad: user_id.as_bytes(),
+    };
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|e| e.to_string())?;
+
+    Ok(URL_SAFE_NO_PAD.encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+pub fn decrypt_secret(encryption_key: &str, user_id: Uuid, encrypted: &str) -> Result<Vec<u8>, String> {
+    let encrypted = URL_SAFE_NO_PAD.decode(encrypted).map_err(|e| e.to_string())?;
+    if encrypted.len() < 12 {
+        return Err("invalid encrypted TOTP secret".to_string());
+    }
+    let (nonce, ciphertext) = encrypted.split_at(12);
+    let payload = Payload {
+        msg: ciphertext,
+        aad: user_id.as_bytes(),
+    };
+
+    cipher(encryption_key)?
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| "the TOTP secret cannot be decrypted, was the MFA encryption key changed?".to_string())
+}
+
+/// Generates a set of recovery codes like "k7wq-m3xa", to be shown to the user once.
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut chars = (0..8)
+                .map(|_| RECOVERY_CODE_CHARSET[rng.random_range(0..RECOVERY_CODE_CHARSET.len())] as char)
+                .collect::<String>();
+            chars.insert(4, '-');
+            chars
+        })
+        .collect()
+}
+
+/// The hash a recovery code is stored as. Case, spaces and dashes do not matter.
+pub fn hash_recovery_code(code: &str) -> Vec<u8> {
+    let normalized = code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_ascii_lowercase();
+    Sha256::digest(normalized.as_bytes()).to_vec()
+}
+
+/// HOTP (RFC 4226): HMAC-SHA1 of the counter, dynamically truncated to the number of digits.
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let truncated = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]]) & 0x7fff_ffff;
+    truncated % 10u32.pow(DIGITS)
+}
+
+/// AES-256-GCM keyed with the SHA-256 hash of the configured key.
+fn cipher(encryption_key: &str) -> Result<Aes256Gcm, String> {
+    Aes256Gcm::new_from_slice(&Sha256::digest(encryption_key.as_bytes())).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// The SHA-1 secret of the RFC 6238 test vectors
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(timestamp, 0).unwrap()
+    }
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        // The RFC lists eight-digit codes, these are their last six digits
+        assert_eq!(code(RFC_SECRET, time_step(at(59))), "287082");
+        assert_eq!(code(RFC_SECRET, time_step(at(1111111109))), "081804");
+        assert_eq!(code(RFC_SECRET, time_step(at(1234567890))), "005924");
+        assert_eq!(code(RFC_SECRET, time_step(at(20000000000))), "353130");
+    }
+
+    #[test]
+    fn test_verify_allows_drift() {
+        let now = at(1234567890);
+        let step = time_step(now);
+
+        assert_eq!(verify(RFC_SECRET, "005924", now), Some(step));
+        assert_eq!(verify(RFC_SECRET, &code(RFC_SECRET, step - 1), now), Some(step - 1));
+        assert_eq!(verify(RFC_SECRET, &code(RFC_SECRET, step + 1), now), Some(step + 1));
+        assert_eq!(verify(RFC_SECRET, &code(RFC_SECRET, step - 2), now), None);
+        assert_eq!(verify(RFC_SECRET, "005 924", now), Some(step));
+        assert_eq!(verify(RFC_SECRET, "5924", now), None);
+    }
+
+    #[test]
+    fn test_base32() {
+        assert_eq!(base32(RFC_SECRET), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        // RFC 4648 test vectors without padding
+        assert_eq!(base32(b"f"), "MY");
+        assert_eq!(base32(b"foob"), "MZXW6YQ");
+        assert_eq!(base32(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        assert_eq!(
+            otpauth_uri("ACME Auth", "alice@example.com", RFC_SECRET),
+            "otpauth://totp/ACME%20Auth:alice%40example%2Ecom?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &issuer=ACME%20Auth&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn test_secret_round_trip() {
+        let key = "0123456789abcdef0123456789abcdef";
+        let user_id = Uuid::new_v4();
+        let encrypted = encrypt_secret(key, user_id, RFC_SECRET).unwrap();
+
+        assert_eq!(decrypt_secret(key, user_id, &encrypted).unwrap(), RFC_SECRET);
+        assert!(decrypt_secret("another key of at least 32 characters", user_id, &encrypted).is_err());
+        assert!(decrypt_secret(key, Uuid::new_v4(), &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes.iter().all(|c| c.len() == 9 && c.chars().nth(4) == Some('-')));
+        assert_eq!(hash_recovery_code("K7WQ-M3XA"), hash_recovery_code(" k7wqm3xa "));
+    }
+}
@@ -16,18 +16,76 @@ pub struct OpenIDConfiguration {
     pub userinfo_endpoint: String,
     /// URL of the OP's JSON Web Key Set document
     pub jwks_uri: String,
+    /// URL of the OP's OAuth 2.0 Token Introspection Endpoint (RFC 7662)
+    pub introspection_endpoint: String,
+    /// URL of the OP's OAuth 2.0 Token Revocation Endpoint (RFC 7009)
+    pub revocation_endpoint: String,
+    /// URL of the OP's Dynamic Client Registration Endpoint (RFC 7591)
+    pub registration_endpoint: String,
     /// List of the OAuth 2.0 response_type values that this OP supports
     pub response_types_supported: Vec<String>,
+    /// List of the OAuth 2.0 grant type values that this OP supports
+    pub grant_types_supported: Vec<String>,
     /// List of the Subject Identifier types that this OP supports
     pub subject_types_supported: Vec<String>,
     /// List of the JWS signing algorithms supported by the OP for ID Token signatures
     pub id_token_signing_alg_values_supported: Vec<String>,
+    /// List of the JWE key management algorithms supported for ID Token encryption
+    pub id_token_encryption_alg_values_supported: Vec<String>,
+    /// List of the JWE content encryption algorithms supported for ID Token encryption
+    pub id_token_encryption_enc_values_supported: Vec<String>,
+    /// List of the JWE key management algorithms supported for UserInfo encryption
+    pub userinfo_encryption_alg_values_supported: Vec<String>,
+    /// List of the JWE content encryption algorithms supported for UserInfo encryption
+    pub userinfo_encryption_enc_values_supported: Vec<String>,
     /// List of the OAuth 2.0 scope values that this server supports
     pub scopes_supported: Vec<String>,
     /// List of Client Authentication methods supported by this Token Endpoint
     pub token_endpoint_auth_methods_supported: Vec<String>,
     /// List of the Claim Names of the Claims that the OpenID Provider MAY be able to supply values for
     pub claims_supported: Vec<String>,
+    /// Whether the OP supports the "claims" request parameter
+    pub claims_parameter_supported: bool,
+    /// List of the Authentication Context Class References that this OP supports
+    pub acr_values_supported: Vec<String>,
     /// List of the supported Code Challenge methods
     pub code_challenge_methods_supported: Vec<String>,
 }
+
+/// A public key in JSON Web Key format as defined by RFC 7517.
+/// Our own keys always carry "use", "alg" and "kid"; keys registered by clients may omit them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonWebKey {
+    /// Key type, "RSA", "EC" or "OKP"
+    pub kty: String,
+    /// Intended use of the key, "sig" or "enc"
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    pub key_use: Option<String>,
+    /// Algorithm the key is used with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    /// Key identifier, matched against the "kid" header of signed tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// RSA modulus, base64url encoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    /// RSA public exponent, base64url encoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    /// Curve of EC and OKP keys, e.g. "P-256" or "Ed25519"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    /// EC x coordinate or OKP public key, base64url encoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    /// EC y coordinate, base64url encoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+}
+
+/// A set of JSON Web Keys, as served by the JWKS endpoint or registered by a client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonWebKeySet {
+    pub keys: Vec<JsonWebKey>,
+}
@@ -0,0 +1,75 @@
+//! OpenID Connect UserInfo endpoint implementation.
+//! Returns claims about the user the presented access token was issued for.
+
+use std::sync::Arc;
+use super::access_token;
+use super::claims::user_claims;
+use super::error::OAuthError;
+use super::{jwe, mappers, organizations, subject};
+use axum::extract::{Extension, State};
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Json, Response};
+use shaku::HasComponent;
+use crate::domain::protocol_mapper::TokenTarget;
+use crate::domain::realm::Realm;
+use crate::repository::client_repository::ClientRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::server::AppState;
+
+/// Handles UserInfo requests authenticated with a bearer access token of either format.
+pub async fn userinfo(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(token) = access_token::bearer_token(&headers) else {
+        return OAuthError::InvalidToken("Missing bearer token".to_string()).to_json_response();
+    };
+
+    let Some(token) = access_token::validate(&state, &realm, token).await else {
+        return OAuthError::InvalidToken("The access token is invalid".to_string()).to_json_response();
+    };
+
+    let Some(user_id) = token.user_id else {
+        return OAuthError::InvalidToken("The access token was not issued to a user".to_string())
+            .to_json_response();
+    };
+
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    let Some(user) = user_repository.find_by_id(realm.uuid, user_id).await else {
+        return OAuthError::InvalidToken("The user no longer exists".to_string()).to_json_response();
+    };
+
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    let Some(client) = client_repository.find_by_id(realm.uuid, &token.client_id).await else {
+        return OAuthError::InvalidToken("The client no longer exists".to_string()).to_json_response();
+    };
+
+    let subject = match subject::subject_identifier(&state.config.oidc, &client, user.uuid) {
+        Ok(subject) => subject,
+        Err(e) => return OAuthError::ServerError(e).to_json_response(),
+    };
+    let mut claims = user_claims(&user, &subject, &token.scopes, &token.claims);
+
+    match mappers::mapped_claims(&state, &realm, &client, &token.scopes, Some(user.uuid), TokenTarget::UserInfo).await {
+        Ok(mapped) => mappers::merge_claims(&mut claims, mapped),
+        Err(e) => return OAuthError::ServerError(e).to_json_response(),
+    }
+    mappers::merge_claims(
+        &mut claims,
+        organizations::organization_claims(&state, &token.scopes, token.organization_id, user.uuid).await,
+    );
+
+    let body = match serde_json::to_vec(&claims) {
+        Ok(body) => body,
+        Err(e) => return OAuthError::ServerError(e.to_string()).to_json_response(),
+    };
+
+    // Clients that registered userinfo encryption receive the claims as a JWE
+    match jwe::encrypt_userinfo(&client, &body) {
+        Some(Ok(jwe)) => ([(header::CONTENT_TYPE, "application/jwt")], jwe).into_response(),
+        Some(Err(e)) => OAuthError::ServerError(e).to_json_response(),
+        None => Json(claims).into_response(),
+    }
+}
@@ -0,0 +1,838 @@
+//! WebAuthn ceremonies (Web Authentication Level 2).
+//! Passkeys are registered in a creation ceremony and used in an assertion ceremony. The browser
+//! relays the authenticator's responses; they are checked against the challenge we issued, our
+//! origin and relying party ID, and the public key stored at registration. Attestation statements
+//! of the "none" and "packed" formats are accepted, without checking the attestation certificate
+//! against trust anchors.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ciborium::value::Value as Cbor;
+use p256::ecdsa::signature::Verifier;
+use p256::pkcs8::DecodePublicKey;
+use rand::RngCore;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use url::Url;
+use uuid::Uuid;
+use x509_cert::der::asn1::ObjectIdentifier;
+use x509_cert::der::{Decode, Encode};
+use x509_cert::ext::pkix::BasicConstraints;
+use x509_cert::Certificate;
+
+/// COSE algorithm identifiers, in the order we prefer them
+pub const ALG_ES256: i64 = -7;
+pub const ALG_EDDSA: i64 = -8;
+pub const ALG_RS256: i64 = -257;
+const SUPPORTED_ALGORITHMS: &[i64] = &[ALG_ES256, ALG_EDDSA, ALG_RS256];
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_BACKUP_ELIGIBLE: u8 = 0x08;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// How long the browser waits for the user, in milliseconds
+const TIMEOUT: u64 = 300_000;
+
+/// The extension of packed attestation certificates naming the authenticator model
+const OID_AAGUID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.45724.1.1.4");
+const OID_BASIC_CONSTRAINTS: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.19");
+
+/// The site passkeys are scoped to: its host name is the RP ID, its origin is where the
+/// ceremonies must take place.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelyingParty {
+    pub id: String,
+    pub origin: String,
+}
+
+impl RelyingParty {
+    /// The relying party of an external URL, like the realm's issuer.
+    pub fn from_url(url: &str) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|e| e.to_string())?;
+        let id = url.host_str().ok_or("the external URL has no host")?.to_string();
+
+        Ok(Self {
+            id,
+            origin: url.origin().ascii_serialization(),
+        })
+    }
+}
+
+/// The browser's response to a creation ceremony, binary fields base64url encoded.
+#[derive(Debug, Deserialize)]
+pub struct RegistrationResponse {
+    pub id: String,
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+    #[serde(default)]
+    pub transports: Vec<String>,
+}
+
+/// The browser's response to an assertion ceremony, binary fields base64url encoded.
+#[derive(Debug, Deserialize)]
+pub struct AuthenticationResponse {
+    pub id: String,
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    /// The user ID given at registration, returned by discoverable credentials
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+/// A credential that passed the creation ceremony, ready to be stored.
+#[derive(Debug, Clone)]
+pub struct RegisteredCredential {
+    pub credential_id: Vec<u8>,
+    /// The credential public key as a COSE key
+    pub public_key: Vec<u8>,
+    pub algorithm: i64,
+    pub sign_count: u32,
+    /// The authenticator model, zero when not attested
+    pub aaguid: Uuid,
+    pub attestation_format: String,
+    pub transports: Vec<String>,
+    pub user_verified: bool,
+    /// Whether the credential may be synced to other devices
+    pub backup_eligible: bool,
+}
+
+/// The outcome of a successful assertion ceremony.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assertion {
+    pub sign_count: u32,
+    pub user_verified: bool,
+}
+
+/// A fresh random challenge, base64url encoded.
+pub fn generate_challenge() -> String {
+    let mut challenge = [0u8; 32];
+    rand::rng().fill_bytes(&mut challenge);
+    URL_SAFE_NO_PAD.encode(challenge)
+}
+
+/// The user handle of a user: the bytes of their ID.
+pub fn user_handle(user_id: Uuid) -> String {
+    URL_SAFE_NO_PAD.encode(user_id.as_bytes())
+}
+
+/// Options for `navigator.credentials.create()`, binary fields base64url encoded. Credentials
+/// the user already has are excluded so the same authenticator is not registered twice.
+pub fn creation_options(
+    rp: &RelyingParty,
+    user_id: Uuid,
+    user_name: &str,
+    challenge: &str,
+    exclude: &[Vec<u8>],
+) -> Value {
+    json!({
+        "rp": { "id": rp.id, "name": rp.id },
+        "user": { "id": user_handle(user_id), "name": user_name, "displayName": user_name },
+        "challenge": challenge,
+        "pubKeyCredParams": SUPPORTED_ALGORITHMS
+            .iter()
+            .map(|alg| json!({ "type": "public-key", "alg": alg }))
+            .collect::<Vec<_>>(),
+        "timeout": TIMEOUT,
+        "excludeCredentials": credential_descriptors(exclude),
+        "authenticatorSelection": { "residentKey": "preferred", "userVerification": "preferred" },
+        "attestation": "direct",
+    })
+}
+
+/// Options for `navigator.credentials.get()`. Without allowed credentials the authenticator
+/// offers the discoverable credentials it holds for us.
+pub fn request_options(rp: &RelyingParty, challenge: &str, allow: &[Vec<u8>], user_verification: &str) -> Value {
+    json!({
+        "rpId": rp.id,
+        "challenge": challenge,
+        "timeout": TIMEOUT,
+        "allowCredentials": credential_descriptors(allow),
+        "userVerification": user_verification,
+    })
+}
+
+/// Checks the response to a creation ceremony (section 7.1).
+pub fn verify_registration(
+    rp: &RelyingParty,
+    challenge: &str,
+    response: &RegistrationResponse,
+    require_user_verification: bool,
+) -> Result<RegisteredCredential, String> {
+    let client_data_json = decode(&response.client_data_json)?;
+    verify_client_data(&client_data_json, "webauthn.create", rp, challenge)?;
+
+    let attestation = cbor_map(ciborium::de::from_reader::<Cbor, _>(decode(&response.attestation_object)?.as_slice())
+        .map_err(|e| format!("invalid attestation object: {}", e))?)?;
+    let format = text_field(&attestation, "fmt")?;
+    let auth_data_bytes = bytes_field(&attestation, "authData")?;
+    let statement = cbor_map(field(&attestation, "attStmt")?.clone())?;
+
+    let auth_data = AuthenticatorData::parse(&auth_data_bytes)?;
+    auth_data.verify(rp, require_user_verification)?;
+    let attested = auth_data.attested.ok_or("the authenticator data has no credential")?;
+
+    if attested.credential_id != decode(&response.id)? {
+        return Err("the credential ID does not match the authenticator data".to_string());
+    }
+    let (public_key, algorithm) = PublicKey::from_cose(&attested.public_key)?;
+
+    let client_data_hash = Sha256::digest(&client_data_json);
+    match format.as_str() {
+        "none" if statement.is_empty() => {}
+        "none" => return Err("the \"none\" attestation statement must be empty".to_string()),
+        "packed" => verify_packed(&statement, &auth_data_bytes, &client_data_hash, &public_key, algorithm, attested.aaguid)?,
+        other => return Err(format!("unsupported attestation format \"{}\"", other)),
+    }
+
+    Ok(RegisteredCredential {
+        credential_id: attested.credential_id,
+        public_key: attested.public_key,
+        algorithm,
+        sign_count: auth_data.sign_count,
+        aaguid: attested.aaguid,
+        attestation_format: format,
+        transports: response.transports.clone(),
+        user_verified: auth_data.flags & FLAG_USER_VERIFIED != 0,
+        backup_eligible: auth_data.flags & FLAG_BACKUP_ELIGIBLE != 0,
+    })
+}
+
+/// Checks the response to an assertion ceremony against the stored credential (section 7.2).
+/// The signature counter must have increased, unless the authenticator does not keep one.
+pub fn verify_assertion(
+    rp: &RelyingParty,
+    challenge: &str,
+    response: &AuthenticationResponse,
+    public_key: &[u8],
+    stored_sign_count: u32,
+    require_user_verification: bool,
+) -> Result<Assertion, String> {
+    let client_data_json = decode(&response.client_data_json)?;
+    verify_client_data(&client_data_json, "webauthn.get", rp, challenge)?;
+
+    let auth_data_bytes = decode(&response.authenticator_data)?;
+    let auth_data = AuthenticatorData::parse(&auth_data_bytes)?;
+    auth_data.verify(rp, require_user_verification)?;
+
+    let (public_key, _) = PublicKey::from_cose(public_key)?;
+    let signed = [auth_data_bytes.as_slice(), &Sha256::digest(&client_data_json)].concat();
+    public_key.verify(&signed, &decode(&response.signature)?)?;
+
+    if !sign_count_increased(stored_sign_count, auth_data.sign_count) {
+        return Err("the signature counter did not increase, the authenticator may have been cloned".to_string());
+    }
+
+    Ok(Assertion {
+        sign_count: auth_data.sign_count,
+        user_verified: auth_data.flags & FLAG_USER_VERIFIED != 0,
+    })
+}
+
+/// Authenticators that do not count signatures always report zero.
+pub fn sign_count_increased(stored: u32, received: u32) -> bool {
+    (stored == 0 && received == 0) || received > stored
+}
+
+pub fn decode(value: &str) -> Result<Vec<u8>, String> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|e| format!("invalid base64url value: {}", e))
+}
+
+fn credential_descriptors(credential_ids: &[Vec<u8>]) -> Vec<Value> {
+    credential_ids
+        .iter()
+        .map(|id| json!({ "type": "public-key", "id": URL_SAFE_NO_PAD.encode(id) }))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+    #[serde(default, rename = "crossOrigin")]
+    cross_origin: bool,
+}
+
+fn verify_client_data(json: &[u8], ceremony: &str, rp: &RelyingParty, challenge: &str) -> Result<(), String> {
+    let client_data: ClientData = serde_json::from_slice(json).map_err(|e| format!("invalid client data: {}", e))?;
+
+    if client_data.ceremony != ceremony {
+        return Err(format!("expected a {} ceremony", ceremony));
+    }
+    if client_data.challenge.trim_end_matches('=') != challenge {
+        return Err("the challenge does not match".to_string());
+    }
+    if client_data.origin != rp.origin || client_data.cross_origin {
+        return Err(format!("unexpected origin {}", client_data.origin));
+    }
+    Ok(())
+}
+
+/// The authenticator data (section 6.1)
+struct AuthenticatorData {
+    rp_id_hash: Vec<u8>,
+    flags: u8,
+    sign_count: u32,
+    attested: Option<AttestedCredential>,
+}
+
+struct AttestedCredential {
+    aaguid: Uuid,
+    credential_id: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+impl AuthenticatorData {
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let truncated = || "truncated authenticator data".to_string();
+        if bytes.len() < 37 {
+            return Err(truncated());
+        }
+        let flags = bytes[32];
+        let sign_count = u32::from_be_bytes(bytes[33..37].try_into().unwrap());
+
+        let attested = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+            let rest = &bytes[37..];
+            if rest.len() < 18 {
+                return Err(truncated());
+            }
+            let aaguid = Uuid::from_slice(&rest[..16]).map_err(|e| e.to_string())?;
+            let id_length = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+            let credential_id = rest.get(18..18 + id_length).ok_or_else(truncated)?.to_vec();
+
+            // The COSE key is followed by the extensions, it takes as many bytes as its encoding
+            let mut key_bytes = &rest[18 + id_length..];
+            let available = key_bytes.len();
+            ciborium::de::from_reader::<Cbor, _>(&mut key_bytes).map_err(|e| format!("invalid credential public key: {}", e))?;
+            let key_length = available - key_bytes.len();
+            let public_key = rest[18 + id_length..18 + id_length + key_length].to_vec();
+
+            Some(AttestedCredential {
+                aaguid,
+                credential_id,
+                public_key,
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            rp_id_hash: bytes[..32].to_vec(),
+            flags,
+            sign_count,
+            attested,
+        })
+    }
+
+    fn verify(&self, rp: &RelyingParty, require_user_verification: bool) -> Result<(), String> {
+        if self.rp_id_hash != Sha256::digest(rp.id.as_bytes()).as_slice() {
+            return Err("the credential is scoped to another relying party".to_string());
+        }
+        if self.flags & FLAG_USER_PRESENT == 0 {
+            return Err("the user was not present".to_string());
+        }
+        if require_user_verification && self.flags & FLAG_USER_VERIFIED == 0 {
+            return Err("the user was not verified".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Packed attestation (section 8.2): signed by the credential key itself (self attestation) or by
+/// an attestation certificate of the authenticator model.
+fn verify_packed(
+    statement: &[(Cbor, Cbor)],
+    auth_data: &[u8],
+    client_data_hash: &[u8],
+    credential_key: &PublicKey,
+    credential_algorithm: i64,
+    aaguid: Uuid,
+) -> Result<(), String> {
+    let algorithm = integer_field(statement, "alg")?;
+    let signature = bytes_field(statement, "sig")?;
+    let signed = [auth_data, client_data_hash].concat();
+
+    let Ok(certificates) = field(statement, "x5c") else {
+        if algorithm != credential_algorithm {
+            return Err("the self attestation uses another algorithm than the credential".to_string());
+        }
+        return credential_key.verify(&signed, &signature);
+    };
+
+    let certificate = match certificates {
+        Cbor::Array(certificates) => certificates.first().and_then(|c| c.as_bytes()),
+        _ => None,
+    }
+    .ok_or("the attestation statement has no certificate")?;
+    let certificate = Certificate::from_der(certificate).map_err(|e| format!("invalid attestation certificate: {}", e))?;
+
+    PublicKey::from_certificate(&certificate, algorithm)?.verify(&signed, &signature)?;
+    verify_attestation_certificate(&certificate, aaguid)
+}
+
+/// The requirements on packed attestation certificates (section 8.2.1).
+fn verify_attestation_certificate(certificate: &Certificate, aaguid: Uuid) -> Result<(), String> {
+    let tbs = &certificate.tbs_certificate;
+    if tbs.version != x509_cert::Version::V3 {
+        return Err("the attestation certificate must be a version 3 certificate".to_string());
+    }
+    if !tbs.subject.to_string().split(',').any(|rdn| rdn == "OU=Authenticator Attestation") {
+        return Err("the attestation certificate is not an authenticator attestation certificate".to_string());
+    }
+
+    for extension in tbs.extensions.iter().flatten() {
+        if extension.extn_id == OID_AAGUID {
+            // An OCTET STRING holding the AAGUID
+            let value = extension.extn_value.as_bytes();
+            if value.len() != 18 || value[2..] != aaguid.as_bytes()[..] {
+                return Err("the attestation certificate is for another authenticator model".to_string());
+            }
+        } else if extension.extn_id == OID_BASIC_CONSTRAINTS {
+            let constraints = BasicConstraints::from_der(extension.extn_value.as_bytes()).map_err(|e| e.to_string())?;
+            if constraints.ca {
+                return Err("the attestation certificate must not be a CA certificate".to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A credential public key
+enum PublicKey {
+    Es256(p256::ecdsa::VerifyingKey),
+    EdDsa(ed25519_dalek::VerifyingKey),
+    Rs256(rsa::pkcs1v15::VerifyingKey<Sha256>),
+}
+
+impl PublicKey {
+    /// Reads a COSE key (RFC 9053) and its algorithm.
+    fn from_cose(bytes: &[u8]) -> Result<(Self, i64), String> {
+        let key = cbor_map(ciborium::de::from_reader::<Cbor, _>(bytes).map_err(|e| format!("invalid COSE key: {}", e))?)?;
+        let parameter = |label: i64| {
+            key.iter()
+                .find(|(k, _)| k.as_integer().is_some_and(|k| i128::from(k) == label as i128))
+                .map(|(_, v)| v)
+        };
+        let integer = |label: i64| parameter(label).and_then(|v| v.as_integer()).map(i128::from);
+        let bytes = |label: i64| {
+            parameter(label)
+                .and_then(|v| v.as_bytes())
+                .ok_or_else(|| format!("the COSE key lacks parameter {}", label))
+        };
+
+        let algorithm = integer(3).ok_or("the COSE key has no algorithm")? as i64;
+        let public_key = match (integer(1), algorithm) {
+            // EC2 on P-256
+            (Some(2), ALG_ES256) if integer(-1) == Some(1) => {
+                let x: [u8; 32] = bytes(-2)?.as_slice().try_into().map_err(|_| "invalid P-256 key")?;
+                let y: [u8; 32] = bytes(-3)?.as_slice().try_into().map_err(|_| "invalid P-256 key")?;
+                let point = p256::EncodedPoint::from_affine_coordinates(&x.into(), &y.into(), false);
+                PublicKey::Es256(p256::ecdsa::VerifyingKey::from_encoded_point(&point).map_err(|e| e.to_string())?)
+            }
+            // OKP on Ed25519
+            (Some(1), ALG_EDDSA) if integer(-1) == Some(6) => {
+                let x: [u8; 32] = bytes(-2)?.as_slice().try_into().map_err(|_| "invalid Ed25519 key")?;
+                PublicKey::EdDsa(ed25519_dalek::VerifyingKey::from_bytes(&x).map_err(|e| e.to_string())?)
+            }
+            (Some(3), ALG_RS256) => {
+                let n = rsa::BigUint::from_bytes_be(bytes(-1)?);
+                let e = rsa::BigUint::from_bytes_be(bytes(-2)?);
+                let key = rsa::RsaPublicKey::new(n, e).map_err(|e| e.to_string())?;
+                PublicKey::Rs256(rsa::pkcs1v15::VerifyingKey::new(key))
+            }
+            _ => return Err(format!("unsupported credential algorithm {}", algorithm)),
+        };
+
+        Ok((public_key, algorithm))
+    }
+
+    fn from_certificate(certificate: &Certificate, algorithm: i64) -> Result<Self, String> {
+        let spki = certificate
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(|e| e.to_string())?;
+
+        match algorithm {
+            ALG_ES256 => p256::ecdsa::VerifyingKey::from_public_key_der(&spki)
+                .map(PublicKey::Es256)
+                .map_err(|e| e.to_string()),
+            ALG_RS256 => rsa::RsaPublicKey::from_public_key_der(&spki)
+                .map(|key| PublicKey::Rs256(rsa::pkcs1v15::VerifyingKey::new(key)))
+                .map_err(|e| e.to_string()),
+            other => Err(format!("unsupported attestation algorithm {}", other)),
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), String> {
+        let verified = match self {
+            PublicKey::Es256(key) => p256::ecdsa::Signature::from_der(signature)
+                .map_err(|e| e.to_string())
+                .and_then(|s| key.verify(message, &s).map_err(|e| e.to_string())),
+            PublicKey::EdDsa(key) => ed25519_dalek::Signature::from_slice(signature)
+                .map_err(|e| e.to_string())
+                .and_then(|s| key.verify(message, &s).map_err(|e| e.to_string())),
+            PublicKey::Rs256(key) => rsa::pkcs1v15::Signature::try_from(signature)
+                .map_err(|e| e.to_string())
+                .and_then(|s| key.verify(message, &s).map_err(|e| e.to_string())),
+        };
+        verified.map_err(|_| "invalid signature".to_string())
+    }
+}
+
+fn cbor_map(value: Cbor) -> Result<Vec<(Cbor, Cbor)>, String> {
+    match value {
+        Cbor::Map(entries) => Ok(entries),
+        _ => Err("expected a CBOR map".to_string()),
+    }
+}
+
+fn field<'a>(map: &'a [(Cbor, Cbor)], name: &str) -> Result<&'a Cbor, String> {
+    map.iter()
+        .find(|(k, _)| k.as_text() == Some(name))
+        .map(|(_, v)| v)
+        .ok_or_else(|| format!("missing \"{}\"", name))
+}
+
+fn text_field(map: &[(Cbor, Cbor)], name: &str) -> Result<String, String> {
+    field(map, name)?
+        .as_text()
+        .map(String::from)
+        .ok_or_else(|| format!("\"{}\" must be a text string", name))
+}
+
+fn bytes_field(map: &[(Cbor, Cbor)], name: &str) -> Result<Vec<u8>, String> {
+    field(map, name)?
+        .as_bytes()
+        .cloned()
+        .ok_or_else(|| format!("\"{}\" must be a byte string", name))
+}
+
+fn integer_field(map: &[(Cbor, Cbor)], name: &str) -> Result<i64, String> {
+    field(map, name)?
+        .as_integer()
+        .and_then(|i| i64::try_from(i).ok())
+        .ok_or_else(|| format!("\"{}\" must be an integer", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{DerSignature, SigningKey};
+    use p256::ecdsa::signature::Signer;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use x509_cert::builder::{Builder, CertificateBuilder, Profile};
+    use x509_cert::name::Name;
+    use x509_cert::serial_number::SerialNumber;
+    use x509_cert::spki::SubjectPublicKeyInfoOwned;
+    use x509_cert::time::Validity;
+
+    const AAGUID: Uuid = Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+
+    fn rp() -> RelyingParty {
+        RelyingParty::from_url("https://login.example.com/realms/acme").unwrap()
+    }
+
+    fn to_cbor(value: &Cbor) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(value, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn text(value: &str) -> Cbor {
+        Cbor::Text(value.to_string())
+    }
+
+    /// A software authenticator holding a single P-256 credential
+    struct Authenticator {
+        key: SigningKey,
+        credential_id: Vec<u8>,
+        sign_count: u32,
+        flags: u8,
+    }
+
+    impl Authenticator {
+        fn new() -> Self {
+            Self {
+                key: SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng),
+                credential_id: vec![7; 16],
+                sign_count: 0,
+                flags: FLAG_USER_PRESENT | FLAG_USER_VERIFIED,
+            }
+        }
+
+        fn cose_key(&self) -> Vec<u8> {
+            let point = self.key.verifying_key().to_encoded_point(false);
+            to_cbor(&Cbor::Map(vec![
+                (Cbor::from(1), Cbor::from(2)),
+                (Cbor::from(3), Cbor::from(ALG_ES256)),
+                (Cbor::from(-1), Cbor::from(1)),
+                (Cbor::from(-2), Cbor::Bytes(point.x().unwrap().to_vec())),
+                (Cbor::from(-3), Cbor::Bytes(point.y().unwrap().to_vec())),
+            ]))
+        }
+
+        fn auth_data(&self, rp_id: &str, attested: bool) -> Vec<u8> {
+            let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+            data.push(self.flags | if attested { FLAG_ATTESTED_CREDENTIAL_DATA } else { 0 });
+            data.extend_from_slice(&self.sign_count.to_be_bytes());
+            if attested {
+                data.extend_from_slice(AAGUID.as_bytes());
+                data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+                data.extend_from_slice(&self.credential_id);
+                data.extend_from_slice(&self.cose_key());
+            }
+            data
+        }
+
+        fn client_data(ceremony: &str, challenge: &str, origin: &str) -> Vec<u8> {
+            json!({ "type": ceremony, "challenge": challenge, "origin": origin }).to_string().into_bytes()
+        }
+
+        fn sign(&self, message: &[u8]) -> Vec<u8> {
+            let signature: DerSignature = self.key.sign(message);
+            signature.as_bytes().to_vec()
+        }
+
+        /// Registers with the given attestation format and statement, made from the signed data.
+        fn register(&self, challenge: &str, format: &str, statement: impl Fn(&[u8]) -> Vec<(Cbor, Cbor)>) -> RegistrationResponse {
+            let client_data = Self::client_data("webauthn.create", challenge, &rp().origin);
+            let auth_data = self.auth_data(&rp().id, true);
+            let signed = [auth_data.as_slice(), &Sha256::digest(&client_data)].concat();
+            let attestation = Cbor::Map(vec![
+                (text("fmt"), text(format)),
+                (text("attStmt"), Cbor::Map(statement(&signed))),
+                (text("authData"), Cbor::Bytes(auth_data)),
+            ]);
+
+            RegistrationResponse {
+                id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+                client_data_json: URL_SAFE_NO_PAD.encode(client_data),
+                attestation_object: URL_SAFE_NO_PAD.encode(to_cbor(&attestation)),
+                transports: vec!["internal".to_string()],
+            }
+        }
+
+        fn authenticate(&mut self, challenge: &str, origin: &str) -> AuthenticationResponse {
+            self.sign_count += 1;
+            let client_data = Self::client_data("webauthn.get", challenge, origin);
+            let auth_data = self.auth_data(&rp().id, false);
+            let signed = [auth_data.as_slice(), &Sha256::digest(&client_data)].concat();
+
+            AuthenticationResponse {
+                id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+                client_data_json: URL_SAFE_NO_PAD.encode(client_data),
+                authenticator_data: URL_SAFE_NO_PAD.encode(auth_data),
+                signature: URL_SAFE_NO_PAD.encode(self.sign(&signed)),
+                user_handle: None,
+            }
+        }
+    }
+
+    /// A self-signed attestation certificate of the authenticator model
+    fn attestation_certificate(key: &SigningKey, organizational_unit: &str) -> Vec<u8> {
+        let subject = Name::from_str(&format!("CN=Test Authenticator,OU={},O=Vaulton,C=DE", organizational_unit)).unwrap();
+        let spki = SubjectPublicKeyInfoOwned::from_key(*key.verifying_key()).unwrap();
+        let builder = CertificateBuilder::new(
+            Profile::Leaf {
+                issuer: subject.clone(),
+                enable_key_agreement: false,
+                enable_key_encipherment: false,
+            },
+            SerialNumber::from(1u32),
+            Validity::from_now(Duration::from_secs(3600)).unwrap(),
+            subject,
+            spki,
+            key,
+        )
+        .unwrap();
+        builder.build::<DerSignature>().unwrap().to_der().unwrap()
+    }
+
+    #[test]
+    fn test_relying_party_from_url() {
+        assert_eq!(
+            rp(),
+            RelyingParty {
+                id: "login.example.com".to_string(),
+                origin: "https://login.example.com".to_string(),
+            }
+        );
+        assert_eq!(RelyingParty::from_url("http://localhost:3000").unwrap().origin, "http://localhost:3000");
+    }
+
+    #[test]
+    fn test_register_with_none_attestation() {
+        let authenticator = Authenticator::new();
+        let response = authenticator.register("challenge", "none", |_| vec![]);
+
+        let credential = verify_registration(&rp(), "challenge", &response, true).unwrap();
+        assert_eq!(credential.credential_id, authenticator.credential_id);
+        assert_eq!(credential.public_key, authenticator.cose_key());
+        assert_eq!(credential.algorithm, ALG_ES256);
+        assert_eq!(credential.aaguid, AAGUID);
+        assert_eq!(credential.attestation_format, "none");
+        assert!(credential.user_verified);
+    }
+
+    #[test]
+    fn test_register_with_packed_self_attestation() {
+        let authenticator = Authenticator::new();
+        let response = authenticator.register("challenge", "packed", |signed| {
+            vec![
+                (text("alg"), Cbor::from(ALG_ES256)),
+                (text("sig"), Cbor::Bytes(authenticator.sign(signed))),
+            ]
+        });
+        assert_eq!(verify_registration(&rp(), "challenge", &response, false).unwrap().attestation_format, "packed");
+
+        let forged = authenticator.register("challenge", "packed", |_| {
+            vec![
+                (text("alg"), Cbor::from(ALG_ES256)),
+                (text("sig"), Cbor::Bytes(authenticator.sign(b"something else"))),
+            ]
+        });
+        assert!(verify_registration(&rp(), "challenge", &forged, false).is_err());
+    }
+
+    #[test]
+    fn test_register_with_packed_certificate_attestation() {
+        let authenticator = Authenticator::new();
+        let attestation_key = SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng);
+        let statement = |organizational_unit: &'static str| {
+            let certificate = attestation_certificate(&attestation_key, organizational_unit);
+            let key = attestation_key.clone();
+            move |signed: &[u8]| {
+                let signature: DerSignature = key.sign(signed);
+                vec![
+                    (text("alg"), Cbor::from(ALG_ES256)),
+                    (text("sig"), Cbor::Bytes(signature.as_bytes().to_vec())),
+                    (text("x5c"), Cbor::Array(vec![Cbor::Bytes(certificate.clone())])),
+                ]
+            }
+        };
+
+        let response = authenticator.register("challenge", "packed", statement("Authenticator Attestation"));
+        assert!(verify_registration(&rp(), "challenge", &response, false).is_ok());
+
+        let response = authenticator.register("challenge", "packed", statement("Web Servers"));
+        assert!(verify_registration(&rp(), "challenge", &response, false).is_err());
+    }
+
+    #[test]
+    fn test_registration_checks_ceremony() {
+        let mut authenticator = Authenticator::new();
+        let response = authenticator.register("challenge", "none", |_| vec![]);
+        assert!(verify_registration(&rp(), "another challenge", &response, false).is_err());
+
+        let other_rp = RelyingParty::from_url("https://evil.example.net").unwrap();
+        assert!(verify_registration(&other_rp, "challenge", &response, false).is_err());
+
+        let response = authenticator.register("challenge", "fido-u2f", |_| vec![]);
+        assert!(verify_registration(&rp(), "challenge", &response, false).is_err());
+
+        authenticator.flags = FLAG_USER_PRESENT;
+        let response = authenticator.register("challenge", "none", |_| vec![]);
+        assert!(verify_registration(&rp(), "challenge", &response, true).is_err());
+        assert!(verify_registration(&rp(), "challenge", &response, false).is_ok());
+    }
+
+    #[test]
+    fn test_assertion() {
+        let mut authenticator = Authenticator::new();
+        let public_key = authenticator.cose_key();
+
+        let response = authenticator.authenticate("challenge", &rp().origin);
+        let assertion = verify_assertion(&rp(), "challenge", &response, &public_key, 0, true).unwrap();
+        assert_eq!(
+            assertion,
+            Assertion {
+                sign_count: 1,
+                user_verified: true,
+            }
+        );
+
+        // A replayed or cloned authenticator does not count up
+        assert!(verify_assertion(&rp(), "challenge", &response, &public_key, 1, true).is_err());
+
+        let response = authenticator.authenticate("challenge", "https://evil.example.net");
+        assert!(verify_assertion(&rp(), "challenge", &response, &public_key, 1, true).is_err());
+
+        let mut response = authenticator.authenticate("challenge", &rp().origin);
+        response.signature = URL_SAFE_NO_PAD.encode(authenticator.sign(b"something else"));
+        assert!(verify_assertion(&rp(), "challenge", &response, &public_key, 1, true).is_err());
+
+        let other_key = Authenticator::new().cose_key();
+        let response = authenticator.authenticate("challenge", &rp().origin);
+        assert!(verify_assertion(&rp(), "challenge", &response, &other_key, 1, true).is_err());
+    }
+
+    #[test]
+    fn test_short_p256_coordinates_are_refused() {
+        let point = Authenticator::new().key.verifying_key().to_encoded_point(false);
+        let cose_key = |x: &[u8], y: &[u8]| {
+            to_cbor(&Cbor::Map(vec![
+                (Cbor::from(1), Cbor::from(2)),
+                (Cbor::from(3), Cbor::from(ALG_ES256)),
+                (Cbor::from(-1), Cbor::from(1)),
+                (Cbor::from(-2), Cbor::Bytes(x.to_vec())),
+                (Cbor::from(-3), Cbor::Bytes(y.to_vec())),
+            ]))
+        };
+        let (x, y) = (point.x().unwrap().as_slice(), point.y().unwrap().as_slice());
+
+        assert!(PublicKey::from_cose(&cose_key(x, y)).is_ok());
+        assert!(PublicKey::from_cose(&cose_key(&x[1..], y)).is_err());
+        assert!(PublicKey::from_cose(&cose_key(x, &y[..31])).is_err());
+        assert!(PublicKey::from_cose(&cose_key(x, &[y, &[0]].concat())).is_err());
+    }
+
+    #[test]
+    fn test_ed25519_credential() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[9; 32]);
+        let cose_key = to_cbor(&Cbor::Map(vec![
+            (Cbor::from(1), Cbor::from(1)),
+            (Cbor::from(3), Cbor::from(ALG_EDDSA)),
+            (Cbor::from(-1), Cbor::from(6)),
+            (Cbor::from(-2), Cbor::Bytes(key.verifying_key().to_bytes().to_vec())),
+        ]));
+
+        let (public_key, algorithm) = PublicKey::from_cose(&cose_key).unwrap();
+        assert_eq!(algorithm, ALG_EDDSA);
+        assert!(public_key.verify(b"message", &key.sign(b"message").to_bytes()).is_ok());
+        assert!(public_key.verify(b"another message", &key.sign(b"message").to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_sign_count_increased() {
+        assert!(sign_count_increased(0, 0));
+        assert!(sign_count_increased(0, 1));
+        assert!(sign_count_increased(5, 6));
+        assert!(!sign_count_increased(5, 5));
+        assert!(!sign_count_increased(5, 0));
+    }
+
+    #[test]
+    fn test_options() {
+        let options = creation_options(&rp(), Uuid::nil(), "alice@example.com", "challenge", &[vec![1, 2, 3]]);
+        assert_eq!(options["rp"]["id"], "login.example.com");
+        assert_eq!(options["user"]["id"], "AAAAAAAAAAAAAAAAAAAAAA");
+        assert_eq!(options["pubKeyCredParams"][0]["alg"], ALG_ES256);
+        assert_eq!(options["excludeCredentials"][0]["id"], "AQID");
+
+        let options = request_options(&rp(), "challenge", &[], "required");
+        assert_eq!(options["allowCredentials"], json!([]));
+        assert_eq!(options["userVerification"], "required");
+    }
+}
@@ -0,0 +1,189 @@
+use crate::db::Database;
+use crate::domain::access_token::{AccessToken, AccessTokenFormat};
+use crate::domain::client::ClientId;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct CreateAccessTokenParams {
+    pub uuid: Uuid,
+    pub format: AccessTokenFormat,
+    /// SHA-256 hash of the token value, only set for opaque tokens
+    pub token_hash: Option<Vec<u8>>,
+    pub client_uuid: Uuid,
+    pub user_id: Option<Uuid>,
+    pub scopes: Vec<String>,
+    pub audience: Vec<String>,
+    pub claims: Vec<String>,
+    pub organization_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait AccessTokenRepository: Interface {
+    async fn create(&self, params: CreateAccessTokenParams) -> Result<AccessToken, String>;
+    /// Finds a token issued to a client of the realm.
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<AccessToken>;
+    async fn find_by_hash(&self, realm_uuid: Uuid, token_hash: &[u8]) -> Option<AccessToken>;
+    /// Marks the token as revoked. Returns false if the token does not exist.
+    async fn revoke(&self, id: Uuid) -> Result<bool, String>;
+    /// Revokes every active token issued to the user. Returns the number of revoked tokens.
+    async fn revoke_by_user(&self, user_id: Uuid) -> Result<u64, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = AccessTokenRepository)]
+pub struct PostgresAccessTokenRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl AccessTokenRepository for PostgresAccessTokenRepository {
+    async fn create(&self, params: CreateAccessTokenParams) -> Result<AccessToken, String> {
+        let result = sqlx::query!(
+            r#"
+            with created as (
+                insert into access_tokens (id, format, token_hash, client_id, user_id, scopes, audience, claims,
+                                           organization_id, expires_at)
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                returning *
+            )
+            select created.id, created.format, created.client_id as "client_uuid", clients.client_id,
+                   created.user_id, created.scopes, created.audience, created.claims, created.organization_id,
+                   created.expires_at,
+                   created.revoked_at, created.created_at
+            from created
+            join clients on clients.id = created.client_id
+            "#,
+            params.uuid,
+            params.format.to_string(),
+            params.token_hash,
+            params.client_uuid,
+            params.user_id,
+            params.scopes.as_slice(),
+            params.audience.as_slice(),
+            params.claims.as_slice(),
+            params.organization_id,
+            params.expires_at,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(AccessToken {
+            uuid: result.id,
+            format: result.format.parse()?,
+            client_uuid: result.client_uuid,
+            client_id: ClientId(result.client_id),
+            user_id: result.user_id,
+            scopes: result.scopes,
+            audience: result.audience,
+            claims: result.claims,
+            organization_id: result.organization_id,
+            expires_at: result.expires_at,
+            revoked_at: result.revoked_at,
+            created_at: result.created_at,
+        })
+    }
+
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<AccessToken> {
+        let result = sqlx::query!(
+            r#"
+            select access_tokens.id, access_tokens.format, access_tokens.client_id as "client_uuid",
+                   clients.client_id, access_tokens.user_id, access_tokens.scopes, access_tokens.audience,
+                   access_tokens.claims, access_tokens.organization_id, access_tokens.expires_at, access_tokens.revoked_at, access_tokens.created_at
+            from access_tokens
+            join clients on clients.id = access_tokens.client_id
+            where access_tokens.id = $1 and clients.realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(AccessToken {
+            uuid: result.id,
+            format: result.format.parse().ok()?,
+            client_uuid: result.client_uuid,
+            client_id: ClientId(result.client_id),
+            user_id: result.user_id,
+            scopes: result.scopes,
+            audience: result.audience,
+            claims: result.claims,
+            organization_id: result.organization_id,
+            expires_at: result.expires_at,
+            revoked_at: result.revoked_at,
+            created_at: result.created_at,
+        })
+    }
+
+    async fn find_by_hash(&self, realm_uuid: Uuid, token_hash: &[u8]) -> Option<AccessToken> {
+        let result = sqlx::query!(
+            r#"
+            select access_tokens.id, access_tokens.format, access_tokens.client_id as "client_uuid",
+                   clients.client_id, access_tokens.user_id, access_tokens.scopes, access_tokens.audience,
+                   access_tokens.claims, access_tokens.organization_id, access_tokens.expires_at, access_tokens.revoked_at, access_tokens.created_at
+            from access_tokens
+            join clients on clients.id = access_tokens.client_id
+            where access_tokens.token_hash = $1 and clients.realm_id = $2
+            "#,
+            token_hash,
+            realm_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(AccessToken {
+            uuid: result.id,
+            format: result.format.parse().ok()?,
+            client_uuid: result.client_uuid,
+            client_id: ClientId(result.client_id),
+            user_id: result.user_id,
+            scopes: result.scopes,
+            audience: result.audience,
+            claims: result.claims,
+            organization_id: result.organization_id,
+            expires_at: result.expires_at,
+            revoked_at: result.revoked_at,
+            created_at: result.created_at,
+        })
+    }
+
+    async fn revoke(&self, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update access_tokens
+            set revoked_at = coalesce(revoked_at, now())
+            where id = $1
+            "#,
+            id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn revoke_by_user(&self, user_id: Uuid) -> Result<u64, String> {
+        let result = sqlx::query!(
+            r#"
+            update access_tokens
+            set revoked_at = now()
+            where user_id = $1 and revoked_at is null and expires_at > now()
+            "#,
+            user_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected())
+    }
+}
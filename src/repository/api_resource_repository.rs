@@ -0,0 +1,287 @@
+use crate::db::Database;
+use crate::domain::access_token::AccessTokenFormat;
+use crate::domain::api_resource::{ApiResource, ApiResourceScope};
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct CreateApiResourceParams {
+    pub realm_uuid: Uuid,
+    pub identifier: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub access_token_format: Option<AccessTokenFormat>,
+    pub access_token_lifetime: Option<i32>,
+    pub signing_algorithm: String,
+    pub scopes: Vec<CreateApiResourceScopeParams>,
+}
+
+pub struct CreateApiResourceScopeParams {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[async_trait]
+pub trait ApiResourceRepository: Interface {
+    async fn create(&self, params: CreateApiResourceParams) -> Result<ApiResource, String>;
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<ApiResource>;
+    async fn find_by_identifier(&self, realm_uuid: Uuid, identifier: &str) -> Option<ApiResource>;
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<ApiResource>, String>;
+    async fn add_scope(&self, resource_id: Uuid, params: CreateApiResourceScopeParams) -> Result<ApiResourceScope, String>;
+    /// Looks up resource scopes by name, for showing their descriptions.
+    async fn find_scopes(&self, realm_uuid: Uuid, names: &[String]) -> Result<Vec<ApiResourceScope>, String>;
+    /// Deletes the resource and its scopes. Returns false if it did not exist.
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = ApiResourceRepository)]
+pub struct PostgresApiResourceRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+impl PostgresApiResourceRepository {
+    async fn fetch_scopes(&self, resource_ids: &[Uuid]) -> Result<HashMap<Uuid, Vec<ApiResourceScope>>, String> {
+        let results = sqlx::query!(
+            r#"
+            select id, resource_id, name, description
+            from api_resource_scopes
+            where resource_id = any($1)
+            order by name
+            "#,
+            resource_ids,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let mut scopes: HashMap<Uuid, Vec<ApiResourceScope>> = HashMap::new();
+        for result in results {
+            scopes.entry(result.resource_id).or_default().push(ApiResourceScope {
+                uuid: result.id,
+                name: result.name,
+                description: result.description,
+            });
+        }
+
+        Ok(scopes)
+    }
+}
+
+#[async_trait]
+impl ApiResourceRepository for PostgresApiResourceRepository {
+    async fn create(&self, params: CreateApiResourceParams) -> Result<ApiResource, String> {
+        let mut tx = self.pool.get_pool().begin().await.map_err(|e| e.to_string())?;
+
+        let result = sqlx::query!(
+            r#"
+            insert into api_resources (identifier, name, description, access_token_format,
+                                       access_token_lifetime, signing_algorithm, realm_id)
+            values ($1, $2, $3, $4, $5, $6, $7)
+            returning *
+            "#,
+            params.identifier,
+            params.name,
+            params.description,
+            params.access_token_format.map(|f| f.to_string()),
+            params.access_token_lifetime,
+            params.signing_algorithm,
+            params.realm_uuid,
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let mut scopes = Vec::with_capacity(params.scopes.len());
+        for scope in params.scopes {
+            let scope = sqlx::query!(
+                r#"
+                insert into api_resource_scopes (resource_id, name, description, realm_id)
+                values ($1, $2, $3, $4)
+                returning id, name, description
+                "#,
+                result.id,
+                scope.name,
+                scope.description,
+                params.realm_uuid,
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+            scopes.push(ApiResourceScope {
+                uuid: scope.id,
+                name: scope.name,
+                description: scope.description,
+            });
+        }
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        Ok(ApiResource {
+            uuid: result.id,
+            identifier: result.identifier,
+            name: result.name,
+            description: result.description,
+            access_token_format: result.access_token_format.map(|f| f.parse()).transpose()?,
+            access_token_lifetime: result.access_token_lifetime,
+            signing_algorithm: result.signing_algorithm,
+            scopes,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<ApiResource> {
+        let result = sqlx::query!(
+            r#"
+            select * from api_resources where id = $1 and realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        let mut scopes = self.fetch_scopes(&[result.id]).await.ok()?;
+
+        Some(ApiResource {
+            uuid: result.id,
+            identifier: result.identifier,
+            name: result.name,
+            description: result.description,
+            access_token_format: result.access_token_format.map(|f| f.parse()).transpose().ok()?,
+            access_token_lifetime: result.access_token_lifetime,
+            signing_algorithm: result.signing_algorithm,
+            scopes: scopes.remove(&result.id).unwrap_or_default(),
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn find_by_identifier(&self, realm_uuid: Uuid, identifier: &str) -> Option<ApiResource> {
+        let result = sqlx::query!(
+            r#"
+            select * from api_resources where identifier = $1 and realm_id = $2
+            "#,
+            identifier,
+            realm_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        let mut scopes = self.fetch_scopes(&[result.id]).await.ok()?;
+
+        Some(ApiResource {
+            uuid: result.id,
+            identifier: result.identifier,
+            name: result.name,
+            description: result.description,
+            access_token_format: result.access_token_format.map(|f| f.parse()).transpose().ok()?,
+            access_token_lifetime: result.access_token_lifetime,
+            signing_algorithm: result.signing_algorithm,
+            scopes: scopes.remove(&result.id).unwrap_or_default(),
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<ApiResource>, String> {
+        let results = sqlx::query!(
+            r#"
+            select * from api_resources where realm_id = $1 order by identifier
+            "#,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let ids = results.iter().map(|r| r.id).collect::<Vec<_>>();
+        let mut scopes = self.fetch_scopes(&ids).await?;
+
+        results
+            .into_iter()
+            .map(|result| {
+                Ok(ApiResource {
+                    uuid: result.id,
+                    identifier: result.identifier,
+                    name: result.name,
+                    description: result.description,
+                    access_token_format: result.access_token_format.map(|f| f.parse()).transpose()?,
+                    access_token_lifetime: result.access_token_lifetime,
+                    signing_algorithm: result.signing_algorithm,
+                    scopes: scopes.remove(&result.id).unwrap_or_default(),
+                    created_at: result.created_at,
+                    updated_at: result.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn add_scope(&self, resource_id: Uuid, params: CreateApiResourceScopeParams) -> Result<ApiResourceScope, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into api_resource_scopes (resource_id, name, description, realm_id)
+            select id, $2, $3, realm_id from api_resources where id = $1
+            returning id, name, description
+            "#,
+            resource_id,
+            params.name,
+            params.description,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(ApiResourceScope {
+            uuid: result.id,
+            name: result.name,
+            description: result.description,
+        })
+    }
+
+    async fn find_scopes(&self, realm_uuid: Uuid, names: &[String]) -> Result<Vec<ApiResourceScope>, String> {
+        let results = sqlx::query!(
+            r#"
+            select id, name, description
+            from api_resource_scopes
+            where name = any($1) and realm_id = $2
+            "#,
+            names,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| ApiResourceScope {
+                uuid: result.id,
+                name: result.name,
+                description: result.description,
+            })
+            .collect())
+    }
+
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from api_resources where id = $1 and realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -9,6 +9,7 @@ use crate::oidc::auth::AuthorizationRequest;
 pub trait AuthRequestRepository: Interface {
     async fn store_request(&self, request: &AuthorizationRequest) -> Result<(), String>;
     async fn find_by_id(&self, request_id: &str) -> Option<AuthorizationRequest>;
+    async fn remove(&self, request_id: &str) -> Option<AuthorizationRequest>;
 }
 
 #[derive(Component)]
@@ -18,14 +19,6 @@ pub struct InMemoryAuthRequestRepository {
     requests: Arc<RwLock<HashMap<String, AuthorizationRequest>>>,
 }
 
-impl InMemoryAuthRequestRepository {
-    fn new() -> Self {
-        Self {
-            requests: Arc::new(RwLock::new(HashMap::new())),
-        }
-    }
-}
-
 #[async_trait]
 impl AuthRequestRepository for InMemoryAuthRequestRepository {
     async fn store_request(&self, request: &AuthorizationRequest) -> Result<(), String> {
@@ -38,4 +31,9 @@ impl AuthRequestRepository for InMemoryAuthRequestRepository {
         let requests = self.requests.read().await;
         requests.get(request_id).cloned()
     }
-}
\ No newline at end of file
+
+    async fn remove(&self, request_id: &str) -> Option<AuthorizationRequest> {
+        let mut requests = self.requests.write().await;
+        requests.remove(request_id)
+    }
+}
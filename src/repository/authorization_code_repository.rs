@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use tokio::sync::RwLock;
+use crate::oidc::auth::AuthorizationCode;
+
+#[async_trait]
+pub trait AuthorizationCodeRepository: Interface {
+    async fn store_code(&self, code: &AuthorizationCode) -> Result<(), String>;
+    /// Removes and returns the code, so that every code can be redeemed only once.
+    async fn take_code(&self, code: &str) -> Option<AuthorizationCode>;
+}
+
+#[derive(Component)]
+#[shaku(interface = AuthorizationCodeRepository)]
+pub struct InMemoryAuthorizationCodeRepository {
+    #[shaku(default)]
+    codes: Arc<RwLock<HashMap<String, AuthorizationCode>>>,
+}
+
+#[async_trait]
+impl AuthorizationCodeRepository for InMemoryAuthorizationCodeRepository {
+    async fn store_code(&self, code: &AuthorizationCode) -> Result<(), String> {
+        let mut codes = self.codes.write().await;
+        codes.insert(code.code.clone(), code.clone());
+        Ok(())
+    }
+
+    async fn take_code(&self, code: &str) -> Option<AuthorizationCode> {
+        let mut codes = self.codes.write().await;
+        codes.remove(code)
+    }
+}
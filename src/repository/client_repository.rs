@@ -1,20 +1,93 @@
 use crate::db::Database;
+use crate::domain::access_token::AccessTokenFormat;
 use crate::domain::client::{Client, ClientId};
 use async_trait::async_trait;
 use shaku::{Component, Interface};
+use sqlx::PgExecutor;
 use std::sync::Arc;
+use uuid::Uuid;
 
 pub struct CreateClientParams {
+    pub realm_uuid: Uuid,
     pub client_id: String,
     pub redirect_uris: Vec<String>,
     pub scopes: Vec<String>,
     pub client_secret_hash: Option<Vec<u8>>,
+    pub client_name: Option<String>,
+    pub logo_uri: Option<String>,
+    pub application_type: String,
+    pub subject_type: String,
+    pub sector_identifier_uri: Option<String>,
+    pub grant_types: Vec<String>,
+    pub token_endpoint_auth_method: Option<String>,
+    pub registration_token_hash: Option<Vec<u8>>,
+    pub first_party: bool,
+    pub id_token_role_claims: bool,
+    pub access_token_format: AccessTokenFormat,
+    pub access_token_lifetime: Option<i32>,
+    pub id_token_lifetime: Option<i32>,
+    pub id_token_signed_response_alg: String,
+    pub jwks: Option<serde_json::Value>,
+    pub id_token_encrypted_response_alg: Option<String>,
+    pub id_token_encrypted_response_enc: Option<String>,
+    pub userinfo_encrypted_response_alg: Option<String>,
+    pub userinfo_encrypted_response_enc: Option<String>,
+}
+
+/// The client metadata a client may change about itself through the registration endpoint.
+/// Token formats, lifetimes and the first-party flag stay under the control of administrators.
+pub struct UpdateClientMetadataParams {
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<String>,
+    pub client_name: Option<String>,
+    pub logo_uri: Option<String>,
+    pub application_type: String,
+    pub subject_type: String,
+    pub sector_identifier_uri: Option<String>,
+    pub grant_types: Vec<String>,
+    pub token_endpoint_auth_method: Option<String>,
+    pub id_token_signed_response_alg: String,
+    pub jwks: Option<serde_json::Value>,
+    pub id_token_encrypted_response_alg: Option<String>,
+    pub id_token_encrypted_response_enc: Option<String>,
+    pub userinfo_encrypted_response_alg: Option<String>,
+    pub userinfo_encrypted_response_enc: Option<String>,
 }
 
 #[async_trait]
 pub trait ClientRepository: Interface {
     async fn create(&self, client: CreateClientParams) -> Result<Client, String>;
-    async fn find_by_id(&self, id: &ClientId) -> Option<Client>;
+    /// Creates a dynamically registered client. With an initial access token, one of its uses
+    /// is taken up in the same transaction. Returns None, creating nothing, if the token is
+    /// unknown, expired or has no uses left.
+    async fn register(
+        &self,
+        client: CreateClientParams,
+        initial_access_token_hash: Option<&[u8]>,
+    ) -> Result<Option<Client>, String>;
+    async fn find_by_id(&self, realm_uuid: Uuid, id: &ClientId) -> Option<Client>;
+    /// Returns every client of the realm, ordered by client_id.
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<Client>, String>;
+    async fn update_metadata(&self, realm_uuid: Uuid, id: &ClientId, params: UpdateClientMetadataParams) -> Result<Client, String>;
+    /// Switches role claims in the client's ID tokens on or off. Returns false if the client does not exist.
+    async fn set_id_token_role_claims(&self, realm_uuid: Uuid, id: &ClientId, enabled: bool) -> Result<bool, String>;
+    /// Replaces the access token format and the token lifetimes. Returns false if the client does not exist.
+    async fn set_token_settings(
+        &self,
+        realm_uuid: Uuid,
+        id: &ClientId,
+        access_token_format: AccessTokenFormat,
+        access_token_lifetime: Option<i32>,
+        id_token_lifetime: Option<i32>,
+    ) -> Result<bool, String>;
+    /// Marks the client as first-party or not. Returns false if the client does not exist.
+    async fn set_first_party(&self, realm_uuid: Uuid, id: &ClientId, first_party: bool) -> Result<bool, String>;
+    /// Replaces the hash of the client's registration access token.
+    async fn set_registration_token_hash(&self, realm_uuid: Uuid, id: &ClientId, token_hash: &[u8]) -> Result<(), String>;
+    /// Replaces the hash of the client secret. Returns false if the client does not exist.
+    async fn set_secret_hash(&self, realm_uuid: Uuid, id: &ClientId, secret_hash: &[u8]) -> Result<bool, String>;
+    /// Deletes the client. Returns false if it did not exist.
+    async fn delete(&self, realm_uuid: Uuid, id: &ClientId) -> Result<bool, String>;
 }
 
 #[derive(Component)]
@@ -24,62 +97,383 @@ pub struct PostgresClientRepository {
     pool: Arc<dyn Database>,
 }
 
-impl PostgresClientRepository {
-    fn new(pool: Arc<dyn Database>) -> Self {
-        Self { pool }
-    }
-}
-
 #[async_trait]
 impl ClientRepository for PostgresClientRepository {
     async fn create(&self, params: CreateClientParams) -> Result<Client, String> {
+        insert_client(self.pool.get_pool(), params).await
+    }
+
+    async fn register(
+        &self,
+        params: CreateClientParams,
+        initial_access_token_hash: Option<&[u8]>,
+    ) -> Result<Option<Client>, String> {
+        let mut tx = self.pool.get_pool().begin().await.map_err(|e| e.to_string())?;
+
+        if let Some(token_hash) = initial_access_token_hash {
+            let result = sqlx::query!(
+                r#"
+                update initial_access_tokens
+                set remaining_uses = remaining_uses - 1
+                where token_hash = $1 and realm_id = $2
+                  and (expires_at is null or expires_at > current_timestamp)
+                  and (remaining_uses is null or remaining_uses > 0)
+                "#,
+                token_hash,
+                params.realm_uuid,
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+            if result.rows_affected() == 0 {
+                return Ok(None);
+            }
+        }
+
+        let client = insert_client(&mut *tx, params).await?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        Ok(Some(client))
+    }
+
+    async fn find_by_id(&self, realm_uuid: Uuid, id: &ClientId) -> Option<Client> {
         let result = sqlx::query!(
             r#"
-            insert into clients(client_id, client_secret_hash, redirect_uris, scopes)
-            values ($1, $2, $3, $4)
-            returning *;
+            SELECT id, client_id, client_secret_hash, redirect_uris, scopes, client_name, logo_uri,
+                   application_type, subject_type, sector_identifier_uri, grant_types, token_endpoint_auth_method,
+                   registration_token_hash, first_party, id_token_role_claims,
+                   access_token_format, access_token_lifetime, id_token_lifetime,
+                   id_token_signed_response_alg, jwks, id_token_encrypted_response_alg,
+                   id_token_encrypted_response_enc, userinfo_encrypted_response_alg,
+                   userinfo_encrypted_response_enc, created_at, updated_at
+            FROM clients 
+            WHERE client_id = $1 AND realm_id = $2;
             "#,
-            params.client_id,
-            params.client_secret_hash,
-            params.redirect_uris.as_slice(),
-            params.scopes.as_slice(),
+            id.0.as_str(),
+            realm_uuid,
         )
-        .fetch_one(self.pool.get_pool())
+        .fetch_optional(self.pool.get_pool())
         .await
-        .map_err(|e| e.to_string())?;
+        .ok()??;
 
-        Ok(Client {
+        Some(Client {
             uuid: result.id,
             id: ClientId(result.client_id),
             secret_hash: result.client_secret_hash,
-            redirect_uris: result.redirect_uris.unwrap(),
-            allowed_scopes: result.scopes.unwrap(),
+            redirect_uris: result.redirect_uris.unwrap_or_default(),
+            allowed_scopes: result.scopes.unwrap_or_default(),
+            client_name: result.client_name,
+            logo_uri: result.logo_uri,
+            application_type: result.application_type,
+            subject_type: result.subject_type,
+            sector_identifier_uri: result.sector_identifier_uri,
+            grant_types: result.grant_types,
+            token_endpoint_auth_method: result.token_endpoint_auth_method,
+            registration_token_hash: result.registration_token_hash,
+            first_party: result.first_party,
+            id_token_role_claims: result.id_token_role_claims,
+            access_token_format: result.access_token_format.parse().ok()?,
+            access_token_lifetime: result.access_token_lifetime,
+            id_token_lifetime: result.id_token_lifetime,
+            id_token_signed_response_alg: result.id_token_signed_response_alg,
+            jwks: result.jwks,
+            id_token_encrypted_response_alg: result.id_token_encrypted_response_alg,
+            id_token_encrypted_response_enc: result.id_token_encrypted_response_enc,
+            userinfo_encrypted_response_alg: result.userinfo_encrypted_response_alg,
+            userinfo_encrypted_response_enc: result.userinfo_encrypted_response_enc,
             created_at: result.created_at,
             updated_at: result.updated_at,
         })
     }
 
-    async fn find_by_id(&self, id: &ClientId) -> Option<Client> {
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<Client>, String> {
+        let results = sqlx::query!(
+            r#"
+            select * from clients where realm_id = $1 order by client_id
+            "#,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        results
+            .into_iter()
+            .map(|result| {
+                Ok(Client {
+                    uuid: result.id,
+                    id: ClientId(result.client_id),
+                    secret_hash: result.client_secret_hash,
+                    redirect_uris: result.redirect_uris.unwrap_or_default(),
+                    allowed_scopes: result.scopes.unwrap_or_default(),
+                    client_name: result.client_name,
+                    logo_uri: result.logo_uri,
+                    application_type: result.application_type,
+                    subject_type: result.subject_type,
+                    sector_identifier_uri: result.sector_identifier_uri,
+                    grant_types: result.grant_types,
+                    token_endpoint_auth_method: result.token_endpoint_auth_method,
+                    registration_token_hash: result.registration_token_hash,
+                    first_party: result.first_party,
+                    id_token_role_claims: result.id_token_role_claims,
+                    access_token_format: result.access_token_format.parse()?,
+                    access_token_lifetime: result.access_token_lifetime,
+                    id_token_lifetime: result.id_token_lifetime,
+                    id_token_signed_response_alg: result.id_token_signed_response_alg,
+                    jwks: result.jwks,
+                    id_token_encrypted_response_alg: result.id_token_encrypted_response_alg,
+                    id_token_encrypted_response_enc: result.id_token_encrypted_response_enc,
+                    userinfo_encrypted_response_alg: result.userinfo_encrypted_response_alg,
+                    userinfo_encrypted_response_enc: result.userinfo_encrypted_response_enc,
+                    created_at: result.created_at,
+                    updated_at: result.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn update_metadata(&self, realm_uuid: Uuid, id: &ClientId, params: UpdateClientMetadataParams) -> Result<Client, String> {
         let result = sqlx::query!(
             r#"
-            SELECT id, client_id, client_secret_hash, redirect_uris, scopes, created_at, updated_at 
-            FROM clients 
-            WHERE client_id = $1;
+            update clients
+            set redirect_uris = $2, scopes = $3, client_name = $4, logo_uri = $5, application_type = $6,
+                subject_type = $7, sector_identifier_uri = $8, grant_types = $9, token_endpoint_auth_method = $10,
+                id_token_signed_response_alg = $11, jwks = $12, id_token_encrypted_response_alg = $13,
+                id_token_encrypted_response_enc = $14, userinfo_encrypted_response_alg = $15,
+                userinfo_encrypted_response_enc = $16
+            where client_id = $1 and realm_id = $17
+            returning *
             "#,
             id.0.as_str(),
+            params.redirect_uris.as_slice(),
+            params.scopes.as_slice(),
+            params.client_name,
+            params.logo_uri,
+            params.application_type,
+            params.subject_type,
+            params.sector_identifier_uri,
+            params.grant_types.as_slice(),
+            params.token_endpoint_auth_method,
+            params.id_token_signed_response_alg,
+            params.jwks,
+            params.id_token_encrypted_response_alg,
+            params.id_token_encrypted_response_enc,
+            params.userinfo_encrypted_response_alg,
+            params.userinfo_encrypted_response_enc,
+            realm_uuid,
         )
-        .fetch_optional(self.pool.get_pool())
+        .fetch_one(self.pool.get_pool())
         .await
-        .ok()??;
+        .map_err(|e| e.to_string())?;
 
-        Some(Client {
+        Ok(Client {
             uuid: result.id,
             id: ClientId(result.client_id),
             secret_hash: result.client_secret_hash,
             redirect_uris: result.redirect_uris.unwrap_or_default(),
             allowed_scopes: result.scopes.unwrap_or_default(),
+            client_name: result.client_name,
+            logo_uri: result.logo_uri,
+            application_type: result.application_type,
+            subject_type: result.subject_type,
+            sector_identifier_uri: result.sector_identifier_uri,
+            grant_types: result.grant_types,
+            token_endpoint_auth_method: result.token_endpoint_auth_method,
+            registration_token_hash: result.registration_token_hash,
+            first_party: result.first_party,
+            id_token_role_claims: result.id_token_role_claims,
+            access_token_format: result.access_token_format.parse()?,
+            access_token_lifetime: result.access_token_lifetime,
+            id_token_lifetime: result.id_token_lifetime,
+            id_token_signed_response_alg: result.id_token_signed_response_alg,
+            jwks: result.jwks,
+            id_token_encrypted_response_alg: result.id_token_encrypted_response_alg,
+            id_token_encrypted_response_enc: result.id_token_encrypted_response_enc,
+            userinfo_encrypted_response_alg: result.userinfo_encrypted_response_alg,
+            userinfo_encrypted_response_enc: result.userinfo_encrypted_response_enc,
             created_at: result.created_at,
             updated_at: result.updated_at,
         })
     }
+
+    async fn set_id_token_role_claims(&self, realm_uuid: Uuid, id: &ClientId, enabled: bool) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update clients set id_token_role_claims = $2 where client_id = $1 and realm_id = $3
+            "#,
+            id.0.as_str(),
+            enabled,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_token_settings(
+        &self,
+        realm_uuid: Uuid,
+        id: &ClientId,
+        access_token_format: AccessTokenFormat,
+        access_token_lifetime: Option<i32>,
+        id_token_lifetime: Option<i32>,
+    ) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update clients
+            set access_token_format = $2, access_token_lifetime = $3, id_token_lifetime = $4
+            where client_id = $1 and realm_id = $5
+            "#,
+            id.0.as_str(),
+            access_token_format.to_string(),
+            access_token_lifetime,
+            id_token_lifetime,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_first_party(&self, realm_uuid: Uuid, id: &ClientId, first_party: bool) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update clients set first_party = $2 where client_id = $1 and realm_id = $3
+            "#,
+            id.0.as_str(),
+            first_party,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_registration_token_hash(&self, realm_uuid: Uuid, id: &ClientId, token_hash: &[u8]) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            update clients set registration_token_hash = $2 where client_id = $1 and realm_id = $3
+            "#,
+            id.0.as_str(),
+            token_hash,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn set_secret_hash(&self, realm_uuid: Uuid, id: &ClientId, secret_hash: &[u8]) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update clients set client_secret_hash = $2 where client_id = $1 and realm_id = $3
+            "#,
+            id.0.as_str(),
+            secret_hash,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, realm_uuid: Uuid, id: &ClientId) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from clients where client_id = $1 and realm_id = $2
+            "#,
+            id.0.as_str(),
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Inserts the client, on its own or as part of a transaction.
+async fn insert_client(executor: impl PgExecutor<'_>, params: CreateClientParams) -> Result<Client, String> {
+    let result = sqlx::query!(
+        r#"
+        insert into clients(client_id, client_secret_hash, redirect_uris, scopes, client_name, logo_uri,
+                            application_type, subject_type, sector_identifier_uri, grant_types,
+                            token_endpoint_auth_method, registration_token_hash, first_party, id_token_role_claims,
+                            access_token_format, access_token_lifetime, id_token_lifetime,
+                            id_token_signed_response_alg, jwks, id_token_encrypted_response_alg,
+                            id_token_encrypted_response_enc, userinfo_encrypted_response_alg,
+                            userinfo_encrypted_response_enc, realm_id)
+        values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
+                $21, $22, $23, $24)
+        returning *;
+        "#,
+        params.client_id,
+        params.client_secret_hash,
+        params.redirect_uris.as_slice(),
+        params.scopes.as_slice(),
+        params.client_name,
+        params.logo_uri,
+        params.application_type,
+        params.subject_type,
+        params.sector_identifier_uri,
+        params.grant_types.as_slice(),
+        params.token_endpoint_auth_method,
+        params.registration_token_hash,
+        params.first_party,
+        params.id_token_role_claims,
+        params.access_token_format.to_string(),
+        params.access_token_lifetime,
+        params.id_token_lifetime,
+        params.id_token_signed_response_alg,
+        params.jwks,
+        params.id_token_encrypted_response_alg,
+        params.id_token_encrypted_response_enc,
+        params.userinfo_encrypted_response_alg,
+        params.userinfo_encrypted_response_enc,
+        params.realm_uuid,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(Client {
+        uuid: result.id,
+        id: ClientId(result.client_id),
+        secret_hash: result.client_secret_hash,
+        redirect_uris: result.redirect_uris.unwrap_or_default(),
+        allowed_scopes: result.scopes.unwrap_or_default(),
+        client_name: result.client_name,
+        logo_uri: result.logo_uri,
+        application_type: result.application_type,
+        subject_type: result.subject_type,
+        sector_identifier_uri: result.sector_identifier_uri,
+        grant_types: result.grant_types,
+        token_endpoint_auth_method: result.token_endpoint_auth_method,
+        registration_token_hash: result.registration_token_hash,
+        first_party: result.first_party,
+        id_token_role_claims: result.id_token_role_claims,
+        access_token_format: result.access_token_format.parse()?,
+        access_token_lifetime: result.access_token_lifetime,
+        id_token_lifetime: result.id_token_lifetime,
+        id_token_signed_response_alg: result.id_token_signed_response_alg,
+        jwks: result.jwks,
+        id_token_encrypted_response_alg: result.id_token_encrypted_response_alg,
+        id_token_encrypted_response_enc: result.id_token_encrypted_response_enc,
+        userinfo_encrypted_response_alg: result.userinfo_encrypted_response_alg,
+        userinfo_encrypted_response_enc: result.userinfo_encrypted_response_enc,
+        created_at: result.created_at,
+        updated_at: result.updated_at,
+    })
 }
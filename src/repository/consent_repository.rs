@@ -0,0 +1,145 @@
+use crate::db::Database;
+use crate::domain::client::ClientId;
+use crate::domain::consent::Consent;
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct GrantConsentParams {
+    pub user_id: Uuid,
+    pub client_uuid: Uuid,
+    pub scopes: Vec<String>,
+}
+
+#[async_trait]
+pub trait ConsentRepository: Interface {
+    /// Records the granted scopes, merging them with any scopes granted earlier.
+    async fn grant(&self, params: GrantConsentParams) -> Result<Consent, String>;
+    async fn find(&self, user_id: Uuid, client_uuid: Uuid) -> Option<Consent>;
+    async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<Consent>, String>;
+    /// Removes the consent a user gave to a client. Returns false if there was none.
+    async fn revoke(&self, user_id: Uuid, client_id: &ClientId) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = ConsentRepository)]
+pub struct PostgresConsentRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl ConsentRepository for PostgresConsentRepository {
+    async fn grant(&self, params: GrantConsentParams) -> Result<Consent, String> {
+        let result = sqlx::query!(
+            r#"
+            with granted as (
+                insert into consents (user_id, client_id, scopes)
+                values ($1, $2, $3)
+                on conflict (user_id, client_id) do update
+                set scopes = array(
+                    select distinct unnest(consents.scopes || excluded.scopes) order by 1
+                )
+                returning *
+            )
+            select granted.id, granted.user_id, granted.client_id as "client_uuid",
+                   clients.client_id, granted.scopes, granted.created_at, granted.updated_at
+            from granted
+            join clients on clients.id = granted.client_id
+            "#,
+            params.user_id,
+            params.client_uuid,
+            params.scopes.as_slice(),
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(Consent {
+            uuid: result.id,
+            user_id: result.user_id,
+            client_uuid: result.client_uuid,
+            client_id: ClientId(result.client_id),
+            scopes: result.scopes,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn find(&self, user_id: Uuid, client_uuid: Uuid) -> Option<Consent> {
+        let result = sqlx::query!(
+            r#"
+            select consents.id, consents.user_id, consents.client_id as "client_uuid",
+                   clients.client_id, consents.scopes, consents.created_at, consents.updated_at
+            from consents
+            join clients on clients.id = consents.client_id
+            where consents.user_id = $1 and consents.client_id = $2
+            "#,
+            user_id,
+            client_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Consent {
+            uuid: result.id,
+            user_id: result.user_id,
+            client_uuid: result.client_uuid,
+            client_id: ClientId(result.client_id),
+            scopes: result.scopes,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn list_by_user(&self, user_id: Uuid) -> Result<Vec<Consent>, String> {
+        let results = sqlx::query!(
+            r#"
+            select consents.id, consents.user_id, consents.client_id as "client_uuid",
+                   clients.client_id, consents.scopes, consents.created_at, consents.updated_at
+            from consents
+            join clients on clients.id = consents.client_id
+            where consents.user_id = $1
+            order by clients.client_id
+            "#,
+            user_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Consent {
+                uuid: result.id,
+                user_id: result.user_id,
+                client_uuid: result.client_uuid,
+                client_id: ClientId(result.client_id),
+                scopes: result.scopes,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn revoke(&self, user_id: Uuid, client_id: &ClientId) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from consents
+            using clients
+            where clients.id = consents.client_id
+              and consents.user_id = $1
+              and clients.client_id = $2
+            "#,
+            user_id,
+            client_id.0.as_str(),
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,351 @@
+use crate::db::Database;
+use crate::domain::group::Group;
+use crate::domain::user::User;
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct CreateGroupParams {
+    pub realm_uuid: Uuid,
+    /// The parent group, None for top-level groups
+    pub parent_uuid: Option<Uuid>,
+    pub name: String,
+    pub attributes: serde_json::Value,
+}
+
+#[async_trait]
+pub trait GroupRepository: Interface {
+    async fn create(&self, params: CreateGroupParams) -> Result<Group, String>;
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<Group>;
+    /// All groups of the realm, ordered by path so parents come before their subgroups.
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<Group>, String>;
+    /// Replaces the group's attributes. Returns false if the group does not exist.
+    async fn update_attributes(&self, realm_uuid: Uuid, id: Uuid, attributes: serde_json::Value) -> Result<bool, String>;
+    /// Deletes the group and its subgroups. Returns false if it did not exist.
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String>;
+    /// The groups the user is a direct member of, ordered by path.
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Group>, String>;
+    /// The groups the user is a member of together with all their ancestors, ordered by path.
+    async fn find_lineage_by_user(&self, user_id: Uuid) -> Result<Vec<Group>, String>;
+    /// The direct members of the group, ordered by username.
+    async fn list_members(&self, id: Uuid) -> Result<Vec<User>, String>;
+    /// Adds the user to the group. Adding an existing member has no effect.
+    async fn add_member(&self, id: Uuid, user_id: Uuid) -> Result<(), String>;
+    /// Returns false if the user was not a member of the group.
+    async fn remove_member(&self, id: Uuid, user_id: Uuid) -> Result<bool, String>;
+    /// Maps the role to the group. Mapping an already mapped role has no effect.
+    async fn add_role(&self, id: Uuid, role_id: Uuid) -> Result<(), String>;
+    /// Returns false if the role was not mapped to the group.
+    async fn remove_role(&self, id: Uuid, role_id: Uuid) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = GroupRepository)]
+pub struct PostgresGroupRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl GroupRepository for PostgresGroupRepository {
+    async fn create(&self, params: CreateGroupParams) -> Result<Group, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into groups (parent_id, name, attributes, realm_id)
+            values ($1, $2, $3, $4)
+            returning id
+            "#,
+            params.parent_uuid,
+            params.name,
+            params.attributes,
+            params.realm_uuid,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        // The path is derived from the ancestors, so read the group back
+        self.find_by_id(params.realm_uuid, result.id).await.ok_or("group not found".to_string())
+    }
+
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<Group> {
+        let result = sqlx::query!(
+            r#"
+            with recursive tree(id, path) as (
+                select id, '/' || name from groups where parent_id is null
+                union all
+                select groups.id, tree.path || '/' || groups.name
+                from groups
+                join tree on tree.id = groups.parent_id
+            )
+            select groups.id, groups.parent_id, groups.name, tree.path as "path!", groups.attributes,
+                   groups.created_at, groups.updated_at
+            from groups
+            join tree on tree.id = groups.id
+            where groups.id = $1 and groups.realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Group {
+            uuid: result.id,
+            parent_uuid: result.parent_id,
+            name: result.name,
+            path: result.path,
+            attributes: result.attributes,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<Group>, String> {
+        let results = sqlx::query!(
+            r#"
+            with recursive tree(id, path) as (
+                select id, '/' || name from groups where parent_id is null
+                union all
+                select groups.id, tree.path || '/' || groups.name
+                from groups
+                join tree on tree.id = groups.parent_id
+            )
+            select groups.id, groups.parent_id, groups.name, tree.path as "path!", groups.attributes,
+                   groups.created_at, groups.updated_at
+            from groups
+            join tree on tree.id = groups.id
+            where groups.realm_id = $1
+            order by tree.path
+            "#,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Group {
+                uuid: result.id,
+                parent_uuid: result.parent_id,
+                name: result.name,
+                path: result.path,
+                attributes: result.attributes,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn update_attributes(&self, realm_uuid: Uuid, id: Uuid, attributes: serde_json::Value) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update groups set attributes = $2 where id = $1 and realm_id = $3
+            "#,
+            id,
+            attributes,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from groups where id = $1 and realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Group>, String> {
+        let results = sqlx::query!(
+            r#"
+            with recursive tree(id, path) as (
+                select id, '/' || name from groups where parent_id is null
+                union all
+                select groups.id, tree.path || '/' || groups.name
+                from groups
+                join tree on tree.id = groups.parent_id
+            )
+            select groups.id, groups.parent_id, groups.name, tree.path as "path!", groups.attributes,
+                   groups.created_at, groups.updated_at
+            from groups
+            join tree on tree.id = groups.id
+            join user_groups on user_groups.group_id = groups.id
+            where user_groups.user_id = $1
+            order by tree.path
+            "#,
+            user_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Group {
+                uuid: result.id,
+                parent_uuid: result.parent_id,
+                name: result.name,
+                path: result.path,
+                attributes: result.attributes,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn find_lineage_by_user(&self, user_id: Uuid) -> Result<Vec<Group>, String> {
+        let results = sqlx::query!(
+            r#"
+            with recursive tree(id, path) as (
+                select id, '/' || name from groups where parent_id is null
+                union all
+                select groups.id, tree.path || '/' || groups.name
+                from groups
+                join tree on tree.id = groups.parent_id
+            ),
+            lineage(id) as (
+                select group_id from user_groups where user_id = $1
+                union
+                select groups.parent_id
+                from groups
+                join lineage on lineage.id = groups.id
+                where groups.parent_id is not null
+            )
+            select groups.id, groups.parent_id, groups.name, tree.path as "path!", groups.attributes,
+                   groups.created_at, groups.updated_at
+            from groups
+            join tree on tree.id = groups.id
+            join lineage on lineage.id = groups.id
+            order by tree.path
+            "#,
+            user_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Group {
+                uuid: result.id,
+                parent_uuid: result.parent_id,
+                name: result.name,
+                path: result.path,
+                attributes: result.attributes,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn list_members(&self, id: Uuid) -> Result<Vec<User>, String> {
+        let results = sqlx::query!(
+            r#"
+            select users.*
+            from users
+            join user_groups on user_groups.user_id = users.id
+            where user_groups.group_id = $1 and users.deleted_at is null
+            order by users.username
+            "#,
+            id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| User {
+                uuid: result.id,
+                username: result.username,
+                password_hash: result.password_hash,
+                email: result.email,
+                attributes: result.attributes,
+                enabled: result.enabled,
+                deleted_at: result.deleted_at,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn add_member(&self, id: Uuid, user_id: Uuid) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            insert into user_groups (user_id, group_id)
+            values ($1, $2)
+            on conflict do nothing
+            "#,
+            user_id,
+            id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn remove_member(&self, id: Uuid, user_id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from user_groups where group_id = $1 and user_id = $2
+            "#,
+            id,
+            user_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn add_role(&self, id: Uuid, role_id: Uuid) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            insert into group_roles (group_id, role_id)
+            values ($1, $2)
+            on conflict do nothing
+            "#,
+            id,
+            role_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn remove_role(&self, id: Uuid, role_id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from group_roles where group_id = $1 and role_id = $2
+            "#,
+            id,
+            role_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,98 @@
+use crate::db::Database;
+use crate::domain::initial_access_token::InitialAccessToken;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct CreateInitialAccessTokenParams {
+    pub realm_uuid: Uuid,
+    pub token_hash: Vec<u8>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub remaining_uses: Option<i32>,
+}
+
+#[async_trait]
+pub trait InitialAccessTokenRepository: Interface {
+    async fn create(&self, params: CreateInitialAccessTokenParams) -> Result<InitialAccessToken, String>;
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<InitialAccessToken>, String>;
+    /// Deletes the token. Returns false if it did not exist.
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = InitialAccessTokenRepository)]
+pub struct PostgresInitialAccessTokenRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl InitialAccessTokenRepository for PostgresInitialAccessTokenRepository {
+    async fn create(&self, params: CreateInitialAccessTokenParams) -> Result<InitialAccessToken, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into initial_access_tokens (token_hash, expires_at, remaining_uses, realm_id)
+            values ($1, $2, $3, $4)
+            returning id, expires_at, remaining_uses, created_at, updated_at
+            "#,
+            params.token_hash,
+            params.expires_at,
+            params.remaining_uses,
+            params.realm_uuid,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(InitialAccessToken {
+            uuid: result.id,
+            expires_at: result.expires_at,
+            remaining_uses: result.remaining_uses,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<InitialAccessToken>, String> {
+        let results = sqlx::query!(
+            r#"
+            select id, expires_at, remaining_uses, created_at, updated_at
+            from initial_access_tokens
+            where realm_id = $1
+            order by created_at
+            "#,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| InitialAccessToken {
+                uuid: result.id,
+                expires_at: result.expires_at,
+                remaining_uses: result.remaining_uses,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from initial_access_tokens where id = $1 and realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::db::Database;
+use crate::domain::login_failure::{FailureSubject, LoginFailures};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[async_trait]
+pub trait LoginFailureRepository: Interface {
+    async fn find(&self, realm_id: Uuid, subject: FailureSubject) -> Option<LoginFailures>;
+    /// Counts a failed login. Failures before `forget_before` are forgotten first.
+    async fn record_failure(
+        &self,
+        realm_id: Uuid,
+        subject: FailureSubject,
+        forget_before: DateTime<Utc>,
+    ) -> Result<LoginFailures, String>;
+    /// Locks the subject out until the given time, or until it is unlocked without one.
+    /// The failure count starts over.
+    async fn lock(&self, realm_id: Uuid, subject: FailureSubject, until: Option<DateTime<Utc>>) -> Result<(), String>;
+    /// Forgets the failures and lifts any lockout. Returns false if there was nothing to forget.
+    async fn clear(&self, realm_id: Uuid, subject: FailureSubject) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = LoginFailureRepository)]
+pub struct PostgresLoginFailureRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl LoginFailureRepository for PostgresLoginFailureRepository {
+    async fn find(&self, realm_id: Uuid, subject: FailureSubject) -> Option<LoginFailures> {
+        let result = sqlx::query!(
+            r#"
+            select failures, last_failure_at, locked_until, locked_permanently
+            from login_failures
+            where realm_id = $1 and kind = $2 and key = $3
+            "#,
+            realm_id,
+            subject.kind(),
+            subject.key(),
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(LoginFailures {
+            failures: result.failures,
+            last_failure_at: result.last_failure_at,
+            locked_until: result.locked_until,
+            locked_permanently: result.locked_permanently,
+        })
+    }
+
+    async fn record_failure(
+        &self,
+        realm_id: Uuid,
+        subject: FailureSubject,
+        forget_before: DateTime<Utc>,
+    ) -> Result<LoginFailures, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into login_failures (realm_id, kind, key, failures, last_failure_at)
+            values ($1, $2, $3, 1, now())
+            on conflict (realm_id, kind, key) do update
+            set failures = case when login_failures.last_failure_at < $4 then 1
+                                else login_failures.failures + 1 end,
+                last_failure_at = now()
+            returning failures, last_failure_at, locked_until, locked_permanently
+            "#,
+            realm_id,
+            subject.kind(),
+            subject.key(),
+            forget_before,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(LoginFailures {
+            failures: result.failures,
+            last_failure_at: result.last_failure_at,
+            locked_until: result.locked_until,
+            locked_permanently: result.locked_permanently,
+        })
+    }
+
+    async fn lock(&self, realm_id: Uuid, subject: FailureSubject, until: Option<DateTime<Utc>>) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            update login_failures
+            set failures = 0, locked_until = $4, locked_permanently = $4::timestamptz is null
+            where realm_id = $1 and kind = $2 and key = $3
+            "#,
+            realm_id,
+            subject.kind(),
+            subject.key(),
+            until,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn clear(&self, realm_id: Uuid, subject: FailureSubject) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from login_failures
+            where realm_id = $1 and kind = $2 and key = $3
+            "#,
+            realm_id,
+            subject.kind(),
+            subject.key(),
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,196 @@
+use crate::db::Database;
+use crate::domain::mfa::TotpCredential;
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[async_trait]
+pub trait MfaRepository: Interface {
+    async fn find_totp(&self, user_id: Uuid) -> Option<TotpCredential>;
+    /// Stores a new, unconfirmed authenticator, replacing an unconfirmed one.
+    /// Returns false if the user already has a confirmed authenticator.
+    async fn store_totp(&self, user_id: Uuid, encrypted_secret: &str) -> Result<bool, String>;
+    /// Confirms the authenticator with the time step of the code the user entered.
+    async fn confirm_totp(&self, user_id: Uuid, step: i64) -> Result<bool, String>;
+    /// Records the time step of an accepted code. Returns false if the authenticator is not
+    /// confirmed or a code of the same or a later step was already accepted.
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, String>;
+    /// Replaces the user's recovery codes.
+    async fn replace_recovery_codes(&self, user_id: Uuid, code_hashes: &[Vec<u8>]) -> Result<(), String>;
+    /// Uses up a recovery code. Returns false if the user has no unused code with the hash.
+    async fn use_recovery_code(&self, user_id: Uuid, code_hash: &[u8]) -> Result<bool, String>;
+    async fn count_recovery_codes(&self, user_id: Uuid) -> Result<i64, String>;
+    /// Removes the user's authenticator and recovery codes. Returns false if there were none.
+    async fn reset(&self, user_id: Uuid) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = MfaRepository)]
+pub struct PostgresMfaRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl MfaRepository for PostgresMfaRepository {
+    async fn find_totp(&self, user_id: Uuid) -> Option<TotpCredential> {
+        let result = sqlx::query!(
+            r#"
+            select user_id, encrypted_secret, confirmed_at, last_used_step, created_at
+            from user_totp
+            where user_id = $1
+            "#,
+            user_id,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(TotpCredential {
+            user_id: result.user_id,
+            encrypted_secret: result.encrypted_secret,
+            confirmed_at: result.confirmed_at,
+            last_used_step: result.last_used_step,
+            created_at: result.created_at,
+        })
+    }
+
+    async fn store_totp(&self, user_id: Uuid, encrypted_secret: &str) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into user_totp (user_id, encrypted_secret)
+            values ($1, $2)
+            on conflict (user_id) do update
+            set encrypted_secret = excluded.encrypted_secret, last_used_step = null
+            where user_totp.confirmed_at is null
+            "#,
+            user_id,
+            encrypted_secret,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn confirm_totp(&self, user_id: Uuid, step: i64) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update user_totp set confirmed_at = now(), last_used_step = $2
+            where user_id = $1 and confirmed_at is null
+            "#,
+            user_id,
+            step,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update user_totp set last_used_step = $2
+            where user_id = $1 and confirmed_at is not null
+              and (last_used_step is null or last_used_step < $2)
+            "#,
+            user_id,
+            step,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn replace_recovery_codes(&self, user_id: Uuid, code_hashes: &[Vec<u8>]) -> Result<(), String> {
+        let mut tx = self.pool.get_pool().begin().await.map_err(|e| e.to_string())?;
+
+        sqlx::query!(
+            r#"
+            delete from recovery_codes where user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        sqlx::query!(
+            r#"
+            insert into recovery_codes (user_id, code_hash)
+            select $1, unnest($2::bytea[])
+            "#,
+            user_id,
+            code_hashes,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())
+    }
+
+    async fn use_recovery_code(&self, user_id: Uuid, code_hash: &[u8]) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update recovery_codes set used_at = now()
+            where user_id = $1 and code_hash = $2 and used_at is null
+            "#,
+            user_id,
+            code_hash,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_recovery_codes(&self, user_id: Uuid) -> Result<i64, String> {
+        let result = sqlx::query!(
+            r#"
+            select count(*) as "count!" from recovery_codes where user_id = $1 and used_at is null
+            "#,
+            user_id,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.count)
+    }
+
+    async fn reset(&self, user_id: Uuid) -> Result<bool, String> {
+        let mut tx = self.pool.get_pool().begin().await.map_err(|e| e.to_string())?;
+
+        let totp = sqlx::query!(
+            r#"
+            delete from user_totp where user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let codes = sqlx::query!(
+            r#"
+            delete from recovery_codes where user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        Ok(totp.rows_affected() + codes.rows_affected() > 0)
+    }
+}
@@ -1,4 +1,19 @@
 pub mod client_repository;
 pub mod auth_request_repository;
+pub mod authorization_code_repository;
 pub mod user_repository;
-pub mod roles_repository;
\ No newline at end of file
+pub mod roles_repository;
+pub mod consent_repository;
+pub mod signing_key_repository;
+pub mod access_token_repository;
+pub mod api_resource_repository;
+pub mod initial_access_token_repository;
+pub mod protocol_mapper_repository;
+pub mod group_repository;
+pub mod setup_token_repository;
+pub mod provisioning_repository;
+pub mod realm_repository;
+pub mod organization_repository;
+pub mod mfa_repository;
+pub mod passkey_repository;
+pub mod login_failure_repository;
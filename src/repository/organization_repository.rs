@@ -0,0 +1,545 @@
+use crate::db::Database;
+use crate::domain::organization::{Invitation, Membership, Organization, OrganizationDomain};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct CreateOrganizationParams {
+    pub realm_uuid: Uuid,
+    pub name: String,
+    pub display_name: Option<String>,
+}
+
+pub struct UpdateOrganizationParams {
+    pub display_name: Option<String>,
+}
+
+pub struct CreateInvitationParams {
+    pub organization_uuid: Uuid,
+    pub email: String,
+    pub roles: Vec<String>,
+    pub token_hash: Vec<u8>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait OrganizationRepository: Interface {
+    async fn create(&self, params: CreateOrganizationParams) -> Result<Organization, String>;
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<Organization>;
+    /// All organizations of the realm, ordered by name.
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<Organization>, String>;
+    async fn update(&self, realm_uuid: Uuid, id: Uuid, params: UpdateOrganizationParams) -> Result<Organization, String>;
+    /// Deletes the organization with its domains, memberships and invitations.
+    /// Returns false if it did not exist.
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String>;
+
+    /// The organization's domains, ordered by domain.
+    async fn list_domains(&self, id: Uuid) -> Result<Vec<OrganizationDomain>, String>;
+    async fn add_domain(&self, realm_uuid: Uuid, id: Uuid, domain: &str, verified: bool) -> Result<OrganizationDomain, String>;
+    /// Marks the domain verified. Returns false if the organization has no such domain.
+    async fn verify_domain(&self, id: Uuid, domain: &str) -> Result<bool, String>;
+    /// Returns false if the organization had no such domain.
+    async fn remove_domain(&self, id: Uuid, domain: &str) -> Result<bool, String>;
+    /// The organization of the realm that verified the domain.
+    async fn find_by_verified_domain(&self, realm_uuid: Uuid, domain: &str) -> Option<Organization>;
+
+    /// Adds the user to the organization with the roles, or adds the roles to an existing membership.
+    async fn add_member(&self, id: Uuid, user_id: Uuid, roles: &[String]) -> Result<(), String>;
+    /// Adds the user to the organization or replaces the roles of an existing membership.
+    async fn set_member(&self, id: Uuid, user_id: Uuid, roles: &[String]) -> Result<(), String>;
+    /// Returns false if the user was not a member of the organization.
+    async fn remove_member(&self, id: Uuid, user_id: Uuid) -> Result<bool, String>;
+    /// The members of the organization, oldest first.
+    async fn list_members(&self, id: Uuid) -> Result<Vec<Membership>, String>;
+    async fn find_membership(&self, id: Uuid, user_id: Uuid) -> Option<Membership>;
+    /// The user's memberships, ordered by organization name.
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Membership>, String>;
+
+    async fn create_invitation(&self, params: CreateInvitationParams) -> Result<Invitation, String>;
+    /// The invitations of the organization, newest first.
+    async fn list_invitations(&self, id: Uuid) -> Result<Vec<Invitation>, String>;
+    /// Looks up an invitation to an organization of the realm by the hash of its token.
+    async fn find_invitation_by_hash(&self, realm_uuid: Uuid, token_hash: &[u8]) -> Option<Invitation>;
+    /// Returns false if the organization had no such invitation.
+    async fn delete_invitation(&self, id: Uuid, invitation_id: Uuid) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = OrganizationRepository)]
+pub struct PostgresOrganizationRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl OrganizationRepository for PostgresOrganizationRepository {
+    async fn create(&self, params: CreateOrganizationParams) -> Result<Organization, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into organizations (realm_id, name, display_name)
+            values ($1, $2, $3)
+            returning id, name, display_name, created_at, updated_at
+            "#,
+            params.realm_uuid,
+            params.name,
+            params.display_name,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(Organization {
+            uuid: result.id,
+            name: result.name,
+            display_name: result.display_name,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<Organization> {
+        let result = sqlx::query!(
+            r#"
+            select id, name, display_name, created_at, updated_at
+            from organizations
+            where id = $1 and realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Organization {
+            uuid: result.id,
+            name: result.name,
+            display_name: result.display_name,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<Organization>, String> {
+        let results = sqlx::query!(
+            r#"
+            select id, name, display_name, created_at, updated_at
+            from organizations
+            where realm_id = $1
+            order by name
+            "#,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Organization {
+                uuid: result.id,
+                name: result.name,
+                display_name: result.display_name,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn update(&self, realm_uuid: Uuid, id: Uuid, params: UpdateOrganizationParams) -> Result<Organization, String> {
+        let result = sqlx::query!(
+            r#"
+            update organizations set display_name = $3
+            where id = $1 and realm_id = $2
+            returning id, name, display_name, created_at, updated_at
+            "#,
+            id,
+            realm_uuid,
+            params.display_name,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(Organization {
+            uuid: result.id,
+            name: result.name,
+            display_name: result.display_name,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from organizations where id = $1 and realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_domains(&self, id: Uuid) -> Result<Vec<OrganizationDomain>, String> {
+        let results = sqlx::query!(
+            r#"
+            select domain, verified, created_at
+            from organization_domains
+            where organization_id = $1
+            order by domain
+            "#,
+            id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| OrganizationDomain {
+                domain: result.domain,
+                verified: result.verified,
+                created_at: result.created_at,
+            })
+            .collect())
+    }
+
+    async fn add_domain(&self, realm_uuid: Uuid, id: Uuid, domain: &str, verified: bool) -> Result<OrganizationDomain, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into organization_domains (organization_id, realm_id, domain, verified)
+            values ($1, $2, $3, $4)
+            returning domain, verified, created_at
+            "#,
+            id,
+            realm_uuid,
+            domain,
+            verified,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(OrganizationDomain {
+            domain: result.domain,
+            verified: result.verified,
+            created_at: result.created_at,
+        })
+    }
+
+    async fn verify_domain(&self, id: Uuid, domain: &str) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update organization_domains set verified = true
+            where organization_id = $1 and domain = $2
+            "#,
+            id,
+            domain,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn remove_domain(&self, id: Uuid, domain: &str) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from organization_domains where organization_id = $1 and domain = $2
+            "#,
+            id,
+            domain,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_by_verified_domain(&self, realm_uuid: Uuid, domain: &str) -> Option<Organization> {
+        let result = sqlx::query!(
+            r#"
+            select organizations.id, organizations.name, organizations.display_name,
+                   organizations.created_at, organizations.updated_at
+            from organizations
+            join organization_domains on organization_domains.organization_id = organizations.id
+            where organization_domains.realm_id = $1 and organization_domains.domain = $2
+              and organization_domains.verified
+            "#,
+            realm_uuid,
+            domain,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Organization {
+            uuid: result.id,
+            name: result.name,
+            display_name: result.display_name,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn add_member(&self, id: Uuid, user_id: Uuid, roles: &[String]) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            insert into organization_members (organization_id, user_id, roles)
+            values ($1, $2, $3)
+            on conflict (organization_id, user_id) do update
+            set roles = array(
+                select distinct unnest(organization_members.roles || excluded.roles) order by 1
+            )
+            "#,
+            id,
+            user_id,
+            roles,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn set_member(&self, id: Uuid, user_id: Uuid, roles: &[String]) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            insert into organization_members (organization_id, user_id, roles)
+            values ($1, $2, $3)
+            on conflict (organization_id, user_id) do update set roles = excluded.roles
+            "#,
+            id,
+            user_id,
+            roles,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn remove_member(&self, id: Uuid, user_id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from organization_members where organization_id = $1 and user_id = $2
+            "#,
+            id,
+            user_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_members(&self, id: Uuid) -> Result<Vec<Membership>, String> {
+        let results = sqlx::query!(
+            r#"
+            select organizations.id, organizations.name, organizations.display_name,
+                   organizations.created_at, organizations.updated_at,
+                   organization_members.user_id, organization_members.roles,
+                   organization_members.created_at as joined_at
+            from organization_members
+            join organizations on organizations.id = organization_members.organization_id
+            where organization_members.organization_id = $1
+            order by organization_members.created_at
+            "#,
+            id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Membership {
+                organization: Organization {
+                    uuid: result.id,
+                    name: result.name,
+                    display_name: result.display_name,
+                    created_at: result.created_at,
+                    updated_at: result.updated_at,
+                },
+                user_id: result.user_id,
+                roles: result.roles,
+                created_at: result.joined_at,
+            })
+            .collect())
+    }
+
+    async fn find_membership(&self, id: Uuid, user_id: Uuid) -> Option<Membership> {
+        let result = sqlx::query!(
+            r#"
+            select organizations.id, organizations.name, organizations.display_name,
+                   organizations.created_at, organizations.updated_at,
+                   organization_members.user_id, organization_members.roles,
+                   organization_members.created_at as joined_at
+            from organization_members
+            join organizations on organizations.id = organization_members.organization_id
+            where organization_members.organization_id = $1 and organization_members.user_id = $2
+            "#,
+            id,
+            user_id,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Membership {
+            organization: Organization {
+                uuid: result.id,
+                name: result.name,
+                display_name: result.display_name,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            },
+            user_id: result.user_id,
+            roles: result.roles,
+            created_at: result.joined_at,
+        })
+    }
+
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Membership>, String> {
+        let results = sqlx::query!(
+            r#"
+            select organizations.id, organizations.name, organizations.display_name,
+                   organizations.created_at, organizations.updated_at,
+                   organization_members.user_id, organization_members.roles,
+                   organization_members.created_at as joined_at
+            from organization_members
+            join organizations on organizations.id = organization_members.organization_id
+            where organization_members.user_id = $1
+            order by organizations.name
+            "#,
+            user_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Membership {
+                organization: Organization {
+                    uuid: result.id,
+                    name: result.name,
+                    display_name: result.display_name,
+                    created_at: result.created_at,
+                    updated_at: result.updated_at,
+                },
+                user_id: result.user_id,
+                roles: result.roles,
+                created_at: result.joined_at,
+            })
+            .collect())
+    }
+
+    async fn create_invitation(&self, params: CreateInvitationParams) -> Result<Invitation, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into organization_invitations (organization_id, email, roles, token_hash, expires_at)
+            values ($1, $2, $3, $4, $5)
+            returning id, organization_id, email, roles, expires_at, created_at
+            "#,
+            params.organization_uuid,
+            params.email,
+            params.roles.as_slice(),
+            params.token_hash,
+            params.expires_at,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(Invitation {
+            uuid: result.id,
+            organization_uuid: result.organization_id,
+            email: result.email,
+            roles: result.roles,
+            expires_at: result.expires_at,
+            created_at: result.created_at,
+        })
+    }
+
+    async fn list_invitations(&self, id: Uuid) -> Result<Vec<Invitation>, String> {
+        let results = sqlx::query!(
+            r#"
+            select id, organization_id, email, roles, expires_at, created_at
+            from organization_invitations
+            where organization_id = $1
+            order by created_at desc
+            "#,
+            id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Invitation {
+                uuid: result.id,
+                organization_uuid: result.organization_id,
+                email: result.email,
+                roles: result.roles,
+                expires_at: result.expires_at,
+                created_at: result.created_at,
+            })
+            .collect())
+    }
+
+    async fn find_invitation_by_hash(&self, realm_uuid: Uuid, token_hash: &[u8]) -> Option<Invitation> {
+        let result = sqlx::query!(
+            r#"
+            select organization_invitations.id, organization_invitations.organization_id,
+                   organization_invitations.email, organization_invitations.roles,
+                   organization_invitations.expires_at, organization_invitations.created_at
+            from organization_invitations
+            join organizations on organizations.id = organization_invitations.organization_id
+            where organization_invitations.token_hash = $1 and organizations.realm_id = $2
+            "#,
+            token_hash,
+            realm_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Invitation {
+            uuid: result.id,
+            organization_uuid: result.organization_id,
+            email: result.email,
+            roles: result.roles,
+            expires_at: result.expires_at,
+            created_at: result.created_at,
+        })
+    }
+
+    async fn delete_invitation(&self, id: Uuid, invitation_id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from organization_invitations where organization_id = $1 and id = $2
+            "#,
+            id,
+            invitation_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::db::Database;
+use crate::domain::passkey::Passkey;
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct CreatePasskeyParams {
+    pub user_id: Uuid,
+    pub credential_id: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub algorithm: i32,
+    pub sign_count: i64,
+    pub aaguid: Uuid,
+    pub attestation_format: String,
+    pub transports: Vec<String>,
+    pub backup_eligible: bool,
+    pub name: String,
+}
+
+#[async_trait]
+pub trait PasskeyRepository: Interface {
+    async fn create(&self, params: CreatePasskeyParams) -> Result<Passkey, String>;
+    async fn find_by_credential_id(&self, credential_id: &[u8]) -> Option<Passkey>;
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Passkey>, String>;
+    /// Records a use of the passkey with the authenticator's new signature counter. Returns false
+    /// if the counter did not increase in the meantime, as happens when a response is replayed.
+    async fn record_use(&self, id: Uuid, sign_count: i64) -> Result<bool, String>;
+    async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<bool, String>;
+    /// Removes all of the user's passkeys. Returns false if there were none.
+    async fn delete_by_user(&self, user_id: Uuid) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = PasskeyRepository)]
+pub struct PostgresPasskeyRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl PasskeyRepository for PostgresPasskeyRepository {
+    async fn create(&self, params: CreatePasskeyParams) -> Result<Passkey, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into passkeys (user_id, credential_id, public_key, algorithm, sign_count, aaguid,
+                                  attestation_format, transports, backup_eligible, name)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            returning id, created_at
+            "#,
+            params.user_id,
+            params.credential_id,
+            params.public_key,
+            params.algorithm,
+            params.sign_count,
+            params.aaguid,
+            params.attestation_format,
+            &params.transports,
+            params.backup_eligible,
+            params.name,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(Passkey {
+            uuid: result.id,
+            user_id: params.user_id,
+            credential_id: params.credential_id,
+            public_key: params.public_key,
+            algorithm: params.algorithm,
+            sign_count: params.sign_count,
+            aaguid: params.aaguid,
+            attestation_format: params.attestation_format,
+            transports: params.transports,
+            backup_eligible: params.backup_eligible,
+            name: params.name,
+            last_used_at: None,
+            created_at: result.created_at,
+        })
+    }
+
+    async fn find_by_credential_id(&self, credential_id: &[u8]) -> Option<Passkey> {
+        let result = sqlx::query!(
+            r#"
+            select id, user_id, credential_id, public_key, algorithm, sign_count, aaguid,
+                   attestation_format, transports, backup_eligible, name, last_used_at, created_at
+            from passkeys
+            where credential_id = $1
+            "#,
+            credential_id,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Passkey {
+            uuid: result.id,
+            user_id: result.user_id,
+            credential_id: result.credential_id,
+            public_key: result.public_key,
+            algorithm: result.algorithm,
+            sign_count: result.sign_count,
+            aaguid: result.aaguid,
+            attestation_format: result.attestation_format,
+            transports: result.transports,
+            backup_eligible: result.backup_eligible,
+            name: result.name,
+            last_used_at: result.last_used_at,
+            created_at: result.created_at,
+        })
+    }
+
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Passkey>, String> {
+        let results = sqlx::query!(
+            r#"
+            select id, user_id, credential_id, public_key, algorithm, sign_count, aaguid,
+                   attestation_format, transports, backup_eligible, name, last_used_at, created_at
+            from passkeys
+            where user_id = $1
+            order by created_at
+            "#,
+            user_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Passkey {
+                uuid: result.id,
+                user_id: result.user_id,
+                credential_id: result.credential_id,
+                public_key: result.public_key,
+                algorithm: result.algorithm,
+                sign_count: result.sign_count,
+                aaguid: result.aaguid,
+                attestation_format: result.attestation_format,
+                transports: result.transports,
+                backup_eligible: result.backup_eligible,
+                name: result.name,
+                last_used_at: result.last_used_at,
+                created_at: result.created_at,
+            })
+            .collect())
+    }
+
+    async fn record_use(&self, id: Uuid, sign_count: i64) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update passkeys set sign_count = $2, last_used_at = now()
+            where id = $1 and (sign_count < $2 or (sign_count = 0 and $2 = 0))
+            "#,
+            id,
+            sign_count,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from passkeys where id = $1 and user_id = $2
+            "#,
+            id,
+            user_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_by_user(&self, user_id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from passkeys where user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::db::Database;
+use crate::domain::protocol_mapper::{MapperSource, ProtocolMapper};
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct CreateProtocolMapperParams {
+    pub realm_uuid: Uuid,
+    pub name: String,
+    pub client_uuid: Option<Uuid>,
+    pub scope: Option<String>,
+    pub source: MapperSource,
+    pub claim_name: String,
+    pub id_token: bool,
+    pub access_token: bool,
+    pub userinfo: bool,
+}
+
+#[async_trait]
+pub trait ProtocolMapperRepository: Interface {
+    async fn create(&self, params: CreateProtocolMapperParams) -> Result<ProtocolMapper, String>;
+    async fn list_for_client(&self, client_uuid: Uuid) -> Result<Vec<ProtocolMapper>, String>;
+    async fn list_for_scope(&self, realm_uuid: Uuid, scope: &str) -> Result<Vec<ProtocolMapper>, String>;
+    /// The mappers of the client and of the given scopes, scope mappers first.
+    async fn find_applicable(&self, realm_uuid: Uuid, client_uuid: Uuid, scopes: &[String]) -> Result<Vec<ProtocolMapper>, String>;
+    /// Deletes the mapper. Returns false if it did not exist.
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = ProtocolMapperRepository)]
+pub struct PostgresProtocolMapperRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl ProtocolMapperRepository for PostgresProtocolMapperRepository {
+    async fn create(&self, params: CreateProtocolMapperParams) -> Result<ProtocolMapper, String> {
+        let (mapper_type, config) = params.source.to_parts();
+
+        let result = sqlx::query!(
+            r#"
+            insert into protocol_mappers (name, client_id, scope, mapper_type, config, claim_name,
+                                          id_token, access_token, userinfo, realm_id)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            returning *
+            "#,
+            params.name,
+            params.client_uuid,
+            params.scope,
+            mapper_type,
+            config,
+            params.claim_name,
+            params.id_token,
+            params.access_token,
+            params.userinfo,
+            params.realm_uuid,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(ProtocolMapper {
+            uuid: result.id,
+            name: result.name,
+            client_uuid: result.client_id,
+            scope: result.scope,
+            source: MapperSource::from_parts(&result.mapper_type, result.config)?,
+            claim_name: result.claim_name,
+            id_token: result.id_token,
+            access_token: result.access_token,
+            userinfo: result.userinfo,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn list_for_client(&self, client_uuid: Uuid) -> Result<Vec<ProtocolMapper>, String> {
+        let results = sqlx::query!(
+            r#"
+            select * from protocol_mappers where client_id = $1 order by created_at
+            "#,
+            client_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        results
+            .into_iter()
+            .map(|result| {
+                Ok(ProtocolMapper {
+                    uuid: result.id,
+                    name: result.name,
+                    client_uuid: result.client_id,
+                    scope: result.scope,
+                    source: MapperSource::from_parts(&result.mapper_type, result.config)?,
+                    claim_name: result.claim_name,
+                    id_token: result.id_token,
+                    access_token: result.access_token,
+                    userinfo: result.userinfo,
+                    created_at: result.created_at,
+                    updated_at: result.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn list_for_scope(&self, realm_uuid: Uuid, scope: &str) -> Result<Vec<ProtocolMapper>, String> {
+        let results = sqlx::query!(
+            r#"
+            select * from protocol_mappers where scope = $1 and realm_id = $2 order by created_at
+            "#,
+            scope,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        results
+            .into_iter()
+            .map(|result| {
+                Ok(ProtocolMapper {
+                    uuid: result.id,
+                    name: result.name,
+                    client_uuid: result.client_id,
+                    scope: result.scope,
+                    source: MapperSource::from_parts(&result.mapper_type, result.config)?,
+                    claim_name: result.claim_name,
+                    id_token: result.id_token,
+                    access_token: result.access_token,
+                    userinfo: result.userinfo,
+                    created_at: result.created_at,
+                    updated_at: result.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn find_applicable(&self, realm_uuid: Uuid, client_uuid: Uuid, scopes: &[String]) -> Result<Vec<ProtocolMapper>, String> {
+        // Client mappers come last so they win over scope mappers for the same claim
+        let results = sqlx::query!(
+            r#"
+            select * from protocol_mappers
+            where client_id = $1 or (scope = any($2) and realm_id = $3)
+            order by client_id nulls first, created_at
+            "#,
+            client_uuid,
+            scopes,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        results
+            .into_iter()
+            .map(|result| {
+                Ok(ProtocolMapper {
+                    uuid: result.id,
+                    name: result.name,
+                    client_uuid: result.client_id,
+                    scope: result.scope,
+                    source: MapperSource::from_parts(&result.mapper_type, result.config)?,
+                    claim_name: result.claim_name,
+                    id_token: result.id_token,
+                    access_token: result.access_token,
+                    userinfo: result.userinfo,
+                    created_at: result.created_at,
+                    updated_at: result.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from protocol_mappers where id = $1 and realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::db::Database;
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Keeps track of the objects managed by declarative provisioning.
+#[async_trait]
+pub trait ProvisioningRepository: Interface {
+    /// The names of the realm's provisioned objects of a kind ("client", "role", "group" or "user"), ordered by name.
+    async fn list(&self, realm_uuid: Uuid, kind: &str) -> Result<Vec<String>, String>;
+    /// Marks the object as provisioned. Recording it again has no effect.
+    async fn record(&self, realm_uuid: Uuid, kind: &str, name: &str) -> Result<(), String>;
+    async fn forget(&self, realm_uuid: Uuid, kind: &str, name: &str) -> Result<(), String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = ProvisioningRepository)]
+pub struct PostgresProvisioningRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl ProvisioningRepository for PostgresProvisioningRepository {
+    async fn list(&self, realm_uuid: Uuid, kind: &str) -> Result<Vec<String>, String> {
+        let results = sqlx::query!(
+            r#"
+            select name from provisioned_objects where kind = $1 and realm_id = $2 order by name
+            "#,
+            kind,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results.into_iter().map(|result| result.name).collect())
+    }
+
+    async fn record(&self, realm_uuid: Uuid, kind: &str, name: &str) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            insert into provisioned_objects (kind, name, realm_id)
+            values ($1, $2, $3)
+            on conflict do nothing
+            "#,
+            kind,
+            name,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn forget(&self, realm_uuid: Uuid, kind: &str, name: &str) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            delete from provisioned_objects where kind = $1 and name = $2 and realm_id = $3
+            "#,
+            kind,
+            name,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+}
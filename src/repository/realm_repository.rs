@@ -0,0 +1,191 @@
+use crate::db::Database;
+use crate::domain::realm::Realm;
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct CreateRealmParams {
+    pub name: String,
+    pub external_url: Option<String>,
+    pub host: Option<String>,
+}
+
+/// Realm changes. Unlike the name, the external URL and the host can be cleared.
+pub struct UpdateRealmParams {
+    pub external_url: Option<String>,
+    pub host: Option<String>,
+}
+
+#[async_trait]
+pub trait RealmRepository: Interface {
+    async fn create(&self, params: CreateRealmParams) -> Result<Realm, String>;
+    async fn find_by_id(&self, id: Uuid) -> Option<Realm>;
+    async fn find_by_name(&self, name: &str) -> Option<Realm>;
+    async fn find_by_host(&self, host: &str) -> Option<Realm>;
+    /// All realms, ordered by name.
+    async fn list(&self) -> Result<Vec<Realm>, String>;
+    async fn update(&self, id: Uuid, params: UpdateRealmParams) -> Result<Realm, String>;
+    /// Deletes the realm and everything in it. Returns false if it did not exist.
+    async fn delete(&self, id: Uuid) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = RealmRepository)]
+pub struct PostgresRealmRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl RealmRepository for PostgresRealmRepository {
+    async fn create(&self, params: CreateRealmParams) -> Result<Realm, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into realms (name, external_url, host)
+            values ($1, $2, $3)
+            returning *
+            "#,
+            params.name,
+            params.external_url,
+            params.host,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(Realm {
+            uuid: result.id,
+            name: result.name,
+            external_url: result.external_url,
+            host: result.host,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Option<Realm> {
+        let result = sqlx::query!(
+            r#"
+            select * from realms where id = $1
+            "#,
+            id,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Realm {
+            uuid: result.id,
+            name: result.name,
+            external_url: result.external_url,
+            host: result.host,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn find_by_name(&self, name: &str) -> Option<Realm> {
+        let result = sqlx::query!(
+            r#"
+            select * from realms where name = $1
+            "#,
+            name,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Realm {
+            uuid: result.id,
+            name: result.name,
+            external_url: result.external_url,
+            host: result.host,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn find_by_host(&self, host: &str) -> Option<Realm> {
+        let result = sqlx::query!(
+            r#"
+            select * from realms where lower(host) = lower($1)
+            "#,
+            host,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Realm {
+            uuid: result.id,
+            name: result.name,
+            external_url: result.external_url,
+            host: result.host,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn list(&self) -> Result<Vec<Realm>, String> {
+        let results = sqlx::query!(
+            r#"
+            select * from realms order by name
+            "#,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Realm {
+                uuid: result.id,
+                name: result.name,
+                external_url: result.external_url,
+                host: result.host,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn update(&self, id: Uuid, params: UpdateRealmParams) -> Result<Realm, String> {
+        let result = sqlx::query!(
+            r#"
+            update realms set external_url = $2, host = $3
+            where id = $1
+            returning *
+            "#,
+            id,
+            params.external_url,
+            params.host,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(Realm {
+            uuid: result.id,
+            name: result.name,
+            external_url: result.external_url,
+            host: result.host,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from realms where id = $1
+            "#,
+            id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
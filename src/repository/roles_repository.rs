@@ -1,19 +1,67 @@
 use crate::db::Database;
-use crate::domain::role::{Role, UserRole};
+use crate::domain::client::ClientId;
+use crate::domain::role::Role;
+use crate::domain::user::User;
 use async_trait::async_trait;
 use shaku::{Component, Interface};
 use std::sync::Arc;
 use uuid::Uuid;
 
 pub struct CreateRoleParams {
+    pub realm_uuid: Uuid,
+    /// The owning client for client roles, None for realm roles
+    pub client_uuid: Option<Uuid>,
     pub name: String,
     pub description: Option<String>,
 }
 
+/// Role changes. Unset fields are left as they are.
+pub struct UpdateRoleParams {
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
 #[async_trait]
 pub trait RoleRepository: Interface {
     async fn create(&self, role: CreateRoleParams) -> Result<Role, String>;
-    async fn find_by_id(&self, id: Uuid) -> Option<Role>;
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<Role>;
+    /// The realm role, or the role of the client, with the name.
+    async fn find_by_name(&self, realm_uuid: Uuid, client_uuid: Option<Uuid>, name: &str) -> Option<Role>;
+    /// The realm roles, or the roles of the client, ordered by name.
+    async fn list(&self, realm_uuid: Uuid, client_uuid: Option<Uuid>) -> Result<Vec<Role>, String>;
+    async fn update(&self, realm_uuid: Uuid, id: Uuid, params: UpdateRoleParams) -> Result<Role, String>;
+    /// Assigns the role to the user. Assigning a role the user already has has no effect.
+    async fn assign(&self, user_id: Uuid, role_id: Uuid) -> Result<(), String>;
+    /// Returns false if the role was not assigned to the user.
+    async fn unassign(&self, user_id: Uuid, role_id: Uuid) -> Result<bool, String>;
+    /// The users the role is assigned to directly, ordered by username.
+    async fn list_members(&self, role_id: Uuid) -> Result<Vec<User>, String>;
+    /// The roles assigned to the user, realm and client roles alike, ordered by name.
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Role>, String>;
+    /// The roles mapped to the group itself, ordered by name.
+    async fn find_by_group(&self, group_id: Uuid) -> Result<Vec<Role>, String>;
+    /// The roles the user has directly, through their groups and their ancestors, or through
+    /// composite roles, ordered by name.
+    async fn find_effective_by_user(&self, user_id: Uuid) -> Result<Vec<Role>, String>;
+    /// The role itself and the roles it contains through composite roles, ordered by name.
+    async fn find_effective_by_role(&self, role_id: Uuid) -> Result<Vec<Role>, String>;
+    /// The roles members of the group get: those of the group and its ancestors, and the roles
+    /// they contain through composite roles, ordered by name.
+    async fn find_effective_by_group(&self, group_id: Uuid) -> Result<Vec<Role>, String>;
+    /// Whether the user has the role, directly, through groups or through composite roles.
+    async fn user_has_role(&self, user_id: Uuid, role_id: Uuid) -> Result<bool, String>;
+    /// Whether any enabled, not deleted user has the role, directly, through groups or through
+    /// composite roles.
+    async fn has_active_members(&self, role_id: Uuid) -> Result<bool, String>;
+    /// The direct child roles of a composite role, ordered by name.
+    async fn list_composites(&self, role_id: Uuid) -> Result<Vec<Role>, String>;
+    /// Makes the child role part of the composite role. Returns false, leaving the roles
+    /// unchanged, if the child role already contains the composite role.
+    async fn add_composite(&self, role_id: Uuid, child_role_id: Uuid) -> Result<bool, String>;
+    /// Returns false if the child role was not part of the composite role.
+    async fn remove_composite(&self, role_id: Uuid, child_role_id: Uuid) -> Result<bool, String>;
+    /// Deletes the role. Returns false if it did not exist.
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String>;
 }
 
 #[derive(Component)]
@@ -23,23 +71,26 @@ pub struct PostgresRoleRepository {
     pool: Arc<dyn Database>,
 }
 
-impl PostgresRoleRepository {
-    fn new(pool: Arc<dyn Database>) -> Self {
-        Self { pool }
-    }
-}
-
 #[async_trait]
 impl RoleRepository for PostgresRoleRepository {
     async fn create(&self, params: CreateRoleParams) -> Result<Role, String> {
         let result = sqlx::query!(
             r#"
-            insert into roles (name, description)
-            values ($1, $2)
-            returning id as "uuid", name, description, created_at, updated_at
+            with created as (
+                insert into roles (client_id, name, description, realm_id)
+                values ($1, $2, $3, $4)
+                returning *
+            )
+            select created.id as "uuid!", created.client_id as "client_uuid", clients.client_id as "client_id?",
+                   created.name as "name!", created.description, created.created_at as "created_at!",
+                   created.updated_at as "updated_at!"
+            from created
+            left join clients on clients.id = created.client_id
             "#,
+            params.client_uuid,
             params.name.clone(),
             params.description,
+            params.realm_uuid,
         )
         .fetch_one(self.pool.get_pool())
         .await
@@ -47,6 +98,35 @@ impl RoleRepository for PostgresRoleRepository {
 
         Ok(Role {
             uuid: result.uuid,
+            client_uuid: result.client_uuid,
+            client_id: result.client_id.map(ClientId),
+            name: result.name,
+            description: result.description,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<Role> {
+        let result = sqlx::query!(
+            r#"
+            select roles.id as "uuid", roles.client_id as "client_uuid", clients.client_id as "client_id?",
+                   roles.name, roles.description, roles.created_at, roles.updated_at
+            from roles
+            left join clients on clients.id = roles.client_id
+            where roles.id = $1 and roles.realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(Role {
+            uuid: result.uuid,
+            client_uuid: result.client_uuid,
+            client_id: result.client_id.map(ClientId),
             name: result.name,
             description: result.description,
             created_at: result.created_at,
@@ -54,14 +134,18 @@ impl RoleRepository for PostgresRoleRepository {
         })
     }
 
-    async fn find_by_id(&self, id: Uuid) -> Option<Role> {
+    async fn find_by_name(&self, realm_uuid: Uuid, client_uuid: Option<Uuid>, name: &str) -> Option<Role> {
         let result = sqlx::query!(
             r#"
-            select id as "uuid", name, description, created_at, updated_at
+            select roles.id as "uuid", roles.client_id as "client_uuid", clients.client_id as "client_id?",
+                   roles.name, roles.description, roles.created_at, roles.updated_at
             from roles
-            where id = $1
+            left join clients on clients.id = roles.client_id
+            where roles.realm_id = $3 and roles.client_id is not distinct from $1 and roles.name = $2
             "#,
-            id.clone()
+            client_uuid,
+            name,
+            realm_uuid,
         )
         .fetch_optional(self.pool.get_pool())
         .await
@@ -69,10 +153,497 @@ impl RoleRepository for PostgresRoleRepository {
 
         Some(Role {
             uuid: result.uuid,
+            client_uuid: result.client_uuid,
+            client_id: result.client_id.map(ClientId),
             name: result.name,
             description: result.description,
             created_at: result.created_at,
             updated_at: result.updated_at,
         })
     }
-}
\ No newline at end of file
+
+    async fn update(&self, realm_uuid: Uuid, id: Uuid, params: UpdateRoleParams) -> Result<Role, String> {
+        let result = sqlx::query!(
+            r#"
+            with updated as (
+                update roles
+                set name = coalesce($2, name), description = coalesce($3, description)
+                where id = $1 and realm_id = $4
+                returning *
+            )
+            select updated.id as "uuid!", updated.client_id as "client_uuid", clients.client_id as "client_id?",
+                   updated.name as "name!", updated.description, updated.created_at as "created_at!",
+                   updated.updated_at as "updated_at!"
+            from updated
+            left join clients on clients.id = updated.client_id
+            "#,
+            id,
+            params.name,
+            params.description,
+            realm_uuid,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(Role {
+            uuid: result.uuid,
+            client_uuid: result.client_uuid,
+            client_id: result.client_id.map(ClientId),
+            name: result.name,
+            description: result.description,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn assign(&self, user_id: Uuid, role_id: Uuid) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            insert into user_roles (user_id, role_id)
+            values ($1, $2)
+            on conflict do nothing
+            "#,
+            user_id,
+            role_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn unassign(&self, user_id: Uuid, role_id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from user_roles where user_id = $1 and role_id = $2
+            "#,
+            user_id,
+            role_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_members(&self, role_id: Uuid) -> Result<Vec<User>, String> {
+        let results = sqlx::query!(
+            r#"
+            select users.*
+            from users
+            join user_roles on user_roles.user_id = users.id
+            where user_roles.role_id = $1 and users.deleted_at is null
+            order by users.username
+            "#,
+            role_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| User {
+                uuid: result.id,
+                username: result.username,
+                password_hash: result.password_hash,
+                email: result.email,
+                attributes: result.attributes,
+                enabled: result.enabled,
+                deleted_at: result.deleted_at,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn list(&self, realm_uuid: Uuid, client_uuid: Option<Uuid>) -> Result<Vec<Role>, String> {
+        let results = sqlx::query!(
+            r#"
+            select roles.id as "uuid", roles.client_id as "client_uuid", clients.client_id as "client_id?",
+                   roles.name, roles.description, roles.created_at, roles.updated_at
+            from roles
+            left join clients on clients.id = roles.client_id
+            where roles.realm_id = $2 and roles.client_id is not distinct from $1
+            order by roles.name
+            "#,
+            client_uuid,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Role {
+                uuid: result.uuid,
+                client_uuid: result.client_uuid,
+                client_id: result.client_id.map(ClientId),
+                name: result.name,
+                description: result.description,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Role>, String> {
+        let results = sqlx::query!(
+            r#"
+            select roles.id as "uuid", roles.client_id as "client_uuid", clients.client_id as "client_id?",
+                   roles.name, roles.description, roles.created_at, roles.updated_at
+            from roles
+            join user_roles on user_roles.role_id = roles.id
+            left join clients on clients.id = roles.client_id
+            where user_roles.user_id = $1
+            order by roles.name
+            "#,
+            user_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Role {
+                uuid: result.uuid,
+                client_uuid: result.client_uuid,
+                client_id: result.client_id.map(ClientId),
+                name: result.name,
+                description: result.description,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn find_by_group(&self, group_id: Uuid) -> Result<Vec<Role>, String> {
+        let results = sqlx::query!(
+            r#"
+            select roles.id as "uuid", roles.client_id as "client_uuid", clients.client_id as "client_id?",
+                   roles.name, roles.description, roles.created_at, roles.updated_at
+            from roles
+            join group_roles on group_roles.role_id = roles.id
+            left join clients on clients.id = roles.client_id
+            where group_roles.group_id = $1
+            order by roles.name
+            "#,
+            group_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Role {
+                uuid: result.uuid,
+                client_uuid: result.client_uuid,
+                client_id: result.client_id.map(ClientId),
+                name: result.name,
+                description: result.description,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn find_effective_by_user(&self, user_id: Uuid) -> Result<Vec<Role>, String> {
+        let results = sqlx::query!(
+            r#"
+            with recursive lineage(id) as (
+                select group_id from user_groups where user_id = $1
+                union
+                select groups.parent_id
+                from groups
+                join lineage on lineage.id = groups.id
+                where groups.parent_id is not null
+            ),
+            effective(id) as (
+                select role_id from user_roles where user_id = $1
+                union
+                select group_roles.role_id from group_roles join lineage on lineage.id = group_roles.group_id
+                union
+                select role_composites.child_role_id
+                from role_composites
+                join effective on effective.id = role_composites.role_id
+            )
+            select roles.id as "uuid", roles.client_id as "client_uuid", clients.client_id as "client_id?",
+                   roles.name, roles.description, roles.created_at, roles.updated_at
+            from roles
+            join effective on effective.id = roles.id
+            left join clients on clients.id = roles.client_id
+            order by roles.name
+            "#,
+            user_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Role {
+                uuid: result.uuid,
+                client_uuid: result.client_uuid,
+                client_id: result.client_id.map(ClientId),
+                name: result.name,
+                description: result.description,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn find_effective_by_role(&self, role_id: Uuid) -> Result<Vec<Role>, String> {
+        let results = sqlx::query!(
+            r#"
+            with recursive effective(id) as (
+                select $1::uuid
+                union
+                select role_composites.child_role_id
+                from role_composites
+                join effective on effective.id = role_composites.role_id
+            )
+            select roles.id as "uuid", roles.client_id as "client_uuid", clients.client_id as "client_id?",
+                   roles.name, roles.description, roles.created_at, roles.updated_at
+            from roles
+            join effective on effective.id = roles.id
+            left join clients on clients.id = roles.client_id
+            order by roles.name
+            "#,
+            role_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Role {
+                uuid: result.uuid,
+                client_uuid: result.client_uuid,
+                client_id: result.client_id.map(ClientId),
+                name: result.name,
+                description: result.description,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn find_effective_by_group(&self, group_id: Uuid) -> Result<Vec<Role>, String> {
+        let results = sqlx::query!(
+            r#"
+            with recursive lineage(id) as (
+                select $1::uuid
+                union
+                select groups.parent_id
+                from groups
+                join lineage on lineage.id = groups.id
+                where groups.parent_id is not null
+            ),
+            effective(id) as (
+                select group_roles.role_id from group_roles join lineage on lineage.id = group_roles.group_id
+                union
+                select role_composites.child_role_id
+                from role_composites
+                join effective on effective.id = role_composites.role_id
+            )
+            select roles.id as "uuid", roles.client_id as "client_uuid", clients.client_id as "client_id?",
+                   roles.name, roles.description, roles.created_at, roles.updated_at
+            from roles
+            join effective on effective.id = roles.id
+            left join clients on clients.id = roles.client_id
+            order by roles.name
+            "#,
+            group_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Role {
+                uuid: result.uuid,
+                client_uuid: result.client_uuid,
+                client_id: result.client_id.map(ClientId),
+                name: result.name,
+                description: result.description,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn user_has_role(&self, user_id: Uuid, role_id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            with recursive lineage(id) as (
+                select group_id from user_groups where user_id = $1
+                union
+                select groups.parent_id
+                from groups
+                join lineage on lineage.id = groups.id
+                where groups.parent_id is not null
+            ),
+            effective(id) as (
+                select role_id from user_roles where user_id = $1
+                union
+                select group_roles.role_id from group_roles join lineage on lineage.id = group_roles.group_id
+                union
+                select role_composites.child_role_id
+                from role_composites
+                join effective on effective.id = role_composites.role_id
+            )
+            select exists(select 1 from effective where id = $2) as "has_role!"
+            "#,
+            user_id,
+            role_id,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.has_role)
+    }
+
+    async fn has_active_members(&self, role_id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            with recursive granting(id) as (
+                select $1::uuid
+                union
+                select role_composites.role_id
+                from role_composites
+                join granting on granting.id = role_composites.child_role_id
+            ),
+            granting_groups(id) as (
+                select group_roles.group_id from group_roles join granting on granting.id = group_roles.role_id
+                union
+                select groups.id
+                from groups
+                join granting_groups on granting_groups.id = groups.parent_id
+            )
+            select exists(
+                select 1
+                from users
+                where users.enabled and users.deleted_at is null
+                  and (exists(select 1 from user_roles
+                              where user_roles.user_id = users.id
+                                and user_roles.role_id in (select id from granting))
+                       or exists(select 1 from user_groups
+                                 where user_groups.user_id = users.id
+                                   and user_groups.group_id in (select id from granting_groups)))
+            ) as "has_members!"
+            "#,
+            role_id,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.has_members)
+    }
+
+    async fn list_composites(&self, role_id: Uuid) -> Result<Vec<Role>, String> {
+        let results = sqlx::query!(
+            r#"
+            select roles.id as "uuid", roles.client_id as "client_uuid", clients.client_id as "client_id?",
+                   roles.name, roles.description, roles.created_at, roles.updated_at
+            from roles
+            join role_composites on role_composites.child_role_id = roles.id
+            left join clients on clients.id = roles.client_id
+            where role_composites.role_id = $1
+            order by roles.name
+            "#,
+            role_id,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| Role {
+                uuid: result.uuid,
+                client_uuid: result.client_uuid,
+                client_id: result.client_id.map(ClientId),
+                name: result.name,
+                description: result.description,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+    async fn add_composite(&self, role_id: Uuid, child_role_id: Uuid) -> Result<bool, String> {
+        let mut tx = self.pool.get_pool().begin().await.map_err(|e| e.to_string())?;
+
+        // Serializes changes to the hierarchy, so concurrent additions cannot form a cycle together
+        sqlx::query!("lock table role_composites in share row exclusive mode")
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let result = sqlx::query!(
+            r#"
+            with recursive descendants(id) as (
+                select $2::uuid
+                union
+                select role_composites.child_role_id
+                from role_composites
+                join descendants on descendants.id = role_composites.role_id
+            )
+            insert into role_composites (role_id, child_role_id)
+            select $1, $2
+            where not exists (select 1 from descendants where id = $1)
+            returning role_id
+            "#,
+            role_id,
+            child_role_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        Ok(result.is_some())
+    }
+
+    async fn remove_composite(&self, role_id: Uuid, child_role_id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from role_composites where role_id = $1 and child_role_id = $2
+            "#,
+            role_id,
+            child_role_id,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from roles where id = $1 and realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use tokio::sync::RwLock;
+
+/// The one-time token for creating the first administrator. It only lives as long as the
+/// process; a new one is issued on startup while there is no administrator.
+#[async_trait]
+pub trait SetupTokenRepository: Interface {
+    /// Replaces the current setup token.
+    async fn store(&self, token_hash: Vec<u8>);
+    /// Uses up the setup token. Returns false if it does not match or was used already.
+    async fn consume(&self, token_hash: &[u8]) -> bool;
+}
+
+#[derive(Component)]
+#[shaku(interface = SetupTokenRepository)]
+pub struct InMemorySetupTokenRepository {
+    #[shaku(default)]
+    token_hash: Arc<RwLock<Option<Vec<u8>>>>,
+}
+
+#[async_trait]
+impl SetupTokenRepository for InMemorySetupTokenRepository {
+    async fn store(&self, token_hash: Vec<u8>) {
+        *self.token_hash.write().await = Some(token_hash);
+    }
+
+    async fn consume(&self, token_hash: &[u8]) -> bool {
+        let mut current = self.token_hash.write().await;
+        if current.as_deref() != Some(token_hash) {
+            return false;
+        }
+
+        *current = None;
+        true
+    }
+}
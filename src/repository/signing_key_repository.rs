@@ -0,0 +1,177 @@
+use crate::db::Database;
+use crate::domain::signing_key::SigningKey;
+use async_trait::async_trait;
+use shaku::{Component, Interface};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct CreateSigningKeyParams {
+    pub realm_uuid: Uuid,
+    pub kid: String,
+    pub algorithm: String,
+    pub private_key: String,
+    pub public_key: String,
+}
+
+#[async_trait]
+pub trait SigningKeyRepository: Interface {
+    async fn create(&self, params: CreateSigningKeyParams) -> Result<SigningKey, String>;
+    async fn find_by_kid(&self, realm_uuid: Uuid, kid: &str) -> Option<SigningKey>;
+    /// Returns the most recently created active key for the algorithm.
+    async fn find_active(&self, realm_uuid: Uuid, algorithm: &str) -> Option<SigningKey>;
+    /// Returns every stored key, active or not, for publishing in the JWKS.
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<SigningKey>, String>;
+    /// Stops signing with the other keys of the algorithm. They are still published for verification.
+    async fn deactivate_others(&self, realm_uuid: Uuid, algorithm: &str, kid: &str) -> Result<(), String>;
+    /// Marks the key as active or inactive. Returns false if the key does not exist.
+    async fn set_active(&self, realm_uuid: Uuid, kid: &str, active: bool) -> Result<bool, String>;
+}
+
+#[derive(Component)]
+#[shaku(interface = SigningKeyRepository)]
+pub struct PostgresSigningKeyRepository {
+    #[shaku(inject)]
+    pool: Arc<dyn Database>,
+}
+
+#[async_trait]
+impl SigningKeyRepository for PostgresSigningKeyRepository {
+    async fn create(&self, params: CreateSigningKeyParams) -> Result<SigningKey, String> {
+        let result = sqlx::query!(
+            r#"
+            insert into signing_keys (kid, algorithm, private_key, public_key, realm_id)
+            values ($1, $2, $3, $4, $5)
+            returning *
+            "#,
+            params.kid,
+            params.algorithm,
+            params.private_key,
+            params.public_key,
+            params.realm_uuid,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(SigningKey {
+            uuid: result.id,
+            kid: result.kid,
+            algorithm: result.algorithm,
+            private_key: result.private_key,
+            public_key: result.public_key,
+            active: result.active,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn find_by_kid(&self, realm_uuid: Uuid, kid: &str) -> Option<SigningKey> {
+        let result = sqlx::query!(
+            r#"
+            select * from signing_keys where kid = $1 and realm_id = $2
+            "#,
+            kid,
+            realm_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(SigningKey {
+            uuid: result.id,
+            kid: result.kid,
+            algorithm: result.algorithm,
+            private_key: result.private_key,
+            public_key: result.public_key,
+            active: result.active,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn find_active(&self, realm_uuid: Uuid, algorithm: &str) -> Option<SigningKey> {
+        let result = sqlx::query!(
+            r#"
+            select * from signing_keys
+            where algorithm = $1 and realm_id = $2 and active
+            order by created_at desc
+            limit 1
+            "#,
+            algorithm,
+            realm_uuid,
+        )
+        .fetch_optional(self.pool.get_pool())
+        .await
+        .ok()??;
+
+        Some(SigningKey {
+            uuid: result.id,
+            kid: result.kid,
+            algorithm: result.algorithm,
+            private_key: result.private_key,
+            public_key: result.public_key,
+            active: result.active,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn list(&self, realm_uuid: Uuid) -> Result<Vec<SigningKey>, String> {
+        let results = sqlx::query!(
+            r#"
+            select * from signing_keys where realm_id = $1 order by created_at
+            "#,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| SigningKey {
+                uuid: result.id,
+                kid: result.kid,
+                algorithm: result.algorithm,
+                private_key: result.private_key,
+                public_key: result.public_key,
+                active: result.active,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect())
+    }
+
+    async fn deactivate_others(&self, realm_uuid: Uuid, algorithm: &str, kid: &str) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            update signing_keys set active = false
+            where algorithm = $1 and kid <> $2 and realm_id = $3 and active
+            "#,
+            algorithm,
+            kid,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn set_active(&self, realm_uuid: Uuid, kid: &str, active: bool) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update signing_keys set active = $2 where kid = $1 and realm_id = $3
+            "#,
+            kid,
+            active,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
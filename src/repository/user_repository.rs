@@ -3,18 +3,66 @@ use std::sync::Arc;
 use crate::db::Database;
 use crate::domain::user::User;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use shaku::{Component, Interface};
+use uuid::Uuid;
 
 pub struct CreateUserParams {
+    pub realm_uuid: Uuid,
     pub username: String,
     pub password_hash: String,
     pub email: String,
+    pub attributes: serde_json::Value,
+}
+
+/// Filters for listing users. Username and email match case-insensitively on substrings.
+#[derive(Debug, Default)]
+pub struct ListUsersParams {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub enabled: Option<bool>,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+/// A page of users, with the number of users matching the filters across all pages.
+pub struct UserPage {
+    pub users: Vec<User>,
+    pub total: i64,
+}
+
+/// Profile changes. Unset fields are left as they are.
+pub struct UpdateUserParams {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub attributes: Option<serde_json::Value>,
 }
 
 #[async_trait]
 pub trait UserRepository: Interface {
     async fn create(&self, params: CreateUserParams) -> Result<User, String>;
-    async fn find_by_username_or_email(&self, username_or_email: &str) -> Option<User>;
+    async fn find_by_username_or_email(&self, realm_uuid: Uuid, username_or_email: &str) -> Option<User>;
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<User>;
+    /// Users matching the filters, oldest first. Soft-deleted users are left out.
+    async fn list(&self, realm_uuid: Uuid, params: ListUsersParams) -> Result<UserPage, String>;
+    async fn update(&self, realm_uuid: Uuid, id: Uuid, params: UpdateUserParams) -> Result<User, String>;
+    /// Returns false if the user does not exist.
+    async fn set_password_hash(&self, realm_uuid: Uuid, id: Uuid, password_hash: &str) -> Result<bool, String>;
+    /// Returns false if the user does not exist.
+    async fn set_enabled(&self, realm_uuid: Uuid, id: Uuid, enabled: bool) -> Result<bool, String>;
+    /// Marks the user as deleted and disables them, keeping the record. Returns false if the
+    /// user does not exist.
+    async fn soft_delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String>;
+    /// Deletes the user and everything that belongs to them. Returns false if the user did not exist.
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String>;
+}
+
+/// Escapes the wildcards of a LIKE pattern and matches the text anywhere.
+fn contains_pattern(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
 }
 
 #[derive(Component)]
@@ -24,24 +72,20 @@ pub struct PostgresUserRepository {
     pool: Arc<dyn Database>,
 }
 
-impl PostgresUserRepository {
-    fn new(pool: Arc<dyn Database>) -> Self {
-        Self { pool }
-    }
-}
-
 #[async_trait]
 impl UserRepository for PostgresUserRepository {
     async fn create(&self, params: CreateUserParams) -> Result<User, String> {
         let result = sqlx::query!(
             r#"
-            insert into users(username, password_hash, email)
-            values($1, $2, lower($3))
+            insert into users(username, password_hash, email, attributes, realm_id)
+            values($1, $2, lower($3), $4, $5)
             returning *;
             "#,
             params.username,
             params.password_hash.clone(),
             params.email,
+            params.attributes,
+            params.realm_uuid,
         )
         .fetch_one(self.pool.get_pool())
         .await
@@ -52,17 +96,22 @@ impl UserRepository for PostgresUserRepository {
             username: result.username,
             password_hash: result.password_hash,
             email: result.email,
+            attributes: result.attributes,
+            enabled: result.enabled,
+            deleted_at: result.deleted_at,
             created_at: result.created_at,
             updated_at: result.updated_at,
         })
     }
 
-    async fn find_by_username_or_email(&self, username_or_email: &str) -> Option<User> {
+    async fn find_by_username_or_email(&self, realm_uuid: Uuid, username_or_email: &str) -> Option<User> {
         let result = sqlx::query!(
             r#"
-            select * from users where username = $1 or email = lower($1);
+            select * from users
+            where (username = $1 or email = lower($1)) and realm_id = $2 and deleted_at is null;
             "#,
-            username_or_email.clone(),
+            username_or_email,
+            realm_uuid,
         )
             .fetch_optional(self.pool.get_pool())
             .await
@@ -74,8 +123,192 @@ impl UserRepository for PostgresUserRepository {
             username: result.username,
             password_hash: result.password_hash,
             email: result.email,
+            attributes: result.attributes,
+            enabled: result.enabled,
+            deleted_at: result.deleted_at,
             created_at: result.created_at,
             updated_at: result.updated_at,
         })
     }
+
+    async fn find_by_id(&self, realm_uuid: Uuid, id: Uuid) -> Option<User> {
+        let result = sqlx::query!(
+            r#"
+            select * from users where id = $1 and realm_id = $2 and deleted_at is null;
+            "#,
+            id,
+            realm_uuid,
+        )
+            .fetch_optional(self.pool.get_pool())
+            .await
+            .ok()??;
+
+        Some(User{
+            uuid: result.id,
+            username: result.username,
+            password_hash: result.password_hash,
+            email: result.email,
+            attributes: result.attributes,
+            enabled: result.enabled,
+            deleted_at: result.deleted_at,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn list(&self, realm_uuid: Uuid, params: ListUsersParams) -> Result<UserPage, String> {
+        let results = sqlx::query!(
+            r#"
+            select *, count(*) over () as "total!"
+            from users
+            where deleted_at is null
+              and realm_id = $8
+              and ($1::text is null or username ilike $1)
+              and ($2::text is null or email ilike $2)
+              and ($3::timestamptz is null or created_at >= $3)
+              and ($4::timestamptz is null or created_at < $4)
+              and ($5::boolean is null or enabled = $5)
+            order by created_at, id
+            offset $6
+            limit $7
+            "#,
+            params.username.as_deref().map(contains_pattern),
+            params.email.as_deref().map(contains_pattern),
+            params.created_after,
+            params.created_before,
+            params.enabled,
+            params.offset,
+            params.limit,
+            realm_uuid,
+        )
+        .fetch_all(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let total = results.first().map(|result| result.total).unwrap_or(0);
+        let users = results
+            .into_iter()
+            .map(|result| User {
+                uuid: result.id,
+                username: result.username,
+                password_hash: result.password_hash,
+                email: result.email,
+                attributes: result.attributes,
+                enabled: result.enabled,
+                deleted_at: result.deleted_at,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+            })
+            .collect();
+
+        Ok(UserPage { users, total })
+    }
+
+    async fn update(&self, realm_uuid: Uuid, id: Uuid, params: UpdateUserParams) -> Result<User, String> {
+        let result = sqlx::query!(
+            r#"
+            update users
+            set username = coalesce($2, username),
+                email = coalesce(lower($3), email),
+                attributes = coalesce($4, attributes)
+            where id = $1 and realm_id = $5 and deleted_at is null
+            returning *
+            "#,
+            id,
+            params.username,
+            params.email,
+            params.attributes,
+            realm_uuid,
+        )
+        .fetch_one(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(User {
+            uuid: result.id,
+            username: result.username,
+            password_hash: result.password_hash,
+            email: result.email,
+            attributes: result.attributes,
+            enabled: result.enabled,
+            deleted_at: result.deleted_at,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn set_password_hash(&self, realm_uuid: Uuid, id: Uuid, password_hash: &str) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update users set password_hash = $2 where id = $1 and realm_id = $3 and deleted_at is null
+            "#,
+            id,
+            password_hash,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_enabled(&self, realm_uuid: Uuid, id: Uuid, enabled: bool) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update users set enabled = $2 where id = $1 and realm_id = $3 and deleted_at is null
+            "#,
+            id,
+            enabled,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn soft_delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            update users set enabled = false, deleted_at = now()
+            where id = $1 and realm_id = $2 and deleted_at is null
+            "#,
+            id,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, realm_uuid: Uuid, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query!(
+            r#"
+            delete from users where id = $1 and realm_id = $2
+            "#,
+            id,
+            realm_uuid,
+        )
+        .execute(self.pool.get_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_pattern() {
+        assert_eq!(contains_pattern("alice"), "%alice%");
+        assert_eq!(contains_pattern("50%_off"), "%50\\%\\_off%");
+        assert_eq!(contains_pattern("a\\b"), "%a\\\\b%");
+    }
 }
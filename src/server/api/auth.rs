@@ -0,0 +1,245 @@
+//! Authentication and authorization of the admin REST API.
+//! Requests must carry an active access token issued by Vaulton. Missing, expired or revoked
+//! tokens get a 401, tokens without the permission the route needs a 403.
+//!
+//! Only tokens issued for the admin API are accepted: tokens of the built-in admin client, and
+//! tokens of other clients with the admin API among their audience, requested as the
+//! "{issuer}/api" resource. Dynamically registered clients never get to the admin API, as
+//! anybody may be able to register one. Client tokens carry the permissions of their scopes,
+//! tokens issued to a user only those both their scopes and the user's realm roles grant.
+//!
+//! Tokens are checked in the realm the request addresses. Administrators of the default realm
+//! may administer every other realm as well.
+//!
+//! The self-service routes under "/api/me" act on the user a token was issued to, whatever the
+//! client, and need no permissions.
+
+use std::sync::Arc;
+use crate::domain::access_token::AccessToken;
+use crate::domain::realm::{Realm, DEFAULT_REALM};
+use crate::oidc::access_token;
+use crate::domain::client::Client;
+use crate::oidc::error::OAuthError;
+use crate::repository::client_repository::ClientRepository;
+use crate::repository::realm_repository::RealmRepository;
+use crate::repository::roles_repository::RoleRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::server::api::permissions::{self, Granted, ADMIN};
+use crate::server::bootstrap::ADMIN_CLIENT_ID;
+use crate::server::AppState;
+use axum::extract::{Extension, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use shaku::HasComponent;
+use uuid::Uuid;
+
+pub async fn authorize(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(token) = access_token::bearer_token(request.headers()) else {
+        return OAuthError::InvalidToken("Missing bearer token".to_string()).to_json_response();
+    };
+
+    let (token, token_realm) = match access_token::validate(&state, &realm, token).await {
+        Some(token) => (token, realm.clone()),
+        None => match default_realm_token(&state, &realm, token).await {
+            Some(found) => found,
+            None => {
+                return OAuthError::InvalidToken("The access token is invalid".to_string()).to_json_response()
+            }
+        },
+    };
+
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    let Some(client) = client_repository.find_by_id(token_realm.uuid, &token.client_id).await else {
+        return OAuthError::InvalidToken("The client of the access token no longer exists".to_string())
+            .to_json_response();
+    };
+
+    let admin_audience = permissions::admin_audience(&state.external_url(&token_realm));
+    if let Err(e) = check_audience(&token, &client, &admin_audience) {
+        return e.to_json_response();
+    }
+
+    let mut granted = token.scopes.clone();
+
+    // Users act with what both the consented scopes and their realm roles allow
+    if let Some(user_id) = token.user_id {
+        let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+        if !user_repository.find_by_id(token_realm.uuid, user_id).await.is_some_and(|user| user.is_active()) {
+            return OAuthError::InvalidToken("The user is disabled or no longer exists".to_string())
+                .to_json_response();
+        }
+
+        let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+        let roles = match role_repository.find_effective_by_user(user_id).await {
+            Ok(roles) => roles.into_iter().filter(|r| r.is_realm_role()).map(|r| r.name).collect::<Vec<_>>(),
+            Err(e) => return OAuthError::ServerError(e).to_json_response(),
+        };
+        granted = permissions::user_permissions(&token.scopes, &roles);
+    }
+
+    // Only administrators reach across realms
+    let required = permissions::required_permission(request.method(), request.uri().path());
+    let allowed = match token_realm.uuid == realm.uuid {
+        true => permissions::is_granted(required, &granted),
+        false => permissions::is_granted(None, &granted),
+    };
+    if !allowed {
+        let needed = required.map(|p| p.to_string()).unwrap_or_else(|| ADMIN.to_string());
+        return OAuthError::InsufficientScope(format!("The '{}' permission is required", needed))
+            .to_json_response();
+    }
+
+    request.extensions_mut().insert(Granted(granted));
+    next.run(request).await
+}
+
+/// The user a self-service request acts for.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthenticatedUser(pub Uuid);
+
+/// Lets requests through that carry an active access token issued to an active user of the realm.
+pub async fn authenticate_user(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(token) = access_token::bearer_token(request.headers()) else {
+        return OAuthError::InvalidToken("Missing bearer token".to_string()).to_json_response();
+    };
+
+    let Some(user_id) = access_token::validate(&state, &realm, token).await.and_then(|token| token.user_id) else {
+        return OAuthError::InvalidToken("The access token is invalid or not issued to a user".to_string())
+            .to_json_response();
+    };
+
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    if !user_repository.find_by_id(realm.uuid, user_id).await.is_some_and(|user| user.is_active()) {
+        return OAuthError::InvalidToken("The user is disabled or no longer exists".to_string()).to_json_response();
+    }
+
+    request.extensions_mut().insert(AuthenticatedUser(user_id));
+    next.run(request).await
+}
+
+fn is_admin_client(token: &AccessToken) -> bool {
+    token.client_id.0 == ADMIN_CLIENT_ID
+}
+
+/// Refuses tokens that were not issued for the admin API, like those of relying parties that
+/// got hold of an administrator's token, and tokens of dynamically registered clients.
+fn check_audience(token: &AccessToken, client: &Client, admin_audience: &str) -> Result<(), OAuthError> {
+    if is_admin_client(token) {
+        return Ok(());
+    }
+    if client.registration_token_hash.is_some() {
+        return Err(OAuthError::InsufficientScope(
+            "Dynamically registered clients cannot use the admin API".to_string(),
+        ));
+    }
+    if token.audience.iter().any(|aud| aud == admin_audience) {
+        return Ok(());
+    }
+
+    Err(OAuthError::InsufficientScope("The access token was not issued for the admin API".to_string()))
+}
+
+/// Looks the token up in the default realm when another realm is addressed.
+async fn default_realm_token(state: &AppState, realm: &Realm, token: &str) -> Option<(AccessToken, Realm)> {
+    if realm.is_default() {
+        return None;
+    }
+
+    let realm_repository: Arc<dyn RealmRepository> = state.module.resolve();
+    let default_realm = realm_repository.find_by_name(DEFAULT_REALM).await?;
+    let token = access_token::validate(state, &default_realm, token).await?;
+
+    Some((token, default_realm))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::access_token::AccessTokenFormat;
+    use crate::domain::client::ClientId;
+    use axum::http::StatusCode;
+    use chrono::{Duration, Utc};
+
+    const ADMIN_AUDIENCE: &str = "https://auth.example.com/api";
+
+    fn client(client_id: &str, registered: bool) -> Client {
+        Client {
+            uuid: Uuid::new_v4(),
+            id: ClientId(client_id.to_string()),
+            secret_hash: Some(vec![0; 32]),
+            redirect_uris: vec![],
+            allowed_scopes: vec![ADMIN.to_string()],
+            client_name: None,
+            logo_uri: None,
+            application_type: "web".to_string(),
+            subject_type: "public".to_string(),
+            sector_identifier_uri: None,
+            grant_types: vec!["client_credentials".to_string()],
+            token_endpoint_auth_method: None,
+            registration_token_hash: registered.then(|| vec![0; 32]),
+            first_party: false,
+            id_token_role_claims: false,
+            access_token_format: AccessTokenFormat::Jwt,
+            access_token_lifetime: None,
+            id_token_lifetime: None,
+            id_token_signed_response_alg: "RS256".to_string(),
+            jwks: None,
+            id_token_encrypted_response_alg: None,
+            id_token_encrypted_response_enc: None,
+            userinfo_encrypted_response_alg: None,
+            userinfo_encrypted_response_enc: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn token(client: &Client, audience: &[&str]) -> AccessToken {
+        AccessToken {
+            uuid: Uuid::new_v4(),
+            format: AccessTokenFormat::Jwt,
+            client_uuid: client.uuid,
+            client_id: client.id.clone(),
+            user_id: Some(Uuid::new_v4()),
+            scopes: vec!["openid".to_string(), ADMIN.to_string()],
+            audience: audience.iter().map(|aud| aud.to_string()).collect(),
+            claims: vec![],
+            organization_id: None,
+            expires_at: Utc::now() + Duration::hours(1),
+            revoked_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn check(client: &Client, audience: &[&str]) -> Result<(), OAuthError> {
+        check_audience(&token(client, audience), client, ADMIN_AUDIENCE)
+    }
+
+    #[test]
+    fn test_tokens_of_ordinary_clients_are_forbidden() {
+        let error = check(&client("app", false), &["app"]).unwrap_err();
+        assert_eq!(error.to_json_response().status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_tokens_for_the_admin_api() {
+        assert!(check(&client(ADMIN_CLIENT_ID, false), &[ADMIN_CLIENT_ID]).is_ok());
+        assert!(check(&client("ops-dashboard", false), &[ADMIN_AUDIENCE]).is_ok());
+        assert!(check(&client("app", false), &["https://other.example.com/api"]).is_err());
+    }
+
+    #[test]
+    fn test_registered_clients_are_forbidden() {
+        let error = check(&client("registered", true), &[ADMIN_AUDIENCE]).unwrap_err();
+        assert_eq!(error.to_json_response().status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -1,8 +1,14 @@
 use std::collections::HashSet;
+use std::sync::Arc;
+use crate::domain::client::ClientId;
+use crate::domain::realm::Realm;
+use crate::repository::client_repository::ClientRepository;
 use crate::server::AppState;
-use axum::extract::{Json, State};
+use axum::extract::{Extension, Json, Path, State};
+use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use serde::Deserialize;
+use shaku::HasComponent;
 
 #[derive(Debug, Deserialize)]
 pub struct CreateClientRequestDto {
@@ -16,3 +22,31 @@ pub async fn create_client(
 ) -> impl IntoResponse {
     unimplemented!()
 }
+
+/// The client settings administrators may change. Unset fields are left as they are.
+#[derive(Debug, Deserialize)]
+pub struct UpdateClientRequestDto {
+    pub id_token_role_claims: Option<bool>,
+}
+
+pub async fn update_client(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(client_id): Path<String>,
+    Json(dto): Json<UpdateClientRequestDto>,
+) -> impl IntoResponse {
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    let client_id = ClientId(client_id);
+
+    if client_repository.find_by_id(realm.uuid, &client_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    if let Some(enabled) = dto.id_token_role_claims {
+        if let Err(e) = client_repository.set_id_token_role_claims(realm.uuid, &client_id, enabled).await {
+            return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+        }
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use crate::domain::client::ClientId;
+use crate::domain::realm::Realm;
+use crate::repository::consent_repository::ConsentRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::server::api::auth::AuthenticatedUser;
+use crate::server::AppState;
+use axum::extract::{Extension, Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use shaku::HasComponent;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+struct ConsentResponseDto {
+    client_id: String,
+    scopes: Vec<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+pub async fn list_user_consents(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(user_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    list_consents(&state, user_id).await
+}
+
+pub async fn revoke_user_consent(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((user_id, client_id)): Path<(Uuid, String)>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    revoke_consent(&state, user_id, client_id).await
+}
+
+/// Lists the consents of the user the access token was issued to.
+pub async fn list_own_consents(
+    State(state): State<AppState>,
+    Extension(AuthenticatedUser(user_id)): Extension<AuthenticatedUser>,
+) -> impl IntoResponse {
+    list_consents(&state, user_id).await
+}
+
+/// Revokes a consent of the user the access token was issued to.
+pub async fn revoke_own_consent(
+    State(state): State<AppState>,
+    Extension(AuthenticatedUser(user_id)): Extension<AuthenticatedUser>,
+    Path(client_id): Path<String>,
+) -> impl IntoResponse {
+    revoke_consent(&state, user_id, client_id).await
+}
+
+async fn list_consents(state: &AppState, user_id: Uuid) -> Response {
+    let consent_repository: Arc<dyn ConsentRepository> = state.module.resolve();
+
+    match consent_repository.list_by_user(user_id).await {
+        Ok(consents) => {
+            let response = consents
+                .into_iter()
+                .map(|consent| ConsentResponseDto {
+                    client_id: consent.client_id.0,
+                    scopes: consent.scopes,
+                    created_at: consent.created_at,
+                    updated_at: consent.updated_at,
+                })
+                .collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn revoke_consent(state: &AppState, user_id: Uuid, client_id: String) -> Response {
+    let consent_repository: Arc<dyn ConsentRepository> = state.module.resolve();
+
+    match consent_repository.revoke(user_id, &ClientId(client_id)).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
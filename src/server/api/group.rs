@@ -0,0 +1,312 @@
+use std::sync::Arc;
+use crate::domain::group::{check_group_name, Group};
+use crate::domain::realm::Realm;
+use crate::repository::group_repository::{CreateGroupParams, GroupRepository};
+use crate::repository::roles_repository::RoleRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::server::api::permissions::Granted;
+use crate::server::api::role::{refuse_grant, RoleResponseDto};
+use crate::server::AppState;
+use axum::extract::{Extension, Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use shaku::HasComponent;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGroupRequestDto {
+    pub name: String,
+    /// Creates a subgroup of this group, a top-level group when unset
+    pub parent_id: Option<Uuid>,
+    /// Custom attributes the members inherit, e.g. {"cost_center": "4711"}
+    pub attributes: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddMemberRequestDto {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddGroupRoleRequestDto {
+    pub role_id: Uuid,
+}
+
+#[derive(Serialize)]
+struct GroupResponseDto {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_id: Option<String>,
+    name: String,
+    path: String,
+    attributes: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct MemberResponseDto {
+    id: String,
+    username: String,
+}
+
+impl From<Group> for GroupResponseDto {
+    fn from(group: Group) -> Self {
+        Self {
+            id: group.uuid.to_string(),
+            parent_id: group.parent_uuid.map(|id| id.to_string()),
+            name: group.name,
+            path: group.path,
+            attributes: group.attributes,
+        }
+    }
+}
+
+pub async fn create_group(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Json(dto): Json<CreateGroupRequestDto>,
+) -> impl IntoResponse {
+    if let Err(e) = check_group_name(&dto.name) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+    let attributes = dto.attributes.unwrap_or_else(|| serde_json::json!({}));
+    if !attributes.is_object() {
+        return (StatusCode::BAD_REQUEST, "Attributes must be a JSON object").into_response();
+    }
+
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+
+    if let Some(parent_id) = dto.parent_id {
+        if group_repository.find_by_id(realm.uuid, parent_id).await.is_none() {
+            return (StatusCode::BAD_REQUEST, "The parent group does not exist").into_response();
+        }
+    }
+
+    let create_params = CreateGroupParams {
+        realm_uuid: realm.uuid,
+        parent_uuid: dto.parent_id,
+        name: dto.name,
+        attributes,
+    };
+
+    match group_repository.create(create_params).await {
+        Ok(group) => (StatusCode::CREATED, Json(GroupResponseDto::from(group))).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+pub async fn list_groups(State(state): State<AppState>, Extension(realm): Extension<Realm>) -> impl IntoResponse {
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+
+    match group_repository.list(realm.uuid).await {
+        Ok(groups) => {
+            let response = groups.into_iter().map(GroupResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn get_group(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+
+    match group_repository.find_by_id(realm.uuid, id).await {
+        Some(group) => (StatusCode::OK, Json(GroupResponseDto::from(group))).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn update_group_attributes(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+    Json(attributes): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    if !attributes.is_object() {
+        return (StatusCode::BAD_REQUEST, "Attributes must be a JSON object").into_response();
+    }
+
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+
+    match group_repository.update_attributes(realm.uuid, id, attributes).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn delete_group(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+
+    match group_repository.delete(realm.uuid, id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn list_members(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+
+    if group_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match group_repository.list_members(id).await {
+        Ok(users) => {
+            let response = users
+                .into_iter()
+                .map(|user| MemberResponseDto {
+                    id: user.uuid.to_string(),
+                    username: user.username,
+                })
+                .collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn add_member(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Extension(granted): Extension<Granted>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<AddMemberRequestDto>,
+) -> impl IntoResponse {
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    if group_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if user_repository.find_by_id(realm.uuid, dto.user_id).await.is_none() {
+        return (StatusCode::BAD_REQUEST, "The user does not exist").into_response();
+    }
+    // Members get the roles of the group and its ancestors
+    if let Some(response) = refuse_grant(role_repository.find_effective_by_group(id).await, &granted) {
+        return response;
+    }
+
+    match group_repository.add_member(id, dto.user_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn remove_member(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+
+    if group_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match group_repository.remove_member(id, user_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn list_group_roles(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    if group_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match role_repository.find_by_group(id).await {
+        Ok(roles) => {
+            let response = roles.into_iter().map(RoleResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn add_group_role(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Extension(granted): Extension<Granted>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<AddGroupRoleRequestDto>,
+) -> impl IntoResponse {
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    if group_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if role_repository.find_by_id(realm.uuid, dto.role_id).await.is_none() {
+        return (StatusCode::BAD_REQUEST, "The role does not exist").into_response();
+    }
+    if let Some(response) = refuse_grant(role_repository.find_effective_by_role(dto.role_id).await, &granted) {
+        return response;
+    }
+
+    match group_repository.add_role(id, dto.role_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn remove_group_role(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((id, role_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+
+    if group_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match group_repository.remove_role(id, role_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn list_user_groups(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(user_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    let group_repository: Arc<dyn GroupRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match group_repository.find_by_user(user_id).await {
+        Ok(groups) => {
+            let response = groups.into_iter().map(GroupResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
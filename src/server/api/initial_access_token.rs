@@ -0,0 +1,105 @@
+use std::sync::Arc;
+use crate::domain::initial_access_token::InitialAccessToken;
+use crate::domain::realm::Realm;
+use crate::oidc::access_token::hash_token;
+use crate::oidc::registration::generate_token;
+use crate::repository::initial_access_token_repository::{
+    CreateInitialAccessTokenParams, InitialAccessTokenRepository,
+};
+use crate::server::AppState;
+use axum::extract::{Extension, Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use shaku::HasComponent;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInitialAccessTokenRequestDto {
+    /// Lifetime in seconds, the token does not expire when unset
+    pub expires_in: Option<i64>,
+    /// Number of clients the token may register, unlimited when unset
+    pub max_uses: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct InitialAccessTokenResponseDto {
+    id: String,
+    /// Only returned when the token is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    remaining_uses: Option<i32>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<InitialAccessToken> for InitialAccessTokenResponseDto {
+    fn from(token: InitialAccessToken) -> Self {
+        Self {
+            id: token.uuid.to_string(),
+            token: None,
+            expires_at: token.expires_at,
+            remaining_uses: token.remaining_uses,
+            created_at: token.created_at,
+        }
+    }
+}
+
+pub async fn create_initial_access_token(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Json(dto): Json<CreateInitialAccessTokenRequestDto>,
+) -> impl IntoResponse {
+    if dto.expires_in.is_some_and(|e| e <= 0) || dto.max_uses.is_some_and(|m| m <= 0) {
+        return (StatusCode::BAD_REQUEST, "expires_in and max_uses must be positive").into_response();
+    }
+
+    let token = generate_token();
+    let create_params = CreateInitialAccessTokenParams {
+        realm_uuid: realm.uuid,
+        token_hash: hash_token(&token),
+        expires_at: dto.expires_in.map(|e| Utc::now() + Duration::seconds(e)),
+        remaining_uses: dto.max_uses,
+    };
+
+    let initial_access_token_repository: Arc<dyn InitialAccessTokenRepository> = state.module.resolve();
+
+    match initial_access_token_repository.create(create_params).await {
+        Ok(created) => {
+            let mut response = InitialAccessTokenResponseDto::from(created);
+            response.token = Some(token);
+            (StatusCode::CREATED, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn list_initial_access_tokens(State(state): State<AppState>, Extension(realm): Extension<Realm>) -> impl IntoResponse {
+    let initial_access_token_repository: Arc<dyn InitialAccessTokenRepository> = state.module.resolve();
+
+    match initial_access_token_repository.list(realm.uuid).await {
+        Ok(tokens) => {
+            let response = tokens
+                .into_iter()
+                .map(InitialAccessTokenResponseDto::from)
+                .collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn delete_initial_access_token(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let initial_access_token_repository: Arc<dyn InitialAccessTokenRepository> = state.module.resolve();
+
+    match initial_access_token_repository.delete(realm.uuid, id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use crate::domain::realm::Realm;
+use crate::repository::mfa_repository::MfaRepository;
+use crate::repository::passkey_repository::PasskeyRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::server::AppState;
+use axum::extract::{Extension, Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use shaku::HasComponent;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+struct MfaStatusResponseDto {
+    /// Whether the user has a confirmed authenticator app
+    totp: bool,
+    totp_confirmed_at: Option<DateTime<Utc>>,
+    recovery_codes_remaining: i64,
+    passkeys: usize,
+}
+
+#[derive(Serialize)]
+struct PasskeyResponseDto {
+    id: Uuid,
+    name: String,
+    /// The authenticator model, all zeroes when the authenticator does not tell
+    aaguid: Uuid,
+    attestation_format: String,
+    transports: Vec<String>,
+    /// Whether the passkey can be synced to the user's other devices
+    backup_eligible: bool,
+    last_used_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+pub async fn get_user_mfa(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(user_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    let mfa_repository: Arc<dyn MfaRepository> = state.module.resolve();
+    let passkey_repository: Arc<dyn PasskeyRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let passkeys = match passkey_repository.find_by_user(user_id).await {
+        Ok(passkeys) => passkeys,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+    let confirmed_at = mfa_repository.find_totp(user_id).await.and_then(|t| t.confirmed_at);
+    match mfa_repository.count_recovery_codes(user_id).await {
+        Ok(remaining) => {
+            let response = MfaStatusResponseDto {
+                totp: confirmed_at.is_some(),
+                totp_confirmed_at: confirmed_at,
+                recovery_codes_remaining: remaining,
+                passkeys: passkeys.len(),
+            };
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Removes the user's authenticator, recovery codes and passkeys, for users who lost them.
+/// Users who need a second factor set up a new one at their next login.
+pub async fn reset_user_mfa(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(user_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    let mfa_repository: Arc<dyn MfaRepository> = state.module.resolve();
+    let passkey_repository: Arc<dyn PasskeyRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let reset = match mfa_repository.reset(user_id).await {
+        Ok(reset) => reset,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+    match passkey_repository.delete_by_user(user_id).await {
+        Ok(deleted) if reset || deleted => StatusCode::NO_CONTENT.into_response(),
+        Ok(_) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn list_user_passkeys(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(user_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    let passkey_repository: Arc<dyn PasskeyRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match passkey_repository.find_by_user(user_id).await {
+        Ok(passkeys) => {
+            let response = passkeys
+                .into_iter()
+                .map(|p| PasskeyResponseDto {
+                    id: p.uuid,
+                    name: p.name,
+                    aaguid: p.aaguid,
+                    attestation_format: p.attestation_format,
+                    transports: p.transports,
+                    backup_eligible: p.backup_eligible,
+                    last_used_at: p.last_used_at,
+                    created_at: p.created_at,
+                })
+                .collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Removes one passkey, e.g. of a lost device.
+pub async fn delete_user_passkey(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((user_id, passkey_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    let passkey_repository: Arc<dyn PasskeyRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match passkey_repository.delete(user_id, passkey_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
@@ -1,12 +1,128 @@
-use axum::Router;
-use axum::routing::post;
+use axum::{middleware, Router};
+use axum::routing::{delete, get, patch, post, put};
 use crate::server::AppState;
 
 pub mod user;
 pub mod client;
+pub mod consent;
+pub mod mfa;
+pub mod resource;
+pub mod initial_access_token;
+pub mod protocol_mapper;
+pub mod role;
+pub mod group;
+pub mod organization;
+pub mod realm;
+pub mod permissions;
+mod auth;
 
 pub fn api_routes(app_state: AppState) -> Router {
     Router::new()
-        .route("/api/users", post(user::create_user))
+        .route("/api/users", post(user::create_user).get(user::list_users))
+        .route(
+            "/api/users/:id",
+            get(user::get_user).patch(user::update_user).delete(user::delete_user),
+        )
+        .route("/api/users/:id/password", put(user::reset_password))
+        .route("/api/users/:id/enable", post(user::enable_user))
+        .route("/api/users/:id/disable", post(user::disable_user))
+        .route("/api/users/:id/unlock", post(user::unlock_user))
+        .route("/api/users/:id/mfa", get(mfa::get_user_mfa).delete(mfa::reset_user_mfa))
+        .route("/api/users/:id/passkeys", get(mfa::list_user_passkeys))
+        .route("/api/users/:id/passkeys/:passkey_id", delete(mfa::delete_user_passkey))
+        .route("/api/users/:id/consents", get(consent::list_user_consents))
+        .route("/api/users/:id/consents/:client_id", delete(consent::revoke_user_consent))
+        .route("/api/resources", post(resource::create_resource).get(resource::list_resources))
+        .route("/api/resources/:id", get(resource::get_resource).delete(resource::delete_resource))
+        .route("/api/resources/:id/scopes", post(resource::add_resource_scope))
+        .route(
+            "/api/initial-access-tokens",
+            post(initial_access_token::create_initial_access_token)
+                .get(initial_access_token::list_initial_access_tokens),
+        )
+        .route(
+            "/api/initial-access-tokens/:id",
+            delete(initial_access_token::delete_initial_access_token),
+        )
+        .route(
+            "/api/clients/:client_id/mappers",
+            post(protocol_mapper::create_client_mapper).get(protocol_mapper::list_client_mappers),
+        )
+        .route(
+            "/api/scopes/:scope/mappers",
+            post(protocol_mapper::create_scope_mapper).get(protocol_mapper::list_scope_mappers),
+        )
+        .route("/api/mappers/:id", delete(protocol_mapper::delete_mapper))
+        .route("/api/clients/:client_id", patch(client::update_client))
+        .route("/api/roles", post(role::create_realm_role).get(role::list_realm_roles))
+        .route(
+            "/api/roles/:id",
+            get(role::get_role).patch(role::update_role).delete(role::delete_role),
+        )
+        .route("/api/roles/:id/members", get(role::list_role_members))
+        .route("/api/roles/:id/composites", post(role::add_composite).get(role::list_composites))
+        .route("/api/roles/:id/composites/:child_id", delete(role::remove_composite))
+        .route("/api/users/:id/roles", post(role::assign_user_role).get(role::list_user_roles))
+        .route(
+            "/api/users/:id/roles/:role_id",
+            get(role::check_user_role).delete(role::unassign_user_role),
+        )
+        .route("/api/users/:id/groups", get(group::list_user_groups))
+        .route("/api/groups", post(group::create_group).get(group::list_groups))
+        .route("/api/groups/:id", get(group::get_group).delete(group::delete_group))
+        .route("/api/groups/:id/attributes", put(group::update_group_attributes))
+        .route("/api/groups/:id/members", post(group::add_member).get(group::list_members))
+        .route("/api/groups/:id/members/:user_id", delete(group::remove_member))
+        .route("/api/groups/:id/roles", post(group::add_group_role).get(group::list_group_roles))
+        .route("/api/groups/:id/roles/:role_id", delete(group::remove_group_role))
+        .route(
+            "/api/clients/:client_id/roles",
+            post(role::create_client_role).get(role::list_client_roles),
+        )
+        .route(
+            "/api/organizations",
+            post(organization::create_organization).get(organization::list_organizations),
+        )
+        .route(
+            "/api/organizations/:id",
+            get(organization::get_organization)
+                .patch(organization::update_organization)
+                .delete(organization::delete_organization),
+        )
+        .route(
+            "/api/organizations/:id/domains",
+            post(organization::add_domain).get(organization::list_domains),
+        )
+        .route("/api/organizations/:id/domains/:domain", delete(organization::remove_domain))
+        .route("/api/organizations/:id/domains/:domain/verify", post(organization::verify_domain))
+        .route(
+            "/api/organizations/:id/members",
+            post(organization::add_member).get(organization::list_members),
+        )
+        .route(
+            "/api/organizations/:id/members/:user_id",
+            put(organization::update_member).delete(organization::remove_member),
+        )
+        .route(
+            "/api/organizations/:id/invitations",
+            post(organization::create_invitation).get(organization::list_invitations),
+        )
+        .route("/api/organizations/:id/invitations/:invitation_id", delete(organization::delete_invitation))
+        .route("/api/users/:id/organizations", get(organization::list_user_organizations))
+        .route("/api/realms", post(realm::create_realm).get(realm::list_realms))
+        .route(
+            "/api/realms/:name",
+            get(realm::get_realm).patch(realm::update_realm).delete(realm::delete_realm),
+        )
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), auth::authorize))
         .with_state(app_state.clone())
-}
\ No newline at end of file
+}
+
+/// Self-service routes of the signed-in user, authorized by the user's own access token.
+pub fn account_routes(app_state: AppState) -> Router {
+    Router::new()
+        .route("/api/me/consents", get(consent::list_own_consents))
+        .route("/api/me/consents/:client_id", delete(consent::revoke_own_consent))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), auth::authenticate_user))
+        .with_state(app_state.clone())
+}
@@ -0,0 +1,506 @@
+use std::sync::Arc;
+use crate::domain::organization::{
+    check_organization_roles, normalize_domain, Invitation, Membership, Organization, OrganizationDomain,
+};
+use crate::domain::realm::Realm;
+use crate::oidc::access_token::hash_token;
+use crate::oidc::registration::generate_token;
+use crate::repository::organization_repository::{
+    CreateInvitationParams, CreateOrganizationParams, OrganizationRepository, UpdateOrganizationParams,
+};
+use crate::repository::user_repository::UserRepository;
+use crate::server::AppState;
+use axum::extract::{Extension, Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use shaku::HasComponent;
+use uuid::Uuid;
+
+/// Invitations expire after a week unless the administrator asks otherwise
+const DEFAULT_INVITATION_LIFETIME: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrganizationRequestDto {
+    pub name: String,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateOrganizationRequestDto {
+    /// Replaces the display name, null clears it
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddDomainRequestDto {
+    pub domain: String,
+    /// Whether the organization's ownership of the domain was checked, false when unset
+    #[serde(default)]
+    pub verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddOrganizationMemberRequestDto {
+    pub user_id: Uuid,
+    /// Added to the roles of an existing member
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateOrganizationMemberRequestDto {
+    /// Replaces the member's roles
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvitationRequestDto {
+    pub email: String,
+    /// The roles the user gets when accepting
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Lifetime in seconds, a week when unset
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct OrganizationResponseDto {
+    id: String,
+    name: String,
+    display_name: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct DomainResponseDto {
+    domain: String,
+    verified: bool,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct MembershipResponseDto {
+    organization_id: String,
+    organization_name: String,
+    user_id: String,
+    roles: Vec<String>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct InvitationResponseDto {
+    id: String,
+    email: String,
+    roles: Vec<String>,
+    expires_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    /// The link to hand to the invited user, only returned when the invitation is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+}
+
+impl From<Organization> for OrganizationResponseDto {
+    fn from(organization: Organization) -> Self {
+        Self {
+            id: organization.uuid.to_string(),
+            name: organization.name,
+            display_name: organization.display_name,
+            created_at: organization.created_at,
+            updated_at: organization.updated_at,
+        }
+    }
+}
+
+impl From<OrganizationDomain> for DomainResponseDto {
+    fn from(domain: OrganizationDomain) -> Self {
+        Self {
+            domain: domain.domain,
+            verified: domain.verified,
+            created_at: domain.created_at,
+        }
+    }
+}
+
+impl From<Membership> for MembershipResponseDto {
+    fn from(membership: Membership) -> Self {
+        Self {
+            organization_id: membership.organization.uuid.to_string(),
+            organization_name: membership.organization.name,
+            user_id: membership.user_id.to_string(),
+            roles: membership.roles,
+            created_at: membership.created_at,
+        }
+    }
+}
+
+impl From<Invitation> for InvitationResponseDto {
+    fn from(invitation: Invitation) -> Self {
+        Self {
+            id: invitation.uuid.to_string(),
+            email: invitation.email,
+            roles: invitation.roles,
+            expires_at: invitation.expires_at,
+            created_at: invitation.created_at,
+            url: None,
+        }
+    }
+}
+
+pub async fn create_organization(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Json(dto): Json<CreateOrganizationRequestDto>,
+) -> impl IntoResponse {
+    if dto.name.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "The name must not be empty").into_response();
+    }
+
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    let create_params = CreateOrganizationParams {
+        realm_uuid: realm.uuid,
+        name: dto.name,
+        display_name: dto.display_name,
+    };
+
+    match organization_repository.create(create_params).await {
+        Ok(organization) => (StatusCode::CREATED, Json(OrganizationResponseDto::from(organization))).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+pub async fn list_organizations(State(state): State<AppState>, Extension(realm): Extension<Realm>) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    match organization_repository.list(realm.uuid).await {
+        Ok(organizations) => {
+            let response = organizations
+                .into_iter()
+                .map(OrganizationResponseDto::from)
+                .collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn get_organization(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    match organization_repository.find_by_id(realm.uuid, id).await {
+        Some(organization) => (StatusCode::OK, Json(OrganizationResponseDto::from(organization))).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn update_organization(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<UpdateOrganizationRequestDto>,
+) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let update_params = UpdateOrganizationParams {
+        display_name: dto.display_name,
+    };
+
+    match organization_repository.update(realm.uuid, id, update_params).await {
+        Ok(organization) => (StatusCode::OK, Json(OrganizationResponseDto::from(organization))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn delete_organization(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    match organization_repository.delete(realm.uuid, id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn add_domain(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<AddDomainRequestDto>,
+) -> impl IntoResponse {
+    let domain = match normalize_domain(&dto.domain) {
+        Ok(domain) => domain,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match organization_repository.add_domain(realm.uuid, id, &domain, dto.verified).await {
+        Ok(domain) => (StatusCode::CREATED, Json(DomainResponseDto::from(domain))).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+pub async fn list_domains(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match organization_repository.list_domains(id).await {
+        Ok(domains) => {
+            let response = domains.into_iter().map(DomainResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Marks a domain verified once the organization proved it owns it, for example through a DNS record.
+pub async fn verify_domain(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((id, domain)): Path<(Uuid, String)>,
+) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match organization_repository.verify_domain(id, &domain.to_ascii_lowercase()).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn remove_domain(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((id, domain)): Path<(Uuid, String)>,
+) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match organization_repository.remove_domain(id, &domain.to_ascii_lowercase()).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn add_member(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<AddOrganizationMemberRequestDto>,
+) -> impl IntoResponse {
+    if let Err(e) = check_organization_roles(&dto.roles) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if user_repository.find_by_id(realm.uuid, dto.user_id).await.is_none() {
+        return (StatusCode::BAD_REQUEST, "The user does not exist").into_response();
+    }
+
+    match organization_repository.add_member(id, dto.user_id, &dto.roles).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn list_members(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match organization_repository.list_members(id).await {
+        Ok(members) => {
+            let response = members.into_iter().map(MembershipResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn update_member(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    Json(dto): Json<UpdateOrganizationMemberRequestDto>,
+) -> impl IntoResponse {
+    if let Err(e) = check_organization_roles(&dto.roles) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if organization_repository.find_membership(id, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match organization_repository.set_member(id, user_id, &dto.roles).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn remove_member(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match organization_repository.remove_member(id, user_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn list_user_organizations(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(user_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match organization_repository.find_by_user(user_id).await {
+        Ok(memberships) => {
+            let response = memberships.into_iter().map(MembershipResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn create_invitation(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<CreateInvitationRequestDto>,
+) -> impl IntoResponse {
+    if !dto.email.contains('@') {
+        return (StatusCode::BAD_REQUEST, "Invalid email address").into_response();
+    }
+    if dto.expires_in.is_some_and(|e| e <= 0) {
+        return (StatusCode::BAD_REQUEST, "expires_in must be positive").into_response();
+    }
+    if let Err(e) = check_organization_roles(&dto.roles) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let token = generate_token();
+    let create_params = CreateInvitationParams {
+        organization_uuid: id,
+        email: dto.email,
+        roles: dto.roles,
+        token_hash: hash_token(&token),
+        expires_at: Utc::now() + Duration::seconds(dto.expires_in.unwrap_or(DEFAULT_INVITATION_LIFETIME)),
+    };
+
+    match organization_repository.create_invitation(create_params).await {
+        Ok(invitation) => {
+            let mut response = InvitationResponseDto::from(invitation);
+            response.url = Some(format!("{}/invitation?token={}", state.external_url(&realm), token));
+            (StatusCode::CREATED, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn list_invitations(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match organization_repository.list_invitations(id).await {
+        Ok(invitations) => {
+            let response = invitations.into_iter().map(InvitationResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn delete_invitation(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((id, invitation_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    let organization_repository: Arc<dyn OrganizationRepository> = state.module.resolve();
+
+    if organization_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match organization_repository.delete_invitation(id, invitation_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
@@ -0,0 +1,235 @@
+//! Permissions of the admin REST API.
+//! Each route needs read or write access to one area, like "users:read" or "clients:write".
+//! Clients are granted permissions through the scopes of their access token. Tokens issued to a
+//! user only carry the permissions both their scopes and the user's realm roles with the same
+//! names grant. The "admin" scope or role grants every permission, and write access to an area
+//! includes read access.
+//!
+//! Roles of users and groups are managed with the "roles" permission, since assigning a role
+//! hands out the permissions it carries. Only administrators may hand out the "admin" role.
+
+use std::fmt;
+use axum::http::Method;
+use crate::domain::role::Role;
+
+/// Grants every permission, as a scope or as a realm role
+pub const ADMIN: &str = "admin";
+
+/// The areas of the API by the first path segment after "/api/". Protocol mappers and
+/// initial access tokens count as client configuration. Other areas, like realms, need "admin".
+const AREAS: &[(&str, &str)] = &[
+    ("users", "users"),
+    ("clients", "clients"),
+    ("roles", "roles"),
+    ("groups", "groups"),
+    ("resources", "resources"),
+    ("organizations", "organizations"),
+    ("scopes", "clients"),
+    ("mappers", "clients"),
+    ("initial-access-tokens", "clients"),
+];
+
+/// The scopes and realm role names of the caller, for handlers that check more than the route.
+#[derive(Debug, Clone)]
+pub struct Granted(pub Vec<String>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Read or write access to an area of the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permission {
+    pub area: &'static str,
+    pub access: Access,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.access {
+            Access::Read => write!(f, "{}:read", self.area),
+            Access::Write => write!(f, "{}:write", self.area),
+        }
+    }
+}
+
+/// The permission a request needs. Safe methods read, all others write. The roles of users and
+/// groups belong to the "roles" area. Paths outside the known areas have no permission of their
+/// own and are left to administrators.
+pub fn required_permission(method: &Method, path: &str) -> Option<Permission> {
+    let mut segments = path.strip_prefix("/api/")?.split('/');
+    let segment = segments.next()?;
+    let area = match (segment, segments.nth(1)) {
+        ("users" | "groups", Some("roles")) => "roles",
+        _ => AREAS.iter().find(|(prefix, _)| *prefix == segment).map(|(_, area)| *area)?,
+    };
+
+    let access = if method == Method::GET || method == Method::HEAD {
+        Access::Read
+    } else {
+        Access::Write
+    };
+
+    Some(Permission { area, access })
+}
+
+/// Whether the granted scopes and role names include the permission.
+pub fn is_granted(required: Option<Permission>, granted: &[String]) -> bool {
+    if granted.iter().any(|g| g == ADMIN) {
+        return true;
+    }
+
+    let Some(required) = required else {
+        return false;
+    };
+
+    let write = Permission { access: Access::Write, ..required };
+    granted
+        .iter()
+        .any(|g| *g == required.to_string() || *g == write.to_string())
+}
+
+/// The resource indicator of the admin API of the realm with the issuer.
+pub fn admin_audience(issuer: &str) -> String {
+    format!("{}/api", issuer)
+}
+
+/// The permissions of a token issued to a user: those both its scopes and the user's realm
+/// roles grant. Neither a consented scope nor a role is enough on its own.
+pub fn user_permissions(scopes: &[String], roles: &[String]) -> Vec<String> {
+    let mut granted = scopes.iter().filter(|scope| covers(roles, scope)).cloned().collect::<Vec<_>>();
+    for role in roles {
+        if covers(scopes, role) && !granted.contains(role) {
+            granted.push(role.clone());
+        }
+    }
+
+    granted
+}
+
+/// Whether the granted scopes or role names include the permission, or "admin" by that name.
+/// Other names, like "openid", are no permissions and never covered.
+fn covers(granted: &[String], name: &str) -> bool {
+    let has = |permission: &str| granted.iter().any(|g| g == permission);
+    match name.split_once(':') {
+        _ if name == ADMIN => has(ADMIN),
+        Some((area, "read")) => has(ADMIN) || has(&format!("{}:read", area)) || has(&format!("{}:write", area)),
+        Some((area, "write")) => has(ADMIN) || has(&format!("{}:write", area)),
+        _ => false,
+    }
+}
+
+/// Whether the caller may hand out the roles, as found by their effective roles. Handing out
+/// the realm's "admin" role, directly or through a composite or group, needs "admin".
+pub fn may_grant(roles: &[Role], granted: &[String]) -> bool {
+    let grants_admin = roles.iter().any(|role| role.is_realm_role() && role.name == ADMIN);
+    !grants_admin || is_granted(None, granted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn granted(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_required_permission() {
+        let permission = |method, path| required_permission(&method, path).map(|p| p.to_string());
+
+        assert_eq!(permission(Method::GET, "/api/users"), Some("users:read".to_string()));
+        assert_eq!(permission(Method::POST, "/api/users"), Some("users:write".to_string()));
+        assert_eq!(permission(Method::POST, "/api/users/1/roles"), Some("roles:write".to_string()));
+        assert_eq!(permission(Method::DELETE, "/api/users/1/roles/2"), Some("roles:write".to_string()));
+        assert_eq!(permission(Method::GET, "/api/users/1/roles"), Some("roles:read".to_string()));
+        assert_eq!(permission(Method::POST, "/api/groups/1/roles"), Some("roles:write".to_string()));
+        assert_eq!(permission(Method::POST, "/api/groups/1/members"), Some("groups:write".to_string()));
+        assert_eq!(permission(Method::POST, "/api/users/roles"), Some("users:write".to_string()));
+        assert_eq!(permission(Method::GET, "/api/clients/app/roles"), Some("clients:read".to_string()));
+        assert_eq!(permission(Method::PATCH, "/api/scopes/email/mappers"), Some("clients:write".to_string()));
+        assert_eq!(permission(Method::GET, "/api/initial-access-tokens"), Some("clients:read".to_string()));
+        assert_eq!(
+            permission(Method::POST, "/api/organizations/1/invitations"),
+            Some("organizations:write".to_string())
+        );
+        assert_eq!(permission(Method::GET, "/api/realms"), None);
+        assert_eq!(permission(Method::GET, "/api/unknown"), None);
+        assert_eq!(permission(Method::GET, "/health"), None);
+    }
+
+    #[test]
+    fn test_is_granted() {
+        let read = required_permission(&Method::GET, "/api/users");
+        let write = required_permission(&Method::POST, "/api/users");
+
+        assert!(is_granted(read, &granted(&["users:read"])));
+        assert!(is_granted(read, &granted(&["openid", "users:write"])));
+        assert!(!is_granted(write, &granted(&["users:read"])));
+        assert!(!is_granted(read, &granted(&["clients:write", "user"])));
+        assert!(is_granted(write, &granted(&["admin"])));
+    }
+
+    #[test]
+    fn test_unknown_areas_need_admin() {
+        assert!(!is_granted(None, &granted(&["users:write"])));
+        assert!(is_granted(None, &granted(&["admin"])));
+    }
+
+    fn role(name: &str, client_uuid: Option<Uuid>) -> Role {
+        Role {
+            uuid: Uuid::new_v4(),
+            client_uuid,
+            client_id: None,
+            name: name.to_string(),
+            description: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_role_assignments_need_roles_write() {
+        let assign = required_permission(&Method::POST, "/api/users/1/roles");
+
+        assert!(!is_granted(assign, &granted(&["users:write"])));
+        assert!(!is_granted(assign, &granted(&["groups:write", "roles:read"])));
+        assert!(is_granted(assign, &granted(&["roles:write"])));
+    }
+
+    #[test]
+    fn test_only_administrators_grant_admin() {
+        let roles_write = granted(&["roles:write"]);
+        let viewer = [role("viewer", None)];
+        // A composite role containing "admin" is found with its effective roles
+        let composite = [role("ops", None), role(ADMIN, None)];
+        let client_role = [role(ADMIN, Some(Uuid::new_v4()))];
+
+        assert!(may_grant(&viewer, &roles_write));
+        assert!(!may_grant(&[role(ADMIN, None)], &roles_write));
+        assert!(!may_grant(&composite, &roles_write));
+        assert!(may_grant(&composite, &granted(&["admin"])));
+        // Client roles named "admin" grant no permissions
+        assert!(may_grant(&client_role, &roles_write));
+    }
+
+    #[test]
+    fn test_user_permissions_need_scopes_and_roles() {
+        let permissions = |scopes: &[&str], roles: &[&str]| user_permissions(&granted(scopes), &granted(roles));
+
+        // A consented scope grants nothing without the role, whatever the user's other roles
+        assert!(permissions(&["openid", "users:write"], &["user"]).is_empty());
+        assert!(permissions(&["admin"], &[]).is_empty());
+        // A role grants nothing the token's scopes do not cover
+        assert!(permissions(&["openid", "profile"], &["admin"]).is_empty());
+
+        assert_eq!(permissions(&["openid", "admin"], &["users:read"]), granted(&["users:read"]));
+        assert_eq!(permissions(&["users:write"], &["admin"]), granted(&["users:write"]));
+        assert_eq!(permissions(&["users:read"], &["users:write"]), granted(&["users:read"]));
+        assert_eq!(permissions(&["admin"], &["admin"]), granted(&["admin"]));
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::Arc;
+use crate::domain::client::ClientId;
+use crate::domain::protocol_mapper::{MapperSource, ProtocolMapper};
+use crate::domain::realm::Realm;
+use crate::oidc::mappers::check_claim_name;
+use crate::repository::client_repository::ClientRepository;
+use crate::repository::protocol_mapper_repository::{CreateProtocolMapperParams, ProtocolMapperRepository};
+use crate::server::AppState;
+use axum::extract::{Extension, Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use shaku::HasComponent;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateProtocolMapperRequestDto {
+    pub name: String,
+    pub claim_name: String,
+    /// "type" selects the source: "user_attribute" with an "attribute", "user_roles",
+    /// "group_membership", or "static" with a "value"
+    #[serde(flatten)]
+    pub source: MapperSource,
+    /// The tokens the claim is added to, all of them when unset
+    pub id_token: Option<bool>,
+    pub access_token: Option<bool>,
+    pub userinfo: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct ProtocolMapperResponseDto {
+    id: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    claim_name: String,
+    #[serde(flatten)]
+    source: MapperSource,
+    id_token: bool,
+    access_token: bool,
+    userinfo: bool,
+}
+
+impl From<ProtocolMapper> for ProtocolMapperResponseDto {
+    fn from(mapper: ProtocolMapper) -> Self {
+        Self {
+            id: mapper.uuid.to_string(),
+            name: mapper.name,
+            scope: mapper.scope,
+            claim_name: mapper.claim_name,
+            source: mapper.source,
+            id_token: mapper.id_token,
+            access_token: mapper.access_token,
+            userinfo: mapper.userinfo,
+        }
+    }
+}
+
+pub async fn create_client_mapper(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(client_id): Path<String>,
+    Json(dto): Json<CreateProtocolMapperRequestDto>,
+) -> impl IntoResponse {
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+
+    let Some(client) = client_repository.find_by_id(realm.uuid, &ClientId(client_id)).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    create_mapper(&state, &realm, Some(client.uuid), None, dto).await
+}
+
+pub async fn list_client_mappers(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(client_id): Path<String>,
+) -> impl IntoResponse {
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+    let protocol_mapper_repository: Arc<dyn ProtocolMapperRepository> = state.module.resolve();
+
+    let Some(client) = client_repository.find_by_id(realm.uuid, &ClientId(client_id)).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match protocol_mapper_repository.list_for_client(client.uuid).await {
+        Ok(mappers) => {
+            let response = mappers.into_iter().map(ProtocolMapperResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn create_scope_mapper(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(scope): Path<String>,
+    Json(dto): Json<CreateProtocolMapperRequestDto>,
+) -> impl IntoResponse {
+    create_mapper(&state, &realm, None, Some(scope), dto).await
+}
+
+pub async fn list_scope_mappers(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(scope): Path<String>,
+) -> impl IntoResponse {
+    let protocol_mapper_repository: Arc<dyn ProtocolMapperRepository> = state.module.resolve();
+
+    match protocol_mapper_repository.list_for_scope(realm.uuid, &scope).await {
+        Ok(mappers) => {
+            let response = mappers.into_iter().map(ProtocolMapperResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn delete_mapper(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let protocol_mapper_repository: Arc<dyn ProtocolMapperRepository> = state.module.resolve();
+
+    match protocol_mapper_repository.delete(realm.uuid, id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn create_mapper(
+    state: &AppState,
+    realm: &Realm,
+    client_uuid: Option<Uuid>,
+    scope: Option<String>,
+    dto: CreateProtocolMapperRequestDto,
+) -> Response {
+    if let Err(e) = check_claim_name(&dto.claim_name) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+    if matches!(&dto.source, MapperSource::UserAttribute { attribute } if attribute.is_empty()) {
+        return (StatusCode::BAD_REQUEST, "The attribute must not be empty").into_response();
+    }
+
+    let create_params = CreateProtocolMapperParams {
+        realm_uuid: realm.uuid,
+        name: dto.name,
+        client_uuid,
+        scope,
+        source: dto.source,
+        claim_name: dto.claim_name,
+        id_token: dto.id_token.unwrap_or(true),
+        access_token: dto.access_token.unwrap_or(true),
+        userinfo: dto.userinfo.unwrap_or(true),
+    };
+
+    let protocol_mapper_repository: Arc<dyn ProtocolMapperRepository> = state.module.resolve();
+
+    match protocol_mapper_repository.create(create_params).await {
+        Ok(mapper) => (StatusCode::CREATED, Json(ProtocolMapperResponseDto::from(mapper))).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::Arc;
+use crate::domain::realm::{check_realm_name, Realm};
+use crate::repository::realm_repository::{CreateRealmParams, RealmRepository, UpdateRealmParams};
+use crate::server::{bootstrap, AppState};
+use axum::extract::{Extension, Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shaku::HasComponent;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRealmRequestDto {
+    pub name: String,
+    pub external_url: Option<String>,
+    pub host: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRealmRequestDto {
+    /// Replaces the external URL, null clears it
+    pub external_url: Option<String>,
+    /// Replaces the host, null clears it
+    pub host: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RealmResponseDto {
+    id: String,
+    name: String,
+    issuer: String,
+    external_url: Option<String>,
+    host: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl RealmResponseDto {
+    fn new(state: &AppState, realm: Realm) -> Self {
+        Self {
+            id: realm.uuid.to_string(),
+            issuer: state.external_url(&realm),
+            name: realm.name,
+            external_url: realm.external_url,
+            host: realm.host,
+            created_at: realm.created_at,
+            updated_at: realm.updated_at,
+        }
+    }
+}
+
+/// Realms are administered from the default realm only.
+fn not_default(realm: &Realm) -> Option<Response> {
+    (!realm.is_default()).then(|| StatusCode::NOT_FOUND.into_response())
+}
+
+pub async fn create_realm(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Json(dto): Json<CreateRealmRequestDto>,
+) -> impl IntoResponse {
+    if let Some(response) = not_default(&realm) {
+        return response;
+    }
+    if let Err(e) = check_realm_name(&dto.name) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+
+    let realm_repository: Arc<dyn RealmRepository> = state.module.resolve();
+
+    if realm_repository.find_by_name(&dto.name).await.is_some() {
+        return (StatusCode::CONFLICT, "A realm with this name already exists").into_response();
+    }
+
+    let create_params = CreateRealmParams {
+        name: dto.name,
+        external_url: dto.external_url,
+        host: dto.host,
+    };
+
+    match bootstrap::create_realm(&state.module, create_params).await {
+        Ok(created) => (StatusCode::CREATED, Json(RealmResponseDto::new(&state, created))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn list_realms(State(state): State<AppState>, Extension(realm): Extension<Realm>) -> impl IntoResponse {
+    if let Some(response) = not_default(&realm) {
+        return response;
+    }
+
+    let realm_repository: Arc<dyn RealmRepository> = state.module.resolve();
+
+    match realm_repository.list().await {
+        Ok(realms) => {
+            let response = realms
+                .into_iter()
+                .map(|r| RealmResponseDto::new(&state, r))
+                .collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn get_realm(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    if let Some(response) = not_default(&realm) {
+        return response;
+    }
+
+    let realm_repository: Arc<dyn RealmRepository> = state.module.resolve();
+
+    match realm_repository.find_by_name(&name).await {
+        Some(found) => (StatusCode::OK, Json(RealmResponseDto::new(&state, found))).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn update_realm(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(name): Path<String>,
+    Json(dto): Json<UpdateRealmRequestDto>,
+) -> impl IntoResponse {
+    if let Some(response) = not_default(&realm) {
+        return response;
+    }
+
+    let realm_repository: Arc<dyn RealmRepository> = state.module.resolve();
+
+    let Some(found) = realm_repository.find_by_name(&name).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let update_params = UpdateRealmParams {
+        external_url: dto.external_url,
+        host: dto.host,
+    };
+
+    match realm_repository.update(found.uuid, update_params).await {
+        Ok(updated) => (StatusCode::OK, Json(RealmResponseDto::new(&state, updated))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn delete_realm(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    if let Some(response) = not_default(&realm) {
+        return response;
+    }
+
+    let realm_repository: Arc<dyn RealmRepository> = state.module.resolve();
+
+    let Some(found) = realm_repository.find_by_name(&name).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if found.is_default() {
+        return (StatusCode::BAD_REQUEST, "The default realm cannot be deleted").into_response();
+    }
+
+    match realm_repository.delete(found.uuid).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
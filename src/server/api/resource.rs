@@ -0,0 +1,187 @@
+use std::sync::Arc;
+use crate::domain::access_token::AccessTokenFormat;
+use crate::domain::api_resource::{ApiResource, ApiResourceScope};
+use crate::domain::realm::Realm;
+use crate::oidc::keys::{DEFAULT_ALGORITHM, SUPPORTED_ALGORITHMS};
+use crate::oidc::resources::is_valid_identifier;
+use crate::repository::api_resource_repository::{
+    ApiResourceRepository, CreateApiResourceParams, CreateApiResourceScopeParams,
+};
+use crate::server::AppState;
+use axum::extract::{Extension, Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use shaku::HasComponent;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiResourceRequestDto {
+    pub identifier: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub access_token_format: Option<String>,
+    pub access_token_lifetime: Option<i32>,
+    pub signing_algorithm: Option<String>,
+    #[serde(default)]
+    pub scopes: Vec<ApiResourceScopeRequestDto>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiResourceScopeRequestDto {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ApiResourceResponseDto {
+    id: String,
+    identifier: String,
+    name: String,
+    description: Option<String>,
+    access_token_format: Option<String>,
+    access_token_lifetime: Option<i32>,
+    signing_algorithm: String,
+    scopes: Vec<ApiResourceScopeResponseDto>,
+}
+
+#[derive(Serialize)]
+struct ApiResourceScopeResponseDto {
+    name: String,
+    description: Option<String>,
+}
+
+impl From<ApiResource> for ApiResourceResponseDto {
+    fn from(resource: ApiResource) -> Self {
+        Self {
+            id: resource.uuid.to_string(),
+            identifier: resource.identifier,
+            name: resource.name,
+            description: resource.description,
+            access_token_format: resource.access_token_format.map(|f| f.to_string()),
+            access_token_lifetime: resource.access_token_lifetime,
+            signing_algorithm: resource.signing_algorithm,
+            scopes: resource.scopes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<ApiResourceScope> for ApiResourceScopeResponseDto {
+    fn from(scope: ApiResourceScope) -> Self {
+        Self {
+            name: scope.name,
+            description: scope.description,
+        }
+    }
+}
+
+pub async fn create_resource(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Json(dto): Json<CreateApiResourceRequestDto>,
+) -> impl IntoResponse {
+    if !is_valid_identifier(&dto.identifier) {
+        return (StatusCode::BAD_REQUEST, "Identifier must be an absolute URI without a fragment").into_response();
+    }
+
+    let access_token_format = match dto.access_token_format.as_deref().map(str::parse::<AccessTokenFormat>) {
+        None => None,
+        Some(Ok(format)) => Some(format),
+        Some(Err(e)) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let signing_algorithm = dto.signing_algorithm.unwrap_or_else(|| DEFAULT_ALGORITHM.to_string());
+    if !SUPPORTED_ALGORITHMS.contains(&signing_algorithm.as_str()) {
+        return (StatusCode::BAD_REQUEST, format!("Unsupported signing algorithm: {}", signing_algorithm)).into_response();
+    }
+
+    let create_params = CreateApiResourceParams {
+        realm_uuid: realm.uuid,
+        identifier: dto.identifier,
+        name: dto.name,
+        description: dto.description,
+        access_token_format,
+        access_token_lifetime: dto.access_token_lifetime,
+        signing_algorithm,
+        scopes: dto
+            .scopes
+            .into_iter()
+            .map(|s| CreateApiResourceScopeParams {
+                name: s.name,
+                description: s.description,
+            })
+            .collect(),
+    };
+
+    let api_resource_repository: Arc<dyn ApiResourceRepository> = state.module.resolve();
+
+    match api_resource_repository.create(create_params).await {
+        Ok(resource) => (StatusCode::CREATED, Json(ApiResourceResponseDto::from(resource))).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+pub async fn list_resources(State(state): State<AppState>, Extension(realm): Extension<Realm>) -> impl IntoResponse {
+    let api_resource_repository: Arc<dyn ApiResourceRepository> = state.module.resolve();
+
+    match api_resource_repository.list(realm.uuid).await {
+        Ok(resources) => {
+            let response = resources
+                .into_iter()
+                .map(ApiResourceResponseDto::from)
+                .collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn get_resource(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let api_resource_repository: Arc<dyn ApiResourceRepository> = state.module.resolve();
+
+    match api_resource_repository.find_by_id(realm.uuid, id).await {
+        Some(resource) => (StatusCode::OK, Json(ApiResourceResponseDto::from(resource))).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn delete_resource(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let api_resource_repository: Arc<dyn ApiResourceRepository> = state.module.resolve();
+
+    match api_resource_repository.delete(realm.uuid, id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn add_resource_scope(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<ApiResourceScopeRequestDto>,
+) -> impl IntoResponse {
+    let api_resource_repository: Arc<dyn ApiResourceRepository> = state.module.resolve();
+
+    if api_resource_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let create_params = CreateApiResourceScopeParams {
+        name: dto.name,
+        description: dto.description,
+    };
+
+    match api_resource_repository.add_scope(id, create_params).await {
+        Ok(scope) => (StatusCode::CREATED, Json(ApiResourceScopeResponseDto::from(scope))).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
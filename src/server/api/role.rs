@@ -0,0 +1,429 @@
+use std::sync::Arc;
+use crate::domain::client::ClientId;
+use crate::domain::realm::Realm;
+use crate::domain::role::Role;
+use crate::oidc::error::OAuthError;
+use crate::repository::client_repository::ClientRepository;
+use crate::repository::roles_repository::{CreateRoleParams, RoleRepository, UpdateRoleParams};
+use crate::repository::user_repository::UserRepository;
+use crate::server::api::permissions::{self, Granted, ADMIN};
+use crate::server::AppState;
+use axum::extract::{Extension, Json, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use shaku::HasComponent;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRoleRequestDto {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleRequestDto {
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListRolesQuery {
+    /// Only the role with exactly this name
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListUserRolesQuery {
+    /// Include the roles the user has through groups and composite roles
+    #[serde(default)]
+    pub effective: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignRoleRequestDto {
+    pub role_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddCompositeRequestDto {
+    /// The role to include in the composite role
+    pub role_id: Uuid,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RoleResponseDto {
+    id: String,
+    /// The owning client of a client role, absent for realm roles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+    name: String,
+    description: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RoleMemberResponseDto {
+    id: String,
+    username: String,
+}
+
+impl From<Role> for RoleResponseDto {
+    fn from(role: Role) -> Self {
+        Self {
+            id: role.uuid.to_string(),
+            client_id: role.client_id.map(|id| id.0),
+            name: role.name,
+            description: role.description,
+        }
+    }
+}
+
+pub async fn create_realm_role(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Json(dto): Json<CreateRoleRequestDto>,
+) -> impl IntoResponse {
+    create_role(&state, &realm, None, dto).await
+}
+
+pub async fn list_realm_roles(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Query(query): Query<ListRolesQuery>,
+) -> impl IntoResponse {
+    list_roles(&state, &realm, None, query).await
+}
+
+pub async fn create_client_role(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(client_id): Path<String>,
+    Json(dto): Json<CreateRoleRequestDto>,
+) -> impl IntoResponse {
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+
+    let Some(client) = client_repository.find_by_id(realm.uuid, &ClientId(client_id)).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    create_role(&state, &realm, Some(client.uuid), dto).await
+}
+
+pub async fn list_client_roles(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(client_id): Path<String>,
+    Query(query): Query<ListRolesQuery>,
+) -> impl IntoResponse {
+    let client_repository: Arc<dyn ClientRepository> = state.module.resolve();
+
+    let Some(client) = client_repository.find_by_id(realm.uuid, &ClientId(client_id)).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    list_roles(&state, &realm, Some(client.uuid), query).await
+}
+
+pub async fn get_role(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    match role_repository.find_by_id(realm.uuid, id).await {
+        Some(role) => (StatusCode::OK, Json(RoleResponseDto::from(role))).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn update_role(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Extension(granted): Extension<Granted>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<UpdateRoleRequestDto>,
+) -> impl IntoResponse {
+    if matches!(&dto.name, Some(name) if name.trim().is_empty()) {
+        return (StatusCode::BAD_REQUEST, "The role name must not be empty").into_response();
+    }
+
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    let Some(role) = role_repository.find_by_id(realm.uuid, id).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    // Renaming a role to "admin" would hand out the admin role to its members
+    let renamed = Role {
+        name: dto.name.clone().unwrap_or(role.name.clone()),
+        ..role
+    };
+    if let Some(response) = refuse_grant(Ok(vec![renamed]), &granted) {
+        return response;
+    }
+
+    let update_params = UpdateRoleParams {
+        name: dto.name,
+        description: dto.description,
+    };
+
+    match role_repository.update(realm.uuid, id, update_params).await {
+        Ok(role) => (StatusCode::OK, Json(RoleResponseDto::from(role))).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+pub async fn delete_role(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    match role_repository.delete(realm.uuid, id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn list_composites(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    if role_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match role_repository.list_composites(id).await {
+        Ok(roles) => {
+            let response = roles.into_iter().map(RoleResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn add_composite(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Extension(granted): Extension<Granted>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<AddCompositeRequestDto>,
+) -> impl IntoResponse {
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    if role_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if role_repository.find_by_id(realm.uuid, dto.role_id).await.is_none() {
+        return (StatusCode::BAD_REQUEST, "The child role does not exist").into_response();
+    }
+    // Members of the composite role get the child role as well
+    if let Some(response) = refuse_grant(role_repository.find_effective_by_role(dto.role_id).await, &granted) {
+        return response;
+    }
+
+    match role_repository.add_composite(id, dto.role_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::CONFLICT, "The role hierarchy would contain a cycle").into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+pub async fn remove_composite(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((id, child_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    if role_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match role_repository.remove_composite(id, child_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// The users the role is assigned to directly, not through groups or composite roles.
+pub async fn list_role_members(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    if role_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match role_repository.list_members(id).await {
+        Ok(users) => {
+            let response = users
+                .into_iter()
+                .map(|user| RoleMemberResponseDto {
+                    id: user.uuid.to_string(),
+                    username: user.username,
+                })
+                .collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// The roles assigned to the user, or with `?effective=true` all roles the user has.
+pub async fn list_user_roles(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<ListUserRolesQuery>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let roles = if query.effective {
+        role_repository.find_effective_by_user(user_id).await
+    } else {
+        role_repository.find_by_user(user_id).await
+    };
+
+    match roles {
+        Ok(roles) => {
+            let response = roles.into_iter().map(RoleResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn assign_user_role(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Extension(granted): Extension<Granted>,
+    Path(user_id): Path<Uuid>,
+    Json(dto): Json<AssignRoleRequestDto>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if role_repository.find_by_id(realm.uuid, dto.role_id).await.is_none() {
+        return (StatusCode::BAD_REQUEST, "The role does not exist").into_response();
+    }
+    if let Some(response) = refuse_grant(role_repository.find_effective_by_role(dto.role_id).await, &granted) {
+        return response;
+    }
+
+    match role_repository.assign(user_id, dto.role_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn unassign_user_role(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((user_id, role_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match role_repository.unassign(user_id, role_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Answers whether the user has the role, directly or through composite roles: 204 if so, 404 otherwise.
+pub async fn check_user_role(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path((user_id, role_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, user_id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match role_repository.user_has_role(user_id, role_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn create_role(
+    state: &AppState,
+    realm: &Realm,
+    client_uuid: Option<Uuid>,
+    dto: CreateRoleRequestDto,
+) -> Response {
+    if dto.name.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "The role name must not be empty").into_response();
+    }
+
+    let create_params = CreateRoleParams {
+        realm_uuid: realm.uuid,
+        client_uuid,
+        name: dto.name,
+        description: dto.description,
+    };
+
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    match role_repository.create(create_params).await {
+        Ok(role) => (StatusCode::CREATED, Json(RoleResponseDto::from(role))).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+async fn list_roles(state: &AppState, realm: &Realm, client_uuid: Option<Uuid>, query: ListRolesQuery) -> Response {
+    let role_repository: Arc<dyn RoleRepository> = state.module.resolve();
+
+    let roles = match query.name {
+        Some(name) => Ok(role_repository.find_by_name(realm.uuid, client_uuid, &name).await.into_iter().collect()),
+        None => role_repository.list(realm.uuid, client_uuid).await,
+    };
+
+    match roles {
+        Ok(roles) => {
+            let response = roles.into_iter().map(RoleResponseDto::from).collect::<Vec<_>>();
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Refuses to hand out roles, as found with the roles they contain, that the caller may not grant.
+/// Returns the response refusing the request, if any.
+pub(crate) fn refuse_grant(roles: Result<Vec<Role>, String>, granted: &Granted) -> Option<Response> {
+    match roles {
+        Ok(roles) if permissions::may_grant(&roles, &granted.0) => None,
+        Ok(_) => Some(
+            OAuthError::InsufficientScope(format!("The '{}' permission is required to grant the admin role", ADMIN))
+                .to_json_response(),
+        ),
+        Err(e) => Some((StatusCode::INTERNAL_SERVER_ERROR, e).into_response()),
+    }
+}
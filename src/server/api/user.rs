@@ -1,56 +1,309 @@
 use std::sync::Arc;
-use crate::repository::user_repository::{CreateUserParams, UserRepository};
+use crate::domain::login_failure::FailureSubject;
+use crate::domain::realm::Realm;
+use crate::domain::user::User;
+use crate::oidc::lockout;
+use crate::oidc::login::hash_password;
+use crate::repository::access_token_repository::AccessTokenRepository;
+use crate::repository::user_repository::{
+    CreateUserParams, ListUsersParams, UpdateUserParams, UserRepository,
+};
 use crate::server::AppState;
-use argon2::password_hash::rand_core::OsRng;
-use argon2::password_hash::SaltString;
-use argon2::{Argon2, PasswordHasher};
-use axum::extract::{Json, State};
+use axum::extract::{Extension, Json, Path, Query, State};
 use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use shaku::HasComponent;
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
 
 #[derive(Debug, Deserialize)]
 pub struct CreateUserRequestDto {
     pub username: String,
     pub email: String,
     pub password: String,
+    /// Custom attributes, e.g. {"tenant_id": "acme"}
+    pub attributes: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListUsersQuery {
+    /// Matches usernames containing the text, ignoring case
+    pub username: Option<String>,
+    /// Matches email addresses containing the text, ignoring case
+    pub email: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub enabled: Option<bool>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserRequestDto {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    /// Replaces all custom attributes
+    pub attributes: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequestDto {
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteUserQuery {
+    /// Removes the user for good instead of marking them as deleted
+    #[serde(default)]
+    pub hard: bool,
 }
 
 #[derive(Serialize)]
 struct UserResponseDto {
     id: String,
+    username: String,
+    email: String,
+    attributes: serde_json::Value,
+    enabled: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct UserPageResponseDto {
+    users: Vec<UserResponseDto>,
+    total: i64,
+    offset: i64,
+    limit: i64,
+}
+
+impl From<User> for UserResponseDto {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.uuid.to_string(),
+            username: user.username,
+            email: user.email,
+            attributes: user.attributes,
+            enabled: user.enabled,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
 }
 
 pub async fn create_user(
     State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
     Json(dto): Json<CreateUserRequestDto>,
 ) -> impl IntoResponse {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
+    let attributes = dto.attributes.unwrap_or_else(|| serde_json::json!({}));
+    if !attributes.is_object() {
+        return (StatusCode::BAD_REQUEST, "Attributes must be a JSON object").into_response();
+    }
+    if dto.password.is_empty() {
+        return (StatusCode::BAD_REQUEST, "The password must not be empty").into_response();
+    }
 
-    let password_hash = match argon2.hash_password(dto.password.as_bytes(), &salt) {
-        Ok(hash) => hash,
-        Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to hash password").into_response();
-        }
+    let Ok(password_hash) = hash_password(&dto.password) else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to hash password").into_response();
     };
 
     let create_params = CreateUserParams {
+        realm_uuid: realm.uuid,
         username: dto.username,
         email: dto.email,
-        password_hash: password_hash.to_string(),
+        password_hash,
+        attributes,
     };
 
     let user_repository: Arc<dyn UserRepository> = state.module.resolve();
 
     match user_repository.create(create_params).await {
-        Ok(user) => {
-            let response = UserResponseDto {
-                id: user.uuid.to_string(),
+        Ok(user) => (StatusCode::CREATED, Json(UserResponseDto::from(user))).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+pub async fn list_users(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Query(query): Query<ListUsersQuery>,
+) -> impl IntoResponse {
+    let offset = query.offset.unwrap_or(0).max(0);
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let list_params = ListUsersParams {
+        username: query.username,
+        email: query.email,
+        created_after: query.created_after,
+        created_before: query.created_before,
+        enabled: query.enabled,
+        offset,
+        limit,
+    };
+
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    match user_repository.list(realm.uuid, list_params).await {
+        Ok(page) => {
+            let response = UserPageResponseDto {
+                users: page.users.into_iter().map(UserResponseDto::from).collect(),
+                total: page.total,
+                offset,
+                limit,
             };
-            (StatusCode::CREATED, Json(response)).into_response()
+            (StatusCode::OK, Json(response)).into_response()
         }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn get_user(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    match user_repository.find_by_id(realm.uuid, id).await {
+        Some(user) => (StatusCode::OK, Json(UserResponseDto::from(user))).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub async fn update_user(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<UpdateUserRequestDto>,
+) -> impl IntoResponse {
+    if matches!(&dto.attributes, Some(attributes) if !attributes.is_object()) {
+        return (StatusCode::BAD_REQUEST, "Attributes must be a JSON object").into_response();
+    }
+
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let update_params = UpdateUserParams {
+        username: dto.username,
+        email: dto.email,
+        attributes: dto.attributes,
+    };
+
+    match user_repository.update(realm.uuid, id, update_params).await {
+        Ok(user) => (StatusCode::OK, Json(UserResponseDto::from(user))).into_response(),
         Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
     }
 }
+
+/// Sets a new password chosen by an administrator.
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<ResetPasswordRequestDto>,
+) -> impl IntoResponse {
+    if dto.password.is_empty() {
+        return (StatusCode::BAD_REQUEST, "The password must not be empty").into_response();
+    }
+
+    let Ok(password_hash) = hash_password(&dto.password) else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to hash password").into_response();
+    };
+
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    match user_repository.set_password_hash(realm.uuid, id, &password_hash).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+pub async fn enable_user(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    match user_repository.set_enabled(realm.uuid, id, true).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Disables the user and revokes their tokens. Disabled users cannot log in.
+pub async fn disable_user(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    match user_repository.set_enabled(realm.uuid, id, false).await {
+        Ok(true) => revoke_tokens(&state, id).await,
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Lifts the user's lockout after too many failed logins, and forgets their failed logins.
+pub async fn unlock_user(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    if user_repository.find_by_id(realm.uuid, id).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match lockout::clear(&state, realm.uuid, FailureSubject::User(id)).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Soft-deletes the user and revokes their tokens, or removes the user entirely with `?hard=true`.
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<DeleteUserQuery>,
+) -> impl IntoResponse {
+    let user_repository: Arc<dyn UserRepository> = state.module.resolve();
+
+    // Tokens of hard-deleted users go with them
+    if query.hard {
+        return match user_repository.delete(realm.uuid, id).await {
+            Ok(true) => StatusCode::NO_CONTENT.into_response(),
+            Ok(false) => StatusCode::NOT_FOUND.into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        };
+    }
+
+    match user_repository.soft_delete(realm.uuid, id).await {
+        Ok(true) => revoke_tokens(&state, id).await,
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn revoke_tokens(state: &AppState, user_id: Uuid) -> Response {
+    let access_token_repository: Arc<dyn AccessTokenRepository> = state.module.resolve();
+
+    match access_token_repository.revoke_by_user(user_id).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
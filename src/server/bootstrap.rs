@@ -0,0 +1,231 @@
+//! First-run bootstrap. Makes sure the default realm has an "admin" realm role, the built-in
+//! admin client and an administrator to manage everything else through the admin API.
+//!
+//! The initial administrator comes from the `bootstrap` configuration. Without one, a one-time
+//! setup token is printed to stdout, which creates the first administrator at `POST /setup`.
+//! Every step only creates what is missing, so the bootstrap runs on each start.
+//!
+//! The admin client always has the client credentials grant for automation. With
+//! `bootstrap.admin_client_redirect_uri` set, administrators also sign in to it with the
+//! authorization code flow and PKCE, and their tokens carry what their realm roles grant.
+
+use std::sync::Arc;
+use crate::config::BootstrapConfig;
+use crate::di::MyModule;
+use crate::domain::access_token::AccessTokenFormat;
+use crate::domain::client::{Client, ClientId};
+use crate::domain::realm::{check_realm_name, Realm};
+use crate::domain::role::Role;
+use crate::domain::user::User;
+use crate::oidc::access_token::hash_token;
+use crate::oidc::client_auth::hash_client_secret;
+use crate::oidc::keys;
+use crate::oidc::login::hash_password;
+use crate::oidc::registration::generate_token;
+use crate::repository::client_repository::{ClientRepository, CreateClientParams, UpdateClientMetadataParams};
+use crate::repository::realm_repository::{CreateRealmParams, RealmRepository};
+use crate::repository::roles_repository::{CreateRoleParams, RoleRepository};
+use crate::repository::setup_token_repository::SetupTokenRepository;
+use crate::repository::signing_key_repository::SigningKeyRepository;
+use crate::repository::user_repository::{CreateUserParams, UserRepository};
+use crate::server::api::permissions::ADMIN;
+use shaku::HasComponent;
+use uuid::Uuid;
+
+/// The built-in client of the admin API, see the module docs
+pub const ADMIN_CLIENT_ID: &str = "vaulton-admin";
+
+pub async fn bootstrap(module: &MyModule, realm_uuid: Uuid, config: &BootstrapConfig) -> Result<(), String> {
+    let admin_role = ensure_admin_role(module, realm_uuid).await?;
+    ensure_admin_client(module, realm_uuid, config).await?;
+
+    if let Some(username) = &config.admin_username {
+        let user_repository: Arc<dyn UserRepository> = module.resolve();
+        let user = match user_repository.find_by_username_or_email(realm_uuid, username).await {
+            Some(user) => user,
+            None => {
+                let (Some(email), Some(password)) = (&config.admin_email, &config.admin_password) else {
+                    return Err("bootstrap.admin_email and bootstrap.admin_password are required to create the admin user".to_string());
+                };
+                println!("Creating admin user {}", username);
+                create_admin(module, realm_uuid, username, email, password).await?
+            }
+        };
+
+        let role_repository: Arc<dyn RoleRepository> = module.resolve();
+        return role_repository.assign(user.uuid, admin_role.uuid).await;
+    }
+
+    let role_repository: Arc<dyn RoleRepository> = module.resolve();
+    if !role_repository.has_active_members(admin_role.uuid).await? {
+        let token = generate_token();
+        let setup_token_repository: Arc<dyn SetupTokenRepository> = module.resolve();
+        setup_token_repository.store(hash_token(&token)).await;
+
+        println!("There is no administrator yet. Create one with this one-time setup token:");
+        println!("  POST /setup {{\"token\": \"{}\", \"username\": ..., \"email\": ..., \"password\": ...}}", token);
+    }
+
+    Ok(())
+}
+
+/// Creates a realm with a signing key and an "admin" role, so it issues tokens right away and
+/// administrators of the default realm can hand out its administration.
+pub async fn create_realm(module: &MyModule, params: CreateRealmParams) -> Result<Realm, String> {
+    check_realm_name(&params.name)?;
+
+    let realm_repository: Arc<dyn RealmRepository> = module.resolve();
+    if realm_repository.find_by_name(&params.name).await.is_some() {
+        return Err(format!("Realm {} already exists", params.name));
+    }
+    let realm = realm_repository.create(params).await?;
+
+    let signing_key_repository: Arc<dyn SigningKeyRepository> = module.resolve();
+    keys::ensure_signing_keys(signing_key_repository.as_ref(), realm.uuid).await?;
+    ensure_admin_role(module, realm.uuid).await?;
+
+    Ok(realm)
+}
+
+/// Creates a user with the "admin" role in the realm.
+pub async fn create_admin(
+    module: &MyModule,
+    realm_uuid: Uuid,
+    username: &str,
+    email: &str,
+    password: &str,
+) -> Result<User, String> {
+    let user_repository: Arc<dyn UserRepository> = module.resolve();
+    let role_repository: Arc<dyn RoleRepository> = module.resolve();
+
+    let admin_role = ensure_admin_role(module, realm_uuid).await?;
+    let user = user_repository
+        .create(CreateUserParams {
+            realm_uuid,
+            username: username.to_string(),
+            email: email.to_string(),
+            password_hash: hash_password(password)?,
+            attributes: serde_json::json!({}),
+        })
+        .await?;
+    role_repository.assign(user.uuid, admin_role.uuid).await?;
+
+    Ok(user)
+}
+
+/// Returns the realm's "admin" role, creating it if it is missing.
+pub async fn ensure_admin_role(module: &MyModule, realm_uuid: Uuid) -> Result<Role, String> {
+    let role_repository: Arc<dyn RoleRepository> = module.resolve();
+
+    if let Some(role) = role_repository.find_by_name(realm_uuid, None, ADMIN).await {
+        return Ok(role);
+    }
+
+    role_repository
+        .create(CreateRoleParams {
+            realm_uuid,
+            client_uuid: None,
+            name: ADMIN.to_string(),
+            description: Some("Full system access".to_string()),
+        })
+        .await
+}
+
+async fn ensure_admin_client(module: &MyModule, realm_uuid: Uuid, config: &BootstrapConfig) -> Result<(), String> {
+    let client_repository: Arc<dyn ClientRepository> = module.resolve();
+
+    if let Some(client) = client_repository.find_by_id(realm_uuid, &ClientId(ADMIN_CLIENT_ID.to_string())).await {
+        return match &config.admin_client_redirect_uri {
+            Some(redirect_uri) => enable_sign_in(client_repository.as_ref(), realm_uuid, client, redirect_uri).await,
+            None => Ok(()),
+        };
+    }
+
+    let generated_secret = config.admin_client_secret.is_none().then(generate_token);
+    let secret = config.admin_client_secret.as_ref().or(generated_secret.as_ref()).unwrap();
+    let (scopes, grant_types) = match config.admin_client_redirect_uri {
+        Some(_) => (vec!["openid".to_string(), ADMIN.to_string()], vec!["client_credentials".to_string(), "authorization_code".to_string()]),
+        None => (vec![ADMIN.to_string()], vec!["client_credentials".to_string()]),
+    };
+
+    client_repository
+        .create(CreateClientParams {
+            realm_uuid,
+            client_id: ADMIN_CLIENT_ID.to_string(),
+            redirect_uris: config.admin_client_redirect_uri.iter().cloned().collect(),
+            scopes,
+            client_secret_hash: Some(hash_client_secret(secret)),
+            client_name: Some("Vaulton administration".to_string()),
+            logo_uri: None,
+            application_type: "web".to_string(),
+            subject_type: "public".to_string(),
+            sector_identifier_uri: None,
+            grant_types,
+            token_endpoint_auth_method: None,
+            registration_token_hash: None,
+            first_party: true,
+            id_token_role_claims: false,
+            access_token_format: AccessTokenFormat::Jwt,
+            access_token_lifetime: None,
+            id_token_lifetime: None,
+            id_token_signed_response_alg: "RS256".to_string(),
+            jwks: None,
+            id_token_encrypted_response_alg: None,
+            id_token_encrypted_response_enc: None,
+            userinfo_encrypted_response_alg: None,
+            userinfo_encrypted_response_enc: None,
+        })
+        .await?;
+
+    match generated_secret {
+        Some(secret) => println!("Created admin client {} with secret {} (shown only once)", ADMIN_CLIENT_ID, secret),
+        None => println!("Created admin client {}", ADMIN_CLIENT_ID),
+    }
+
+    Ok(())
+}
+
+/// Adds the redirect URI, the authorization code grant and the "openid" scope to an existing
+/// admin client, keeping whatever else it was given since.
+async fn enable_sign_in(client_repository: &dyn ClientRepository, realm_uuid: Uuid, client: Client, redirect_uri: &str) -> Result<(), String> {
+    let mut redirect_uris = client.redirect_uris;
+    let mut scopes = client.allowed_scopes;
+    let mut grant_types = client.grant_types;
+    let mut changed = false;
+    for (values, value) in [(&mut redirect_uris, redirect_uri), (&mut scopes, "openid"), (&mut grant_types, "authorization_code")] {
+        if !values.iter().any(|v| v == value) {
+            values.push(value.to_string());
+            changed = true;
+        }
+    }
+    if !changed {
+        return Ok(());
+    }
+
+    client_repository
+        .update_metadata(
+            realm_uuid,
+            &client.id,
+            UpdateClientMetadataParams {
+                redirect_uris,
+                scopes,
+                client_name: client.client_name,
+                logo_uri: client.logo_uri,
+                application_type: client.application_type,
+                subject_type: client.subject_type,
+                sector_identifier_uri: client.sector_identifier_uri,
+                grant_types,
+                token_endpoint_auth_method: client.token_endpoint_auth_method,
+                id_token_signed_response_alg: client.id_token_signed_response_alg,
+                jwks: client.jwks,
+                id_token_encrypted_response_alg: client.id_token_encrypted_response_alg,
+                id_token_encrypted_response_enc: client.id_token_encrypted_response_enc,
+                userinfo_encrypted_response_alg: client.userinfo_encrypted_response_alg,
+                userinfo_encrypted_response_enc: client.userinfo_encrypted_response_enc,
+            },
+        )
+        .await?;
+    println!("Enabled sign-in to admin client {} at {}", ADMIN_CLIENT_ID, redirect_uri);
+
+    Ok(())
+}
@@ -1,12 +1,21 @@
 mod health;
-mod api;
+pub(crate) mod api;
+mod setup;
+mod realm;
+pub mod bootstrap;
+pub mod export;
+pub mod provisioning;
 
+use std::net::SocketAddr;
 use std::sync::Arc;
 use crate::{oidc, Config};
 
-use axum::{Router, routing::get};
-use crate::db::DatabaseImplParameters;
+use axum::{middleware, Router, routing::{get, post}};
 use crate::di::MyModule;
+use crate::domain::realm::{Realm, DEFAULT_REALM};
+use crate::repository::realm_repository::RealmRepository;
+use crate::repository::signing_key_repository::SigningKeyRepository;
+use shaku::HasComponent;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -14,18 +23,44 @@ pub struct AppState {
     pub config: Config,
 }
 
+impl AppState {
+    /// The issuer and base URL of the realm's endpoints.
+    pub fn external_url(&self, realm: &Realm) -> String {
+        realm.external_url(self.config.oidc.external_url.as_deref().unwrap())
+    }
+}
+
 pub async fn create_server(config: Config) -> Router {
     // connect to the database
     let pool = crate::db::connect_to_db(&config.postgres).await.unwrap();
 
     // Create the DI module
-    let module = Arc::new(
-        MyModule::builder()
-            .with_component_parameters::<crate::db::DatabaseImpl>(DatabaseImplParameters{
-                pool,
-            })
-            .build()
-    );
+    let module = Arc::new(crate::di::create_module(pool));
+
+    // Make sure every realm has keys to sign tokens with
+    let realm_repository: Arc<dyn RealmRepository> = module.resolve();
+    let signing_key_repository: Arc<dyn SigningKeyRepository> = module.resolve();
+    for realm in realm_repository.list().await.unwrap() {
+        oidc::keys::ensure_signing_keys(signing_key_repository.as_ref(), realm.uuid).await.unwrap();
+    }
+    let master = realm_repository.find_by_name(DEFAULT_REALM).await.expect("the default realm exists");
+
+    // Make sure there is a way to administer the server
+    bootstrap::bootstrap(&module, master.uuid, &config.bootstrap).await.unwrap_or_else(|e| {
+        eprintln!("Failed to bootstrap: {}", e);
+        std::process::exit(1);
+    });
+
+    // Reconcile the clients, roles, groups and users declared in the configuration
+    let provisioning = &config.provisioning;
+    let prune = provisioning.prune.unwrap_or(false);
+    if !provisioning.is_empty() || prune {
+        let dry_run = provisioning.dry_run.unwrap_or(false);
+        provisioning::run(&module, master.uuid, provisioning, dry_run, prune).await.unwrap_or_else(|e| {
+            eprintln!("Failed to provision: {}", e);
+            std::process::exit(1);
+        });
+    }
 
     // Create the app state
     let state = AppState {
@@ -33,8 +68,34 @@ pub async fn create_server(config: Config) -> Router {
         config,
     };
 
-    Router::new()
+    let routes = Router::new()
         .route("/health", get(health::health_check))
+        .route("/setup", post(setup::complete_setup).with_state(state.clone()))
         .merge(oidc::oidc_routes(state.clone()))
         .merge(api::api_routes(state.clone()))
-}
\ No newline at end of file
+        .merge(api::account_routes(state.clone()));
+
+    // The realm is resolved before routing, as it may strip a path prefix
+    Router::new()
+        .fallback_service(routes)
+        .layer(middleware::from_fn_with_state(state, realm::resolve_realm))
+}
+
+/// Runs the server on the configured address until it is stopped.
+pub async fn serve(config: Config) -> Result<(), String> {
+    let bind_addr = config.server.bind_addr.as_deref().unwrap();
+    let port = config.server.port.unwrap();
+    let addr = format!("{}:{}", bind_addr, port);
+
+    let app = create_server(config).await;
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .map_err(|e| format!("Failed to bind to {}: {}", addr, e))?;
+
+    println!("Server running on http://{}", addr);
+
+    // Failed logins are counted per client address
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,727 @@
+//! Declarative provisioning. Reconciles the clients, roles, groups and users declared in the
+//! `provisioning` configuration into the database, creating what is missing and updating what
+//! differs. Declared objects are recorded as provisioned; with pruning, recorded objects that are
+//! no longer declared are deleted. Objects created through the admin API are left alone.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use crate::config::provisioning::{ClientSpec, GroupSpec, ProvisioningConfig, RoleSpec, UserSpec};
+use crate::di::MyModule;
+use crate::domain::access_token::AccessTokenFormat;
+use crate::domain::client::{Client, ClientId};
+use crate::domain::group::{check_group_name, Group};
+use crate::oidc::client_auth::hash_client_secret;
+use crate::oidc::login::hash_password;
+use crate::oidc::registration::{check_metadata, generate_token, ClientMetadata};
+use crate::repository::client_repository::{ClientRepository, CreateClientParams, UpdateClientMetadataParams};
+use crate::repository::group_repository::{CreateGroupParams, GroupRepository};
+use crate::repository::provisioning_repository::ProvisioningRepository;
+use crate::repository::roles_repository::{CreateRoleParams, RoleRepository, UpdateRoleParams};
+use crate::repository::user_repository::{CreateUserParams, UpdateUserParams, UserRepository};
+use shaku::HasComponent;
+use uuid::Uuid;
+
+const CLIENT: &str = "client";
+const ROLE: &str = "role";
+const GROUP: &str = "group";
+const USER: &str = "user";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Create,
+    Update,
+    Delete,
+}
+
+/// A change to one object, printed as a line of the diff.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub action: Action,
+    pub kind: &'static str,
+    pub name: String,
+    /// What is updated, e.g. "redirect_uris" or "+role auditor"
+    pub details: Vec<String>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self.action {
+            Action::Create => '+',
+            Action::Update => '~',
+            Action::Delete => '-',
+        };
+        write!(f, "{} {} {}", sign, self.kind, self.name)?;
+        if !self.details.is_empty() {
+            write!(f, " ({})", self.details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Reconciles the declarations in the realm and prints the changes.
+pub async fn run(
+    module: &MyModule,
+    realm_uuid: Uuid,
+    config: &ProvisioningConfig,
+    dry_run: bool,
+    prune: bool,
+) -> Result<(), String> {
+    let changes = provision(module, realm_uuid, config, dry_run, prune).await?;
+
+    if changes.is_empty() {
+        println!("Provisioning is up to date");
+        return Ok(());
+    }
+    if dry_run {
+        println!("Provisioning dry run, nothing is changed:");
+    }
+    for change in &changes {
+        println!("{}", change);
+    }
+
+    Ok(())
+}
+
+/// Reconciles the declarations. With `dry_run` nothing is changed and the returned changes
+/// are the ones that would be made.
+pub async fn provision(
+    module: &MyModule,
+    realm_uuid: Uuid,
+    config: &ProvisioningConfig,
+    dry_run: bool,
+    prune: bool,
+) -> Result<Vec<Change>, String> {
+    let mut reconciler = Reconciler {
+        module,
+        realm_uuid,
+        config,
+        dry_run,
+        changes: Vec::new(),
+    };
+
+    for spec in config.clients.iter().flatten() {
+        reconciler.client(spec).await?;
+    }
+    for spec in config.roles.iter().flatten() {
+        reconciler.role(spec).await?;
+    }
+    reconciler.groups().await?;
+    for spec in config.users.iter().flatten() {
+        reconciler.user(spec).await?;
+    }
+
+    if prune {
+        // Dependents first; deleting a client takes its roles with it
+        for kind in [USER, GROUP, ROLE, CLIENT] {
+            reconciler.prune(kind).await?;
+        }
+    }
+
+    Ok(reconciler.changes)
+}
+
+struct Reconciler<'a> {
+    module: &'a MyModule,
+    realm_uuid: Uuid,
+    config: &'a ProvisioningConfig,
+    dry_run: bool,
+    changes: Vec<Change>,
+}
+
+impl Reconciler<'_> {
+    fn change(&mut self, action: Action, kind: &'static str, name: &str, details: Vec<String>) {
+        self.changes.push(Change {
+            action,
+            kind,
+            name: name.to_string(),
+            details,
+        });
+    }
+
+    async fn record(&self, kind: &str, name: &str) -> Result<(), String> {
+        if self.dry_run {
+            return Ok(());
+        }
+        let provisioning_repository: Arc<dyn ProvisioningRepository> = self.module.resolve();
+        provisioning_repository.record(self.realm_uuid, kind, name).await
+    }
+
+    async fn client(&mut self, spec: &ClientSpec) -> Result<(), String> {
+        let client_repository: Arc<dyn ClientRepository> = self.module.resolve();
+        let id = ClientId(spec.client_id.clone());
+
+        // The same checks as for clients registering themselves
+        let metadata = ClientMetadata {
+            redirect_uris: spec.redirect_uris.clone(),
+            scope: spec.scopes.as_ref().map(|scopes| scopes.join(" ")),
+            grant_types: spec.grant_types.clone(),
+            token_endpoint_auth_method: spec.public.then(|| "none".to_string()),
+            client_name: spec.name.clone(),
+            ..Default::default()
+        };
+        let validated = check_metadata(&metadata).map_err(|e| format!("client {}: {}", spec.client_id, e))?;
+
+        let Some(client) = client_repository.find_by_id(self.realm_uuid, &id).await else {
+            self.change(Action::Create, CLIENT, &spec.client_id, vec![]);
+            if !self.dry_run {
+                let generated_secret = (!spec.public && spec.secret.is_none()).then(generate_token);
+                let secret = spec.secret.as_ref().or(generated_secret.as_ref());
+                client_repository.create(create_client_params(self.realm_uuid, spec, validated, secret)).await?;
+                if let Some(secret) = generated_secret {
+                    println!("Generated secret for client {}: {} (shown only once)", spec.client_id, secret);
+                }
+            }
+            return self.record(CLIENT, &spec.client_id).await;
+        };
+
+        if client.is_public() != spec.public {
+            return Err(format!(
+                "client {}: cannot switch between a public and a confidential client",
+                spec.client_id
+            ));
+        }
+
+        let metadata_changes = client_differences(&client, &validated);
+        let first_party_changed = client.first_party != spec.first_party;
+        let secret_hash = spec.secret.as_deref().map(hash_client_secret);
+        let secret_changed = secret_hash.is_some() && secret_hash != client.secret_hash;
+
+        let mut details = metadata_changes.iter().map(|field| field.to_string()).collect::<Vec<_>>();
+        if first_party_changed {
+            details.push("first_party".to_string());
+        }
+        if secret_changed {
+            details.push("secret".to_string());
+        }
+        if !details.is_empty() {
+            self.change(Action::Update, CLIENT, &spec.client_id, details);
+        }
+
+        if !self.dry_run {
+            if !metadata_changes.is_empty() {
+                // Metadata the declaration does not cover is kept as it is
+                let update_params = UpdateClientMetadataParams {
+                    redirect_uris: validated.redirect_uris,
+                    scopes: validated.scopes,
+                    client_name: validated.client_name,
+                    grant_types: validated.grant_types,
+                    logo_uri: client.logo_uri,
+                    application_type: client.application_type,
+                    subject_type: client.subject_type,
+                    sector_identifier_uri: client.sector_identifier_uri,
+                    token_endpoint_auth_method: client.token_endpoint_auth_method,
+                    id_token_signed_response_alg: client.id_token_signed_response_alg,
+                    jwks: client.jwks,
+                    id_token_encrypted_response_alg: client.id_token_encrypted_response_alg,
+                    id_token_encrypted_response_enc: client.id_token_encrypted_response_enc,
+                    userinfo_encrypted_response_alg: client.userinfo_encrypted_response_alg,
+                    userinfo_encrypted_response_enc: client.userinfo_encrypted_response_enc,
+                };
+                client_repository.update_metadata(self.realm_uuid, &id, update_params).await?;
+            }
+            if first_party_changed {
+                client_repository.set_first_party(self.realm_uuid, &id, spec.first_party).await?;
+            }
+            if let (true, Some(secret_hash)) = (secret_changed, secret_hash) {
+                client_repository.set_secret_hash(self.realm_uuid, &id, &secret_hash).await?;
+            }
+        }
+
+        self.record(CLIENT, &spec.client_id).await
+    }
+
+    async fn role(&mut self, spec: &RoleSpec) -> Result<(), String> {
+        let role_repository: Arc<dyn RoleRepository> = self.module.resolve();
+        let key = role_key(spec.client.as_deref(), &spec.name);
+
+        let client_uuid = match &spec.client {
+            Some(client_id) => {
+                let client_repository: Arc<dyn ClientRepository> = self.module.resolve();
+                match client_repository.find_by_id(self.realm_uuid, &ClientId(client_id.clone())).await {
+                    Some(client) => Some(client.uuid),
+                    // The client is created by this run, and so is the role
+                    None if self.dry_run && self.declares_client(client_id) => {
+                        self.change(Action::Create, ROLE, &key, vec![]);
+                        return Ok(());
+                    }
+                    None => return Err(format!("role {}: client {} does not exist", key, client_id)),
+                }
+            }
+            None => None,
+        };
+
+        match role_repository.find_by_name(self.realm_uuid, client_uuid, &spec.name).await {
+            None => {
+                self.change(Action::Create, ROLE, &key, vec![]);
+                if !self.dry_run {
+                    role_repository
+                        .create(CreateRoleParams {
+                            realm_uuid: self.realm_uuid,
+                            client_uuid,
+                            name: spec.name.clone(),
+                            description: spec.description.clone(),
+                        })
+                        .await?;
+                }
+            }
+            Some(role) if role.description != spec.description => {
+                self.change(Action::Update, ROLE, &key, vec!["description".to_string()]);
+                if !self.dry_run {
+                    let update_params = UpdateRoleParams {
+                        name: None,
+                        description: spec.description.clone(),
+                    };
+                    role_repository.update(self.realm_uuid, role.uuid, update_params).await?;
+                }
+            }
+            Some(_) => {}
+        }
+
+        self.record(ROLE, &key).await
+    }
+
+    async fn groups(&mut self) -> Result<(), String> {
+        let group_repository: Arc<dyn GroupRepository> = self.module.resolve();
+        let mut groups = group_repository
+            .list(self.realm_uuid)
+            .await?
+            .into_iter()
+            .map(|group| (group.path.clone(), group))
+            .collect::<HashMap<_, _>>();
+
+        // Parents before their subgroups
+        let mut specs = self.config.groups.iter().flatten().collect::<Vec<_>>();
+        specs.sort_by_key(|spec| spec.path.matches('/').count());
+
+        for spec in specs {
+            let group = self.group(spec, &groups).await?;
+            if let Some(group) = group {
+                groups.insert(group.path.clone(), group);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the created group, None if it exists already or only would be created.
+    async fn group(&mut self, spec: &GroupSpec, groups: &HashMap<String, Group>) -> Result<Option<Group>, String> {
+        let group_repository: Arc<dyn GroupRepository> = self.module.resolve();
+        let role_repository: Arc<dyn RoleRepository> = self.module.resolve();
+        if !spec.attributes.is_object() {
+            return Err(format!("group {}: attributes must be a mapping", spec.path));
+        }
+
+        let declared_roles = spec.roles.iter().cloned().collect::<BTreeSet<_>>();
+
+        if let Some(group) = groups.get(&spec.path) {
+            let current_roles = role_repository
+                .find_by_group(group.uuid)
+                .await?
+                .into_iter()
+                .filter(|role| role.is_realm_role())
+                .map(|role| (role.name, role.uuid))
+                .collect::<HashMap<_, _>>();
+            let current_names = current_roles.keys().cloned().collect::<BTreeSet<_>>();
+            let (added, removed) = diff_names(&current_names, &declared_roles);
+
+            let mut details = Vec::new();
+            if group.attributes != spec.attributes {
+                details.push("attributes".to_string());
+            }
+            details.extend(added.iter().map(|name| format!("+role {}", name)));
+            details.extend(removed.iter().map(|name| format!("-role {}", name)));
+            if !details.is_empty() {
+                self.change(Action::Update, GROUP, &spec.path, details);
+            }
+
+            if !self.dry_run {
+                if group.attributes != spec.attributes {
+                    group_repository.update_attributes(self.realm_uuid, group.uuid, spec.attributes.clone()).await?;
+                }
+                for name in &added {
+                    let role_id = self.realm_role(name).await?;
+                    group_repository.add_role(group.uuid, role_id).await?;
+                }
+                for name in &removed {
+                    group_repository.remove_role(group.uuid, current_roles[name]).await?;
+                }
+            }
+
+            self.record(GROUP, &spec.path).await?;
+            return Ok(None);
+        }
+
+        let Some((parent_path, name)) = spec.path.rsplit_once('/') else {
+            return Err(format!("group {}: the path must start with '/'", spec.path));
+        };
+        check_group_name(name).map_err(|e| format!("group {}: {}", spec.path, e))?;
+
+        let parent_uuid = match parent_path {
+            "" => None,
+            parent_path => match groups.get(parent_path) {
+                Some(parent) => Some(parent.uuid),
+                None if self.dry_run && self.declares_group(parent_path) => None,
+                None => return Err(format!("group {}: the parent group {} does not exist", spec.path, parent_path)),
+            },
+        };
+
+        self.change(Action::Create, GROUP, &spec.path, vec![]);
+        if self.dry_run {
+            return Ok(None);
+        }
+
+        let group = group_repository
+            .create(CreateGroupParams {
+                realm_uuid: self.realm_uuid,
+                parent_uuid,
+                name: name.to_string(),
+                attributes: spec.attributes.clone(),
+            })
+            .await?;
+        for name in &declared_roles {
+            let role_id = self.realm_role(name).await?;
+            group_repository.add_role(group.uuid, role_id).await?;
+        }
+
+        self.record(GROUP, &spec.path).await?;
+        Ok(Some(group))
+    }
+
+    async fn user(&mut self, spec: &UserSpec) -> Result<(), String> {
+        let user_repository: Arc<dyn UserRepository> = self.module.resolve();
+        let role_repository: Arc<dyn RoleRepository> = self.module.resolve();
+        let group_repository: Arc<dyn GroupRepository> = self.module.resolve();
+        if !spec.attributes.is_object() {
+            return Err(format!("user {}: attributes must be a mapping", spec.username));
+        }
+
+        let declared_roles = spec.roles.iter().cloned().collect::<BTreeSet<_>>();
+        let declared_groups = spec.groups.iter().cloned().collect::<BTreeSet<_>>();
+
+        let Some(user) = user_repository.find_by_username_or_email(self.realm_uuid, &spec.username).await else {
+            self.change(Action::Create, USER, &spec.username, vec![]);
+            if self.dry_run {
+                return Ok(());
+            }
+
+            // Without a password nobody knows, so the user cannot log in
+            let password = spec.password.clone().unwrap_or_else(generate_token);
+            let user = user_repository
+                .create(CreateUserParams {
+                    realm_uuid: self.realm_uuid,
+                    username: spec.username.clone(),
+                    email: spec.email.clone(),
+                    password_hash: hash_password(&password)?,
+                    attributes: spec.attributes.clone(),
+                })
+                .await?;
+            if !spec.enabled {
+                user_repository.set_enabled(self.realm_uuid, user.uuid, false).await?;
+            }
+            for name in &declared_roles {
+                role_repository.assign(user.uuid, self.realm_role(name).await?).await?;
+            }
+            for path in &declared_groups {
+                group_repository.add_member(self.group_id(path).await?, user.uuid).await?;
+            }
+
+            return self.record(USER, &spec.username).await;
+        };
+
+        let current_roles = role_repository
+            .find_by_user(user.uuid)
+            .await?
+            .into_iter()
+            .filter(|role| role.is_realm_role())
+            .map(|role| (role.name, role.uuid))
+            .collect::<HashMap<_, _>>();
+        let current_groups = group_repository
+            .find_by_user(user.uuid)
+            .await?
+            .into_iter()
+            .map(|group| (group.path, group.uuid))
+            .collect::<HashMap<_, _>>();
+        let (added_roles, removed_roles) =
+            diff_names(&current_roles.keys().cloned().collect(), &declared_roles);
+        let (added_groups, removed_groups) =
+            diff_names(&current_groups.keys().cloned().collect(), &declared_groups);
+        let profile_changed = user.email != spec.email || user.attributes != spec.attributes;
+
+        let mut details = Vec::new();
+        if user.email != spec.email {
+            details.push("email".to_string());
+        }
+        if user.attributes != spec.attributes {
+            details.push("attributes".to_string());
+        }
+        if user.enabled != spec.enabled {
+            details.push(if spec.enabled { "enabled" } else { "disabled" }.to_string());
+        }
+        details.extend(added_roles.iter().map(|name| format!("+role {}", name)));
+        details.extend(removed_roles.iter().map(|name| format!("-role {}", name)));
+        details.extend(added_groups.iter().map(|path| format!("+group {}", path)));
+        details.extend(removed_groups.iter().map(|path| format!("-group {}", path)));
+        if !details.is_empty() {
+            self.change(Action::Update, USER, &spec.username, details);
+        }
+
+        if !self.dry_run {
+            if profile_changed {
+                let update_params = UpdateUserParams {
+                    username: None,
+                    email: Some(spec.email.clone()),
+                    attributes: Some(spec.attributes.clone()),
+                };
+                user_repository.update(self.realm_uuid, user.uuid, update_params).await?;
+            }
+            if user.enabled != spec.enabled {
+                user_repository.set_enabled(self.realm_uuid, user.uuid, spec.enabled).await?;
+            }
+            for name in &added_roles {
+                role_repository.assign(user.uuid, self.realm_role(name).await?).await?;
+            }
+            for name in &removed_roles {
+                role_repository.unassign(user.uuid, current_roles[name]).await?;
+            }
+            for path in &added_groups {
+                group_repository.add_member(self.group_id(path).await?, user.uuid).await?;
+            }
+            for path in &removed_groups {
+                group_repository.remove_member(current_groups[path], user.uuid).await?;
+            }
+        }
+
+        self.record(USER, &spec.username).await
+    }
+
+    /// Deletes the provisioned objects of the kind that are no longer declared.
+    async fn prune(&mut self, kind: &'static str) -> Result<(), String> {
+        let provisioning_repository: Arc<dyn ProvisioningRepository> = self.module.resolve();
+        let declared = self.declared_names(kind);
+
+        // Subgroups sort after their parents, so go backwards to delete them first
+        for name in provisioning_repository.list(self.realm_uuid, kind).await?.into_iter().rev() {
+            if declared.contains(&name) {
+                continue;
+            }
+
+            if self.delete(kind, &name).await? {
+                self.change(Action::Delete, kind, &name, vec![]);
+            }
+            if !self.dry_run {
+                provisioning_repository.forget(self.realm_uuid, kind, &name).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the object, unless this is a dry run. Returns false if it is gone already.
+    async fn delete(&self, kind: &str, name: &str) -> Result<bool, String> {
+        match kind {
+            CLIENT => {
+                let client_repository: Arc<dyn ClientRepository> = self.module.resolve();
+                let id = ClientId(name.to_string());
+                if client_repository.find_by_id(self.realm_uuid, &id).await.is_none() {
+                    return Ok(false);
+                }
+                if !self.dry_run {
+                    client_repository.delete(self.realm_uuid, &id).await?;
+                }
+            }
+            ROLE => {
+                let role_repository: Arc<dyn RoleRepository> = self.module.resolve();
+                let (client_uuid, role_name) = match name.split_once('/') {
+                    Some((client_id, role_name)) => {
+                        let client_repository: Arc<dyn ClientRepository> = self.module.resolve();
+                        match client_repository.find_by_id(self.realm_uuid, &ClientId(client_id.to_string())).await {
+                            Some(client) => (Some(client.uuid), role_name),
+                            None => return Ok(false),
+                        }
+                    }
+                    None => (None, name),
+                };
+                let Some(role) = role_repository.find_by_name(self.realm_uuid, client_uuid, role_name).await else {
+                    return Ok(false);
+                };
+                if !self.dry_run {
+                    role_repository.delete(self.realm_uuid, role.uuid).await?;
+                }
+            }
+            GROUP => {
+                let group_repository: Arc<dyn GroupRepository> = self.module.resolve();
+                let groups = group_repository.list(self.realm_uuid).await?;
+                let Some(group) = groups.into_iter().find(|group| group.path == name) else {
+                    return Ok(false);
+                };
+                if !self.dry_run {
+                    group_repository.delete(self.realm_uuid, group.uuid).await?;
+                }
+            }
+            _ => {
+                let user_repository: Arc<dyn UserRepository> = self.module.resolve();
+                let Some(user) = user_repository.find_by_username_or_email(self.realm_uuid, name).await else {
+                    return Ok(false);
+                };
+                if !self.dry_run {
+                    user_repository.delete(self.realm_uuid, user.uuid).await?;
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn declared_names(&self, kind: &str) -> BTreeSet<String> {
+        let config = self.config;
+        match kind {
+            CLIENT => config.clients.iter().flatten().map(|spec| spec.client_id.clone()).collect(),
+            ROLE => config
+                .roles
+                .iter()
+                .flatten()
+                .map(|spec| role_key(spec.client.as_deref(), &spec.name))
+                .collect(),
+            GROUP => config.groups.iter().flatten().map(|spec| spec.path.clone()).collect(),
+            _ => config.users.iter().flatten().map(|spec| spec.username.clone()).collect(),
+        }
+    }
+
+    fn declares_client(&self, client_id: &str) -> bool {
+        self.declared_names(CLIENT).contains(client_id)
+    }
+
+    fn declares_group(&self, path: &str) -> bool {
+        self.declared_names(GROUP).contains(path)
+    }
+
+    async fn realm_role(&self, name: &str) -> Result<Uuid, String> {
+        let role_repository: Arc<dyn RoleRepository> = self.module.resolve();
+        role_repository
+            .find_by_name(self.realm_uuid, None, name)
+            .await
+            .map(|role| role.uuid)
+            .ok_or_else(|| format!("role {} does not exist", name))
+    }
+
+    async fn group_id(&self, path: &str) -> Result<Uuid, String> {
+        let group_repository: Arc<dyn GroupRepository> = self.module.resolve();
+        group_repository
+            .list(self.realm_uuid)
+            .await?
+            .into_iter()
+            .find(|group| group.path == path)
+            .map(|group| group.uuid)
+            .ok_or_else(|| format!("group {} does not exist", path))
+    }
+}
+
+/// Provisioned client roles are recorded as "client_id/name".
+fn role_key(client_id: Option<&str>, name: &str) -> String {
+    match client_id {
+        Some(client_id) => format!("{}/{}", client_id, name),
+        None => name.to_string(),
+    }
+}
+
+/// The names to add and to remove to get from the current to the declared names.
+fn diff_names(current: &BTreeSet<String>, declared: &BTreeSet<String>) -> (Vec<String>, Vec<String>) {
+    let added = declared.difference(current).cloned().collect();
+    let removed = current.difference(declared).cloned().collect();
+    (added, removed)
+}
+
+/// The declared client metadata fields that differ from the client's.
+fn client_differences(client: &Client, validated: &UpdateClientMetadataParams) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if client.client_name != validated.client_name {
+        fields.push("name");
+    }
+    if client.redirect_uris != validated.redirect_uris {
+        fields.push("redirect_uris");
+    }
+    if client.allowed_scopes != validated.scopes {
+        fields.push("scopes");
+    }
+    if client.grant_types != validated.grant_types {
+        fields.push("grant_types");
+    }
+    fields
+}
+
+fn create_client_params(
+    realm_uuid: Uuid,
+    spec: &ClientSpec,
+    validated: UpdateClientMetadataParams,
+    secret: Option<&String>,
+) -> CreateClientParams {
+    CreateClientParams {
+        realm_uuid,
+        client_id: spec.client_id.clone(),
+        redirect_uris: validated.redirect_uris,
+        scopes: validated.scopes,
+        client_secret_hash: secret.map(|secret| hash_client_secret(secret)),
+        client_name: validated.client_name,
+        logo_uri: validated.logo_uri,
+        application_type: validated.application_type,
+        subject_type: validated.subject_type,
+        sector_identifier_uri: validated.sector_identifier_uri,
+        grant_types: validated.grant_types,
+        token_endpoint_auth_method: validated.token_endpoint_auth_method,
+        registration_token_hash: None,
+        first_party: spec.first_party,
+        id_token_role_claims: false,
+        access_token_format: AccessTokenFormat::Jwt,
+        access_token_lifetime: None,
+        id_token_lifetime: None,
+        id_token_signed_response_alg: validated.id_token_signed_response_alg,
+        jwks: validated.jwks,
+        id_token_encrypted_response_alg: validated.id_token_encrypted_response_alg,
+        id_token_encrypted_response_enc: validated.id_token_encrypted_response_enc,
+        userinfo_encrypted_response_alg: validated.userinfo_encrypted_response_alg,
+        userinfo_encrypted_response_enc: validated.userinfo_encrypted_response_enc,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_change_display() {
+        let change = |action, details: &[&str]| Change {
+            action,
+            kind: CLIENT,
+            name: "app".to_string(),
+            details: details.iter().map(|d| d.to_string()).collect(),
+        };
+
+        assert_eq!(change(Action::Create, &[]).to_string(), "+ client app");
+        assert_eq!(
+            change(Action::Update, &["redirect_uris", "secret"]).to_string(),
+            "~ client app (redirect_uris, secret)"
+        );
+        assert_eq!(change(Action::Delete, &[]).to_string(), "- client app");
+    }
+
+    #[test]
+    fn test_diff_names() {
+        let (added, removed) = diff_names(&names(&["admin", "user"]), &names(&["user", "auditor"]));
+        assert_eq!(added, vec!["auditor".to_string()]);
+        assert_eq!(removed, vec!["admin".to_string()]);
+
+        let (added, removed) = diff_names(&names(&["user"]), &names(&["user"]));
+        assert!(added.is_empty() && removed.is_empty());
+    }
+
+    #[test]
+    fn test_role_key() {
+        assert_eq!(role_key(None, "auditor"), "auditor");
+        assert_eq!(role_key(Some("app"), "reports:read"), "app/reports:read");
+    }
+}
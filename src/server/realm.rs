@@ -0,0 +1,98 @@
+//! Realm resolution.
+//! Every request is served in one realm: the one named by a "/realms/{name}" path prefix, else
+//! the one whose host matches the Host header, else the default realm. The prefix is stripped
+//! before routing, so every realm has the same routes.
+
+use std::sync::Arc;
+use crate::domain::realm::{Realm, DEFAULT_REALM};
+use crate::repository::realm_repository::RealmRepository;
+use crate::server::AppState;
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use shaku::HasComponent;
+
+pub async fn resolve_realm(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let realm_repository: Arc<dyn RealmRepository> = state.module.resolve();
+
+    let realm = match realm_path(request.uri().path()) {
+        Some((name, path)) => {
+            let Some(realm) = realm_repository.find_by_name(name).await else {
+                return (StatusCode::NOT_FOUND, "Realm not found").into_response();
+            };
+            let uri = match strip_realm(request.uri(), path) {
+                Ok(uri) => uri,
+                Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+            };
+            *request.uri_mut() = uri;
+            realm
+        }
+        None => match find_by_host(realm_repository.as_ref(), host(&request)).await {
+            Some(realm) => realm,
+            None => match realm_repository.find_by_name(DEFAULT_REALM).await {
+                Some(realm) => realm,
+                None => return (StatusCode::INTERNAL_SERVER_ERROR, "The default realm is missing").into_response(),
+            },
+        },
+    };
+
+    request.extensions_mut().insert(realm);
+    next.run(request).await
+}
+
+async fn find_by_host(realm_repository: &dyn RealmRepository, host: Option<String>) -> Option<Realm> {
+    realm_repository.find_by_host(&host?).await
+}
+
+/// The host name of the Host header, without the port.
+fn host(request: &Request) -> Option<String> {
+    let host = request.headers().get(header::HOST)?.to_str().ok()?;
+    host.split(':').next().map(str::to_string)
+}
+
+/// Splits "/realms/{name}/rest" into the realm name and the path within the realm.
+fn realm_path(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix("/realms/")?;
+    let (name, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+
+    (!name.is_empty()).then_some((name, path))
+}
+
+fn strip_realm(uri: &Uri, path: &str) -> Result<Uri, String> {
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().map_err(|_| "Invalid path".to_string())?);
+    Uri::from_parts(parts).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_realm_path() {
+        assert_eq!(realm_path("/realms/sales/token"), Some(("sales", "/token")));
+        assert_eq!(realm_path("/realms/sales/api/users/1"), Some(("sales", "/api/users/1")));
+        assert_eq!(realm_path("/realms/sales"), Some(("sales", "/")));
+        assert_eq!(realm_path("/realms/"), None);
+        assert_eq!(realm_path("/token"), None);
+        assert_eq!(realm_path("/api/realms"), None);
+    }
+
+    #[test]
+    fn test_strip_realm() {
+        let uri: Uri = "/realms/sales/authorize?client_id=app".parse().unwrap();
+        assert_eq!(strip_realm(&uri, "/authorize").unwrap(), "/authorize?client_id=app");
+
+        let uri: Uri = "/realms/sales/jwks".parse().unwrap();
+        assert_eq!(strip_realm(&uri, "/jwks").unwrap(), "/jwks");
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use crate::domain::realm::Realm;
+use crate::oidc::access_token::hash_token;
+use crate::repository::setup_token_repository::SetupTokenRepository;
+use crate::server::bootstrap;
+use crate::server::AppState;
+use axum::extract::{Extension, Json, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use shaku::HasComponent;
+
+#[derive(Debug, Deserialize)]
+pub struct SetupRequestDto {
+    /// The one-time setup token printed on startup
+    pub token: String,
+    pub username: String,
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize)]
+struct SetupResponseDto {
+    id: String,
+    username: String,
+}
+
+/// Creates the first administrator of the default realm with the one-time setup token.
+pub async fn complete_setup(
+    State(state): State<AppState>,
+    Extension(realm): Extension<Realm>,
+    Json(dto): Json<SetupRequestDto>,
+) -> impl IntoResponse {
+    if !realm.is_default() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if dto.password.is_empty() {
+        return (StatusCode::BAD_REQUEST, "The password must not be empty").into_response();
+    }
+
+    let setup_token_repository: Arc<dyn SetupTokenRepository> = state.module.resolve();
+    let token_hash = hash_token(&dto.token);
+
+    if !setup_token_repository.consume(&token_hash).await {
+        return (StatusCode::FORBIDDEN, "The setup token is invalid or was used already").into_response();
+    }
+
+    match bootstrap::create_admin(&state.module, realm.uuid, &dto.username, &dto.email, &dto.password).await {
+        Ok(user) => {
+            let response = SetupResponseDto {
+                id: user.uuid.to_string(),
+                username: user.username,
+            };
+            (StatusCode::CREATED, Json(response)).into_response()
+        }
+        Err(e) => {
+            // Let the administrator try again with other details
+            setup_token_repository.store(token_hash).await;
+            (StatusCode::BAD_REQUEST, e).into_response()
+        }
+    }
+}
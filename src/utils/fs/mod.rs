@@ -27,6 +27,12 @@ impl FileSystem for LocalFileSystem {
     }
 }
 
+impl<FS: FileSystem> FileSystem for &FS {
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        (*self).read_to_string(path)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
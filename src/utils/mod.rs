@@ -1,4 +1,5 @@
 pub mod merge;
 pub mod env;
 pub mod fs;
-pub mod env_parser;
\ No newline at end of file
+pub mod env_parser;
+pub mod html;